@@ -0,0 +1,29 @@
+-- Overloading show, == and compare for a user defined type.
+
+data Shape = Circle Integer | Rect Integer Integer
+
+class HasArea a where
+  area :: a -> Integer
+  describe :: a -> String
+  describe x = "shape of area " ++ show (area x)
+
+instance HasArea Shape where
+  area (Circle r) = 3 * r * r
+  area (Rect w h) = w * h
+
+instance Eq Shape where
+  s == t = area s == area t
+
+instance Ord Shape where
+  compare s t = compare (area s) (area t)
+
+instance Show Shape where
+  showsPrec d (Circle r) s = "Circle " ++ showsPrec 11 r s
+  showsPrec d (Rect w h) s = "Rect " ++ showsPrec 11 w (" " ++ showsPrec 11 h s)
+
+shapes = [Circle 2, Rect 3 4, Rect 2 5]
+
+largest [x] = x
+largest (x:xs) = max x (largest xs)
+
+show (largest shapes) ++ ", " ++ describe (Rect 2 5) ++ ", " ++ show (Circle 2 == Rect 3 4)
@@ -0,0 +1,5 @@
+module Cycle.Left where
+
+import Cycle.Right
+
+left = 1
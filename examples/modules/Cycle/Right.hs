@@ -0,0 +1,5 @@
+module Cycle.Right where
+
+import Cycle.Left
+
+right = 2
@@ -0,0 +1,20 @@
+module Data.Queue (Queue, empty, push, pop, toList) where
+
+data Queue a = Queue [a] [a]
+
+empty = Queue [] []
+
+push x (Queue f b) = Queue f (x : b)
+
+pop (Queue [] []) = Nothing
+pop (Queue [] b) = pop (Queue (reverse' b) [])
+pop (Queue (x:f) b) = Just (x, Queue f b)
+
+toList q = case pop q of
+  Nothing -> []
+  Just (x, q') -> x : toList q'
+
+reverse' xs = go xs []
+  where
+    go [] acc = acc
+    go (y:ys) acc = go ys (y : acc)
@@ -0,0 +1,6 @@
+module Geometry (module Shapes, perimeter) where
+
+import Shapes
+
+perimeter (Square s) = 4.0 * s
+perimeter (Circle r) = 6.0 * r
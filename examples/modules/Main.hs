@@ -0,0 +1,14 @@
+-- A queue from `Data.Queue` and shapes from `Shapes`, run with
+-- `haskell_exe -s examples/modules/Main.hs`
+module Main where
+
+import qualified Data.Queue as Q
+import Shapes (Shape(..), area)
+import Prelude hiding (lookup)
+
+lookup _ [] = Nothing
+lookup k ((k', v):rest) = if k == k' then Just v else lookup k rest
+
+queue = Q.push (Circle 1.0) (Q.push (Square 2.0) Q.empty)
+
+(map area (Q.toList queue), lookup 2 [(1, "one"), (2, "two")])
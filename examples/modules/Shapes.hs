@@ -0,0 +1,11 @@
+module Shapes (Shape(..), area, size) where
+
+data Shape = Circle Double | Square Double
+
+area (Circle r) = 3.0 * r * r
+area (Square s) = s * s
+
+size xs = length' xs
+
+length' [] = 0
+length' (_:xs) = 1 + length' xs
@@ -0,0 +1,3 @@
+module Sizes where
+
+size = 42
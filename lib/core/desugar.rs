@@ -0,0 +1,952 @@
+use crate::core::*;
+use crate::evaluator::classes::type_constructor;
+use crate::parser::ast::{self, ClassDecl, Clause, DataDecl, Dict, Ident, InstanceDecl};
+use crate::parser::ast::{Pattern, Prefix, Rhs, Stmt};
+use crate::parser::depend::{dependency_order, Group};
+use crate::parser::desugar as surface;
+use crate::parser::rename::source_name;
+use crate::parser::visit::{self, Visitor};
+use crate::typecheck::TypeEnv;
+use std::collections::HashSet;
+
+/// Desugars a module the type checker has elaborated into Core. The
+/// imports named are Monkey functions, the builtins among them.
+pub fn desugar_program(
+    env: &TypeEnv,
+    program: &ast::Program,
+    monkey: impl IntoIterator<Item = Name>,
+) -> Program {
+    let mut desugarer = Desugarer::new(env);
+    desugarer.monkey.extend(monkey);
+    desugarer.program(program)
+}
+
+/// Translates the surface syntax into Core. Patterns are compiled into
+/// `case`s on one constructor at a time as in chapter 5 of The
+/// Implementation of Functional Programming Languages: a run of equations
+/// whose first patterns are all constructors, all literals or all
+/// variables is matched on its own, and falls through to the runs after
+/// it. Where several alternatives fall through to the same expression, it
+/// is bound to a `$fail` variable rather than copied.
+///
+/// The constructors of the data types, and the superclasses and methods of
+/// the classes, are those the type checker has declared.
+///
+/// `f(x, y)` applies a Monkey function to `x` and `y`, but any other
+/// function to the tuple. The evaluator tells them apart by their values;
+/// here a Monkey function is one bound to `fn`, a parameter of one, or a
+/// Monkey import.
+pub struct Desugarer<'env> {
+    env: &'env TypeEnv,
+    fresh: usize,
+    scripts: usize,
+    monkey: HashSet<Name>,
+}
+
+// An equation of a function, or an alternative of a `case`, as far as it
+// has been matched: the patterns still to match against the variables
+// left, and the variables of those matched, bound to the variables they
+// matched
+struct Equation {
+    pats: Vec<Pattern>,
+    bound: Vec<(Name, Name)>,
+    rhs: Rhs,
+    wheres: ast::Program,
+}
+
+// The top of the first pattern of an equation, with the variables and
+// as-patterns around it taken away
+enum Head {
+    Wild,
+    Con(Name, Vec<Pattern>),
+    Lit(Literal),
+}
+
+impl Head {
+    fn kind(&self) -> u8 {
+        match self {
+            Head::Wild => 0,
+            Head::Con(..) => 1,
+            Head::Lit(_) => 2,
+        }
+    }
+}
+
+impl<'env> Desugarer<'env> {
+    pub fn new(env: &'env TypeEnv) -> Self {
+        Desugarer {
+            env,
+            fresh: 0,
+            scripts: 0,
+            monkey: HashSet::new(),
+        }
+    }
+
+    pub fn program(&mut self, program: &ast::Program) -> Program {
+        MonkeyFunctions(&mut self.monkey).visit_program(program);
+        let mut binds = vec![];
+        for group in dependency_order(program.clone()) {
+            match group {
+                Group::Bindings {
+                    bindings,
+                    recursive,
+                } => binds.extend(self.bindings(&bindings, recursive)),
+                Group::Stmt(Stmt::ExprStmt(expr) | Stmt::ReturnStmt(expr)) => {
+                    self.scripts += 1;
+                    let name = format!("$it{}", self.scripts);
+                    binds.push(Bind::NonRec(name, self.expr(&expr)));
+                }
+                Group::Stmt(Stmt::DataStmt(decl)) => {
+                    binds.extend(self.selectors(&decl));
+                    if decl.deriving.iter().any(|Ident(class)| class == "Read") {
+                        binds.push(read_instance(&decl.name.0, decl.params.len()));
+                    }
+                }
+                Group::Stmt(Stmt::DerivingStmt(decl)) if decl.class.unqualified() == "Read" => {
+                    let tycon = type_constructor(&decl.ty).unwrap_or_default();
+                    binds.push(read_instance(&tycon, decl.context.len()));
+                }
+                Group::Stmt(Stmt::ClassStmt(decl)) => binds.extend(self.class(&decl)),
+                Group::Stmt(Stmt::InstanceStmt(decl)) => binds.push(self.instance(&decl)),
+                // the other derived instances are written out before the
+                // desugaring, and signatures, fixities and defaults have
+                // done their work
+                Group::Stmt(_) => {}
+            }
+        }
+        Program { binds }
+    }
+
+    pub fn expr(&mut self, expr: &ast::Expr) -> Expr {
+        match expr {
+            ast::Expr::Located(_, expr) => self.expr(expr),
+            ast::Expr::IdentExpr(Ident(name)) => self.apply(name, vec![]),
+            ast::Expr::HoleExpr(Ident(name)) => Expr::var(name),
+            ast::Expr::LitExpr(literal) => literal_expr(literal),
+            ast::Expr::PrefixExpr(Prefix::PrefixMinus, operand) => {
+                Expr::app(Expr::var(NEGATE), self.expr(operand))
+            }
+            ast::Expr::InfixExpr(op, left, right) => {
+                let arguments = vec![self.expr(left), self.expr(right)];
+                self.apply(op.name(), arguments)
+            }
+            ast::Expr::InfixChain(..) => Expr::error("Operators not associated by fixity"),
+            ast::Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            } => {
+                let cond = self.expr(cond);
+                let consequence = self.block(consequence);
+                let alternative = match alternative {
+                    Some(alternative) => self.block(alternative),
+                    None => unit(),
+                };
+                if_then_else(cond, consequence, alternative)
+            }
+            ast::Expr::FnExpr { params, body } => {
+                let params = params.iter().map(|Ident(param)| param.clone()).collect();
+                lambdas(params, self.block(body))
+            }
+            ast::Expr::CallExpr { .. } => {
+                // `C x y` is a call of a call, whose head is what is applied
+                let mut spine = vec![];
+                let mut function = expr;
+                while let ast::Expr::CallExpr {
+                    function: inner,
+                    arguments,
+                } = function.unlocated()
+                {
+                    spine.push(arguments);
+                    function = inner;
+                }
+                let function = function.unlocated();
+                let monkey = match function {
+                    ast::Expr::IdentExpr(Ident(name)) => self.monkey.contains(name),
+                    function => matches!(function, ast::Expr::FnExpr { .. }),
+                };
+                let mut arguments = vec![];
+                for components in spine.into_iter().rev() {
+                    let components = components.iter().map(|c| self.expr(c));
+                    match components.len() {
+                        1 => arguments.extend(components),
+                        _ if monkey => arguments.extend(components),
+                        n => arguments.push(Expr::Con(Ident::tuple_con(n).0, components.collect())),
+                    }
+                }
+                match function {
+                    ast::Expr::IdentExpr(Ident(name)) => self.apply(name, arguments),
+                    function => arguments.into_iter().fold(self.expr(function), Expr::app),
+                }
+            }
+            ast::Expr::ArrayExpr(elements) => {
+                let elements = elements.iter().map(|e| self.expr(e)).collect();
+                list(elements)
+            }
+            ast::Expr::HashExpr(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        let tuple = Ident::tuple_con(2).0;
+                        Expr::Con(tuple, vec![literal_expr(key), self.expr(value)])
+                    })
+                    .collect();
+                Expr::app(Expr::var(HASH), list(pairs))
+            }
+            ast::Expr::IndexExpr { array, index } => {
+                let array = Expr::app(Expr::var(INDEX), self.expr(array));
+                Expr::app(array, self.expr(index))
+            }
+            ast::Expr::LambdaExpr { params, body } => self.lambda(params, body),
+            ast::Expr::CaseExpr { scrutinee, alts } => self.case(scrutinee, alts),
+            ast::Expr::LambdaCaseExpr(alts) => {
+                self.expr(&surface::desugar_lambda_case(alts.clone()))
+            }
+            ast::Expr::MultiIfExpr(guards) => {
+                self.expr(&surface::desugar_multi_way_if(guards.clone()))
+            }
+            ast::Expr::LetExpr { bindings, body } => {
+                let body = self.expr(body);
+                self.local(bindings, body)
+            }
+            ast::Expr::TupleExpr(components) => {
+                let con = Ident::tuple_con(components.len()).0;
+                Expr::Con(con, components.iter().map(|c| self.expr(c)).collect())
+            }
+            ast::Expr::TupleSectionExpr(components) => {
+                self.expr(&surface::desugar_tuple_section(components.clone()))
+            }
+            ast::Expr::LeftSectionExpr(left, op) => {
+                let left = self.expr(left);
+                self.apply(op.name(), vec![left])
+            }
+            ast::Expr::RightSectionExpr(op, right) => {
+                // `(op e)` is `\x -> x op e`, with `e` evaluated once
+                let right = self.expr(right);
+                let x = self.fresh("x");
+                self.share("ds", right, |this, right| {
+                    let body = this.apply(op.name(), vec![Expr::Var(x.clone()), right]);
+                    Expr::Lam(x, Box::new(body))
+                })
+            }
+            ast::Expr::ListCompExpr { expr, qualifiers } => {
+                let comprehension =
+                    surface::desugar_comprehension(*expr.clone(), qualifiers.clone());
+                self.expr(&comprehension)
+            }
+            ast::Expr::DoExpr(stmts) => self.expr(&surface::desugar_do(stmts.clone())),
+            ast::Expr::TypedExpr(expr, ..) => self.expr(expr),
+            ast::Expr::DictExpr(dict) => self.dict(dict),
+        }
+    }
+
+    // A variable or a constructor applied to arguments, a constructor to
+    // all its fields
+    fn apply(&mut self, name: &str, arguments: Vec<Expr>) -> Expr {
+        let con = Ident(name.to_owned());
+        if con.is_constructor() || name == "[]" {
+            if let Some(arity) = self.arity(con.unqualified()) {
+                return self.construct(con.unqualified(), arity, arguments);
+            }
+        }
+        arguments.into_iter().fold(Expr::var(name), Expr::app)
+    }
+
+    // A constructor given fewer arguments than it has fields is a function
+    // of the others
+    fn construct(&mut self, con: &str, arity: usize, mut arguments: Vec<Expr>) -> Expr {
+        if arguments.len() >= arity {
+            let rest = arguments.split_off(arity);
+            return rest
+                .into_iter()
+                .fold(Expr::Con(con.to_owned(), arguments), Expr::app);
+        }
+        let mut shared = vec![];
+        let mut fields: Vec<Expr> = arguments
+            .into_iter()
+            .map(|argument| match argument.is_trivial() {
+                true => argument,
+                false => {
+                    let name = self.fresh("ds");
+                    shared.push((name.clone(), argument));
+                    Expr::Var(name)
+                }
+            })
+            .collect();
+        let params: Vec<Name> = (fields.len()..arity).map(|_| self.fresh("ds")).collect();
+        fields.extend(params.iter().cloned().map(Expr::Var));
+        let function = lambdas(params, Expr::Con(con.to_owned(), fields));
+        shared
+            .into_iter()
+            .rev()
+            .fold(function, |body, (name, argument)| {
+                Expr::Let(Box::new(Bind::NonRec(name, argument)), Box::new(body))
+            })
+    }
+
+    // The number of fields of a constructor, the tuples' and lists'
+    // included
+    fn arity(&self, con: &str) -> Option<usize> {
+        let datatype = self.env.constructor_type(con)?;
+        let (_, arity) = datatype.constructors.iter().find(|(c, _)| c == con)?;
+        Some(*arity)
+    }
+
+    // The bindings of a group, recursive or not. A group binds at least one
+    // name, and only one when it is not recursive.
+    fn bindings(&mut self, bindings: &[Stmt], recursive: bool) -> Option<Bind> {
+        let mut binds: Vec<(Name, Expr)> =
+            bindings.iter().filter_map(|b| self.binding(b)).collect();
+        match (recursive, binds.len()) {
+            (_, 0) => None,
+            (false, 1) => {
+                let (name, expr) = binds.remove(0);
+                Some(Bind::NonRec(name, expr))
+            }
+            _ => Some(Bind::Rec(binds)),
+        }
+    }
+
+    fn binding(&mut self, stmt: &Stmt) -> Option<(Name, Expr)> {
+        match stmt {
+            Stmt::LetStmt(Ident(name), expr) => Some((name.clone(), self.expr(expr))),
+            Stmt::FunStmt(Ident(name), clauses) => {
+                Some((name.clone(), self.function(name, clauses)))
+            }
+            _ => None,
+        }
+    }
+
+    // The bindings of a `let` or a `where` around an expression
+    fn local(&mut self, bindings: &[Stmt], body: Expr) -> Expr {
+        let groups = dependency_order(bindings.to_vec());
+        groups
+            .into_iter()
+            .rev()
+            .fold(body, |body, group| match group {
+                Group::Bindings {
+                    bindings,
+                    recursive,
+                } => match self.bindings(&bindings, recursive) {
+                    Some(bind) => Expr::Let(Box::new(bind), Box::new(body)),
+                    None => body,
+                },
+                Group::Stmt(_) => body,
+            })
+    }
+
+    // A function is the lambda of its parameters, matched against the
+    // patterns of its equations in turn. The parameters of a function of
+    // one equation keep their names where they are variables.
+    fn function(&mut self, name: &str, clauses: &[Clause]) -> Expr {
+        let arity = clauses.first().map_or(0, |clause| clause.params.len());
+        let vars: Vec<Name> = (0..arity)
+            .map(|i| match clauses {
+                [Clause { params, .. }] => self.param(&params[i]),
+                _ => self.fresh("ds"),
+            })
+            .collect();
+        let equations = clauses
+            .iter()
+            .map(|clause| Equation {
+                pats: clause.params.clone(),
+                bound: vec![],
+                rhs: clause.rhs.clone(),
+                wheres: clause.wheres.clone(),
+            })
+            .collect();
+        let fail = Expr::error(&format!(
+            "Non-exhaustive patterns in function {}",
+            source_name(name)
+        ));
+        let body = self.matches(&vars, equations, fail);
+        lambdas(vars, body)
+    }
+
+    fn lambda(&mut self, params: &[Pattern], body: &ast::Expr) -> Expr {
+        let vars: Vec<Name> = params.iter().map(|param| self.param(param)).collect();
+        let equation = Equation {
+            pats: params.to_vec(),
+            bound: vec![],
+            rhs: Rhs::Plain(body.clone()),
+            wheres: vec![],
+        };
+        let fail = Expr::error("Non-exhaustive patterns in lambda");
+        let body = self.matches(&vars, vec![equation], fail);
+        lambdas(vars, body)
+    }
+
+    fn param(&mut self, pat: &Pattern) -> Name {
+        match pat {
+            Pattern::VarPat(Ident(name)) => name.clone(),
+            _ => self.fresh("ds"),
+        }
+    }
+
+    // The scrutinee is bound to a variable, unless it is one
+    fn case(&mut self, scrutinee: &ast::Expr, alts: &[ast::Alt]) -> Expr {
+        let scrutinee = self.expr(scrutinee);
+        let (var, bind) = match scrutinee {
+            Expr::Var(var) => (var, None),
+            scrutinee => (self.fresh("ds"), Some(scrutinee)),
+        };
+        let equations = alts
+            .iter()
+            .map(|alt| Equation {
+                pats: vec![alt.pat.clone()],
+                bound: vec![],
+                rhs: alt.rhs.clone(),
+                wheres: alt.wheres.clone(),
+            })
+            .collect();
+        let fail = Expr::error("Non-exhaustive patterns in case");
+        let body = self.matches(std::slice::from_ref(&var), equations, fail);
+        match bind {
+            Some(scrutinee) => Expr::Let(Box::new(Bind::NonRec(var, scrutinee)), Box::new(body)),
+            None => body,
+        }
+    }
+
+    // Matches the variables against the patterns of the equations, the
+    // first equation that matches and whose guards hold giving the value,
+    // or else `fail`
+    fn matches(&mut self, vars: &[Name], equations: Vec<Equation>, fail: Expr) -> Expr {
+        let (var, rest) = match vars.split_first() {
+            Some(split) => split,
+            None => return self.bodies(equations, fail),
+        };
+        let mut blocks: Vec<Vec<(Head, Equation)>> = vec![];
+        for mut equation in equations {
+            let pat = equation.pats.remove(0);
+            let head = head(pat, var, &mut equation.bound);
+            match blocks.last_mut() {
+                Some(block) if block[0].0.kind() == head.kind() => block.push((head, equation)),
+                _ => blocks.push(vec![(head, equation)]),
+            }
+        }
+        blocks.into_iter().rev().fold(fail, |fail, block| {
+            self.share("fail", fail, |this, fail| {
+                this.block_of(var, rest, block, fail)
+            })
+        })
+    }
+
+    // A run of equations whose first patterns are of one kind
+    fn block_of(
+        &mut self,
+        var: &Name,
+        rest: &[Name],
+        block: Vec<(Head, Equation)>,
+        fail: Expr,
+    ) -> Expr {
+        let mut groups: Vec<(AltCon, Vec<Name>, Vec<Equation>)> = vec![];
+        let mut wild = vec![];
+        for (head, mut equation) in block {
+            let (con, args) = match head {
+                Head::Wild => {
+                    wild.push(equation);
+                    continue;
+                }
+                Head::Con(con, args) => (AltCon::Con(con), args),
+                Head::Lit(literal) => (AltCon::Lit(literal), vec![]),
+            };
+            args.into_iter()
+                .rev()
+                .for_each(|arg| equation.pats.insert(0, arg));
+            match groups.iter_mut().find(|(c, ..)| *c == con) {
+                Some((_, _, equations)) => equations.push(equation),
+                None => {
+                    let fields = equation.pats.len() - rest.len();
+                    let binders = (0..fields).map(|_| self.fresh("ds")).collect();
+                    groups.push((con, binders, vec![equation]));
+                }
+            }
+        }
+        if groups.is_empty() {
+            return self.matches(rest, wild, fail);
+        }
+        let complete = match groups[0].0 {
+            AltCon::Con(ref con) => self.env.constructor_type(con).is_some_and(|datatype| {
+                datatype.constructors.iter().all(|(c, _)| {
+                    groups
+                        .iter()
+                        .any(|(con, ..)| *con == AltCon::Con(c.clone()))
+                })
+            }),
+            _ => false,
+        };
+        let mut alts = vec![];
+        for (con, binders, equations) in groups {
+            let vars = [binders.as_slice(), rest].concat();
+            let body = self.matches(&vars, equations, fail.clone());
+            alts.push(Alt { con, binders, body });
+        }
+        if !complete {
+            alts.push(Alt {
+                con: AltCon::Default,
+                binders: vec![],
+                body: fail,
+            });
+        }
+        Expr::Case(Box::new(Expr::Var(var.clone())), alts)
+    }
+
+    // The equations whose patterns have all matched: the first, whose
+    // guards fall through to the others
+    fn bodies(&mut self, equations: Vec<Equation>, fail: Expr) -> Expr {
+        let mut equations = equations.into_iter();
+        let first = match equations.next() {
+            Some(equation) => equation,
+            None => return fail,
+        };
+        if !falls_through(&first.rhs) {
+            return self.body(first, fail);
+        }
+        let rest = self.bodies(equations.collect(), fail);
+        self.share("fail", rest, |this, fail| this.body(first, fail))
+    }
+
+    // The right-hand side of an equation in the scope of its `where` and of
+    // the variables of its patterns
+    fn body(&mut self, equation: Equation, fail: Expr) -> Expr {
+        let rhs = match equation.rhs {
+            Rhs::Plain(ref expr) => self.expr(expr),
+            Rhs::Guarded(ref guards) => {
+                guards
+                    .iter()
+                    .rev()
+                    .fold(fail, |otherwise, (guard, expr)| match always(guard) {
+                        true => self.expr(expr),
+                        false => {
+                            let guard = self.expr(guard);
+                            if_then_else(guard, self.expr(expr), otherwise)
+                        }
+                    })
+            }
+        };
+        let rhs = self.local(&equation.wheres, rhs);
+        equation
+            .bound
+            .into_iter()
+            .rev()
+            .fold(rhs, |body, (name, var)| {
+                Expr::Let(Box::new(Bind::NonRec(name, Expr::Var(var))), Box::new(body))
+            })
+    }
+
+    // Gives the continuation the expression, bound to a fresh variable
+    // unless it is cheap to copy
+    fn share(&mut self, prefix: &str, expr: Expr, k: impl FnOnce(&mut Self, Expr) -> Expr) -> Expr {
+        if expr.is_trivial() || is_error(&expr) {
+            return k(self, expr);
+        }
+        let name = self.fresh(prefix);
+        let body = k(self, Expr::Var(name.clone()));
+        match mentions(&body, &name) {
+            true => Expr::Let(Box::new(Bind::NonRec(name, expr)), Box::new(body)),
+            false => body,
+        }
+    }
+
+    // A Monkey block: its bindings are in scope in the statements after
+    // them, and its value is that of its last statement or of the first
+    // `return`
+    fn block(&mut self, stmts: &[Stmt]) -> Expr {
+        self.statements(&dependency_order(stmts.to_vec()))
+    }
+
+    fn statements(&mut self, groups: &[Group]) -> Expr {
+        let (group, rest) = match groups.split_first() {
+            Some(split) => split,
+            None => return unit(),
+        };
+        match group {
+            Group::Bindings {
+                bindings,
+                recursive,
+            } => {
+                let bind = self.bindings(bindings, *recursive);
+                let body = self.statements(rest);
+                match bind {
+                    Some(bind) => Expr::Let(Box::new(bind), Box::new(body)),
+                    None => body,
+                }
+            }
+            Group::Stmt(Stmt::ReturnStmt(expr)) => self.expr(expr),
+            Group::Stmt(Stmt::ExprStmt(expr)) if rest.is_empty() => self.expr(expr),
+            // the rest of the block follows each branch of an `if` that
+            // may return
+            Group::Stmt(Stmt::ExprStmt(ast::Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            })) if returns(consequence) || alternative.as_deref().is_some_and(returns) => {
+                let cond = self.expr(cond);
+                let branch = |this: &mut Self, stmts: &[Stmt]| {
+                    let mut groups = dependency_order(stmts.to_vec());
+                    groups.extend(rest.iter().cloned());
+                    this.statements(&groups)
+                };
+                let consequence = branch(self, consequence);
+                let alternative = branch(self, alternative.as_deref().unwrap_or_default());
+                if_then_else(cond, consequence, alternative)
+            }
+            // the value of an expression before the last goes unused
+            Group::Stmt(_) => self.statements(rest),
+        }
+    }
+
+    // A record's field labels are functions from the values of the
+    // constructors that have them
+    fn selectors(&mut self, decl: &DataDecl) -> Vec<Bind> {
+        let mut labels: Vec<&Ident> = vec![];
+        for con in &decl.constructors {
+            for label in &con.labels {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+        }
+        let mut binds = vec![];
+        for Ident(label) in labels {
+            let record = self.fresh("r");
+            let mut alts = vec![];
+            for con in &decl.constructors {
+                if let Some(i) = con.labels.iter().position(|Ident(l)| l == label) {
+                    let binders: Vec<Name> = con.fields.iter().map(|_| self.fresh("ds")).collect();
+                    let body = Expr::Var(binders[i].clone());
+                    let con = AltCon::Con(con.name.0.clone());
+                    alts.push(Alt { con, binders, body });
+                }
+            }
+            if alts.len() < decl.constructors.len() {
+                alts.push(Alt {
+                    con: AltCon::Default,
+                    binders: vec![],
+                    body: Expr::error(&format!("No match in record selector {}", label)),
+                });
+            }
+            let case = Expr::Case(Box::new(Expr::Var(record.clone())), alts);
+            binds.push(Bind::NonRec(
+                label.clone(),
+                Expr::Lam(record, Box::new(case)),
+            ));
+        }
+        binds
+    }
+
+    // The superclasses of a class, and its methods in the order of the
+    // fields of its dictionaries
+    fn fields(&self, class: &str) -> (Vec<String>, Vec<String>) {
+        match self.env.classes.get(class) {
+            Some(info) => {
+                let mut methods: Vec<String> = info.methods.keys().cloned().collect();
+                methods.sort();
+                (info.superclasses.clone(), methods)
+            }
+            None => (vec![], vec![]),
+        }
+    }
+
+    // The selectors of the superclasses and methods of a class, and the
+    // default of each method, failing for those the class gives none
+    fn class(&mut self, decl: &ClassDecl) -> Vec<Bind> {
+        let Ident(ref class) = decl.name;
+        let (superclasses, methods) = self.fields(class);
+        let selectors = (1..=superclasses.len())
+            .map(|n| superclass_selector(class, n))
+            .chain(methods.iter().cloned());
+        let arity = superclasses.len() + methods.len();
+        let mut binds = vec![];
+        for (i, selector) in selectors.enumerate() {
+            let dict = self.fresh("dict");
+            let binders: Vec<Name> = (0..arity).map(|_| self.fresh("ds")).collect();
+            let alt = Alt {
+                con: AltCon::Con(dictionary_con(class)),
+                body: Expr::Var(binders[i].clone()),
+                binders,
+            };
+            let case = Expr::Case(Box::new(Expr::Var(dict.clone())), vec![alt]);
+            binds.push(Bind::NonRec(selector, Expr::Lam(dict, Box::new(case))));
+        }
+        for method in &methods {
+            let default = decl
+                .body
+                .iter()
+                .filter(|stmt| binding_name(stmt) == Some(method))
+                .find_map(|stmt| self.binding(stmt));
+            let default = match default {
+                Some((_, default)) => default,
+                None => Expr::error(&format!(
+                    "No instance nor default method for class operation {}",
+                    method
+                )),
+            };
+            let this = Dict::this().0;
+            let default = Expr::Lam(this, Box::new(default));
+            binds.push(Bind::NonRec(default_method(method), default));
+        }
+        binds
+    }
+
+    // The function from the dictionaries of the context of an instance and
+    // of the superclasses to the dictionary, whose methods the class's
+    // defaults are given
+    fn instance(&mut self, decl: &InstanceDecl) -> Bind {
+        let class = decl.class.unqualified();
+        let tycon = type_constructor(&decl.ty).unwrap_or_default();
+        let (superclasses, methods) = self.fields(class);
+        let mut params: Vec<Name> = (0..decl.context.len())
+            .map(|i| Dict::context(i).0)
+            .collect();
+        let superclasses: Vec<Name> = superclasses.iter().map(|_| self.fresh("sc")).collect();
+        params.extend(superclasses.iter().cloned());
+        let this = Dict::this().0;
+        let mut fields: Vec<Expr> = superclasses.into_iter().map(Expr::Var).collect();
+        for method in &methods {
+            let defined = decl
+                .body
+                .iter()
+                .filter(|stmt| {
+                    binding_name(stmt)
+                        .is_some_and(|name| Ident(name.clone()).unqualified() == method)
+                })
+                .find_map(|stmt| self.binding(stmt));
+            fields.push(match defined {
+                Some((_, expr)) => expr,
+                None => Expr::app(Expr::var(&default_method(method)), Expr::var(&this)),
+            });
+        }
+        let dict = Expr::Con(dictionary_con(class), fields);
+        let bind = match mentions(&dict, &this) {
+            true => Bind::Rec(vec![(this.clone(), dict)]),
+            false => Bind::NonRec(this.clone(), dict),
+        };
+        let body = Expr::Let(Box::new(bind), Box::new(Expr::Var(this)));
+        Bind::NonRec(instance_fun(class, &tycon), lambdas(params, body))
+    }
+
+    // The dictionaries of the type checker are ordinary values: an instance
+    // applied to those of its context and superclasses, a parameter, or a
+    // superclass selected from a subclass
+    fn dict(&mut self, dict: &Dict) -> Expr {
+        match dict {
+            Dict::Instance {
+                class,
+                tycon,
+                context,
+                superclasses,
+            } => {
+                let function = Expr::Var(instance_fun(class.unqualified(), &tycon.0));
+                context
+                    .iter()
+                    .chain(superclasses)
+                    .fold(function, |function, dict| {
+                        Expr::app(function, self.dict(dict))
+                    })
+            }
+            Dict::Param(Ident(name)) => Expr::var(name),
+            Dict::Super(Ident(class), Ident(subclass), dict) => {
+                let (superclasses, _) = self.fields(subclass);
+                let n = superclasses
+                    .iter()
+                    .position(|s| s == class)
+                    .map_or(0, |i| i + 1);
+                let selector = Expr::Var(superclass_selector(subclass, n));
+                Expr::app(selector, self.dict(dict))
+            }
+            Dict::Dynamic(Ident(class)) => Expr::Var(format!("$dyn{}", class)),
+        }
+    }
+
+    fn fresh(&mut self, prefix: &str) -> Name {
+        self.fresh += 1;
+        format!("${}{}", prefix, self.fresh)
+    }
+}
+
+// The top of a pattern matched against a variable, binding the variables
+// and as-patterns on the way to it to the variable. Lists, tuples, strings
+// and booleans are the constructors they stand for.
+fn head(pat: Pattern, var: &Name, bound: &mut Vec<(Name, Name)>) -> Head {
+    match pat {
+        Pattern::WildPat => Head::Wild,
+        Pattern::VarPat(Ident(name)) => {
+            if name != *var {
+                bound.push((name, var.clone()));
+            }
+            Head::Wild
+        }
+        Pattern::AsPat(Ident(name), pat) => {
+            bound.push((name, var.clone()));
+            head(*pat, var, bound)
+        }
+        Pattern::SigPat(pat, _) => head(*pat, var, bound),
+        Pattern::LitPat(ast::Literal::BoolLiteral(b)) => Head::Con(bool_con(b), vec![]),
+        Pattern::LitPat(ast::Literal::StringLiteral(s)) => {
+            let chars = s
+                .chars()
+                .map(|c| Pattern::LitPat(ast::Literal::CharLiteral(c)))
+                .collect();
+            head(Pattern::ListPat(chars), var, bound)
+        }
+        Pattern::LitPat(literal) => match literal_expr(&literal) {
+            Expr::Lit(literal) => Head::Lit(literal),
+            _ => Head::Wild,
+        },
+        Pattern::ConPat(con, args) => Head::Con(con.unqualified().to_owned(), args),
+        Pattern::ListPat(mut elements) => match elements.is_empty() {
+            true => Head::Con("[]".to_owned(), vec![]),
+            false => {
+                let first = elements.remove(0);
+                Head::Con(":".to_owned(), vec![first, Pattern::ListPat(elements)])
+            }
+        },
+        Pattern::TuplePat(mut components) if components.len() == 1 => {
+            head(components.remove(0), var, bound)
+        }
+        Pattern::TuplePat(components) => {
+            Head::Con(Ident::tuple_con(components.len()).0, components)
+        }
+    }
+}
+
+fn literal_expr(literal: &ast::Literal) -> Expr {
+    match literal {
+        ast::Literal::IntLiteral(i) => Expr::Lit(Literal::Int(*i)),
+        ast::Literal::FloatLiteral(x) => Expr::Lit(Literal::Double(*x)),
+        ast::Literal::CharLiteral(c) => Expr::Lit(Literal::Char(*c)),
+        ast::Literal::StringLiteral(s) => Expr::Lit(Literal::String(s.clone())),
+        ast::Literal::BoolLiteral(b) => Expr::Con(bool_con(*b), vec![]),
+    }
+}
+
+fn bool_con(b: bool) -> Name {
+    match b {
+        true => "True".to_owned(),
+        false => "False".to_owned(),
+    }
+}
+
+// The Monkey functions a program binds, and their parameters
+struct MonkeyFunctions<'a>(&'a mut HashSet<Name>);
+
+impl<'ast> Visitor<'ast> for MonkeyFunctions<'_> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let Stmt::LetStmt(Ident(name), ast::Expr::FnExpr { .. }) = stmt {
+            self.0.insert(name.clone());
+        }
+        visit::visit_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr) {
+        if let ast::Expr::FnExpr { params, .. } = expr {
+            self.0
+                .extend(params.iter().map(|Ident(param)| param.clone()));
+        }
+        visit::visit_expr(self, expr)
+    }
+}
+
+fn unit() -> Expr {
+    Expr::Con(Ident::tuple_con(0).0, vec![])
+}
+
+fn list(elements: Vec<Expr>) -> Expr {
+    elements
+        .into_iter()
+        .rev()
+        .fold(Expr::Con("[]".to_owned(), vec![]), |list, element| {
+            Expr::Con(":".to_owned(), vec![element, list])
+        })
+}
+
+// A derived `Read` instance of a type constructor, a function of the
+// dictionaries of its context, reads with the evaluator's parser
+fn read_instance(tycon: &str, context: usize) -> Bind {
+    let params = (0..context).map(|i| Dict::context(i).0).collect();
+    let read = Expr::app(Expr::var(READ), Expr::Lit(Literal::String(tycon.to_owned())));
+    let dict = Expr::Con(dictionary_con("Read"), vec![read]);
+    Bind::NonRec(instance_fun("Read", tycon), lambdas(params, dict))
+}
+
+fn lambdas(params: Vec<Name>, body: Expr) -> Expr {
+    params
+        .into_iter()
+        .rev()
+        .fold(body, |body, param| Expr::Lam(param, Box::new(body)))
+}
+
+fn if_then_else(cond: Expr, consequence: Expr, alternative: Expr) -> Expr {
+    let alt = |con: &str, body| Alt {
+        con: AltCon::Con(con.to_owned()),
+        binders: vec![],
+        body,
+    };
+    Expr::Case(
+        Box::new(cond),
+        vec![alt("True", consequence), alt("False", alternative)],
+    )
+}
+
+fn binding_name(stmt: &Stmt) -> Option<&String> {
+    match stmt {
+        Stmt::LetStmt(Ident(name), _) | Stmt::FunStmt(Ident(name), _) => Some(name),
+        _ => None,
+    }
+}
+
+// Whether no guard may hold, which `otherwise` and `True` always do
+fn falls_through(rhs: &Rhs) -> bool {
+    match rhs {
+        Rhs::Plain(_) => false,
+        Rhs::Guarded(guards) => !guards.iter().any(|(guard, _)| always(guard)),
+    }
+}
+
+fn always(guard: &ast::Expr) -> bool {
+    match guard.unlocated() {
+        ast::Expr::IdentExpr(Ident(name)) => name == "otherwise" || name == "True",
+        ast::Expr::LitExpr(ast::Literal::BoolLiteral(b)) => *b,
+        _ => false,
+    }
+}
+
+// Whether a Monkey block may `return` from the function it is in
+fn returns(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::ReturnStmt(_) => true,
+        Stmt::ExprStmt(ast::Expr::IfExpr {
+            consequence,
+            alternative,
+            ..
+        }) => returns(consequence) || alternative.as_deref().is_some_and(returns),
+        _ => false,
+    })
+}
+
+fn is_error(expr: &Expr) -> bool {
+    matches!(expr, Expr::App(f, message)
+        if **f == Expr::var(ERROR) && matches!(**message, Expr::Lit(_)))
+}
+
+// Whether a name occurs in an expression, bound there or not
+fn mentions(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Var(var) => var == name,
+        Expr::Lit(_) => false,
+        Expr::Lam(_, body) => mentions(body, name),
+        Expr::App(function, argument) => mentions(function, name) || mentions(argument, name),
+        Expr::Let(bind, body) => {
+            let bound = match &**bind {
+                Bind::NonRec(_, expr) => mentions(expr, name),
+                Bind::Rec(binds) => binds.iter().any(|(_, expr)| mentions(expr, name)),
+            };
+            bound || mentions(body, name)
+        }
+        Expr::Case(scrutinee, alts) => {
+            mentions(scrutinee, name) || alts.iter().any(|alt| mentions(&alt.body, name))
+        }
+        Expr::Con(_, fields) => fields.iter().any(|field| mentions(field, name)),
+    }
+}
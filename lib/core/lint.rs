@@ -0,0 +1,373 @@
+use crate::core::*;
+use crate::typecheck::TypeEnv;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A mistake of the desugarer: Core that uses a variable out of scope,
+/// binds a name twice in one scope, or applies or matches a constructor
+/// with the wrong number of fields.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LintError {
+    pub message: String,
+    /// The top-level binding the mistake is in
+    pub binding: Name,
+}
+
+impl fmt::Display for LintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n  In the Core of `{}`", self.message, self.binding)
+    }
+}
+
+/// The names the Core of a module uses without binding them: those it
+/// imports, the primitives, the constructors of the data types the type
+/// checker knows, and the dictionary constructors, selectors, defaults and
+/// instances of its classes. The module's own instances are bound in its
+/// Core.
+pub struct Globals<'env> {
+    env: &'env TypeEnv,
+    module: String,
+    names: HashSet<String>,
+}
+
+impl<'env> Globals<'env> {
+    pub fn new(env: &'env TypeEnv, module: &str, names: impl IntoIterator<Item = String>) -> Self {
+        Globals {
+            env,
+            module: module.to_owned(),
+            names: names.into_iter().collect(),
+        }
+    }
+
+    fn is_var(&self, name: &str) -> bool {
+        if self.names.contains(name) || is_primitive(name) {
+            return true;
+        }
+        self.env.classes.iter().any(|(class, info)| {
+            info.methods
+                .keys()
+                .any(|method| method == name || default_method(method) == name)
+                || (1..=info.superclasses.len()).any(|n| superclass_selector(class, n) == name)
+                || name
+                    .strip_prefix(&instance_fun(class, ""))
+                    .and_then(|tycon| self.env.instance(class, tycon))
+                    .is_some_and(|instance| instance.module != self.module)
+        })
+    }
+
+    // The number of fields of a constructor in scope
+    fn arity(&self, con: &str) -> Option<usize> {
+        if let Some(info) = self
+            .env
+            .classes
+            .iter()
+            .find_map(|(class, info)| (dictionary_con(class) == con).then_some(info))
+        {
+            return Some(info.superclasses.len() + info.methods.len());
+        }
+        let datatype = self.env.constructor_type(con)?;
+        let (_, arity) = datatype.constructors.iter().find(|(c, _)| c == con)?;
+        Some(*arity)
+    }
+}
+
+/// Checks that every variable of a program is in scope where it is used,
+/// that no scope binds a name twice, and that constructors are applied to,
+/// and matched with, as many fields as they have. The top-level bindings
+/// are in scope everywhere.
+pub fn lint(program: &Program, globals: &Globals) -> Result<(), Vec<LintError>> {
+    let mut lint = Lint {
+        globals,
+        top: HashSet::new(),
+        locals: vec![],
+        binding: String::new(),
+        errors: vec![],
+    };
+    for bind in &program.binds {
+        for name in bind.names() {
+            lint.binding = name.clone();
+            if !lint.top.insert(name.clone()) {
+                lint.error(format!("`{}` is bound twice at the top level", name));
+            }
+        }
+    }
+    for bind in &program.binds {
+        let binds = match bind {
+            Bind::NonRec(name, rhs) => vec![(name, rhs)],
+            Bind::Rec(binds) => binds.iter().map(|(name, rhs)| (name, rhs)).collect(),
+        };
+        for (name, rhs) in binds {
+            lint.binding = name.clone();
+            lint.expr(rhs);
+        }
+    }
+    match lint.errors.is_empty() {
+        true => Ok(()),
+        false => Err(lint.errors),
+    }
+}
+
+struct Lint<'a, 'env> {
+    globals: &'a Globals<'env>,
+    top: HashSet<Name>,
+    // the local variables in scope, the innermost last
+    locals: Vec<Name>,
+    binding: Name,
+    errors: Vec<LintError>,
+}
+
+impl Lint<'_, '_> {
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Var(name) => {
+                if !self.in_scope(name) {
+                    self.error(format!("Variable not in scope: `{}`", name));
+                }
+            }
+            Expr::Lit(_) => {}
+            Expr::Lam(param, body) => {
+                self.within(std::slice::from_ref(param), |lint| lint.expr(body))
+            }
+            Expr::App(function, argument) => {
+                self.expr(function);
+                self.expr(argument);
+            }
+            Expr::Let(bind, body) => match &**bind {
+                Bind::NonRec(name, rhs) => {
+                    self.expr(rhs);
+                    self.within(std::slice::from_ref(name), |lint| lint.expr(body));
+                }
+                Bind::Rec(binds) => {
+                    let names: Vec<Name> = binds.iter().map(|(name, _)| name.clone()).collect();
+                    self.within(&names, |lint| {
+                        for (_, rhs) in binds {
+                            lint.expr(rhs);
+                        }
+                        lint.expr(body);
+                    });
+                }
+            },
+            Expr::Case(scrutinee, alts) => {
+                self.expr(scrutinee);
+                self.alts(alts);
+            }
+            Expr::Con(con, fields) => {
+                self.fields(con, fields.len(), "applied to");
+                for field in fields {
+                    self.expr(field);
+                }
+            }
+        }
+    }
+
+    fn alts(&mut self, alts: &[Alt]) {
+        for (i, alt) in alts.iter().enumerate() {
+            if alts[..i].iter().any(|other| other.con == alt.con) {
+                self.error(format!(
+                    "A `case` has two alternatives for `{}`",
+                    alt_con(&alt.con)
+                ));
+            }
+            match &alt.con {
+                AltCon::Con(con) => self.fields(con, alt.binders.len(), "matched with"),
+                AltCon::Default if i + 1 < alts.len() => {
+                    self.error("The default alternative of a `case` is not the last".to_owned())
+                }
+                _ if !alt.binders.is_empty() => self.error(format!(
+                    "The alternative for `{}` binds fields",
+                    alt_con(&alt.con)
+                )),
+                _ => {}
+            }
+            self.within(&alt.binders, |lint| lint.expr(&alt.body));
+        }
+    }
+
+    fn fields(&mut self, con: &str, given: usize, how: &str) {
+        match self.globals.arity(con) {
+            Some(arity) if arity != given => self.error(format!(
+                "The constructor `{}` has {} fields, but is {} {}",
+                con, arity, how, given
+            )),
+            Some(_) => {}
+            None => self.error(format!("Constructor not in scope: `{}`", con)),
+        }
+    }
+
+    // Checks the expressions of a scope binding the names, none of them
+    // twice
+    fn within(&mut self, names: &[Name], check: impl FnOnce(&mut Self)) {
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                self.error(format!("`{}` is bound twice in one scope", name));
+            }
+        }
+        let depth = self.locals.len();
+        self.locals.extend(names.iter().cloned());
+        check(self);
+        self.locals.truncate(depth);
+    }
+
+    fn in_scope(&self, name: &str) -> bool {
+        self.locals.iter().any(|local| local == name)
+            || self.top.contains(name)
+            || self.globals.is_var(name)
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(LintError {
+            message,
+            binding: self.binding.clone(),
+        });
+    }
+}
+
+fn alt_con(con: &AltCon) -> String {
+    match con {
+        AltCon::Con(con) => con.clone(),
+        AltCon::Lit(literal) => literal.to_string(),
+        AltCon::Default => "_".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typecheck::types::{Kind, Ty};
+    use crate::typecheck::{ClassInfo, InstanceInfo};
+    use std::collections::HashMap;
+
+    fn var(name: &str) -> Expr {
+        Expr::var(name)
+    }
+
+    fn lam(param: &str, body: Expr) -> Expr {
+        Expr::Lam(param.to_owned(), Box::new(body))
+    }
+
+    fn alt(con: &str, binders: &[&str], body: Expr) -> Alt {
+        Alt {
+            con: match con {
+                "_" => AltCon::Default,
+                con => AltCon::Con(con.to_owned()),
+            },
+            binders: binders.iter().map(|b| b.to_string()).collect(),
+            body,
+        }
+    }
+
+    fn check(binds: Vec<Bind>) -> Result<(), Vec<String>> {
+        let env = TypeEnv::new();
+        let globals = Globals::new(&env, "Main", vec!["print".to_owned()]);
+        lint(&Program { binds }, &globals)
+            .map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    #[test]
+    fn test_lint() {
+        // `go (x : xs) = go xs; go _ = print (x, 'c')`
+        let go = lam(
+            "xs",
+            Expr::Case(
+                Box::new(var("xs")),
+                vec![
+                    alt(":", &["y", "ys"], Expr::app(var("go"), var("ys"))),
+                    alt(
+                        "_",
+                        &[],
+                        Expr::app(
+                            var("print"),
+                            Expr::Con(
+                                "(,)".to_owned(),
+                                vec![var("y"), Expr::Lit(Literal::Char('c'))],
+                            ),
+                        ),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(
+            check(vec![Bind::Rec(vec![("go".to_owned(), go.clone())])]),
+            Err(vec![
+                "Variable not in scope: `y`\n  In the Core of `go`".to_owned()
+            ])
+        );
+        let fixed = match go {
+            Expr::Lam(xs, body) => match *body {
+                Expr::Case(scrutinee, mut alts) => {
+                    alts[1].binders = vec![];
+                    alts[1].body = Expr::app(var("print"), var(&xs));
+                    lam(&xs, Expr::Case(scrutinee, alts))
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert_eq!(check(vec![Bind::NonRec("go".to_owned(), fixed)]), Ok(()));
+
+        let wrong = Expr::Case(
+            Box::new(Expr::Con("[]".to_owned(), vec![])),
+            vec![
+                alt("_", &[], Expr::Con(":".to_owned(), vec![var("z")])),
+                alt(":", &["z"], var("z")),
+                alt("Nothing", &[], var("z")),
+            ],
+        );
+        let twice = Bind::Rec(vec![
+            ("z".to_owned(), Expr::Lit(Literal::Int(1))),
+            ("z".to_owned(), Expr::Lit(Literal::Int(2))),
+        ]);
+        let wrong = Expr::Let(Box::new(twice), Box::new(wrong));
+        assert_eq!(
+            check(vec![
+                Bind::NonRec("f".to_owned(), wrong),
+                Bind::NonRec("f".to_owned(), var("f")),
+            ]),
+            Err(vec![
+                "`f` is bound twice at the top level\n  In the Core of `f`".to_owned(),
+                "`z` is bound twice in one scope\n  In the Core of `f`".to_owned(),
+                "The default alternative of a `case` is not the last\n  In the Core of `f`"
+                    .to_owned(),
+                "The constructor `:` has 2 fields, but is applied to 1\n  In the Core of `f`"
+                    .to_owned(),
+                "The constructor `:` has 2 fields, but is matched with 1\n  In the Core of `f`"
+                    .to_owned(),
+                "Constructor not in scope: `Nothing`\n  In the Core of `f`".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_instances() {
+        let mut env = TypeEnv::new();
+        let eq = ClassInfo {
+            module: "Prelude".to_owned(),
+            var: "a".to_owned(),
+            kind: Kind::Star,
+            superclasses: vec![],
+            methods: HashMap::new(),
+        };
+        env.classes.insert("Eq".to_owned(), eq);
+        for (tycon, module) in [("Bool", "Prelude"), ("T", "Main")] {
+            let instance = InstanceInfo {
+                module: module.to_owned(),
+                vars: 0,
+                head: Ty::con(tycon),
+                context: vec![],
+            };
+            env.instances.insert(("Eq".to_owned(), tycon.to_owned()), instance);
+        }
+        let globals = Globals::new(&env, "Main", vec![]);
+        let eqs = Expr::Con("(,)".to_owned(), vec![var("$fEqBool"), var("$fEqT")]);
+        let eqs = Bind::NonRec("eqs".to_owned(), eqs);
+        // the module's own instances are defined in its Core
+        let errors = lint(&Program { binds: vec![eqs.clone()] }, &globals).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "Variable not in scope: `$fEqT`\n  In the Core of `eqs`"
+        );
+        let instance = Bind::NonRec("$fEqT".to_owned(), Expr::Con("Eq:Eq".to_owned(), vec![]));
+        let binds = vec![instance, eqs];
+        assert_eq!(lint(&Program { binds }, &globals), Ok(()));
+    }
+}
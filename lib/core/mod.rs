@@ -0,0 +1,161 @@
+pub mod desugar;
+pub mod lint;
+pub mod pretty;
+
+pub use crate::core::desugar::desugar_program;
+pub use crate::core::lint::{lint, Globals, LintError};
+
+use crate::parser::ast::Infix;
+
+/// A variable or constructor of Core, as the renamer left it: `x#3` for a
+/// local, the name as written for a top-level or imported one. The names
+/// the desugarer makes up start with `$`, which no program can write.
+pub type Name = String;
+
+/// The small language every module is desugared into once it type checks.
+/// Patterns, guards, `where`, `do`, comprehensions, sections, records and
+/// classes are all gone: matching is a `case` on one constructor at a time,
+/// and a class constraint is a dictionary passed like any other argument.
+///
+/// The dictionary of a class `C` is built by the constructor `C:C`, whose
+/// fields are the dictionaries of its superclasses, in the order of its
+/// declaration, then its methods by name. An instance `C T` is the
+/// function `$fCT` from the dictionaries of its context and of the
+/// superclasses at `T` to its dictionary, a method `m` selects its field,
+/// `$p1C` the first superclass, and `$dmm` is the default of `m`, given the
+/// dictionary it is a method of.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expr {
+    Var(Name),
+    Lit(Literal),
+    Lam(Name, Box<Expr>),
+    App(Box<Expr>, Box<Expr>),
+    Let(Box<Bind>, Box<Expr>),
+    /// A match on the constructor or literal at the top of the value of the
+    /// scrutinee, the first alternative that applies being taken
+    Case(Box<Expr>, Vec<Alt>),
+    /// A constructor applied to all its fields, the lambda of one applied
+    /// to fewer
+    Con(Name, Vec<Expr>),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Literal {
+    Int(i64),
+    Double(f64),
+    Char(char),
+    String(String),
+}
+
+/// The bindings of a `let` or of the top level. Those of a `Rec` are in
+/// scope in each other; a `NonRec` binding is not in scope in itself.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Bind {
+    NonRec(Name, Expr),
+    Rec(Vec<(Name, Expr)>),
+}
+
+/// `C x y -> e`, binding the fields of the constructor, `3 -> e` or
+/// `_ -> e`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Alt {
+    pub con: AltCon,
+    pub binders: Vec<Name>,
+    pub body: Expr,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum AltCon {
+    Con(Name),
+    Lit(Literal),
+    Default,
+}
+
+/// The bindings of a module in dependency order, each in scope in all of
+/// them. The expressions of a script are bound in order to `$it1`,
+/// `$it2`...
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Program {
+    pub binds: Vec<Bind>,
+}
+
+impl Expr {
+    pub fn var(name: &str) -> Self {
+        Expr::Var(name.to_owned())
+    }
+
+    pub fn app(function: Expr, argument: Expr) -> Self {
+        Expr::App(Box::new(function), Box::new(argument))
+    }
+
+    /// `$error "message"`, the failure of a match no alternative applies to.
+    pub fn error(message: &str) -> Self {
+        Expr::app(
+            Expr::var(ERROR),
+            Expr::Lit(Literal::String(message.to_owned())),
+        )
+    }
+
+    /// Whether evaluating the expression can do no more than look up a
+    /// variable or give a literal, so that it can be copied.
+    pub fn is_trivial(&self) -> bool {
+        matches!(self, Expr::Var(_) | Expr::Lit(_))
+    }
+}
+
+impl Bind {
+    pub fn names(&self) -> Vec<&Name> {
+        match self {
+            Bind::NonRec(name, _) => vec![name],
+            Bind::Rec(binds) => binds.iter().map(|(name, _)| name).collect(),
+        }
+    }
+}
+
+/// Fails with its message.
+pub const ERROR: &str = "$error";
+
+/// Negates a number of any type, as the evaluator does `- e`.
+pub const NEGATE: &str = "$negate";
+
+/// Makes a Monkey hash of a list of key and value pairs.
+pub const HASH: &str = "$hash";
+
+/// Indexes a Monkey array or hash.
+pub const INDEX: &str = "$index";
+
+/// Applied to the name of a type, reads a value of the type from a string,
+/// as the `read` of the `Read` instances built into the evaluator.
+pub const READ: &str = "$read";
+
+/// The names a Core program uses that nothing binds: those above, the
+/// methods numeric literals are elaborated into, the dictionaries `$dynC`
+/// that pick the instance of `C` from the arguments of each method, as for
+/// Monkey values, and the operators built into the evaluator.
+pub fn is_primitive(name: &str) -> bool {
+    matches!(
+        name,
+        ERROR | NEGATE | HASH | INDEX | READ | "$fromInteger" | "$fromRational"
+    ) || name.starts_with("$dyn")
+        || !matches!(Infix::from_name(name), Infix::Op(_))
+}
+
+/// The name of the constructor of the dictionaries of a class.
+pub fn dictionary_con(class: &str) -> Name {
+    format!("{}:{}", class, class)
+}
+
+/// The name of the function building the dictionary of an instance.
+pub fn instance_fun(class: &str, tycon: &str) -> Name {
+    format!("$f{}{}", class, tycon)
+}
+
+/// The name of the selector of the `n`-th superclass of a class, from 1.
+pub fn superclass_selector(class: &str, n: usize) -> Name {
+    format!("$p{}{}", n, class)
+}
+
+/// The name of the default of a method, a function of the dictionary.
+pub fn default_method(method: &str) -> Name {
+    format!("$dm{}", method)
+}
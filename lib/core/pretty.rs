@@ -0,0 +1,163 @@
+use crate::core::*;
+use std::fmt;
+
+/// Core as text, in the layout of GHC's `-ddump-ds`: the alternatives of a
+/// `case` on lines of their own, indented under it, and the body of a `let`
+/// on the line after its binding. Operators, constructors included, are
+/// written in parentheses.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let binds: Vec<String> = self.binds.iter().map(top_level).collect();
+        write!(f, "{}", binds.join("\n\n"))
+    }
+}
+
+impl fmt::Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", top_level(self))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", expr(self, 0))
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Int(i) => write!(f, "{}", i),
+            Literal::Double(x) => write!(f, "{:?}", x),
+            Literal::Char(c) => write!(f, "{:?}", c),
+            Literal::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+// A recursive group of the top level is written between `Rec {` and `}`
+fn top_level(bind: &Bind) -> String {
+    match bind {
+        Bind::NonRec(name, rhs) => binding(name, rhs, 0),
+        Bind::Rec(binds) => {
+            let binds: Vec<String> = binds
+                .iter()
+                .map(|(name, rhs)| binding(name, rhs, 0))
+                .collect();
+            format!("Rec {{\n{}\n}}", binds.join("\n\n"))
+        }
+    }
+}
+
+fn binding(name: &str, rhs: &Expr, indent: usize) -> String {
+    format!("{} = {}", var(name), expr(rhs, indent + 2))
+}
+
+// The expression, its lines after the first indented by `indent`
+fn expr(e: &Expr, indent: usize) -> String {
+    match e {
+        Expr::Var(name) => var(name),
+        Expr::Lit(literal) => literal.to_string(),
+        Expr::Con(con, fields) if fields.is_empty() => var(con),
+        Expr::Con(con, fields) => {
+            let fields: Vec<String> = fields.iter().map(|field| atom(field, indent)).collect();
+            format!("{} {}", var(con), fields.join(" "))
+        }
+        Expr::App(..) => {
+            let mut arguments = vec![];
+            let mut function = e;
+            while let Expr::App(f, argument) = function {
+                arguments.push(argument);
+                function = f;
+            }
+            let mut text = atom(function, indent);
+            for argument in arguments.into_iter().rev() {
+                text.push(' ');
+                text.push_str(&atom(argument, indent));
+            }
+            text
+        }
+        Expr::Lam(..) => {
+            let mut params = vec![];
+            let mut body = e;
+            while let Expr::Lam(param, inner) = body {
+                params.push(var(param));
+                body = inner;
+            }
+            format!("\\{} ->{}", params.join(" "), below(body, indent + 2))
+        }
+        Expr::Let(bind, body) => {
+            let bind = match &**bind {
+                Bind::NonRec(name, rhs) => format!("let {}", binding(name, rhs, indent + 4)),
+                Bind::Rec(binds) => {
+                    let binds: Vec<String> = binds
+                        .iter()
+                        .map(|(name, rhs)| {
+                            format!("\n{}{}", pad(indent + 2), binding(name, rhs, indent + 2))
+                        })
+                        .collect();
+                    format!("letrec{}", binds.concat())
+                }
+            };
+            format!("{} in\n{}{}", bind, pad(indent), expr(body, indent))
+        }
+        Expr::Case(scrutinee, alts) => {
+            let mut text = format!("case {} of", expr(scrutinee, indent + 5));
+            for alt in alts {
+                text.push_str(&format!(
+                    "\n{}{} ->{}",
+                    pad(indent + 2),
+                    alt_con(alt),
+                    below(&alt.body, indent + 4)
+                ));
+            }
+            text
+        }
+    }
+}
+
+// An expression after `->`: on the same line, unless it takes lines of its
+// own
+fn below(e: &Expr, indent: usize) -> String {
+    match e {
+        Expr::Let(..) | Expr::Case(..) => format!("\n{}{}", pad(indent), expr(e, indent)),
+        e => format!(" {}", expr(e, indent)),
+    }
+}
+
+// An argument or field, parenthesized unless it is a name or a literal
+fn atom(e: &Expr, indent: usize) -> String {
+    match e {
+        Expr::Var(_) | Expr::Lit(_) => expr(e, indent),
+        Expr::Con(_, fields) if fields.is_empty() => expr(e, indent),
+        e => format!("({})", expr(e, indent + 1)),
+    }
+}
+
+fn alt_con(alt: &Alt) -> String {
+    let con = match &alt.con {
+        AltCon::Con(con) => var(con),
+        AltCon::Lit(literal) => literal.to_string(),
+        AltCon::Default => "_".to_owned(),
+    };
+    let mut text = con;
+    for binder in &alt.binders {
+        text.push(' ');
+        text.push_str(&var(binder));
+    }
+    text
+}
+
+// `(+)` and `(:)`, but `x#1`, `$fShow[]` and `(,)`
+fn var(name: &str) -> String {
+    let symbolic = !name
+        .starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | '(' | '[' | '\''));
+    match symbolic {
+        true => format!("({})", name),
+        false => name.to_owned(),
+    }
+}
+
+fn pad(indent: usize) -> String {
+    " ".repeat(indent)
+}
@@ -0,0 +1,324 @@
+use crate::evaluator::object::*;
+use crate::parser::ast::*;
+
+pub struct BuiltinsFunctions;
+
+impl Default for BuiltinsFunctions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuiltinsFunctions {
+    pub fn new() -> Self {
+        BuiltinsFunctions {}
+    }
+
+    pub fn get_builtins(&self) -> Vec<(Ident, Object)> {
+        vec![
+            add_builtin("primPrint", 1, bprint_fn),
+            add_builtin("len", 1, blen_fn),
+            add_builtin("head", 1, bhead_fn),
+            add_builtin("tail", 1, btail_fn),
+            add_builtin("cons", 2, bcons_fn),
+            add_builtin(":", 2, bcons_fn),
+            add_builtin("++", 2, bappend_fn),
+            add_builtin("primEq", 2, bprim_eq_fn),
+            add_builtin("primCompare", 2, bprim_compare_fn),
+            add_builtin("primShow", 1, bprim_show_fn),
+            add_builtin("primChr", 1, bprim_chr_fn),
+            add_builtin("primOrd", 1, bprim_ord_fn),
+            add_builtin("primIntToDouble", 1, bprim_int_to_double_fn),
+            add_builtin("primOne", 1, bprim_one_fn),
+            add_builtin("primEnumFromTo", 2, bprim_enum_from_to_fn),
+            add_builtin("primEnumFromThenTo", 3, bprim_enum_from_then_to_fn),
+            add_builtin("primDiv", 2, bprim_div_fn),
+            add_builtin("primMod", 2, bprim_mod_fn),
+            add_builtin("error", 1, berror_fn),
+        ]
+    }
+}
+
+fn add_builtin(name: &str, param_num: usize, func: BuiltinFunction) -> (Ident, Object) {
+    let name = name.to_owned();
+    (Ident(name.clone()), Object::Builtin(name, param_num, func))
+}
+
+fn bprint_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::String(t)) => {
+            println!("{}", t);
+            Ok(Object::Null)
+        }
+        Some(o) => {
+            println!("{}", o);
+            Ok(Object::Null)
+        }
+        _ => Err(String::from("invalid arguments for print")),
+    }
+}
+
+fn blen_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::String(s)) => Ok(Object::Integer(s.len() as i64)),
+        Some(Object::Array(arr)) => Ok(Object::Integer(arr.len() as i64)),
+        _ => Err(String::from("invalid arguments for len")),
+    }
+}
+
+fn bhead_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.into_iter().next() {
+        Some(Object::Array(arr)) => match arr.into_iter().next() {
+            None => Err(String::from("empty array")),
+            Some(x) => Ok(x),
+        },
+        Some(Object::Cons(head, _)) => Ok(*head),
+        _ => Err(String::from("invalid arguments for head")),
+    }
+}
+
+fn btail_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.into_iter().next() {
+        Some(Object::Array(mut arr)) => match arr.len() {
+            0 => Err(String::from("empty array")),
+            _ => {
+                arr.remove(0);
+                Ok(Object::Array(arr))
+            }
+        },
+        Some(Object::Cons(_, tail)) => Ok(*tail),
+        _ => Err(String::from("invalid arguments for tail")),
+    }
+}
+
+pub fn bcons_fn(args: Vec<Object>) -> Result<Object, String> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(o), Some(Object::Array(mut os))) => {
+            os.insert(0, o);
+            Ok(Object::Array(os))
+        }
+        (Some(Object::Char(c)), Some(Object::String(s))) => Ok(Object::String(format!("{}{}", c, s))),
+        (Some(o), Some(Object::String(s))) => {
+            let mut os: Vec<Object> = s.chars().map(Object::Char).collect();
+            os.insert(0, o);
+            Ok(Object::Array(os))
+        }
+        _ => Err(String::from("invalid arguments for cons")),
+    }
+}
+
+pub fn bappend_fn(args: Vec<Object>) -> Result<Object, String> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(Object::Array(mut xs)), Some(Object::Array(ys))) => {
+            xs.extend(ys);
+            Ok(Object::Array(xs))
+        }
+        (Some(Object::String(xs)), Some(Object::String(ys))) => Ok(Object::String(xs + &ys)),
+        (Some(Object::String(xs)), Some(Object::Array(ys)))
+        | (Some(Object::Array(ys)), Some(Object::String(xs))) if ys.is_empty() => {
+            Ok(Object::String(xs))
+        }
+        _ => Err(String::from("invalid arguments for ++")),
+    }
+}
+
+fn bprim_eq_fn(args: Vec<Object>) -> Result<Object, String> {
+    match (args.first(), args.get(1)) {
+        (Some(x), Some(y)) => Ok(Object::Boolean(x == y)),
+        _ => Err(String::from("invalid arguments for primEq")),
+    }
+}
+
+fn bprim_compare_fn(args: Vec<Object>) -> Result<Object, String> {
+    let ordering = match (args.first(), args.get(1)) {
+        (Some(Object::Integer(x)), Some(Object::Integer(y))) => x.cmp(y),
+        (Some(Object::Float(x)), Some(Object::Float(y))) => x
+            .partial_cmp(y)
+            .ok_or_else(|| String::from("NaN is not comparable"))?,
+        (Some(Object::Char(x)), Some(Object::Char(y))) => x.cmp(y),
+        (Some(Object::Boolean(x)), Some(Object::Boolean(y))) => x.cmp(y),
+        (Some(Object::String(x)), Some(Object::String(y))) => x.cmp(y),
+        (Some(Object::Integer(_)), Some(o)) | (Some(o), Some(Object::Integer(_))) => {
+            return Err(format!("{} is not an integer", o))
+        }
+        (Some(Object::Boolean(_)), Some(o)) | (Some(o), Some(Object::Boolean(_))) => {
+            return Err(format!("{} is not a bool", o))
+        }
+        _ => return Err(String::from("invalid arguments for primCompare")),
+    };
+    let name = match ordering {
+        std::cmp::Ordering::Less => "LT",
+        std::cmp::Ordering::Equal => "EQ",
+        std::cmp::Ordering::Greater => "GT",
+    };
+    Ok(Object::Data(name.to_owned(), vec![]))
+}
+
+// Haskell's `show` for the primitive types
+fn bprim_show_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::Integer(i)) => Ok(Object::String(i.to_string())),
+        Some(Object::Float(x)) => Ok(Object::String(format!("{:?}", x))),
+        Some(Object::Boolean(true)) => Ok(Object::String(String::from("True"))),
+        Some(Object::Boolean(false)) => Ok(Object::String(String::from("False"))),
+        Some(Object::Char(c)) => Ok(Object::String(format!("{:?}", c))),
+        Some(Object::String(s)) => Ok(Object::String(format!("{:?}", s))),
+        Some(Object::Array(cs)) => {
+            let mut s = String::new();
+            for c in cs {
+                match c {
+                    Object::Char(c) => s.push(*c),
+                    _ => return Err(String::from("invalid arguments for primShow")),
+                }
+            }
+            Ok(Object::String(format!("{:?}", s)))
+        }
+        Some(Object::Data(name, fields)) if fields.is_empty() => Ok(Object::String(name.clone())),
+        _ => Err(String::from("invalid arguments for primShow")),
+    }
+}
+
+fn bprim_chr_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::Integer(i)) => u32::try_from(*i)
+            .ok()
+            .and_then(char::from_u32)
+            .map(Object::Char)
+            .ok_or_else(|| format!("Prelude.chr: bad argument: {}", i)),
+        _ => Err(String::from("invalid arguments for primChr")),
+    }
+}
+
+fn bprim_ord_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::Char(c)) => Ok(Object::Integer(*c as i64)),
+        _ => Err(String::from("invalid arguments for primOrd")),
+    }
+}
+
+fn bprim_int_to_double_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::Integer(i)) => Ok(Object::Float(*i as f64)),
+        _ => Err(String::from("invalid arguments for primIntToDouble")),
+    }
+}
+
+// The `1` of the number's type, for the Prelude whose literals are integers
+fn bprim_one_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.first() {
+        Some(Object::Float(_)) => Ok(Object::Float(1.0)),
+        Some(_) => Ok(Object::Integer(1)),
+        None => Err(String::from("invalid arguments for primOne")),
+    }
+}
+
+// `div` and `mod` round towards negative infinity, unlike `/` on integers.
+// The quotient is `None` where it overflows, as `minBound `div` (-1)` does.
+fn floor_div(args: &[Object], name: &str) -> Result<(Option<i64>, i64), String> {
+    match (args.first(), args.get(1)) {
+        (Some(Object::Integer(_)), Some(Object::Integer(0))) => Err(String::from("divide by zero")),
+        (Some(Object::Integer(x)), Some(Object::Integer(y))) => {
+            let r = x.checked_rem(*y).unwrap_or(0);
+            let floor = r != 0 && (r < 0) != (*y < 0);
+            let q = x.checked_div(*y).map(|q| if floor { q - 1 } else { q });
+            Ok((q, if floor { r + y } else { r }))
+        }
+        _ => Err(format!("invalid arguments for {}", name)),
+    }
+}
+
+fn bprim_div_fn(args: Vec<Object>) -> Result<Object, String> {
+    let (q, _) = floor_div(&args, "primDiv")?;
+    q.map(Object::Integer).ok_or_else(|| String::from("arithmetic overflow"))
+}
+
+fn bprim_mod_fn(args: Vec<Object>) -> Result<Object, String> {
+    floor_div(&args, "primMod").map(|(_, r)| Object::Integer(r))
+}
+
+// `[x .. z]`, without working out the `x + 1` that `[x, x + 1 .. z]` would
+// need past the largest integer
+fn bprim_enum_from_to_fn(args: Vec<Object>) -> Result<Object, String> {
+    match (args.first(), args.get(1)) {
+        (Some(Object::Integer(x)), Some(Object::Integer(z))) => Ok(int_range(*x, 1, *z)),
+        (Some(Object::Float(x)), Some(Object::Float(z))) => {
+            float_range(vec![Object::Float(*x), Object::Float(1.0), Object::Float(z + 0.5)])
+        }
+        _ => Err(String::from("invalid arguments for primEnumFromTo")),
+    }
+}
+
+// `[x, y .. z]` for integers and floats, where floats go up to half a step
+// past the limit as in the Haskell report. The list is lazy, its tail the
+// rest of the range applied to the next element.
+fn bprim_enum_from_then_to_fn(args: Vec<Object>) -> Result<Object, String> {
+    match (args.first(), args.get(1), args.get(2)) {
+        (Some(Object::Integer(x)), Some(Object::Integer(y)), Some(Object::Integer(z))) => {
+            let step = y
+                .checked_sub(*x)
+                .ok_or_else(|| String::from("enumeration step out of range"))?;
+            Ok(int_range(*x, step, *z))
+        }
+        (Some(Object::Float(x)), Some(Object::Float(y)), Some(Object::Float(z))) => {
+            let step = y - x;
+            let limit = z + step / 2.0;
+            float_range(vec![Object::Float(*x), Object::Float(step), Object::Float(limit)])
+        }
+        _ => Err(String::from("invalid arguments for primEnumFromThenTo")),
+    }
+}
+
+// From `n` by `step` up or down to `z`, and no further than the integers go
+fn int_range(n: i64, step: i64, z: i64) -> Object {
+    if (step >= 0 && n > z) || (step < 0 && n < z) {
+        return Object::Array(vec![]);
+    }
+    let tail = match n.checked_add(step) {
+        Some(next) => {
+            let rest = Object::Builtin("intRange".to_owned(), 3, bint_range_fn);
+            let args = vec![Object::Integer(next), Object::Integer(step), Object::Integer(z)];
+            Object::thunk(Thunk::Apply(rest, args))
+        }
+        None => Object::Array(vec![]),
+    };
+    Object::Cons(Box::new(Object::Integer(n)), Box::new(tail))
+}
+
+fn bint_range_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.as_slice() {
+        [Object::Integer(n), Object::Integer(step), Object::Integer(z)] => {
+            Ok(int_range(*n, *step, *z))
+        }
+        _ => Err(String::from("invalid arguments for intRange")),
+    }
+}
+
+// The `k`-th element on is `x + k * step`, which doesn't add up the error
+// of adding the step over and over
+fn float_range(args: Vec<Object>) -> Result<Object, String> {
+    let (x, step, limit, k) = match args.as_slice() {
+        [Object::Float(x), Object::Float(step), Object::Float(limit)] => (*x, *step, *limit, 0.0),
+        [Object::Float(x), Object::Float(step), Object::Float(limit), Object::Float(k)] => {
+            (*x, *step, *limit, *k)
+        }
+        _ => return Err(String::from("invalid arguments for floatRange")),
+    };
+    let n = x + k * step;
+    if (step > 0.0 && n > limit) || (step < 0.0 && n < limit) {
+        return Ok(Object::Array(vec![]));
+    }
+    let rest = Object::Builtin("floatRange".to_owned(), 4, float_range);
+    let args = [x, step, limit, k + 1.0].map(Object::Float).to_vec();
+    let tail = Object::thunk(Thunk::Apply(rest, args));
+    Ok(Object::Cons(Box::new(Object::Float(n)), Box::new(tail)))
+}
+
+fn berror_fn(args: Vec<Object>) -> Result<Object, String> {
+    match args.into_iter().next() {
+        Some(Object::String(s)) => Err(s),
+        Some(o) => Err(format!("{}", o)),
+        None => Err(String::from("invalid arguments for error")),
+    }
+}
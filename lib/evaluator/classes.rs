@@ -0,0 +1,361 @@
+use crate::evaluator::object::*;
+use crate::parser::ast::*;
+use std::collections::HashMap;
+
+/// Where a method finds the value whose type selects the instance.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Dispatch {
+    /// The argument at this position has the class type, as in `show :: a -> String`.
+    Direct(usize),
+    /// The argument at this position is a list of it, as in `showList :: [a] -> ShowS`.
+    ListElem(usize),
+    /// A component of the tuple at this position, as in `range :: (a, a) -> [a]`.
+    TupleElem(usize, usize),
+}
+
+/// What a method returns, for resolving values whose instance is chosen by
+/// the type they are used at, such as `minBound` or `toEnum 3`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Returns {
+    Class,
+    ListOfClass,
+    Other,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct MethodSig {
+    pub arity: usize,
+    pub dispatch: Vec<Dispatch>,
+    pub returns: Returns,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Class {
+    pub superclasses: Vec<String>,
+    pub methods: HashMap<String, MethodSig>,
+    pub defaults: HashMap<String, Object>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Instance {
+    pub methods: HashMap<String, Object>,
+}
+
+/// The classes, instances and data types declared so far.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ClassEnv {
+    classes: HashMap<String, Class>,
+    instances: HashMap<(String, String), Instance>,
+    datatypes: HashMap<String, DataDecl>,
+    constructors: HashMap<String, String>,
+}
+
+impl ClassEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_datatype(&mut self, decl: &DataDecl) {
+        let Ident(ref name) = decl.name;
+        for ConDecl {
+            name: Ident(con), ..
+        } in &decl.constructors
+        {
+            self.constructors.insert(con.clone(), name.clone());
+        }
+        self.datatypes.insert(name.clone(), decl.clone());
+    }
+
+    pub fn datatype(&self, name: &str) -> Option<&DataDecl> {
+        self.datatypes.get(name)
+    }
+
+    pub fn constructor_type(&self, con: &str) -> Option<&str> {
+        self.constructors.get(con).map(String::as_str)
+    }
+
+    pub fn add_class(&mut self, name: &str, class: Class) {
+        self.classes.insert(name.to_owned(), class);
+    }
+
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes.get(name)
+    }
+
+    pub fn add_instance(&mut self, class: &str, tycon: &str, instance: Instance) {
+        self.instances
+            .insert((class.to_owned(), tycon.to_owned()), instance);
+    }
+
+    pub fn has_instance(&self, class: &str, tycon: &str) -> bool {
+        self.instances
+            .contains_key(&(class.to_owned(), tycon.to_owned()))
+    }
+
+    /// The names an object's type may be given in an instance head, most
+    /// specific first.
+    pub fn type_names(&self, object: &Object) -> Vec<String> {
+        let names: &[&str] = match object {
+            Object::Integer(_) => &["Integer", "Int"],
+            Object::Float(_) => &["Double", "Float"],
+            Object::Boolean(_) => &["Bool"],
+            Object::Char(_) => &["Char"],
+            Object::String(_) | Object::Array(_) | Object::Cons(..) | Object::Thunk(_) => &["[]"],
+            Object::Hash(_) => &["Hash"],
+            Object::Null => &["()"],
+            Object::Data(ref con, _) => {
+                return vec![self
+                    .constructors
+                    .get(con)
+                    .cloned()
+                    .unwrap_or_else(|| con.clone())]
+            }
+            _ => &["->"],
+        };
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// The name of the type of the values a pattern matches, when it
+    /// constrains it.
+    pub fn pattern_type(&self, pattern: &Pattern) -> Option<String> {
+        let name = match pattern {
+            Pattern::ListPat(_) | Pattern::LitPat(Literal::StringLiteral(_)) => "[]",
+            Pattern::LitPat(Literal::IntLiteral(_)) => "Integer",
+            Pattern::LitPat(Literal::FloatLiteral(_)) => "Double",
+            Pattern::LitPat(Literal::CharLiteral(_)) => "Char",
+            Pattern::LitPat(Literal::BoolLiteral(_)) => "Bool",
+            Pattern::ConPat(Ident(con), _) if con == ":" => "[]",
+            Pattern::ConPat(con, _) => self.constructor_type(con.unqualified())?,
+            Pattern::TuplePat(patterns) => return Some(Ident::tuple_con(patterns.len()).0),
+            _ => return None,
+        };
+        Some(name.to_owned())
+    }
+
+    /// The instance of a class for the first of the names that has one.
+    pub fn instance(&self, class: &str, names: &[String]) -> Option<&Instance> {
+        names
+            .iter()
+            .find_map(|name| self.instances.get(&(class.to_owned(), name.clone())))
+    }
+
+    /// Finds the implementation of a method for the given type, falling back
+    /// to the class default when the instance does not define it.
+    pub fn lookup(&self, class: &str, method: &str, names: &[String]) -> Result<Object, String> {
+        for name in names {
+            if let Some(instance) = self.instances.get(&(class.to_owned(), name.clone())) {
+                return match instance.methods.get(method) {
+                    Some(o) => Ok(o.clone()),
+                    None => self.default_method(class, method, name),
+                };
+            }
+        }
+        Err(format!(
+            "No instance for ({} {}) arising from a use of `{}`",
+            class,
+            names.first().map(String::as_str).unwrap_or("a"),
+            method
+        ))
+    }
+
+    pub fn default_method(&self, class: &str, method: &str, tycon: &str) -> Result<Object, String> {
+        self.classes
+            .get(class)
+            .and_then(|c| c.defaults.get(method))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "No instance nor default method for class operation `{}` at type {}",
+                    method, tycon
+                )
+            })
+    }
+}
+
+/// The names an instance head may give a type constructor of the type
+/// checker, most specific first: `Int` is `Integer`, `Float` is `Double` and
+/// `instance C String` is for lists.
+pub fn instance_names(tycon: &str) -> Vec<String> {
+    let names: &[&str] = match tycon {
+        "Integer" => &["Integer", "Int"],
+        "Double" => &["Double", "Float"],
+        "[]" => &["[]", "String"],
+        tycon => return vec![tycon.to_owned()],
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// The type checker's name of the type constructor of an instance head,
+/// which `instance_names` gives the other names of.
+pub fn checker_name(tycon: &str) -> &str {
+    match tycon {
+        "Int" => "Integer",
+        "Float" => "Double",
+        "String" => "[]",
+        tycon => tycon,
+    }
+}
+
+/// Describes a method from its signature: the arguments that mention the
+/// class variable and whether the result does.
+pub fn method_sig(var: &Ident, ty: &Type) -> MethodSig {
+    let class_var = Type::VarType(var.clone());
+    let mut direct = vec![];
+    let mut elems = vec![];
+    let mut ty = ty;
+    let mut arity = 0;
+    while let Type::FnType(arg, result) = ty {
+        match **arg {
+            ref v if headed_by(v, &class_var) => direct.push(Dispatch::Direct(arity)),
+            Type::ListType(ref elem) if **elem == class_var => {
+                elems.push(Dispatch::ListElem(arity))
+            }
+            Type::TupleType(ref components) => elems.extend(
+                components
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == class_var)
+                    .map(|(i, _)| Dispatch::TupleElem(arity, i)),
+            ),
+            _ => {}
+        }
+        arity += 1;
+        ty = result;
+    }
+    direct.extend(elems);
+    let returns = match ty {
+        v if headed_by(v, &class_var) => Returns::Class,
+        Type::ListType(elem) if **elem == class_var => Returns::ListOfClass,
+        _ => Returns::Other,
+    };
+    MethodSig {
+        arity,
+        dispatch: direct,
+        returns,
+    }
+}
+
+// `a`, or `m a` for the class variable `m` of a constructor class
+fn headed_by(ty: &Type, class_var: &Type) -> bool {
+    match ty {
+        Type::AppType(f, _) => headed_by(f, class_var),
+        ty => ty == class_var,
+    }
+}
+
+/// The name of the type constructor at the head of an instance type.
+pub fn type_constructor(ty: &Type) -> Option<String> {
+    match ty {
+        Type::ConType(Ident(name)) => Some(name.clone()),
+        Type::ListType(_) => Some("[]".to_owned()),
+        Type::FnType(_, _) => Some("->".to_owned()),
+        Type::TupleType(components) => Some(Ident::tuple_con(components.len()).0),
+        Type::AppType(f, _) | Type::ForallType(_, f) => type_constructor(f),
+        Type::VarType(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Type {
+        Type::VarType(Ident(name.to_owned()))
+    }
+
+    fn fun(arg: Type, result: Type) -> Type {
+        Type::FnType(Box::new(arg), Box::new(result))
+    }
+
+    #[test]
+    fn test_method_sig() {
+        let a = Ident("a".to_owned());
+        let string = Type::ConType(Ident("String".to_owned()));
+        assert_eq!(
+            method_sig(&a, &fun(var("a"), string.clone())),
+            MethodSig {
+                arity: 1,
+                dispatch: vec![Dispatch::Direct(0)],
+                returns: Returns::Other,
+            }
+        );
+        assert_eq!(
+            method_sig(
+                &a,
+                &fun(Type::ListType(Box::new(var("a"))), fun(string.clone(), string.clone()))
+            )
+            .dispatch,
+            vec![Dispatch::ListElem(0)]
+        );
+        assert_eq!(
+            method_sig(&a, &fun(var("b"), fun(var("a"), var("a")))),
+            MethodSig {
+                arity: 2,
+                dispatch: vec![Dispatch::Direct(1)],
+                returns: Returns::Class,
+            }
+        );
+        assert_eq!(
+            method_sig(
+                &a,
+                &fun(
+                    Type::TupleType(vec![var("a"), var("a")]),
+                    fun(var("a"), var("b"))
+                )
+            )
+            .dispatch,
+            vec![
+                Dispatch::Direct(1),
+                Dispatch::TupleElem(0, 0),
+                Dispatch::TupleElem(0, 1)
+            ]
+        );
+        // `m a -> (a -> m b) -> m b` in a class of type constructors `m`
+        let m = Ident("m".to_owned());
+        let app = |f: Type, x: Type| Type::AppType(Box::new(f), Box::new(x));
+        assert_eq!(
+            method_sig(
+                &m,
+                &fun(
+                    app(var("m"), var("a")),
+                    fun(fun(var("a"), app(var("m"), var("b"))), app(var("m"), var("b")))
+                )
+            ),
+            MethodSig {
+                arity: 2,
+                dispatch: vec![Dispatch::Direct(0)],
+                returns: Returns::Class,
+            }
+        );
+        assert_eq!(
+            method_sig(&a, &var("a")),
+            MethodSig {
+                arity: 0,
+                dispatch: vec![],
+                returns: Returns::Class,
+            }
+        );
+    }
+
+    #[test]
+    fn test_type_constructor() {
+        let tree = Type::AppType(
+            Box::new(Type::ConType(Ident("Tree".to_owned()))),
+            Box::new(var("a")),
+        );
+        assert_eq!(type_constructor(&tree), Some("Tree".to_owned()));
+        assert_eq!(
+            type_constructor(&Type::ListType(Box::new(var("a")))),
+            Some("[]".to_owned())
+        );
+        assert_eq!(
+            type_constructor(&Type::TupleType(vec![var("a"), var("b")])),
+            Some("(,)".to_owned())
+        );
+        assert_eq!(
+            type_constructor(&Type::TupleType(vec![])),
+            Some("()".to_owned())
+        );
+        assert_eq!(type_constructor(&var("a")), None);
+    }
+}
@@ -0,0 +1,728 @@
+use crate::evaluator::classes::*;
+use crate::evaluator::object::*;
+use crate::lexer::token::*;
+use crate::lexer::*;
+use crate::parser::ast::*;
+use crate::parser::fixity::Fixities;
+use crate::parser::fold::{Fold, Unlocate};
+use crate::parser::Parser;
+
+/// Source of the instance of a stock class derived for a data type, with the
+/// methods defined as in chapter 11 of the Haskell report. `Read` instances
+/// are built in and handled by `read_value` instead.
+pub fn derive_source(class: &str, decl: &DataDecl) -> Result<String, String> {
+    let body = match class {
+        "Eq" => derive_eq(decl),
+        "Ord" => derive_ord(decl),
+        "Show" => derive_show(decl),
+        "Enum" => derive_enum(decl)?,
+        "Bounded" => derive_bounded(decl)?,
+        "Ix" => derive_ix(decl)?,
+        _ => {
+            return Err(format!(
+                "Can't make a derived instance of `{} {}`: `{}` is not a stock derivable class",
+                class, decl.name.0, class
+            ))
+        }
+    };
+    Ok(format!(
+        "instance {} where {{ {} }}",
+        instance_head(class, decl),
+        body.join("; ")
+    ))
+}
+
+/// The instance `derive_source` gives, parsed. Its spans would be those of
+/// the source made up for it, so it has none.
+pub fn derived_instance(
+    class: &str,
+    decl: &DataDecl,
+    fixities: &mut Fixities,
+) -> Result<InstanceDecl, String> {
+    let source = derive_source(class, decl)?;
+    let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(&source).map_err(|_| source.clone())?;
+    let program = match Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)) {
+        Ok((_, program)) => fixities.resolve_program(program)?,
+        Err(_) => return Err(format!("invalid derived instance: {}", source)),
+    };
+    match Unlocate.fold_program(program).into_iter().next() {
+        Some(Stmt::InstanceStmt(instance)) => Ok(instance),
+        _ => Err(format!("invalid derived instance: {}", source)),
+    }
+}
+
+/// The program with the instances it derives written out as instance
+/// declarations, for the passes that need their methods, such as the
+/// desugaring into Core. `Read` instances are built in and stay derived.
+/// `datatype` gives the declarations of the imported types.
+pub fn derive_instances(
+    program: Program,
+    datatype: impl Fn(&str) -> Option<DataDecl>,
+    fixities: &mut Fixities,
+) -> Result<Program, String> {
+    let local: Vec<DataDecl> = program
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::DataStmt(decl) => Some(decl.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut derived = vec![];
+    for stmt in program {
+        match stmt {
+            Stmt::DataStmt(mut decl) => {
+                let (read, classes) = decl.deriving.drain(..).partition(|Ident(c)| c == "Read");
+                decl.deriving = read;
+                let instances = classes
+                    .iter()
+                    .map(|Ident(class)| derived_instance(class, &decl, fixities))
+                    .collect::<Result<Vec<_>, _>>()?;
+                derived.push(Stmt::DataStmt(decl));
+                derived.extend(instances.into_iter().map(Stmt::InstanceStmt));
+            }
+            Stmt::DerivingStmt(head) if head.class.0 != "Read" => {
+                let tycon = type_constructor(&head.ty).unwrap_or_default();
+                let decl = match local.iter().find(|decl| decl.name.0 == tycon) {
+                    Some(decl) => decl.clone(),
+                    None => datatype(&tycon).ok_or_else(|| {
+                        format!(
+                            "Can't make a derived instance of `{} {}`: not a data type",
+                            head.class.0, tycon
+                        )
+                    })?,
+                };
+                let instance = derived_instance(&head.class.0, &decl, fixities)?;
+                derived.push(Stmt::InstanceStmt(InstanceDecl {
+                    context: head.context,
+                    ty: head.ty,
+                    span: head.span,
+                    ..instance
+                }));
+            }
+            stmt => derived.push(stmt),
+        }
+    }
+    Ok(derived)
+}
+
+/// The builtin declaration of the unit type or of the tuples with the given
+/// number of components, as if written `data (,) a b = (,) a b`.
+pub fn tuple_decl(arity: usize) -> DataDecl {
+    let params: Vec<Ident> = (0..arity)
+        .map(|i| Ident(((b'a' + i as u8) as char).to_string()))
+        .collect();
+    DataDecl {
+        name: Ident::tuple_con(arity),
+        constructors: vec![ConDecl {
+            name: Ident::tuple_con(arity),
+            fields: params.iter().cloned().map(Type::VarType).collect(),
+            labels: vec![],
+        }],
+        params,
+        deriving: vec![],
+    }
+}
+
+// `(Eq a, Eq b) => Eq (T a b)`
+fn instance_head(class: &str, decl: &DataDecl) -> String {
+    let Ident(ref name) = decl.name;
+    if decl.params.is_empty() {
+        return format!("{} {}", class, name);
+    }
+    let params: Vec<&str> = decl.params.iter().map(|p| p.0.as_str()).collect();
+    let context: Vec<String> = params.iter().map(|p| format!("{} {}", class, p)).collect();
+    format!(
+        "({}) => {} ({} {})",
+        context.join(", "),
+        class,
+        name,
+        params.join(" ")
+    )
+}
+
+// The constructor applied to numbered variables, `(C x1 x2)` or `(x1, x2)`
+fn con_pattern(con: &ConDecl, var: &str) -> String {
+    if con.fields.is_empty() {
+        return con.name.0.clone();
+    }
+    let vars: Vec<String> = (1..=con.fields.len())
+        .map(|i| format!("{}{}", var, i))
+        .collect();
+    match con.name.tuple_arity() {
+        Some(_) => format!("({})", vars.join(", ")),
+        None => format!("({} {})", con.name.0, vars.join(" ")),
+    }
+}
+
+fn wild_pattern(con: &ConDecl) -> String {
+    if con.fields.is_empty() {
+        return con.name.0.clone();
+    }
+    match con.name.tuple_arity() {
+        Some(n) => format!("({})", vec!["_"; n].join(", ")),
+        None => format!("({}{})", con.name.0, " _".repeat(con.fields.len())),
+    }
+}
+
+fn is_enumeration(decl: &DataDecl) -> bool {
+    !decl.constructors.is_empty() && decl.constructors.iter().all(|c| c.fields.is_empty())
+}
+
+fn constructor_list(decl: &DataDecl) -> String {
+    let names: Vec<&str> = decl.constructors.iter().map(|c| c.name.0.as_str()).collect();
+    format!("[{}]", names.join(", "))
+}
+
+// Local function numbering the constructors in declaration order
+fn tag_where(decl: &DataDecl) -> String {
+    let tags: Vec<String> = decl
+        .constructors
+        .iter()
+        .enumerate()
+        .map(|(i, con)| format!("tag {} = {}", wild_pattern(con), i))
+        .collect();
+    format!("where {{ {} }}", tags.join("; "))
+}
+
+fn not_enumeration(class: &str, decl: &DataDecl) -> String {
+    format!(
+        "Can't make a derived instance of `{} {}`: `{}` must be an enumeration type \
+         (an enumeration consists of one or more nullary constructors)",
+        class, decl.name.0, decl.name.0
+    )
+}
+
+fn derive_eq(decl: &DataDecl) -> Vec<String> {
+    let mut clauses: Vec<String> = decl
+        .constructors
+        .iter()
+        .map(|con| {
+            let fields: Vec<String> = (1..=con.fields.len())
+                .map(|i| format!("x{} == y{}", i, i))
+                .collect();
+            let body = match fields.is_empty() {
+                true => "True".to_owned(),
+                false => fields.join(" && "),
+            };
+            format!("{} == {} = {}", con_pattern(con, "x"), con_pattern(con, "y"), body)
+        })
+        .collect();
+    if decl.constructors.len() != 1 {
+        let result = match decl.constructors.is_empty() {
+            true => "True",
+            false => "False",
+        };
+        clauses.push(format!("_ == _ = {}", result));
+    }
+    clauses
+}
+
+// Constructors compare in declaration order, then field by field
+fn derive_ord(decl: &DataDecl) -> Vec<String> {
+    let mut clauses: Vec<String> = decl
+        .constructors
+        .iter()
+        .map(|con| {
+            let n = con.fields.len();
+            let mut body = match n {
+                0 => "EQ".to_owned(),
+                _ => format!("compare x{} y{}", n, n),
+            };
+            for i in (1..n).rev() {
+                body = format!(
+                    "case compare x{} y{} of {{ EQ -> {}; o -> o }}",
+                    i, i, body
+                );
+            }
+            format!(
+                "compare {} {} = {}",
+                con_pattern(con, "x"),
+                con_pattern(con, "y"),
+                body
+            )
+        })
+        .collect();
+    if decl.constructors.len() > 1 {
+        clauses.push(format!(
+            "compare x y = compare (tag x) (tag y) {}",
+            tag_where(decl)
+        ));
+    }
+    clauses
+}
+
+// Applications are shown at precedence 10, records at 11, as by `showsPrec`
+fn derive_show(decl: &DataDecl) -> Vec<String> {
+    decl.constructors
+        .iter()
+        .map(|con| {
+            let Ident(ref name) = con.name;
+            let n = con.fields.len();
+            if n == 0 {
+                return format!("showsPrec _ {} = showString \"{}\"", name, name);
+            }
+            if con.name.tuple_arity().is_some() {
+                let mut shown = "\")\" ++ s".to_owned();
+                for i in (1..=n).rev() {
+                    let prefix = if i == 1 { "(" } else { "," };
+                    shown = format!("\"{}\" ++ showsPrec 0 x{} ({})", prefix, i, shown);
+                }
+                return format!("showsPrec _ {} = \\s -> {}", con_pattern(con, "x"), shown);
+            }
+            let (open, close, separator, field_prec, paren) = match con.labels.is_empty() {
+                true => (format!("{} ", name), String::new(), " ", 11, "d > 10"),
+                false => (format!("{} {{", name), "}".to_owned(), ", ", 0, "d >= 11"),
+            };
+            let mut shown = match close.is_empty() {
+                true => "s".to_owned(),
+                false => format!("\"{}\" ++ s", close),
+            };
+            for i in (1..=n).rev() {
+                let label = match con.labels.get(i - 1) {
+                    Some(Ident(label)) => format!("{} = ", label),
+                    None => String::new(),
+                };
+                let prefix = match i {
+                    1 => format!("{}{}", open, label),
+                    _ => format!("{}{}", separator, label),
+                };
+                shown = format!(
+                    "\"{}\" ++ showsPrec {} x{} ({})",
+                    prefix, field_prec, i, shown
+                );
+            }
+            format!(
+                "showsPrec d {} = showParen ({}) (\\s -> {})",
+                con_pattern(con, "x"),
+                paren,
+                shown
+            )
+        })
+        .collect()
+}
+
+fn derive_enum(decl: &DataDecl) -> Result<Vec<String>, String> {
+    if !is_enumeration(decl) {
+        return Err(not_enumeration("Enum", decl));
+    }
+    let Ident(ref name) = decl.name;
+    let cons: Vec<&str> = decl.constructors.iter().map(|c| c.name.0.as_str()).collect();
+    let (first, last) = (cons[0], cons[cons.len() - 1]);
+    let all = constructor_list(decl);
+    let from: Vec<String> = cons
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} -> {}", c, i))
+        .collect();
+    let to: Vec<String> = cons
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} -> {}", i, c))
+        .collect();
+    let succ: Vec<String> = cons.windows(2).map(|w| format!("{} -> {}", w[0], w[1])).collect();
+    let pred: Vec<String> = cons.windows(2).map(|w| format!("{} -> {}", w[1], w[0])).collect();
+    let bad = |method: &str| format!("_ -> error \"Prelude.Enum.{}.{}: bad argument\"", name, method);
+    Ok(vec![
+        format!("fromEnum x = case x of {{ {} }}", from.join("; ")),
+        format!("toEnum n = case n of {{ {}; {} }}", to.join("; "), bad("toEnum")),
+        format!("succ x = case x of {{ {} }}", [succ, vec![bad("succ")]].concat().join("; ")),
+        format!("pred x = case x of {{ {} }}", [pred, vec![bad("pred")]].concat().join("; ")),
+        format!("enumFrom x = drop (fromEnum x) {}", all),
+        format!(
+            "enumFromTo x y = take (fromEnum y - fromEnum x + 1) (drop (fromEnum x) {})",
+            all
+        ),
+        format!(
+            "enumFromThen x y = enumFromThenTo x y (if fromEnum y >= fromEnum x then {} else {})",
+            last, first
+        ),
+        format!(
+            "enumFromThenTo x y z = map (\\i -> head (drop i {})) \
+             (numericEnumFromThenTo (fromEnum x) (fromEnum y) (fromEnum z))",
+            all
+        ),
+    ])
+}
+
+fn derive_bounded(decl: &DataDecl) -> Result<Vec<String>, String> {
+    match decl.constructors.as_slice() {
+        [con] => {
+            let Ident(ref name) = con.name;
+            Ok(vec![
+                format!("minBound = {}{}", name, " minBound".repeat(con.fields.len())),
+                format!("maxBound = {}{}", name, " maxBound".repeat(con.fields.len())),
+            ])
+        }
+        cons if is_enumeration(decl) => Ok(vec![
+            format!("minBound = {}", cons[0].name.0),
+            format!("maxBound = {}", cons[cons.len() - 1].name.0),
+        ]),
+        _ => Err(format!(
+            "Can't make a derived instance of `Bounded {}`: `{}` must be an enumeration type \
+             or have precisely one constructor",
+            decl.name.0, decl.name.0
+        )),
+    }
+}
+
+fn derive_ix(decl: &DataDecl) -> Result<Vec<String>, String> {
+    if is_enumeration(decl) {
+        let tags = tag_where(decl);
+        return Ok(vec![
+            format!(
+                "range (x, y) = take (tag y - tag x + 1) (drop (tag x) {}) {}",
+                constructor_list(decl),
+                tags
+            ),
+            format!(
+                "index b@(x, _) i = if inRange b i then tag i - tag x \
+                 else error \"Ix.index: index out of range\" {}",
+                tags
+            ),
+            format!(
+                "inRange (x, y) i = tag x <= tag i && tag i <= tag y {}",
+                tags
+            ),
+        ]);
+    }
+    let con = match decl.constructors.as_slice() {
+        [con] => con,
+        _ => {
+            return Err(format!(
+                "Can't make a derived instance of `Ix {}`: `{}` must be an enumeration type \
+                 or have precisely one constructor",
+                decl.name.0, decl.name.0
+            ))
+        }
+    };
+    let n = con.fields.len();
+    let (l, u, i) = (con_pattern(con, "l"), con_pattern(con, "u"), con_pattern(con, "i"));
+    let mut range = format!("[{}]", i);
+    for k in (1..=n).rev() {
+        range = format!(
+            "concatMap (\\i{} -> {}) (range (l{}, u{}))",
+            k, range, k, k
+        );
+    }
+    let mut index = "index (l1, u1) i1".to_owned();
+    for k in 2..=n {
+        index = format!(
+            "({}) * rangeSize (l{}, u{}) + index (l{}, u{}) i{}",
+            index, k, k, k, k, k
+        );
+    }
+    let in_range: Vec<String> = (1..=n)
+        .map(|k| format!("inRange (l{}, u{}) i{}", k, k, k))
+        .collect();
+    Ok(vec![
+        format!("range ({}, {}) = {}", l, u, range),
+        format!("index ({}, {}) {} = {}", l, u, i, index),
+        format!("inRange ({}, {}) {} = {}", l, u, i, in_range.join(" && ")),
+    ])
+}
+
+/// Parses the text of a value of the given type, as `read` does for derived
+/// and builtin instances of `Read`.
+pub fn read_value(classes: &ClassEnv, ty: &Type, input: &str) -> Result<Object, String> {
+    let no_parse = || String::from("Prelude.read: no parse");
+    let (_, tokens) = Lexer::lex_tokens(input).map_err(|_| no_parse())?;
+    let mut reader = Reader {
+        classes,
+        tokens: &tokens,
+        pos: 0,
+    };
+    let object = reader.read(ty, 0).ok_or_else(no_parse)?;
+    match reader.next() {
+        Some(Token::EOF) => Ok(object),
+        _ => Err(no_parse()),
+    }
+}
+
+struct Reader<'a> {
+    classes: &'a ClassEnv,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        match self.next() {
+            Some(ref t) if *t == token => Some(()),
+            _ => None,
+        }
+    }
+
+    // Reads a value at the given precedence, as `readsPrec` does
+    fn read(&mut self, ty: &Type, prec: usize) -> Option<Object> {
+        if let Some(components) = tuple_components(ty) {
+            return self.read_tuple(&components);
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.next();
+            let object = self.read(ty, 0)?;
+            self.expect(Token::RParen)?;
+            return Some(object);
+        }
+        let (head, args) = split_type(ty);
+        match head {
+            Type::ConType(Ident(ref name)) => match name.as_str() {
+                "Integer" | "Int" => self.read_number(prec, false),
+                "Double" | "Float" => self.read_number(prec, true),
+                "Char" => match self.next()? {
+                    Token::CharLiteral(c) => Some(Object::Char(c)),
+                    _ => None,
+                },
+                "String" => self.read_string(),
+                _ => self.read_data(name, &args, prec),
+            },
+            Type::ListType(elem) => match *elem {
+                Type::ConType(Ident(ref name)) if name == "Char" => self.read_string(),
+                elem => self.read_list(&elem),
+            },
+            _ => self.read_any(prec),
+        }
+    }
+
+    fn read_tuple(&mut self, components: &[Type]) -> Option<Object> {
+        self.expect(Token::LParen)?;
+        let mut fields = vec![];
+        for (i, ty) in components.iter().enumerate() {
+            if i > 0 {
+                self.expect(Token::Comma)?;
+            }
+            fields.push(self.read(ty, 0)?);
+        }
+        self.expect(Token::RParen)?;
+        Some(Object::Data(Ident::tuple_con(components.len()).0, fields))
+    }
+
+    fn read_number(&mut self, prec: usize, float: bool) -> Option<Object> {
+        let negate = self.peek() == Some(&Token::Minus) && prec <= 6;
+        if negate {
+            self.next();
+        }
+        let sign = if negate { -1 } else { 1 };
+        match (self.next()?, float) {
+            (Token::IntLiteral(i), false) => Some(Object::Integer(sign * i)),
+            (Token::IntLiteral(i), true) => Some(Object::Float((sign * i) as f64)),
+            (Token::FloatLiteral(f), true) => Some(Object::Float(sign as f64 * f)),
+            _ => None,
+        }
+    }
+
+    fn read_string(&mut self) -> Option<Object> {
+        match self.next()? {
+            Token::StringLiteral(s) => Some(Object::String(s)),
+            _ => None,
+        }
+    }
+
+    fn read_list(&mut self, elem: &Type) -> Option<Object> {
+        self.expect(Token::LBracket)?;
+        let mut objects = vec![];
+        if self.peek() == Some(&Token::RBracket) {
+            self.next();
+            return Some(Object::Array(objects));
+        }
+        loop {
+            objects.push(self.read(elem, 0)?);
+            match self.next()? {
+                Token::Comma => {}
+                Token::RBracket => return Some(Object::Array(objects)),
+                _ => return None,
+            }
+        }
+    }
+
+    // Reads a value of an unknown type, guessing it from the next token
+    fn read_any(&mut self, prec: usize) -> Option<Object> {
+        let ty = match self.peek()? {
+            Token::IntLiteral(_) | Token::Minus => "Integer",
+            Token::FloatLiteral(_) => "Double",
+            Token::CharLiteral(_) => "Char",
+            Token::StringLiteral(_) => "String",
+            Token::LBracket => return self.read_list(&Type::VarType(Ident("a".to_owned()))),
+            Token::TypeIdent(con) => match self.classes.constructor_type(con) {
+                Some(name) => {
+                    let name = name.to_owned();
+                    return self.read_data(&name, &[], prec);
+                }
+                None => return None,
+            },
+            _ => return None,
+        };
+        self.read(&Type::ConType(Ident(ty.to_owned())), prec)
+    }
+
+    fn read_data(&mut self, name: &str, args: &[Type], prec: usize) -> Option<Object> {
+        let decl = self.classes.datatype(name)?;
+        let con_name = match self.next()? {
+            Token::TypeIdent(con) => con,
+            _ => return None,
+        };
+        let con = decl.constructors.iter().find(|c| c.name.0 == con_name)?;
+        // The parameters of the type stand for its arguments in the fields
+        let subst = |ty: &Type| substitute(ty, &decl.params, args);
+        let mut fields = vec![];
+        if !con.labels.is_empty() {
+            if prec > 11 {
+                return None;
+            }
+            self.expect(Token::LBrace)?;
+            for (i, (Ident(label), ty)) in con.labels.iter().zip(&con.fields).enumerate() {
+                if i > 0 {
+                    self.expect(Token::Comma)?;
+                }
+                match self.next()? {
+                    Token::Ident(ref l) if l == label => {}
+                    _ => return None,
+                }
+                self.expect(Token::Assign)?;
+                fields.push(self.read(&subst(ty), 0)?);
+            }
+            self.expect(Token::RBrace)?;
+        } else {
+            if !con.fields.is_empty() && prec > 10 {
+                return None;
+            }
+            for ty in &con.fields {
+                fields.push(self.read(&subst(ty), 11)?);
+            }
+        }
+        Some(match (name, con_name.as_str()) {
+            ("Bool", "True") => Object::Boolean(true),
+            ("Bool", "False") => Object::Boolean(false),
+            _ => Object::Data(con_name, fields),
+        })
+    }
+}
+
+// The component types of `(a, b)`, also when written `(,) a b`
+fn tuple_components(ty: &Type) -> Option<Vec<Type>> {
+    if let Type::TupleType(components) = ty {
+        return Some(components.clone());
+    }
+    match split_type(ty) {
+        (Type::ConType(con), args) if con.tuple_arity() == Some(args.len()) => Some(args),
+        _ => None,
+    }
+}
+
+// `T a b` as `T` and `[a, b]`
+fn split_type(ty: &Type) -> (Type, Vec<Type>) {
+    match ty {
+        Type::AppType(f, arg) => {
+            let (head, mut args) = split_type(f);
+            args.push((**arg).clone());
+            (head, args)
+        }
+        ty => (ty.clone(), vec![]),
+    }
+}
+
+fn substitute(ty: &Type, params: &[Ident], args: &[Type]) -> Type {
+    match ty {
+        Type::VarType(v) => match params.iter().position(|p| p == v) {
+            Some(i) if i < args.len() => args[i].clone(),
+            _ => ty.clone(),
+        },
+        Type::ConType(_) => ty.clone(),
+        Type::AppType(f, a) => Type::AppType(
+            Box::new(substitute(f, params, args)),
+            Box::new(substitute(a, params, args)),
+        ),
+        Type::FnType(a, r) => Type::FnType(
+            Box::new(substitute(a, params, args)),
+            Box::new(substitute(r, params, args)),
+        ),
+        Type::ListType(t) => Type::ListType(Box::new(substitute(t, params, args))),
+        Type::TupleType(ts) => {
+            Type::TupleType(ts.iter().map(|t| substitute(t, params, args)).collect())
+        }
+        Type::ForallType(vars, t) => {
+            Type::ForallType(vars.clone(), Box::new(substitute(t, params, args)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color() -> DataDecl {
+        let con = |name: &str| ConDecl {
+            name: Ident(name.to_owned()),
+            fields: vec![],
+            labels: vec![],
+        };
+        DataDecl {
+            name: Ident("Color".to_owned()),
+            params: vec![],
+            constructors: vec![con("Red"), con("Green")],
+            deriving: vec![],
+        }
+    }
+
+    #[test]
+    fn test_derive_source() {
+        assert_eq!(
+            derive_source("Eq", &color()).unwrap(),
+            "instance Eq Color where { Red == Red = True; Green == Green = True; _ == _ = False }"
+        );
+        assert_eq!(
+            derive_source("Bounded", &color()).unwrap(),
+            "instance Bounded Color where { minBound = Red; maxBound = Green }"
+        );
+        assert_eq!(
+            derive_source("Show", &tuple_decl(2)).unwrap(),
+            "instance (Show a, Show b) => Show ((,) a b) where { showsPrec _ (x1, x2) = \\s -> \
+             \"(\" ++ showsPrec 0 x1 (\",\" ++ showsPrec 0 x2 (\")\" ++ s)) }"
+        );
+        assert!(derive_source("Functor", &color()).is_err());
+    }
+
+    #[test]
+    fn test_read_value() {
+        let mut classes = ClassEnv::new();
+        classes.add_datatype(&color());
+        let integer = Type::ConType(Ident("Integer".to_owned()));
+        assert_eq!(read_value(&classes, &integer, " (-12) "), Ok(Object::Integer(-12)));
+        assert_eq!(
+            read_value(&classes, &Type::ListType(Box::new(integer)), "[1,2]"),
+            Ok(Object::Array(vec![Object::Integer(1), Object::Integer(2)]))
+        );
+        let color = Type::ConType(Ident("Color".to_owned()));
+        assert_eq!(
+            read_value(&classes, &color, "Green"),
+            Ok(Object::Data("Green".to_owned(), vec![]))
+        );
+        assert_eq!(
+            read_value(&classes, &color, "Blue"),
+            Err("Prelude.read: no parse".to_owned())
+        );
+        assert_eq!(
+            read_value(
+                &classes,
+                &Type::TupleType(vec![color.clone(), color]),
+                "(Red,Green)"
+            ),
+            Ok(Object::Data(
+                "(,)".to_owned(),
+                vec![
+                    Object::Data("Red".to_owned(), vec![]),
+                    Object::Data("Green".to_owned(), vec![])
+                ]
+            ))
+        );
+    }
+}
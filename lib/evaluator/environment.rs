@@ -27,11 +27,11 @@ impl Environment {
         }
     }
 
+    /// Builtins are only stored in the outermost environment, nested scopes
+    /// find them through their parent.
     pub fn new_with_outer(outer: Rc<RefCell<Environment>>) -> Self {
-        let mut hashmap = HashMap::new();
-        Self::fill_env_with_builtins(&mut hashmap);
         Environment {
-            store: hashmap,
+            store: HashMap::new(),
             parent: Some(outer),
         }
     }
@@ -48,6 +48,14 @@ impl Environment {
         self.store.insert(name.to_string(), val);
     }
 
+    /// The names bound in this scope, not in the enclosing ones.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        self.store
+            .iter()
+            .map(|(name, object)| (name.clone(), object.clone()))
+            .collect()
+    }
+
     pub fn get(&self, name: &str) -> Option<Object> {
         match self.store.get(name) {
             Some(o) => Some(o.clone()),
@@ -0,0 +1,2704 @@
+pub mod builtins;
+pub mod classes;
+pub mod deriving;
+pub mod environment;
+pub mod modules;
+pub mod object;
+
+use crate::evaluator::classes::*;
+use crate::evaluator::deriving::*;
+use crate::evaluator::environment::*;
+use crate::evaluator::modules::*;
+use crate::evaluator::object::*;
+use crate::lexer::token::*;
+use crate::lexer::*;
+use crate::parser::ast::*;
+use crate::parser::depend::*;
+use crate::parser::desugar::*;
+use crate::parser::fixity::*;
+use crate::parser::*;
+use crate::typecheck::{ErrorFormat, TypeEnv};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const PRELUDE: &str = include_str!("prelude.hs");
+
+/// The largest tuples with builtin instances, the minimum the report asks for.
+const MAX_TUPLE: usize = 15;
+
+pub struct Evaluator {
+    env: Rc<RefCell<Environment>>,
+    classes: ClassEnv,
+    fixities: Fixities,
+    modules: Modules,
+    types: TypeEnv,
+    error_format: ErrorFormat,
+    // the warnings of the modules and lines checked, rendered
+    warnings: Vec<String>,
+}
+
+fn parse_prelude() -> Program {
+    let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(PRELUDE).expect("prelude lexes");
+    let (_, prelude) =
+        Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).expect("prelude parses");
+    prelude
+}
+
+// The dictionary of a class whose methods pick their instances from their
+// arguments
+fn dynamic_dictionary(class: &str) -> Object {
+    Object::Dictionary(Rc::new(Dictionary {
+        class: class.to_owned(),
+        tycon: None,
+        context: vec![],
+        superclasses: vec![],
+    }))
+}
+
+// The type a dictionary of `Read` is for: `[Double]` for the instance
+// `Read [a]` with the dictionary of `Read Double` for its context
+fn dictionary_type(dict: &Dictionary) -> Option<Type> {
+    let tycon = dict.tycon.as_ref()?;
+    let mut args = vec![];
+    for context in &dict.context {
+        match context {
+            Object::Dictionary(context) => args.push(dictionary_type(context)?),
+            _ => return None,
+        }
+    }
+    let con = Ident(tycon.clone());
+    Some(match args.len() {
+        1 if tycon == "[]" => Type::ListType(Box::new(args.remove(0))),
+        n if con.tuple_arity() == Some(n) => Type::TupleType(args),
+        _ => args.into_iter().fold(Type::ConType(con), |f, arg| {
+            Type::AppType(Box::new(f), Box::new(arg))
+        }),
+    })
+}
+
+// Equations without parameters define a constant, not a function
+fn is_constant(clauses: &[Clause]) -> bool {
+    clauses.first().is_some_and(|clause| clause.params.is_empty())
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        let mut evaluator = Evaluator {
+            env: Rc::new(RefCell::new(Environment::new())),
+            classes: ClassEnv::new(),
+            fixities: Fixities::new(),
+            modules: Modules::new(),
+            types: TypeEnv::new(),
+            error_format: ErrorFormat::Human,
+            warnings: vec![],
+        };
+        for arity in [0].into_iter().chain(2..=MAX_TUPLE) {
+            evaluator.register_datatype(&tuple_decl(arity));
+        }
+        let prelude = parse_prelude();
+        if let Object::Error(err) = evaluator.eval_program(prelude.clone()) {
+            panic!("prelude failed to load: {}", err);
+        }
+        evaluator.modules.set_prelude(&evaluator.env, &prelude);
+        // the methods numeric literals are elaborated into, which no import
+        // can hide
+        for (class, method) in [("Num", "fromInteger"), ("Fractional", "fromRational")] {
+            let object = Object::Method(class.to_owned(), method.to_owned());
+            evaluator.env.borrow_mut().set(&format!("${}", method), object);
+        }
+        evaluator
+    }
+
+    fn returned(&mut self, object: Object) -> Object {
+        match object {
+            Object::ReturnValue(v) => *v,
+            o => o,
+        }
+    }
+
+    pub fn eval_program(&mut self, prog: Program) -> Object {
+        let prog = match self.fixities.resolve_program(prog) {
+            Ok(prog) => prog,
+            Err(err) => return Object::Error(err),
+        };
+        if let Err(err) = self.hoist_decls(&prog) {
+            return Object::Error(err);
+        }
+        let return_data = self.eval_groups(prog);
+        let object = self.returned(return_data);
+        // as in GHCi, an action at the top level runs in `IO` and shows its
+        // result
+        let object = match object {
+            Object::Overloaded(ref class, ..) if class == "Monad" => self.force_as(object, "IO"),
+            object => self.force(object),
+        };
+        let object = match object {
+            Object::Data(con, mut fields) if con == "IO" && fields.len() == 1 => fields.remove(0),
+            object => object,
+        };
+        self.force_deep(object)
+    }
+
+    // Data types, classes and instances are in scope in the whole program,
+    // whatever the order they are declared in.
+    fn hoist_decls(&mut self, prog: &Program) -> Result<(), String> {
+        for stmt in prog {
+            if let Stmt::DataStmt(decl) = stmt {
+                self.register_datatype(decl);
+            }
+        }
+        for stmt in prog {
+            if let Stmt::ClassStmt(decl) = stmt {
+                self.register_class(decl)?;
+            }
+        }
+        let mut heads = vec![];
+        for stmt in prog {
+            match stmt {
+                Stmt::InstanceStmt(decl) => heads.push(self.register_instance(decl)?),
+                Stmt::DataStmt(decl) => {
+                    for Ident(class) in &decl.deriving {
+                        heads.push(self.derive_instance(class, decl, None)?);
+                    }
+                }
+                Stmt::DerivingStmt(head) => {
+                    let tycon = type_constructor(&head.ty).unwrap_or_default();
+                    let decl = match self.classes.datatype(&tycon) {
+                        Some(decl) => decl.clone(),
+                        // builtin types only have the builtin `Read` instances
+                        None if head.class.0 == "Read" => DataDecl {
+                            name: Ident(tycon),
+                            params: vec![],
+                            constructors: vec![],
+                            deriving: vec![],
+                        },
+                        None => {
+                            return Err(format!(
+                                "Can't make a derived instance of `{} {}`: not a data type",
+                                head.class.0, tycon
+                            ))
+                        }
+                    };
+                    heads.push(self.derive_instance(&head.class.0, &decl, Some(head))?);
+                }
+                _ => {}
+            }
+        }
+        for (class, tycon) in heads {
+            let superclasses = self
+                .classes
+                .class(&class)
+                .map(|c| c.superclasses.clone())
+                .unwrap_or_default();
+            // `instance C Int` needs the `Eq Integer` of the Prelude
+            let names = instance_names(checker_name(&tycon));
+            for superclass in superclasses {
+                if !names.iter().any(|name| self.classes.has_instance(&superclass, name)) {
+                    return Err(format!(
+                        "No instance for ({} {}) arising from the superclasses of an instance declaration",
+                        superclass, tycon
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn register_datatype(&mut self, decl: &DataDecl) {
+        self.classes.add_datatype(decl);
+        self.register_selectors(decl);
+        for ConDecl {
+            name: Ident(name),
+            fields,
+            ..
+        } in &decl.constructors
+        {
+            // `Bool` is declared in the prelude but represented natively
+            let object = match (decl.name.0.as_str(), name.as_str()) {
+                ("Bool", "True") => Object::Boolean(true),
+                ("Bool", "False") => Object::Boolean(false),
+                _ if fields.is_empty() => Object::Data(name.clone(), vec![]),
+                _ => Object::Constructor(name.clone(), fields.len()),
+            };
+            self.env.borrow_mut().set(name, object);
+        }
+    }
+
+    // Record fields are functions extracting the field from any constructor
+    // that has it.
+    fn register_selectors(&mut self, decl: &DataDecl) {
+        let mut selectors: Vec<(Ident, Vec<Clause>)> = vec![];
+        for con in &decl.constructors {
+            for (i, label) in con.labels.iter().enumerate() {
+                let params = (0..con.fields.len())
+                    .map(|j| match i == j {
+                        true => Pattern::VarPat(label.clone()),
+                        false => Pattern::WildPat,
+                    })
+                    .collect();
+                let clause = Clause {
+                    params: vec![Pattern::ConPat(con.name.clone(), params)],
+                    rhs: Rhs::Plain(Expr::IdentExpr(label.clone())),
+                    wheres: vec![],
+                };
+                match selectors.iter_mut().find(|(l, _)| l == label) {
+                    Some((_, clauses)) => clauses.push(clause),
+                    None => selectors.push((label.clone(), vec![clause])),
+                }
+            }
+        }
+        for (Ident(label), clauses) in selectors {
+            let selector = Object::Closure(clauses, Rc::clone(&self.env));
+            self.env.borrow_mut().set(&label, selector);
+        }
+    }
+
+    // Registers the instance generated for a `deriving` clause, or for a
+    // standalone deriving declaration with its own context and head.
+    fn derive_instance(
+        &mut self,
+        class: &str,
+        decl: &DataDecl,
+        standalone: Option<&InstanceDecl>,
+    ) -> Result<(String, String), String> {
+        let ty = match standalone {
+            Some(head) => head.ty.clone(),
+            None => decl.params.iter().fold(Type::ConType(decl.name.clone()), |f, p| {
+                Type::AppType(Box::new(f), Box::new(Type::VarType(p.clone())))
+            }),
+        };
+        if class == "Read" {
+            if self.classes.class(class).is_none() {
+                return Err(format!("Not a class: `{}`", class));
+            }
+            let mut instance = Instance::default();
+            instance
+                .methods
+                .insert("read".to_owned(), Object::Reader(ty));
+            self.classes.add_instance(class, &decl.name.0, instance);
+            return Ok((class.to_owned(), decl.name.0.clone()));
+        }
+        let mut instance = derived_instance(class, decl, &mut self.fixities)?;
+        if let Some(head) = standalone {
+            instance.context = head.context.clone();
+            instance.ty = head.ty.clone();
+        }
+        self.register_instance(&instance)
+    }
+
+    // Instances of the stock classes for tuples are derived the first time
+    // they are needed rather than for every size up front.
+    fn derive_tuple_instance(&mut self, class: &str, names: &[String]) -> Result<(), String> {
+        let arity = match names.first() {
+            Some(name) if !self.classes.has_instance(class, name) => {
+                match Ident(name.clone()).tuple_arity() {
+                    Some(arity) if arity <= MAX_TUPLE => arity,
+                    _ => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+        // the report only asks for `Ix` instances up to 5 components
+        match class {
+            "Eq" | "Ord" | "Show" | "Read" | "Bounded" => {}
+            "Ix" if arity <= 5 => {}
+            _ => return Ok(()),
+        }
+        self.derive_instance(class, &tuple_decl(arity), None)
+            .map(|_| ())
+    }
+
+    fn register_class(&mut self, decl: &ClassDecl) -> Result<(), String> {
+        let Ident(ref name) = decl.name;
+        let mut class = Class {
+            superclasses: decl.context.iter().map(|p| p.class.0.clone()).collect(),
+            ..Class::default()
+        };
+        let env = self.dictionaries(&[(Dict::this(), name.clone())]);
+        for stmt in &decl.body {
+            match stmt {
+                Stmt::SigStmt(methods, _, ty, _) => {
+                    for Ident(method) in methods {
+                        class
+                            .methods
+                            .insert(method.clone(), method_sig(&decl.var, ty));
+                        self.env
+                            .borrow_mut()
+                            .set(method, Object::Method(name.clone(), method.clone()));
+                    }
+                }
+                Stmt::FixityStmt(..) => {}
+                stmt => {
+                    let (Ident(method), object) = self.method_binding(stmt, &env)?;
+                    class.defaults.insert(method, object);
+                }
+            }
+        }
+        for method in class.defaults.keys() {
+            if !class.methods.contains_key(method) {
+                return Err(format!(
+                    "The default method `{}` is not a method of class `{}`",
+                    method, name
+                ));
+            }
+        }
+        self.classes.add_class(name, class);
+        Ok(())
+    }
+
+    fn register_instance(&mut self, decl: &InstanceDecl) -> Result<(String, String), String> {
+        let Ident(ref class) = decl.class;
+        let methods = match self.classes.class(class) {
+            Some(c) => c.methods.clone(),
+            None => return Err(format!("Not a class: `{}`", class)),
+        };
+        let tycon = type_constructor(&decl.ty)
+            .ok_or_else(|| format!("Illegal instance declaration for `{} {:?}`", class, decl.ty))?;
+        let mut instance = Instance::default();
+        let context: Vec<(Ident, String)> = decl
+            .context
+            .iter()
+            .enumerate()
+            .map(|(i, pred)| (Dict::context(i), pred.class.0.clone()))
+            .collect();
+        let env = self.dictionaries(&context);
+        for stmt in &decl.body {
+            if let Stmt::SigStmt(..) | Stmt::FixityStmt(..) = stmt {
+                continue;
+            }
+            let (Ident(method), object) = self.method_binding(stmt, &env)?;
+            if !methods.contains_key(&method) {
+                return Err(format!(
+                    "`{}` is not a (visible) method of class `{}`",
+                    method, class
+                ));
+            }
+            instance.methods.insert(method, object);
+        }
+        self.classes.add_instance(class, &tycon, instance);
+        Ok((class.clone(), tycon))
+    }
+
+    // The scope of the methods of a class or an instance, over the top
+    // level: the dictionaries a type-checked module passes them are bound
+    // when a dictionary selects them, and otherwise pick their instances from
+    // the arguments of each method.
+    fn dictionaries(&self, names: &[(Ident, String)]) -> Rc<RefCell<Environment>> {
+        let mut env = Environment::new_with_outer(Rc::clone(&self.env));
+        for (Ident(name), class) in names {
+            env.set(name, dynamic_dictionary(class));
+        }
+        Rc::new(RefCell::new(env))
+    }
+
+    // Method bodies close over the scope of their class or instance;
+    // constant bindings are evaluated each time the method is used.
+    fn method_binding(
+        &self,
+        stmt: &Stmt,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<(Ident, Object), String> {
+        match stmt {
+            Stmt::FunStmt(name, clauses) => Ok((
+                name.clone(),
+                Object::Closure(clauses.clone(), Rc::clone(env)),
+            )),
+            Stmt::LetStmt(name, expr) => {
+                let clause = Clause {
+                    params: vec![],
+                    rhs: Rhs::Plain(expr.clone()),
+                    wheres: vec![],
+                };
+                Ok((name.clone(), Object::Closure(vec![clause], Rc::clone(env))))
+            }
+            stmt => Err(format!("unexpected declaration in class or instance: {:?}", stmt)),
+        }
+    }
+
+    pub fn eval_blockstmt(&mut self, mut prog: Program) -> Object {
+        match prog.len() {
+            0 => Object::Null,
+            1 => self.eval_statement(prog.remove(0)),
+            _ => {
+                let s = prog.remove(0);
+                let object = self.eval_statement(s);
+                if object.is_returned() {
+                    object
+                } else {
+                    self.eval_blockstmt(prog)
+                }
+            }
+        }
+    }
+
+    pub fn eval_statement(&mut self, stmt: Stmt) -> Object {
+        match stmt {
+            Stmt::ExprStmt(expr) => self.eval_expr(expr),
+            Stmt::ReturnStmt(expr) => Object::ReturnValue(Box::new(self.eval_expr(expr))),
+            Stmt::LetStmt(ident, expr) => {
+                let object = self.eval_expr(expr);
+                self.register_ident(ident, object)
+            }
+            // a constant with guards or a `where` is evaluated as a `let` is
+            Stmt::FunStmt(ident, clauses) if is_constant(&clauses) => {
+                let closure = Object::Closure(clauses, Rc::clone(&self.env));
+                let object = self.apply(closure, vec![]);
+                self.register_ident(ident, object)
+            }
+            Stmt::FunStmt(ident, clauses) => {
+                let closure = Object::Closure(clauses, Rc::clone(&self.env));
+                self.register_ident(ident, closure);
+                Object::Null
+            }
+            Stmt::PatStmt(..) => self.eval_groups(vec![stmt]),
+            Stmt::SigStmt(..)
+            | Stmt::DataStmt(_)
+            | Stmt::TypeStmt(..)
+            | Stmt::ClassStmt(_)
+            | Stmt::InstanceStmt(_)
+            | Stmt::DerivingStmt(_)
+            | Stmt::FixityStmt(..)
+            | Stmt::DefaultStmt(_) => Object::Null,
+            Stmt::ErrorStmt(error) => Object::Error(error.to_string()),
+        }
+    }
+
+    // Runs the statements of a program with its bindings in dependency
+    // order. The bindings of a recursive group are all bound before any of
+    // them is evaluated, the values as thunks.
+    fn eval_groups(&mut self, prog: Program) -> Object {
+        let mut object = Object::Null;
+        for group in dependency_order(prog) {
+            object = match group {
+                Group::Stmt(stmt) => self.eval_statement(stmt),
+                Group::Bindings {
+                    bindings,
+                    recursive: false,
+                } => self.eval_blockstmt(bindings),
+                Group::Bindings { bindings, .. } => {
+                    for binding in bindings {
+                        match binding {
+                            Stmt::LetStmt(ident, expr) => {
+                                let thunk = Thunk::Pending(expr, Rc::clone(&self.env));
+                                self.register_ident(ident, Object::thunk(thunk));
+                            }
+                            Stmt::FunStmt(ident, clauses) if is_constant(&clauses) => {
+                                let closure = Object::Closure(clauses, Rc::clone(&self.env));
+                                let thunk = Thunk::Apply(closure, vec![]);
+                                self.register_ident(ident, Object::thunk(thunk));
+                            }
+                            stmt => {
+                                self.eval_statement(stmt);
+                            }
+                        }
+                    }
+                    Object::Null
+                }
+            };
+            if object.is_returned() {
+                return object;
+            }
+        }
+        object
+    }
+
+    // Binds the declarations of a `let` or `where` in the current scope
+    fn bind_decls(&mut self, decls: Program) {
+        self.eval_groups(decls);
+    }
+
+    pub fn register_ident(&mut self, ident: Ident, object: Object) -> Object {
+        let Ident(name) = ident;
+        self.env.borrow_mut().set(&name, object.clone());
+        object
+    }
+
+    pub fn eval_expr(&mut self, mut expr: Expr) -> Object {
+        // spans are for the type checker; skipped in a loop rather than by a
+        // call, so that they don't deepen the evaluator's recursion
+        while let Expr::Located(_, located) = expr {
+            expr = *located;
+        }
+        match expr {
+            Expr::Located(..) => unreachable!("spans are skipped above"),
+            Expr::IdentExpr(i) => self.eval_ident(i),
+            Expr::HoleExpr(i) => self.eval_hole(i),
+            Expr::LitExpr(l) => self.eval_literal(l),
+            Expr::PrefixExpr(prefix, expr) => self.eval_prefix(&prefix, *expr),
+            Expr::InfixExpr(infix, expr1, expr2) => self.eval_infix(&infix, *expr1, *expr2),
+            Expr::InfixChain(..) => Object::Error(String::from("unresolved operator chain")),
+            Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            } => self.eval_if(*cond, consequence, alternative),
+            Expr::FnExpr { params, body } => self.eval_fn(params, body),
+            Expr::CallExpr {
+                function: fn_exp,
+                arguments,
+            } => self.eval_call(*fn_exp, arguments),
+            Expr::ArrayExpr(exprs) => self.eval_array(exprs),
+            Expr::HashExpr(hash_exprs) => self.eval_hash(hash_exprs),
+            Expr::IndexExpr { array, index } => self.eval_index(*array, *index),
+            Expr::LambdaExpr { params, body } => {
+                let clause = Clause {
+                    params,
+                    rhs: Rhs::Plain(*body),
+                    wheres: vec![],
+                };
+                Object::Closure(vec![clause], Rc::clone(&self.env))
+            }
+            Expr::CaseExpr { scrutinee, alts } => self.eval_case(*scrutinee, alts),
+            Expr::TupleExpr(exprs) => {
+                let con = Ident::tuple_con(exprs.len()).0;
+                Object::Data(con, exprs.into_iter().map(|e| self.eval_expr(e)).collect())
+            }
+            Expr::LeftSectionExpr(expr, infix) => match self.eval_expr(*expr) {
+                Object::Error(s) => Object::Error(s),
+                left => self.section(infix, Some(left), None),
+            },
+            Expr::RightSectionExpr(infix, expr) => match self.eval_expr(*expr) {
+                Object::Error(s) => Object::Error(s),
+                right => self.section(infix, None, Some(right)),
+            },
+            Expr::ListCompExpr { expr, qualifiers } => {
+                self.eval_expr(desugar_comprehension(*expr, qualifiers))
+            }
+            Expr::DoExpr(stmts) => self.eval_expr(desugar_do(stmts)),
+            Expr::LambdaCaseExpr(alts) => self.eval_expr(desugar_lambda_case(alts)),
+            Expr::MultiIfExpr(guards) => self.eval_expr(desugar_multi_way_if(guards)),
+            Expr::TupleSectionExpr(components) => {
+                self.eval_expr(desugar_tuple_section(components))
+            }
+            Expr::TypedExpr(expr, _, ty) => {
+                let object = self.eval_expr(*expr);
+                self.annotate(object, &ty)
+            }
+            Expr::DictExpr(dict) => self.eval_dict(dict),
+            Expr::LetExpr { bindings, body } => {
+                let old_env = Rc::clone(&self.env);
+                let new_env = Environment::new_with_outer(Rc::clone(&old_env));
+                self.env = Rc::new(RefCell::new(new_env));
+                self.bind_decls(bindings);
+                let object = self.eval_expr(*body);
+                self.env = old_env;
+                object
+            }
+        }
+    }
+
+    pub fn eval_case(&mut self, scrutinee: Expr, alts: Vec<Alt>) -> Object {
+        let object = self.eval_expr(scrutinee);
+        if let Object::Error(_) = object {
+            return object;
+        }
+        let env = Rc::clone(&self.env);
+        for alt in alts {
+            let mut bindings = vec![];
+            if self.match_pattern(&alt.pat, &object, &mut bindings) {
+                if let Some(o) = self.eval_rhs(&env, bindings, alt.rhs, alt.wheres) {
+                    return o;
+                }
+            }
+        }
+        Object::Error(String::from("Non-exhaustive patterns in case"))
+    }
+
+    // Evaluates a right hand side in a new scope holding the pattern
+    // bindings and the `where` declarations. Returns `None` when every
+    // guard fails, so that the next equation is tried.
+    fn eval_rhs(
+        &mut self,
+        env: &Rc<RefCell<Environment>>,
+        bindings: Vec<(String, Object)>,
+        rhs: Rhs,
+        wheres: Program,
+    ) -> Option<Object> {
+        let old_env = Rc::clone(&self.env);
+        let mut new_env = Environment::new_with_outer(Rc::clone(env));
+        for (name, o) in bindings {
+            new_env.set(&name, o);
+        }
+        self.env = Rc::new(RefCell::new(new_env));
+        self.bind_decls(wheres);
+        let result = match rhs {
+            Rhs::Plain(expr) => Some(self.eval_expr(expr)),
+            Rhs::Guarded(guards) => {
+                let mut result = None;
+                for (guard, expr) in guards {
+                    match self.eval_expr(guard) {
+                        Object::Boolean(true) => {
+                            result = Some(self.eval_expr(expr));
+                            break;
+                        }
+                        Object::Boolean(false) => {}
+                        o => {
+                            result = Some(self.otb(o).err().unwrap_or(Object::Null));
+                            break;
+                        }
+                    }
+                }
+                result
+            }
+        };
+        self.env = old_env;
+        result
+    }
+
+    pub fn eval_ident(&mut self, ident: Ident) -> Object {
+        let Ident(name) = ident;
+        let var = self.env.borrow().get(&name);
+        match var {
+            // constants like `minBound` take their instance from where they are used
+            Some(Object::Method(class, method)) => match self.classes.class(&class) {
+                Some(c) if c.methods.get(&method).is_some_and(|m| m.arity == 0) => {
+                    Object::Overloaded(class, method, vec![])
+                }
+                _ => Object::Method(class, method),
+            },
+            Some(o) => o,
+            // the built in arithmetic operators have no binding of their own
+            None => match Infix::from_name(&name) {
+                Infix::Op(_) => Object::Error(format!("identifier not found: {}", name)),
+                infix => self.section(infix, None, None),
+            },
+        }
+    }
+
+    // The dictionary a type-checked module passes for a class constraint
+    fn eval_dict(&mut self, dict: Dict) -> Object {
+        match dict {
+            Dict::Instance {
+                class,
+                tycon,
+                context,
+                superclasses,
+            } => {
+                let context = context.into_iter().map(|d| self.eval_dict(d)).collect();
+                let superclasses = superclasses
+                    .into_iter()
+                    .map(|d| self.eval_dict(d))
+                    .collect();
+                Object::Dictionary(Rc::new(Dictionary {
+                    class: class.0,
+                    tycon: Some(tycon.0),
+                    context,
+                    superclasses,
+                }))
+            }
+            Dict::Param(name) => self.eval_ident(name),
+            Dict::Super(Ident(class), _, dict) => match self.eval_dict(*dict) {
+                Object::Dictionary(dict) if dict.tycon.is_none() => dynamic_dictionary(&class),
+                Object::Dictionary(dict) => dict
+                    .superclasses
+                    .iter()
+                    .find(|s| matches!(s, Object::Dictionary(s) if s.class == class))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        Object::Error(format!("No superclass `{}` of `{}`", class, dict.class))
+                    }),
+                object => object,
+            },
+            Dict::Dynamic(Ident(class)) => dynamic_dictionary(&class),
+        }
+    }
+
+    pub fn eval_hole(&mut self, hole: Ident) -> Object {
+        let bound = self.env.borrow().get(&hole.0).is_some();
+        match bound {
+            true => self.eval_ident(hole),
+            false => Object::Error(format!("Found hole: {}", hole.0)),
+        }
+    }
+
+    /// The value of `e :: ty`: the annotation picks the instance of an
+    /// overloaded value such as `read s`, and makes an integer a float at
+    /// `Double` as it would an integer literal. A type variable, scoped or
+    /// not, says nothing at run time.
+    pub fn annotate(&mut self, object: Object, ty: &Type) -> Object {
+        let name = match type_constructor(ty) {
+            Some(name) if name == "String" => "[]".to_owned(),
+            Some(name) => name,
+            None => return object,
+        };
+        match self.force(object) {
+            // `read` parses at the whole type, `[Int]` rather than `[a]`
+            Object::Overloaded(class, method, args) if class == "Read" && method == "read" => {
+                self.apply(Object::Reader(ty.clone()), args)
+            }
+            object @ Object::Overloaded(..) => self.force_as(object, &name),
+            Object::Integer(i) if name == "Double" || name == "Float" => Object::Float(i as f64),
+            object => object,
+        }
+    }
+
+    // The function `\x y -> x op y` with the given operands already bound,
+    // for sections and operators used as values
+    fn section(&mut self, infix: Infix, left: Option<Object>, right: Option<Object>) -> Object {
+        let mut env = Environment::new_with_outer(Rc::clone(&self.env));
+        let mut params = vec![];
+        for (name, operand) in [("$x", left), ("$y", right)] {
+            match operand {
+                Some(o) => env.set(name, o),
+                None => params.push(Pattern::VarPat(Ident(name.to_owned()))),
+            }
+        }
+        let var = |name: &str| Box::new(Expr::IdentExpr(Ident(name.to_owned())));
+        let clause = Clause {
+            params,
+            rhs: Rhs::Plain(Expr::InfixExpr(infix, var("$x"), var("$y"))),
+            wheres: vec![],
+        };
+        Object::Closure(vec![clause], Rc::new(RefCell::new(env)))
+    }
+
+    pub fn eval_literal(&mut self, literal: Literal) -> Object {
+        match literal {
+            Literal::IntLiteral(i) => Object::Integer(i),
+            Literal::BoolLiteral(b) => Object::Boolean(b),
+            Literal::StringLiteral(s) => Object::String(s),
+            Literal::FloatLiteral(f) => Object::Float(f),
+            Literal::CharLiteral(c) => Object::Char(c),
+        }
+    }
+
+    pub fn eval_prefix(&mut self, prefix: &Prefix, expr: Expr) -> Object {
+        let object = self.eval_expr(expr);
+        match *prefix {
+            Prefix::PrefixMinus => match object {
+                Object::Float(f) => Object::Float(-f),
+                object => match self.oti(object) {
+                    Ok(i) => Object::Integer(-i),
+                    Err(err) => err,
+                },
+            },
+        }
+    }
+
+    pub fn eval_infix(&mut self, infix: &Infix, expr1: Expr, expr2: Expr) -> Object {
+        if let Infix::Op(Ident(ref op)) = *infix {
+            match op.as_str() {
+                ":" => return self.eval_cons(expr1, expr2),
+                "++" => {
+                    let xs = self.eval_expr(expr1);
+                    let ys = self.delay(expr2);
+                    return self.append(xs, ys);
+                }
+                _ => {}
+            }
+        }
+        let object1 = self.eval_expr(expr1);
+        let object2 = self.eval_expr(expr2);
+        // an overloaded operand such as `fromIntegral n` takes the type of
+        // the number on the other side
+        let (object1, object2) = match (object1, object2) {
+            (o1 @ Object::Overloaded(..), o2 @ (Object::Integer(_) | Object::Float(_))) => {
+                let names = self.classes.type_names(&o2);
+                (self.resolve(o1, &names), o2)
+            }
+            (o1 @ (Object::Integer(_) | Object::Float(_)), o2 @ Object::Overloaded(..)) => {
+                let names = self.classes.type_names(&o1);
+                (o1, self.resolve(o2, &names))
+            }
+            operands => operands,
+        };
+        if let Infix::Op(_)
+        | Infix::Equal
+        | Infix::NotEqual
+        | Infix::GreaterThanEqual
+        | Infix::LessThanEqual
+        | Infix::GreaterThan
+        | Infix::LessThan = *infix
+        {
+            // Comparisons go through the class methods when they are in scope
+            let op = self.env.borrow().get(infix.name());
+            if let Some(op) = op {
+                return match (object1, object2) {
+                    (Object::Error(s), _) | (_, Object::Error(s)) => Object::Error(s),
+                    (object1, object2) => self.apply(op, vec![object1, object2]),
+                };
+            }
+        }
+        // a value bound in a recursive group is still a thunk
+        let object1 = self.force(object1);
+        let object2 = self.force(object2);
+        if let (Object::Float(_), _) | (_, Object::Float(_)) = (&object1, &object2) {
+            return self.float_arith(infix, object1, object2);
+        }
+        match *infix {
+            // `1+-3` lexes `+-` as one operator
+            Infix::Op(Ident(ref op)) => match op.strip_suffix('-') {
+                Some(prefix) if !matches!(Infix::from_name(prefix), Infix::Op(_)) => {
+                    Object::Error(format!(
+                        "identifier not found: {}; write `x {} (-y)` for a negative operand",
+                        op, prefix
+                    ))
+                }
+                _ => Object::Error(format!("identifier not found: {}", op)),
+            },
+            Infix::Plus => self.object_add(object1, object2),
+            Infix::Minus => {
+                if let Object::Function(..)
+                | Object::Builtin(..)
+                | Object::Closure(..)
+                | Object::Constructor(..)
+                | Object::Method(..)
+                | Object::Partial(..) = object1
+                {
+                    return Object::Error(
+                        "cannot subtract from a function: `f -1` is `f - 1`, \
+                         write `f (-1)` to apply `f` to a negative number"
+                            .to_owned(),
+                    );
+                }
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Integer(i1 - i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::Divide => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Integer(i1 / i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::Multiply => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Integer(i1 * i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::Equal => Object::Boolean(object1 == object2),
+            Infix::NotEqual => Object::Boolean(object1 != object2),
+            Infix::GreaterThanEqual => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Boolean(i1 >= i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::GreaterThan => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Boolean(i1 > i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::LessThanEqual => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Boolean(i1 <= i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+            Infix::LessThan => {
+                let i1 = self.oti(object1);
+                let i2 = self.oti(object2);
+                match (i1, i2) {
+                    (Ok(i1), Ok(i2)) => Object::Boolean(i1 < i2),
+                    (Err(err), _) | (_, Err(err)) => err,
+                }
+            }
+        }
+    }
+
+    fn float_arith(&mut self, infix: &Infix, object1: Object, object2: Object) -> Object {
+        let to_float = |o: Object| match o {
+            Object::Float(f) => Ok(f),
+            Object::Error(s) => Err(Object::Error(s)),
+            o => Err(Object::Error(format!("{} is not a float", o))),
+        };
+        match (to_float(object1), to_float(object2)) {
+            (Ok(f1), Ok(f2)) => match *infix {
+                Infix::Plus => Object::Float(f1 + f2),
+                Infix::Minus => Object::Float(f1 - f2),
+                Infix::Multiply => Object::Float(f1 * f2),
+                Infix::Divide => Object::Float(f1 / f2),
+                Infix::Equal => Object::Boolean(f1 == f2),
+                Infix::NotEqual => Object::Boolean(f1 != f2),
+                Infix::GreaterThanEqual => Object::Boolean(f1 >= f2),
+                Infix::LessThanEqual => Object::Boolean(f1 <= f2),
+                Infix::GreaterThan => Object::Boolean(f1 > f2),
+                Infix::LessThan => Object::Boolean(f1 < f2),
+                Infix::Op(ref op) => Object::Error(format!("identifier not found: {}", op.0)),
+            },
+            (Err(err), _) | (_, Err(err)) => err,
+        }
+    }
+
+    pub fn eval_if(&mut self, cond: Expr, conse: Program, maybe_alter: Option<Program>) -> Object {
+        let object = self.eval_expr(cond);
+        match self.otb(object) {
+            Ok(b) => {
+                if b {
+                    self.eval_blockstmt(conse)
+                } else {
+                    match maybe_alter {
+                        Some(else_conse) => self.eval_blockstmt(else_conse),
+                        _ => Object::Null,
+                    }
+                }
+            }
+            Err(err) => err,
+        }
+    }
+
+    pub fn eval_fn(&mut self, params: Vec<Ident>, body: Program) -> Object {
+        Object::Function(params, body, Rc::clone(&self.env))
+    }
+
+    pub fn eval_call(&mut self, fn_expr: Expr, args_expr: Vec<Expr>) -> Object {
+        let fn_object = self.eval_expr(fn_expr);
+        let fn_ = self.otf(fn_object);
+        if let Object::Error(_) = fn_ {
+            return fn_;
+        }
+        let args = args_expr
+            .into_iter()
+            .map(|e| self.eval_expr(e))
+            .collect::<Vec<_>>();
+        match fn_ {
+            Object::Function(..) | Object::Builtin(..) => self.apply(fn_, args),
+            // `f ()` and `f (x, y)` pass a single tuple to curried functions
+            _ if args.len() != 1 => {
+                let con = Ident::tuple_con(args.len()).0;
+                self.apply(fn_, vec![Object::Data(con, args)])
+            }
+            _ => self.apply(fn_, args),
+        }
+    }
+
+    /// Applies a function value to arguments. Haskell functions are curried:
+    /// missing arguments give a partial application and extra arguments are
+    /// passed on to the result.
+    pub fn apply(&mut self, fn_: Object, mut args: Vec<Object>) -> Object {
+        match fn_ {
+            Object::Function(params, body, f_env) => self.eval_fn_call(args, params, body, &f_env),
+            Object::Builtin(name, num_params, b_fn) if args.len() < num_params && !args.is_empty() => {
+                Object::Partial(Box::new(Object::Builtin(name, num_params, b_fn)), args)
+            }
+            Object::Builtin(name, num_params, b_fn) => {
+                // `head` and `tail` also work on infinite lists
+                let args = args
+                    .into_iter()
+                    .map(|o| match name.as_str() {
+                        "head" | "tail" => self.force(o),
+                        _ => self.force_deep(o),
+                    })
+                    .collect();
+                self.eval_builtin_call(args, num_params, b_fn)
+            }
+            Object::Closure(clauses, env) => {
+                let arity = clauses.first().map_or(0, |c| c.params.len());
+                if args.len() < arity {
+                    return match args.is_empty() {
+                        true => Object::Closure(clauses, env),
+                        false => Object::Partial(Box::new(Object::Closure(clauses, env)), args),
+                    };
+                }
+                let rest = args.split_off(arity);
+                let result = self.eval_clauses(clauses, &env, args);
+                match rest.is_empty() {
+                    true => result,
+                    false => self.apply(result, rest),
+                }
+            }
+            Object::Constructor(name, arity) => {
+                if args.len() < arity {
+                    return match args.is_empty() {
+                        true => Object::Constructor(name, arity),
+                        false => Object::Partial(Box::new(Object::Constructor(name, arity)), args),
+                    };
+                }
+                let rest = args.split_off(arity);
+                let data = Object::Data(name, args);
+                match rest.is_empty() {
+                    true => data,
+                    false => self.apply(data, rest),
+                }
+            }
+            Object::Method(class, method) => self.eval_method(class, method, args),
+            // a constant such as `minBound` applied to its dictionary
+            Object::Overloaded(class, method, previous)
+                if previous.is_empty() && matches!(args.first(), Some(Object::Dictionary(_))) =>
+            {
+                self.eval_method(class, method, args)
+            }
+            Object::Reader(ty) => match args.first().cloned().map(|o| self.force_deep(o)).as_ref() {
+                Some(Object::String(s)) => match read_value(&self.classes, &ty, s) {
+                    Ok(o) => o,
+                    Err(err) => Object::Error(err),
+                },
+                Some(Object::Error(s)) => Object::Error(s.clone()),
+                Some(o) => Object::Error(format!("{} is not a string", o)),
+                None => Object::Reader(ty),
+            },
+            Object::Partial(f, mut previous) => {
+                previous.extend(args);
+                self.apply(*f, previous)
+            }
+            f if args.is_empty() => f,
+            // a function defined in a recursive binding group
+            f @ Object::Thunk(_) => {
+                let f = self.force(f);
+                self.apply(f, args)
+            }
+            Object::Error(s) => Object::Error(s),
+            f => Object::Error(format!("{} is not a valid function", f)),
+        }
+    }
+
+    fn eval_clauses(
+        &mut self,
+        clauses: Vec<Clause>,
+        env: &Rc<RefCell<Environment>>,
+        args: Vec<Object>,
+    ) -> Object {
+        for clause in clauses {
+            let mut bindings = vec![];
+            let matched = clause
+                .params
+                .iter()
+                .zip(&args)
+                .all(|(p, a)| self.match_pattern(p, a, &mut bindings));
+            if matched {
+                if let Some(o) = self.eval_rhs(env, bindings, clause.rhs, clause.wheres) {
+                    return o;
+                }
+            }
+        }
+        // errors from evaluating a lazy list while matching are passed on too
+        let error = args.iter().find_map(|a| match a {
+            Object::Error(s) => Some(s.clone()),
+            Object::Thunk(thunk) => match *thunk.borrow() {
+                Thunk::Done(Object::Error(ref s)) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        });
+        Object::Error(error.unwrap_or_else(|| String::from("Non-exhaustive patterns in function")))
+    }
+
+    // Picks the instance from the type of the first argument the method
+    // signature mentions the class variable in. Methods that only mention it
+    // in their result are resolved later, by the type they are used at.
+    fn eval_method(&mut self, class: String, method: String, mut args: Vec<Object>) -> Object {
+        if let Some(Object::Dictionary(dict)) = args.first() {
+            let dict = Rc::clone(dict);
+            args.remove(0);
+            return self.select_method(class, method, dict, args);
+        }
+        let sig = match self.classes.class(&class).and_then(|c| c.methods.get(&method)) {
+            Some(sig) => sig.clone(),
+            None => return Object::Error(format!("`{}` is not a method of class `{}`", method, class)),
+        };
+        let needed = sig
+            .dispatch
+            .iter()
+            .map(|p| match p {
+                Dispatch::Direct(i) | Dispatch::ListElem(i) | Dispatch::TupleElem(i, _) => i + 1,
+            })
+            .max()
+            .unwrap_or(sig.arity);
+        if args.len() < needed {
+            return match args.is_empty() {
+                true => Object::Method(class, method),
+                false => Object::Partial(Box::new(Object::Method(class, method)), args),
+            };
+        }
+        if sig.dispatch.is_empty() {
+            return Object::Overloaded(class, method, args);
+        }
+        for position in &sig.dispatch {
+            if let Dispatch::ListElem(i) = *position {
+                let arg = std::mem::replace(&mut args[i], Object::Null);
+                args[i] = self.force(arg);
+            }
+        }
+        let mut selected = None;
+        let mut ambiguous = false;
+        for position in &sig.dispatch {
+            let object = match *position {
+                Dispatch::Direct(i) => args.get(i).cloned(),
+                Dispatch::ListElem(i) => match args.get(i) {
+                    Some(Object::Array(xs)) => xs
+                        .iter()
+                        .find(|x| !matches!(x, Object::Overloaded(..)))
+                        .cloned(),
+                    Some(Object::String(s)) => s.chars().next().map(Object::Char),
+                    Some(Object::Cons(head, _)) => Some((**head).clone()),
+                    Some(o) => Some(o.clone()),
+                    None => None,
+                },
+                Dispatch::TupleElem(i, j) => match args.get(i) {
+                    Some(Object::Data(_, fields)) => fields.get(j).cloned(),
+                    Some(o) => Some(o.clone()),
+                    None => None,
+                },
+            };
+            match object {
+                Some(Object::Overloaded(..)) => ambiguous = true,
+                Some(object) => {
+                    selected = Some(object);
+                    break;
+                }
+                None => {}
+            }
+        }
+        let names = match selected {
+            Some(Object::Error(s)) => return Object::Error(s),
+            Some(object) => self.classes.type_names(&object),
+            // as in `return x >>= k`, the type of the result decides
+            None if ambiguous && sig.returns == Returns::Class => {
+                return Object::Overloaded(class, method, args)
+            }
+            None if ambiguous => {
+                return Object::Error(format!(
+                    "Ambiguous type variable arising from a use of `{}`",
+                    method
+                ))
+            }
+            None => match self.classes.default_method(&class, &method, "a") {
+                Ok(f) => return self.apply(f, args),
+                Err(err) => return Object::Error(err),
+            },
+        };
+        for position in &sig.dispatch {
+            match *position {
+                Dispatch::Direct(i) => {
+                    let arg = std::mem::replace(&mut args[i], Object::Null);
+                    args[i] = self.resolve(arg, &names);
+                }
+                Dispatch::ListElem(i) => {
+                    let arg = std::mem::replace(&mut args[i], Object::Null);
+                    args[i] = self.resolve_list(arg, &names);
+                }
+                Dispatch::TupleElem(i, j) => {
+                    if let Object::Data(_, ref mut fields) = args[i] {
+                        if j < fields.len() {
+                            let field = std::mem::replace(&mut fields[j], Object::Null);
+                            fields[j] = self.resolve(field, &names);
+                        }
+                    }
+                }
+            }
+        }
+        if let Err(err) = self.derive_tuple_instance(&class, &names) {
+            return Object::Error(err);
+        }
+        match self.classes.lookup(&class, &method, &names) {
+            Ok(f) => {
+                let result = self.apply(f, args);
+                self.resolve_result(&sig.returns, result, &names)
+            }
+            Err(err) => Object::Error(err),
+        }
+    }
+
+    // The method of the instance a dictionary holds, whose methods find the
+    // dictionaries of the instance's context bound, and whose class's default
+    // methods find the dictionary itself.
+    fn select_method(
+        &mut self,
+        class: String,
+        method: String,
+        dict: Rc<Dictionary>,
+        args: Vec<Object>,
+    ) -> Object {
+        let names = match dict.tycon {
+            Some(ref tycon) => instance_names(tycon),
+            None => return self.eval_method(class, method, args),
+        };
+        if let Err(err) = self.derive_tuple_instance(&class, &names) {
+            return Object::Error(err);
+        }
+        let (f, bindings) = match self.classes.instance(&class, &names) {
+            Some(instance) => match instance.methods.get(&method) {
+                Some(f) => {
+                    let context = (0..dict.context.len()).map(Dict::context);
+                    (f.clone(), context.zip(dict.context.clone()).collect())
+                }
+                None => match self.classes.default_method(&class, &method, &names[0]) {
+                    Ok(f) => (f, vec![(Dict::this(), Object::Dictionary(Rc::clone(&dict)))]),
+                    Err(err) => return Object::Error(err),
+                },
+            },
+            None => {
+                return Object::Error(format!(
+                    "No instance for ({} {}) arising from a use of `{}`",
+                    class, names[0], method
+                ))
+            }
+        };
+        let f = match f {
+            // a derived `read` parses at the whole type, which the
+            // dictionary has
+            Object::Reader(ty) => Object::Reader(dictionary_type(&dict).unwrap_or(ty)),
+            Object::Closure(clauses, env) => {
+                let mut env = Environment::new_with_outer(env);
+                for (Ident(name), dict) in bindings {
+                    env.set(&name, dict);
+                }
+                Object::Closure(clauses, Rc::new(RefCell::new(env)))
+            }
+            f => f,
+        };
+        self.apply(f, args)
+    }
+
+    // Fixes the instance of an overloaded value now that its type is known
+    fn resolve(&mut self, object: Object, names: &[String]) -> Object {
+        match object {
+            Object::Overloaded(class, method, args) => {
+                let returns = self
+                    .classes
+                    .class(&class)
+                    .and_then(|c| c.methods.get(&method))
+                    .map_or(Returns::Other, |sig| sig.returns.clone());
+                if let Err(err) = self.derive_tuple_instance(&class, names) {
+                    return Object::Error(err);
+                }
+                match self.classes.lookup(&class, &method, names) {
+                    Ok(f) => {
+                        let result = self.apply(f, args);
+                        self.resolve_result(&returns, result, names)
+                    }
+                    Err(err) => Object::Error(err),
+                }
+            }
+            object => object,
+        }
+    }
+
+    fn resolve_result(&mut self, returns: &Returns, result: Object, names: &[String]) -> Object {
+        match (returns, result) {
+            (Returns::Class, result) => self.resolve(result, names),
+            (Returns::ListOfClass, result) => self.resolve_list(result, names),
+            (_, result) => result,
+        }
+    }
+
+    fn eval_fn_call(
+        &mut self,
+        args: Vec<Object>,
+        params: Vec<Ident>,
+        body: Program,
+        f_env: &Rc<RefCell<Environment>>,
+    ) -> Object {
+        if args.len() != params.len() {
+            Object::Error(format!(
+                "wrong number of arguments: {} expected but {} given",
+                params.len(),
+                args.len()
+            ))
+        } else {
+            let old_env = Rc::clone(&self.env);
+            let mut new_env = Environment::new_with_outer(Rc::clone(f_env));
+            for (Ident(name), o) in params.into_iter().zip(args) {
+                new_env.set(&name, o);
+            }
+            self.env = Rc::new(RefCell::new(new_env));
+            let object = self.eval_blockstmt(body);
+            self.env = old_env;
+            self.returned(object)
+        }
+    }
+
+    fn eval_builtin_call(
+        &mut self,
+        args: Vec<Object>,
+        num_params: usize,
+        b_fn: BuiltinFunction,
+    ) -> Object {
+        if args.len() != num_params {
+            Object::Error(format!(
+                "wrong number of arguments: {} expected but {} given",
+                num_params,
+                args.len()
+            ))
+        } else {
+            b_fn(args).unwrap_or_else(Object::Error)
+        }
+    }
+
+    pub fn eval_array(&mut self, exprs: Vec<Expr>) -> Object {
+        let new_vec = exprs.into_iter().map(|e| self.eval_expr(e)).collect();
+        Object::Array(new_vec)
+    }
+
+    pub fn object_add(&mut self, object1: Object, object2: Object) -> Object {
+        match (object1, object2) {
+            (Object::Integer(i1), Object::Integer(i2)) => Object::Integer(i1 + i2),
+            (Object::String(s1), Object::String(s2)) => Object::String(s1 + &s2),
+            (Object::Error(s), _) | (_, Object::Error(s)) => Object::Error(s),
+            (x, y) => Object::Error(format!("{:?} and {:?} are not addable", x, y)),
+        }
+    }
+
+    // Keys are checked by `oth` to be integers, booleans or strings
+    #[allow(clippy::mutable_key_type)]
+    pub fn eval_hash(&mut self, hs: Vec<(Literal, Expr)>) -> Object {
+        let hashmap = hs.into_iter().map(|pair| self.eval_pair(pair)).collect();
+        Object::Hash(hashmap)
+    }
+
+    fn eval_pair(&mut self, tuple: (Literal, Expr)) -> (Object, Object) {
+        let (l, e) = tuple;
+        let hash = self.l2h(l);
+        let object = self.eval_expr(e);
+        (hash, object)
+    }
+
+    pub fn eval_index(&mut self, target_exp: Expr, id_exp: Expr) -> Object {
+        let target = self.eval_expr(target_exp);
+        let target = self.force_deep(target);
+        let index = self.eval_expr(id_exp);
+        match target {
+            Object::Array(arr) => match self.oti(index) {
+                Ok(index_number) => arr
+                    .into_iter()
+                    .nth(index_number as usize)
+                    .unwrap_or(Object::Null),
+                Err(err) => err,
+            },
+            Object::Hash(mut hash) => {
+                let name = self.oth(index);
+                match name {
+                    Object::Error(_) => name,
+                    _ => hash.remove(&name).unwrap_or(Object::Null),
+                }
+            }
+            o => Object::Error(format!("unexpected index target: {}", o)),
+        }
+    }
+
+    pub fn otb(&mut self, object: Object) -> Result<bool, Object> {
+        match object {
+            Object::Thunk(_) => {
+                let object = self.force(object);
+                self.otb(object)
+            }
+            Object::Boolean(b) => Ok(b),
+            Object::Error(s) => Err(Object::Error(s)),
+            b => Err(Object::Error(format!("{} is not a bool", b))),
+        }
+    }
+
+    pub fn oti(&mut self, object: Object) -> Result<i64, Object> {
+        match object {
+            Object::Thunk(_) => {
+                let object = self.force(object);
+                self.oti(object)
+            }
+            Object::Integer(i) => Ok(i),
+            Object::Error(s) => Err(Object::Error(s)),
+            i => Err(Object::Error(format!("{} is not an integer", i))),
+        }
+    }
+
+    pub fn otf(&mut self, object: Object) -> Object {
+        match object {
+            Object::Thunk(_) => {
+                let object = self.force(object);
+                self.otf(object)
+            }
+            Object::Function(_, _, _)
+            | Object::Builtin(_, _, _)
+            | Object::Closure(_, _)
+            | Object::Constructor(_, _)
+            | Object::Method(_, _)
+            | Object::Partial(_, _)
+            | Object::Overloaded(..)
+            | Object::Reader(_) => object,
+            Object::Error(s) => Object::Error(s),
+            f => Object::Error(format!("{} is not a valid function", f)),
+        }
+    }
+
+    pub fn oth(&mut self, object: Object) -> Object {
+        match object {
+            Object::Thunk(_) => {
+                let object = self.force(object);
+                self.oth(object)
+            }
+            Object::Integer(i) => Object::Integer(i),
+            Object::Boolean(b) => Object::Boolean(b),
+            Object::String(s) => Object::String(s),
+            Object::Error(s) => Object::Error(s),
+            x => Object::Error(format!("{} is not hashable", x)),
+        }
+    }
+
+    /// Matches an object against a pattern, collecting the variables it binds.
+    /// Lists are only evaluated as far as the pattern needs.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        object: &Object,
+        bindings: &mut Vec<(String, Object)>,
+    ) -> bool {
+        let forced;
+        let object = match (pattern, object) {
+            (
+                Pattern::WildPat | Pattern::VarPat(_) | Pattern::AsPat(..) | Pattern::SigPat(..),
+                o,
+            ) => o,
+            (_, Object::Thunk(_) | Object::Overloaded(..)) => {
+                forced = match self.classes.pattern_type(pattern) {
+                    Some(name) => self.force_as(object.clone(), &name),
+                    None => self.force(object.clone()),
+                };
+                &forced
+            }
+            (_, o) => o,
+        };
+        match (pattern, object) {
+            (Pattern::WildPat, _) => true,
+            (Pattern::VarPat(Ident(name)), o) => {
+                bindings.push((name.clone(), o.clone()));
+                true
+            }
+            (Pattern::AsPat(Ident(name), pattern), o) => {
+                bindings.push((name.clone(), o.clone()));
+                self.match_pattern(pattern, o, bindings)
+            }
+            (Pattern::SigPat(pattern, ty), o) => {
+                let o = self.annotate(o.clone(), ty);
+                self.match_pattern(pattern, &o, bindings)
+            }
+            (Pattern::LitPat(Literal::StringLiteral(s)), Object::Cons(head, tail)) => {
+                let mut chars = s.chars();
+                match chars.next() {
+                    Some(c) => {
+                        **head == Object::Char(c)
+                            && self.match_pattern(
+                                &Pattern::LitPat(Literal::StringLiteral(chars.collect())),
+                                tail,
+                                bindings,
+                            )
+                    }
+                    None => false,
+                }
+            }
+            (Pattern::LitPat(literal), o) => match (literal, o) {
+                (Literal::IntLiteral(i), Object::Integer(j)) => i == j,
+                (Literal::FloatLiteral(f), Object::Float(g)) => f == g,
+                (Literal::FloatLiteral(f), Object::Integer(j)) => *f == *j as f64,
+                // an integer literal is a number of any type, a float here
+                (Literal::IntLiteral(i), Object::Float(g)) => *i as f64 == *g,
+                (Literal::BoolLiteral(b), Object::Boolean(c)) => b == c,
+                (Literal::CharLiteral(c), Object::Char(d)) => c == d,
+                (Literal::StringLiteral(s), Object::String(t)) => s == t,
+                (Literal::StringLiteral(s), Object::Array(xs)) => s.is_empty() && xs.is_empty(),
+                _ => false,
+            },
+            (Pattern::ConPat(con, args), Object::Boolean(b)) if args.is_empty() => {
+                (con.unqualified() == "True" && *b) || (con.unqualified() == "False" && !*b)
+            }
+            (Pattern::ConPat(Ident(con), args), Object::Array(xs))
+                if con == ":" && args.len() == 2 =>
+            {
+                match xs.split_first() {
+                    Some((head, tail)) => {
+                        self.match_pattern(&args[0], head, bindings)
+                            && self.match_pattern(&args[1], &Object::Array(tail.to_vec()), bindings)
+                    }
+                    None => false,
+                }
+            }
+            (Pattern::ConPat(Ident(con), args), Object::String(s))
+                if con == ":" && args.len() == 2 =>
+            {
+                let mut chars = s.chars();
+                match chars.next() {
+                    Some(c) => {
+                        self.match_pattern(&args[0], &Object::Char(c), bindings)
+                            && self.match_pattern(
+                                &args[1],
+                                &Object::String(chars.collect()),
+                                bindings,
+                            )
+                    }
+                    None => false,
+                }
+            }
+            (Pattern::ConPat(Ident(con), args), Object::Cons(head, tail))
+                if con == ":" && args.len() == 2 =>
+            {
+                self.match_pattern(&args[0], head, bindings)
+                    && self.match_pattern(&args[1], tail, bindings)
+            }
+            (Pattern::ConPat(con, args), Object::Data(name, fields)) => {
+                con.unqualified() == name && self.match_patterns(args, fields, bindings)
+            }
+            (Pattern::TuplePat(patterns), Object::Data(name, fields)) => {
+                *name == Ident::tuple_con(patterns.len()).0
+                    && self.match_patterns(patterns, fields, bindings)
+            }
+            (Pattern::ListPat(patterns), Object::Array(xs)) => {
+                self.match_patterns(patterns, xs, bindings)
+            }
+            (Pattern::ListPat(patterns), Object::String(s)) => {
+                let chars: Vec<Object> = s.chars().map(Object::Char).collect();
+                self.match_patterns(patterns, &chars, bindings)
+            }
+            (Pattern::ListPat(patterns), Object::Cons(head, tail)) => match patterns.split_first() {
+                Some((first, rest)) => {
+                    self.match_pattern(first, head, bindings)
+                        && self.match_pattern(&Pattern::ListPat(rest.to_vec()), tail, bindings)
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn match_patterns(
+        &mut self,
+        patterns: &[Pattern],
+        objects: &[Object],
+        bindings: &mut Vec<(String, Object)>,
+    ) -> bool {
+        patterns.len() == objects.len()
+            && patterns
+                .iter()
+                .zip(objects)
+                .all(|(p, o)| self.match_pattern(p, o, bindings))
+    }
+
+    /// Evaluates a list as far as its first cell.
+    pub fn force(&mut self, object: Object) -> Object {
+        let mut object = object;
+        while let Object::Thunk(thunk) = object {
+            let pending = std::mem::replace(
+                &mut *thunk.borrow_mut(),
+                Thunk::Done(Object::Error(String::from("<<loop>>"))),
+            );
+            let value = match pending {
+                Thunk::Done(o) => o,
+                Thunk::Pending(expr, env) => {
+                    let old_env = std::mem::replace(&mut self.env, env);
+                    let value = self.eval_expr(expr);
+                    self.env = old_env;
+                    self.force(value)
+                }
+                Thunk::Append(xs, ys) => {
+                    let value = self.append(xs, ys);
+                    self.force(value)
+                }
+                Thunk::Resolve(xs, names) => {
+                    let xs = self.force(xs);
+                    self.resolve_list(xs, &names)
+                }
+                Thunk::Apply(f, args) => {
+                    let value = self.apply(f, args);
+                    self.force(value)
+                }
+            };
+            *thunk.borrow_mut() = Thunk::Done(value.clone());
+            object = value;
+        }
+        object
+    }
+
+    /// Evaluates a value as far as `force` does, resolving a method
+    /// overloaded on its result type to the instance for type `name`.
+    pub fn force_as(&mut self, object: Object, name: &str) -> Object {
+        match self.force(object) {
+            object @ Object::Overloaded(..) => {
+                let object = self.resolve(object, &[name.to_owned()]);
+                self.force(object)
+            }
+            object => object,
+        }
+    }
+
+    /// Evaluates a value completely, turning lazy lists into arrays, or
+    /// strings when they hold characters.
+    pub fn force_deep(&mut self, object: Object) -> Object {
+        match self.force(object) {
+            Object::Cons(head, tail) => {
+                let mut elems = vec![];
+                let mut rest = Object::Cons(head, tail);
+                loop {
+                    match self.force_as(rest, "[]") {
+                        Object::Cons(head, tail) => {
+                            match self.force_deep(*head) {
+                                Object::Error(s) => return Object::Error(s),
+                                head => elems.push(head),
+                            }
+                            rest = *tail;
+                        }
+                        Object::Array(xs) => {
+                            elems.extend(xs);
+                            break;
+                        }
+                        Object::String(s) => {
+                            elems.extend(s.chars().map(Object::Char));
+                            break;
+                        }
+                        Object::Error(s) => return Object::Error(s),
+                        o => return Object::Error(format!("{} is not a list", o)),
+                    }
+                }
+                let chars: Option<String> = elems
+                    .iter()
+                    .map(|o| match o {
+                        Object::Char(c) => Some(*c),
+                        _ => None,
+                    })
+                    .collect();
+                match chars {
+                    Some(s) => Object::String(s),
+                    None => self.force_deep(Object::Array(elems)),
+                }
+            }
+            Object::Array(xs) => {
+                let mut elems = Vec::with_capacity(xs.len());
+                for x in xs {
+                    match self.force_deep(x) {
+                        Object::Error(s) => return Object::Error(s),
+                        x => elems.push(x),
+                    }
+                }
+                Object::Array(elems)
+            }
+            Object::Data(con, fields) => {
+                let fields = fields.into_iter().map(|o| self.force_deep(o)).collect();
+                Object::Data(con, fields)
+            }
+            o => o,
+        }
+    }
+
+    // The tail of a list cell is only evaluated when it is needed, unless it
+    // is already a value
+    fn delay(&mut self, expr: Expr) -> Object {
+        match expr {
+            Expr::Located(_, expr) => self.delay(*expr),
+            Expr::LitExpr(_) | Expr::ArrayExpr(_) => self.eval_expr(expr),
+            Expr::IdentExpr(Ident(ref name)) => match self.env.borrow().get(name) {
+                Some(
+                    o @ (Object::Array(_) | Object::String(_) | Object::Cons(..) | Object::Thunk(_)),
+                ) => o,
+                _ => Object::thunk(Thunk::Pending(expr, Rc::clone(&self.env))),
+            },
+            expr => Object::thunk(Thunk::Pending(expr, Rc::clone(&self.env))),
+        }
+    }
+
+    fn eval_cons(&mut self, head: Expr, tail: Expr) -> Object {
+        let head = self.eval_expr(head);
+        if let Object::Error(_) = head {
+            return head;
+        }
+        match self.delay(tail) {
+            Object::Error(s) => Object::Error(s),
+            tail @ (Object::Array(_) | Object::String(_)) => {
+                builtins::bcons_fn(vec![head, tail]).unwrap_or_else(Object::Error)
+            }
+            tail => Object::Cons(Box::new(head), Box::new(tail)),
+        }
+    }
+
+    // `xs ++ ys` evaluates `xs` cell by cell and leaves `ys` for later
+    fn append(&mut self, xs: Object, ys: Object) -> Object {
+        match self.force_as(xs, "[]") {
+            Object::Array(xs) if xs.is_empty() => ys,
+            Object::String(xs) if xs.is_empty() => ys,
+            xs @ (Object::Array(_) | Object::String(_)) => match ys {
+                ys @ (Object::Array(_) | Object::String(_)) => {
+                    builtins::bappend_fn(vec![xs, ys]).unwrap_or_else(Object::Error)
+                }
+                ys => {
+                    let elems = match xs {
+                        Object::String(s) => s.chars().map(Object::Char).collect(),
+                        Object::Array(xs) => xs,
+                        _ => vec![],
+                    };
+                    elems.into_iter().rev().fold(ys, |tail, head| {
+                        Object::Cons(Box::new(head), Box::new(tail))
+                    })
+                }
+            },
+            Object::Cons(head, tail) => {
+                Object::Cons(head, Box::new(Object::thunk(Thunk::Append(*tail, ys))))
+            }
+            Object::Error(s) => Object::Error(s),
+            xs => Object::Error(format!("{} is not a list", xs)),
+        }
+    }
+
+    fn resolve_list(&mut self, list: Object, names: &[String]) -> Object {
+        match list {
+            Object::Array(xs) => {
+                Object::Array(xs.into_iter().map(|x| self.resolve(x, names)).collect())
+            }
+            Object::Cons(head, tail) => {
+                let head = self.resolve(*head, names);
+                let tail = Object::thunk(Thunk::Resolve(*tail, names.to_vec()));
+                Object::Cons(Box::new(head), Box::new(tail))
+            }
+            Object::Thunk(_) => Object::thunk(Thunk::Resolve(list, names.to_vec())),
+            list => list,
+        }
+    }
+
+    pub fn l2h(&mut self, literal: Literal) -> Object {
+        let object = self.eval_literal(literal);
+        self.oth(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare_haskell(input: &str, object: Object) {
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let tokens = Tokens::with_spans(&r, &spans);
+        let (_, result_parse) = Parser::parse_tokens(tokens).unwrap();
+        let mut evaluator = Evaluator::new();
+        let eval = evaluator.eval_program(result_parse);
+        assert_eq!(eval, object);
+    }
+
+    fn compare(input: &str, object: Object) {
+        let (_, r) = Lexer::lex_tokens(input).unwrap();
+        let tokens = Tokens::new(&r);
+        let (_, result_parse) = Parser::parse_tokens(tokens).unwrap();
+        let mut evaluator = Evaluator::new();
+        let eval = evaluator.eval_program(result_parse);
+        assert_eq!(eval, object);
+    }
+
+    #[test]
+    fn test_simple() {
+        // ints
+        compare("5", Object::Integer(5));
+        compare("10", Object::Integer(10));
+        // bools
+        compare("true", Object::Boolean(true));
+        compare("false", Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_prefix() {
+        // the prefix -
+        compare("-1", Object::Integer(-1));
+        compare("-5", Object::Integer(-5));
+        compare("-20", Object::Integer(-20));
+        compare(
+            "-true",
+            Object::Error("true is not an integer".to_string()),
+        );
+        compare(
+            "-false",
+            Object::Error("false is not an integer".to_string()),
+        );
+        // negation binds like binary `-`
+        compare("- 2 * 3 + 10", Object::Integer(4));
+        compare("(- 2) * 3 == -6", Object::Boolean(true));
+        compare("negate 1.5 == -1.5", Object::Boolean(true));
+        compare("- 2 ^ 2 == -4", Object::Boolean(true));
+        compare("(-2) ^ 2", Object::Integer(4));
+        compare("2 ^ 3 ^ 2", Object::Integer(512));
+        compare("1.5 ^ 3", Object::Float(3.375));
+        compare("1.5 ^ 0 == 1.0", Object::Boolean(true));
+        compare("1.5 ^ 0", Object::Float(1.0));
+        compare(
+            "let x = 4; 1 - -x",
+            Object::Error(
+                "Precedence parsing error: cannot mix `-` [infixl 6] and prefix `-` \
+                 [infixl 6] in the same infix expression"
+                    .to_string(),
+            ),
+        );
+        compare(
+            "let f = fn(x) { x }; f -1",
+            Object::Error(
+                "cannot subtract from a function: `f -1` is `f - 1`, \
+                 write `f (-1)` to apply `f` to a negative number"
+                    .to_string(),
+            ),
+        );
+        compare(
+            "1+-3",
+            Object::Error(
+                "identifier not found: +-; write `x + (-y)` for a negative operand".to_string(),
+            ),
+        );
+        compare_haskell(
+            "{-# LANGUAGE NegativeLiterals #-}\nsquare x = x * x\nsquare -3 + square (-1)",
+            Object::Integer(10),
+        );
+    }
+
+    #[test]
+    fn test_infix_op() {
+        // algebra
+        compare("5 + 5 + 5 + 5 - 10", Object::Integer(10));
+        compare("2 * 2 * 2 * 2 * 2", Object::Integer(32));
+        compare("-50 + 100 + (-50)", Object::Integer(0));
+        compare("5 * 2 + 10", Object::Integer(20));
+        compare("5 + 2 * 10", Object::Integer(25));
+        compare("20 + 2 * (-10)", Object::Integer(0));
+        compare("50 / 2 * 2 + 10", Object::Integer(60));
+        compare("2 * (5 + 10)", Object::Integer(30));
+        compare("3 * 3 * 3 + 10", Object::Integer(37));
+        compare("3 * (3 * 3) + 10", Object::Integer(37));
+        compare(
+            "(5 + 10 * 2 + 15 / 3) * 2 - 10",
+            Object::Integer(50),
+        );
+        // logic algebra
+        compare("1 < 2", Object::Boolean(true));
+        compare("1 > 2", Object::Boolean(false));
+        compare("1 < 1", Object::Boolean(false));
+        compare("1 > 1", Object::Boolean(false));
+        compare("1 <= 2", Object::Boolean(true));
+        compare("1 >= 2", Object::Boolean(false));
+        compare("1 <= 1", Object::Boolean(true));
+        compare("1 >= 1", Object::Boolean(true));
+        compare("1 == 1", Object::Boolean(true));
+        compare("1 != 1", Object::Boolean(false));
+        compare("1 == 2", Object::Boolean(false));
+        compare("1 != 2", Object::Boolean(true));
+        // combination
+        compare("(1 < 2) == true", Object::Boolean(true));
+        compare("(1 < 2) == false", Object::Boolean(false));
+        compare("(1 > 2) == true", Object::Boolean(false));
+        compare("(1 > 2) == false", Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_conditional() {
+        compare("if (true) { 10 }", Object::Integer(10));
+        compare("if (false) { 10 }", Object::Null);
+        compare(
+            "if (1) { 10 }",
+            Object::Error("1 is not a bool".to_string()),
+        );
+        compare("if (1 < 2) { 10 }", Object::Integer(10));
+        compare("if (1 > 2) { 10 }", Object::Null);
+        compare(
+            "if (1 < 2) { 10 } else { 20 }",
+            Object::Integer(10),
+        );
+        compare(
+            "if (1 > 2) { 10 } else { 20 }",
+            Object::Integer(20),
+        );
+    }
+
+    #[test]
+    fn test_return() {
+        compare("return 10", Object::Integer(10));
+        compare("return 10; 9", Object::Integer(10));
+        compare("return 2 * 5; 9", Object::Integer(10));
+        compare("9; return 2 * 5; 9", Object::Integer(10));
+
+        let input = "if (10 > 1) {\
+                 if (10 > 1) {\
+                     return 10;\
+                 }\
+                 return 1;\
+             }\
+            "
+        ;
+        compare(input, Object::Integer(10));
+    }
+
+    #[test]
+    fn test_bindings() {
+        compare("let a = 5; a;", Object::Integer(5));
+        compare("let a = 5 * 5; a;", Object::Integer(25));
+        compare("let a = 5; let b = a; b;", Object::Integer(5));
+        compare(
+            "let a = 5; let b = a; let c = a + b + 5; c;",
+            Object::Integer(15),
+        );
+        compare(
+            "foobar",
+            Object::Error("identifier not found: foobar".to_string()),
+        );
+        // bindings may use the bindings after them
+        compare("let x = fn () { a; }; let a = 10; x();", Object::Integer(10));
+        compare("let b = a * 2; let a = 5; b;", Object::Integer(10));
+    }
+
+    #[test]
+    fn test_binding_order() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        compare_haskell(
+            "x = double a + 1\n\
+             double n = n * 2\n\
+             a = head ys\n\
+             xs = 1 : ys\n\
+             ys = 2 : xs\n\
+             isEven 0 = True\n\
+             isEven n = isOdd (n - 1)\n\
+             isOdd 0 = False\n\
+             isOdd n = isEven (n - 1)\n\
+             f n = let { y = z + n; z = g n } in y\n\
+             \x20 where\n\
+             \x20   g m = h m + k\n\
+             \x20   k = h 10\n\
+             \x20   h m = m * 3\n\
+             [x, head (tail ys), if isOdd 7 then 1 else 0, f 2]",
+            ints(&[5, 1, 1, 38]),
+        );
+        // a value defined in terms of itself loops only when it is used
+        compare_haskell("x = x + 1\ny = 2\ny", Object::Integer(2));
+        compare_haskell("x = x + 1\nx", Object::Error("<<loop>>".to_owned()));
+        // as in GHCi, a definition replaces the one above it from there on
+        compare_haskell("x = 1\ny = x\nx = 2\n[x, y]", ints(&[2, 1]));
+        // constants with a `where` or guards are values, not functions
+        compare_haskell("x = y where y = 2\nx * 3", Object::Integer(6));
+        compare_haskell("x | True = 1\nx + 1", Object::Integer(2));
+        compare_haskell(
+            "f y = z\n  where z | y > 0 = y\n          | otherwise = 0\n[f 3, f (-1)]",
+            ints(&[3, 0]),
+        );
+        compare_haskell(
+            "even' 0 = True\neven' n = odd' (n - 1)\nodd' 0 = False\nodd' n = even' (n - 1)\n\
+             b | even' 4 = 1\n  | otherwise = b\nb",
+            Object::Integer(1),
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        compare(
+            "\"foobar\"",
+            Object::String("foobar".to_string()),
+        );
+        compare(
+            "\"foo\" + \"bar\"",
+            Object::String("foobar".to_string()),
+        );
+        compare(
+            "\"foo\" + \" \" + \"bar\"",
+            Object::String("foo bar".to_string()),
+        );
+        compare(
+            "\"foo\" - \"bar\"",
+            Object::Error("foo is not an integer".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_fn() {
+        compare(
+            "let identity = fn(x) { x; }; identity(5);",
+            Object::Integer(5),
+        );
+        compare(
+            "let identity = fn(x) { return x; }; identity(5);",
+            Object::Integer(5),
+        );
+        compare(
+            "let double = fn(x) { x * 2; }; double(5);",
+            Object::Integer(10),
+        );
+        compare(
+            "let add = fn(x, y) { x + y; }; add(5, 5);",
+            Object::Integer(10),
+        );
+        compare(
+            "let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));",
+            Object::Integer(20),
+        );
+        compare("fn(x) { x; }(5)", Object::Integer(5));
+        compare(
+            "5();",
+            Object::Error("5 is not a valid function".to_string()),
+        );
+        compare(
+            "false();",
+            Object::Error("false is not a valid function".to_string()),
+        );
+        compare(
+            "let add = fn(x, y) { x + y; }; add(1);",
+            Object::Error("wrong number of arguments: 2 expected but 1 given".to_string()),
+        );
+        compare(
+            "let a = 10; let x = fn () { a; }; x();",
+            Object::Integer(10),
+        );
+        compare(
+            "let x = fn () { a; }; let a = 10; x();",
+            Object::Integer(10),
+        );
+
+        let fn_input1 = "let add = fn(a, b, c, d) { return a + b + c + d; };\
+             add(1, 2, 3, 4);\
+            "
+        ;
+
+        let fn_input2 = "let addThree = fn(x) { return x + 3 };\
+             addThree(3);\
+            "
+        ;
+
+        let fn_input3 = "let max = fn(x, y) { if (x > y) { x } else { y } };\
+             max(5, 10)\
+            "
+        ;
+
+        let fn_input4 = "let factorial = fn(n) {\
+                if (n == 0) {\
+                    1\
+                } else {\
+                    n * factorial(n - 1)\
+                }\
+             }\
+             factorial(5)\
+            "
+        ;
+
+        let fn_input5 = "let addThree = fn(x) { return x + 3 };\
+             let callTwoTimes = fn(x, f) { f(f(x)) }\
+             callTwoTimes(3, addThree);\
+            "
+        ;
+
+        let fn_input6 = "let callTwoTimes = fn(x, f) { f(f(x)) }\
+             callTwoTimes(3, fn(x) { x + 1 });\
+            "
+        ;
+
+        let fn_input7 = "let newAdder = fn(x) { fn(n) { x + n } };\
+             let addTwo = newAdder(2);\
+             addTwo(2);\
+            "
+        ;
+
+        compare(fn_input1, Object::Integer(10));
+        compare(fn_input2, Object::Integer(6));
+        compare(fn_input3, Object::Integer(10));
+        compare(fn_input4, Object::Integer(120));
+        compare(fn_input5, Object::Integer(9));
+        compare(fn_input6, Object::Integer(5));
+        compare(fn_input7, Object::Integer(4));
+    }
+
+    #[test]
+    fn test_array() {
+        compare(
+            "[1, 2, 3, 4]",
+            Object::Array(vec![
+                Object::Integer(1),
+                Object::Integer(2),
+                Object::Integer(3),
+                Object::Integer(4),
+            ]),
+        );
+
+        compare(
+            "let double = fn(x) { x * 2 };[1, double(2), 3 * 3, 4 - 3]",
+            Object::Array(vec![
+                Object::Integer(1),
+                Object::Integer(4),
+                Object::Integer(9),
+                Object::Integer(1),
+            ]),
+        );
+
+        compare("[1, 2, 3][0]", Object::Integer(1));
+        compare("[1, 2, 3][1]", Object::Integer(2));
+        compare("[1, 2, 3][2]", Object::Integer(3));
+        compare("let i = 0; [1][i];", Object::Integer(1));
+        compare("[1, 2, 3][1 + 1];", Object::Integer(3));
+        compare(
+            "let myArray = [1, 2, 3]; myArray[2];",
+            Object::Integer(3),
+        );
+        compare(
+            "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+            Object::Integer(6),
+        );
+        compare(
+            "let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i];",
+            Object::Integer(2),
+        );
+        compare("[1, 2, 3][3]", Object::Null);
+        compare("[1, 2, 3][-1]", Object::Null);
+    }
+
+    #[test]
+    fn test_hash() {
+        let input_beg = "let double = fn(x) {
+           x * 2;
+         };
+         let arr = [1, 2, 3, 4];
+         let h = {
+           \"one\": 10 - 9,
+           \"two\": 8 / 4,
+           3: arr[2],
+           4: double(2),
+           true: if (10 > 8) { true } else { false },
+           false: \"hello\" == \"world\"
+         };
+        ".to_string();
+
+        compare(
+            &(input_beg.clone() + "h[\"one\"]"),
+            Object::Integer(1),
+        );
+        compare(
+            &(input_beg.clone() + "let s = \"two\"; h[s]"),
+            Object::Integer(2),
+        );
+        compare(
+            &(input_beg.clone() + "h[3]"),
+            Object::Integer(3),
+        );
+        compare(
+            &(input_beg.clone() + "h[2 + 2]"),
+            Object::Integer(4),
+        );
+        compare(
+            &(input_beg.clone() + "h[true]"),
+            Object::Boolean(true),
+        );
+        compare(
+            &(input_beg.clone() + "h[5 < 1]"),
+            Object::Boolean(false),
+        );
+        compare(
+            &(input_beg.clone() + "h[100]"),
+            Object::Null,
+        );
+        compare(
+            &(input_beg.clone() + "h[[]]"),
+            Object::Error("[] is not hashable".to_string()),
+        );
+        compare(
+            &(input_beg + "3[true];"),
+            Object::Error("unexpected index target: 3".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_builtins() {
+        // len
+        compare("len(\"hello world!\")", Object::Integer(12));
+        compare("len(\"\")", Object::Integer(0));
+        compare(
+            "len(\"Hey Bob, how ya doin?\")",
+            Object::Integer(21),
+        );
+        compare(
+            "len(3)",
+            Object::Error("invalid arguments for len".to_string()),
+        );
+        compare(
+            "len(\"hello\", \"world\")",
+            Object::Error("wrong number of arguments: 1 expected but 2 given".to_string()),
+        );
+        compare("len([])", Object::Integer(0));
+        compare("len([1, 2, 3, 4])", Object::Integer(4));
+        // head
+        compare("head([1])", Object::Integer(1));
+        compare("head([1, 2, 3, 4])", Object::Integer(1));
+        compare(
+            "head([])",
+            Object::Error("empty array".to_string()),
+        );
+        // tail
+        compare("tail([1])", Object::Array(vec![]));
+        compare(
+            "tail([1, 2, 3, 4])",
+            Object::Array(vec![
+                Object::Integer(2),
+                Object::Integer(3),
+                Object::Integer(4),
+            ]),
+        );
+        compare(
+            "tail([])",
+            Object::Error("empty array".to_string()),
+        );
+        // cons
+        compare(
+            "cons(1, [])",
+            Object::Array(vec![Object::Integer(1)]),
+        );
+        compare(
+            "cons(1, [2, 3, 4])",
+            Object::Array(vec![
+                Object::Integer(1),
+                Object::Integer(2),
+                Object::Integer(3),
+                Object::Integer(4),
+            ]),
+        );
+        // map reduce
+        let map_decl = "let map = fn(f, arr) {\
+              if (len(arr) == 0) {\
+                []\
+              } else {\
+                let h = head(arr);\
+                cons(f(h), map(f, tail(arr)));\
+              }\
+            };\
+            "
+        .to_string();
+
+        let reduce_decl = "let reduce = fn(f, init, arr) {\
+                if (len(arr) == 0) {\
+                    init\
+                } else {\
+                    let newInit = f(init, head(arr));\
+                    reduce(f, newInit, tail(arr));\
+                }\
+            };\
+            "
+        .to_string();
+
+        compare(
+            &(map_decl + "let double = fn(x) { x * 2 }; map(double, [1, 2, 3, 4])"),
+            Object::Array(vec![
+                Object::Integer(2),
+                Object::Integer(4),
+                Object::Integer(6),
+                Object::Integer(8),
+            ]),
+        );
+
+        compare(
+            &(reduce_decl + "let add = fn(x, y) { x + y }; reduce(add, 0, [1, 2, 3, 4, 5])")
+                ,
+            Object::Integer(15),
+        );
+    }
+
+    #[test]
+    fn test_class_dispatch() {
+        let decls = "data Color = Red | Green\n\
+                     instance Eq Color where\n  \
+                     Red == Red = True\n  \
+                     Green == Green = True\n  \
+                     _ == _ = False\n\
+                     instance Show Color where\n  \
+                     show Red = \"red\"\n  \
+                     show Green = \"green\"\n";
+        compare_haskell(
+            &(decls.to_owned() + "Red == Green"),
+            Object::Boolean(false),
+        );
+        compare_haskell(&(decls.to_owned() + "Red /= Green"), Object::Boolean(true));
+        compare_haskell(
+            &(decls.to_owned() + "show [Red, Green]"),
+            Object::String("[red,green]".to_owned()),
+        );
+        compare_haskell(
+            &(decls.to_owned() + "[Red] == [Red]"),
+            Object::Boolean(true),
+        );
+        // builtin instances
+        compare_haskell("show 5", Object::String("5".to_owned()));
+        compare_haskell("show [1, 2]", Object::String("[1,2]".to_owned()));
+        compare_haskell("show \"hi\"", Object::String("\"hi\"".to_owned()));
+        compare_haskell("show (compare 2 1)", Object::String("GT".to_owned()));
+        compare_haskell("compare \"ab\" \"b\" == LT", Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_class_defaults_and_superclasses() {
+        let class = "class Container f where\n  \
+                     size :: f -> Integer\n  \
+                     isEmpty :: f -> Bool\n  \
+                     isEmpty x = size x == 0\n\
+                     data Box = Box Integer | Empty\n\
+                     instance Container Box where\n  \
+                     size Empty = 0\n  \
+                     size (Box _) = 1\n";
+        compare_haskell(&(class.to_owned() + "isEmpty Empty"), Object::Boolean(true));
+        compare_haskell(
+            &(class.to_owned() + "isEmpty (Box 3)"),
+            Object::Boolean(false),
+        );
+        compare_haskell(
+            &(class.to_owned() + "show (Box 3)"),
+            Object::Error("No instance for (Show Box) arising from a use of `show`".to_owned()),
+        );
+        compare_haskell(
+            "data T = T\ninstance Ord T where\n  compare _ _ = EQ\n",
+            Object::Error(
+                "No instance for (Eq T) arising from the superclasses of an instance declaration"
+                    .to_owned(),
+            ),
+        );
+        // the Prelude's instances are for `Integer` and `Double`, which
+        // `Int` and `Float` name too
+        let class = "class Eq a => C a where
+  c :: a -> Bool
+";
+        compare_haskell(
+            &(class.to_owned() + "instance C Int where
+  c x = x == 1
+c (1 :: Int)"),
+            Object::Boolean(true),
+        );
+        compare_haskell(
+            &(class.to_owned() + "instance C Float where
+  c x = x == 1
+c (2 :: Float)"),
+            Object::Boolean(false),
+        );
+    }
+
+    #[test]
+    fn test_deriving() {
+        let decls = "data Color = Red | Green | Blue\n  \
+                     deriving (Eq, Ord, Show, Read, Enum, Bounded, Ix)\n\
+                     data Shape = Circle Integer | Rect Integer Integer\n  \
+                     deriving (Eq, Ord, Show)\n\
+                     data P = P { name :: String, age :: Integer }\n\
+                     deriving instance Show P\n";
+        let check = |expr: &str, object: Object| compare_haskell(&(decls.to_owned() + expr), object);
+        check("Red /= Blue", Object::Boolean(true));
+        check("Rect 1 2 < Circle 5", Object::Boolean(false));
+        check("compare (Rect 1 2) (Rect 1 3)", Object::Data("LT".to_owned(), vec![]));
+        check(
+            "show [Circle (-3), Rect 1 2]",
+            Object::String("[Circle (-3),Rect 1 2]".to_owned()),
+        );
+        check(
+            "showsPrec 11 (Rect 1 2) \"\"",
+            Object::String("(Rect 1 2)".to_owned()),
+        );
+        check(
+            "show (P \"Ann\" 30)",
+            Object::String("P {name = \"Ann\", age = 30}".to_owned()),
+        );
+        check("age (P \"Ann\" 30)", Object::Integer(30));
+        check("read \" ( Green ) \" == Green", Object::Boolean(true));
+        check("succ Red == Green", Object::Boolean(true));
+        check("fromEnum Blue", Object::Integer(2));
+        check("toEnum 1 == Green", Object::Boolean(true));
+        check(
+            "show (enumFromTo Red maxBound)",
+            Object::String("[Red,Green,Blue]".to_owned()),
+        );
+        check("index (Green, Blue) Blue", Object::Integer(1));
+        check(
+            "show (range ((Red, Red), (Green, Blue)))",
+            Object::String(
+                "[(Red,Red),(Red,Green),(Red,Blue),(Green,Red),(Green,Green),(Green,Blue)]"
+                    .to_owned(),
+            ),
+        );
+        check(
+            "succ Blue",
+            Object::Error("Prelude.Enum.Color.succ: bad argument".to_owned()),
+        );
+        compare_haskell(
+            "data T = T Integer deriving Enum",
+            Object::Error(
+                "Can't make a derived instance of `Enum T`: `T` must be an enumeration type \
+                 (an enumeration consists of one or more nullary constructors)"
+                    .to_owned(),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_tuples() {
+        let pair = |a: i64, b: i64| {
+            Object::Data(
+                "(,)".to_owned(),
+                vec![Object::Integer(a), Object::Integer(b)],
+            )
+        };
+        compare_haskell("fst (1, 2)", Object::Integer(1));
+        compare_haskell("snd (1, (2, 3))", pair(2, 3));
+        compare_haskell("curry fst 1 2", Object::Integer(1));
+        compare_haskell("uncurry max (3, 4)", Object::Integer(4));
+        compare_haskell("(,) 1 2", pair(1, 2));
+        compare_haskell("let f () = 5 in f ()", Object::Integer(5));
+        compare_haskell(
+            "show (1, \"a\", [(), ()])",
+            Object::String("(1,\"a\",[(),()])".to_owned()),
+        );
+        compare_haskell("(1, 2) < (1, 3)", Object::Boolean(true));
+        compare_haskell(
+            "compare (1,2,3,4,5,6,7,8,9,10,11,12,13,14,15) (1,2,3,4,5,6,7,8,9,10,11,12,13,14,0)",
+            Object::Data("GT".to_owned(), vec![]),
+        );
+        compare_haskell("minBound == (False, LT)", Object::Boolean(true));
+        compare_haskell("read \"(1, [True])\" == (1, [True])", Object::Boolean(true));
+        compare_haskell(
+            "range ((0, 0), (1, 1))",
+            Object::Array(vec![pair(0, 0), pair(0, 1), pair(1, 0), pair(1, 1)]),
+        );
+        compare_haskell("index ((0, 0), (1, 2)) (1, 1)", Object::Integer(4));
+        compare_haskell("rangeSize ('a', 'z')", Object::Integer(26));
+    }
+
+    #[test]
+    fn test_pattern_bindings() {
+        let pair = |a: i64, b: i64| {
+            Object::Data(
+                "(,)".to_owned(),
+                vec![Object::Integer(a), Object::Integer(b)],
+            )
+        };
+        compare_haskell("let (a, b) = (1, 2) in a + b", Object::Integer(3));
+        compare_haskell("f p = a * b where (a, b) = p\nf (3, 4)", Object::Integer(12));
+        compare_haskell("(x : _, Just y) = ([1, 2], Just 3)\nx + y", Object::Integer(4));
+        compare_haskell("let all@(a, _) = (1, 2) in (fst all, a)", pair(1, 1));
+        // the value is matched when a variable is used, and then in full
+        compare_haskell("let (a, b) = error \"boom\" in 1", Object::Integer(1));
+        compare_haskell(
+            "let [a, b] = [1, 2, 3] in a",
+            Object::Error("Non-exhaustive patterns in case".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_sequences() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        compare_haskell("[1..5]", ints(&[1, 2, 3, 4, 5]));
+        compare_haskell("[1,3..9]", ints(&[1, 3, 5, 7, 9]));
+        compare_haskell("[10,8..0]", ints(&[10, 8, 6, 4, 2, 0]));
+        compare_haskell("[5..1]", ints(&[]));
+        compare_haskell("take 3 [7..]", ints(&[7, 8, 9]));
+        compare_haskell("take 3 [1,-1..]", ints(&[1, -1, -3]));
+        // finite ranges are lazy too, and stop at the largest integer
+        compare_haskell("take 3 [1..200000000]", ints(&[1, 2, 3]));
+        compare_haskell("take 3 [1,1..5]", ints(&[1, 1, 1]));
+        compare_haskell(
+            "[9223372036854775806 .. 9223372036854775807]",
+            ints(&[i64::MAX - 1, i64::MAX]),
+        );
+        compare_haskell("[9223372036854775807 .. 9223372036854775807]", ints(&[i64::MAX]));
+        compare_haskell("['a'..'e']", Object::String("abcde".to_owned()));
+        compare_haskell("take 3 ['x'..]", Object::String("xyz".to_owned()));
+        compare_haskell(
+            "[1.0,1.5..2.0]",
+            Object::Array(vec![Object::Float(1.0), Object::Float(1.5), Object::Float(2.0)]),
+        );
+        compare_haskell(
+            "data C = R | G | B deriving (Show, Enum)\nshow ([G ..], [B, G ..])",
+            Object::String("([G,B],[B,G,R])".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_lazy_lists() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        compare_haskell("ones = 1 : ones\ntake 3 ones", ints(&[1, 1, 1]));
+        compare_haskell(
+            "take 4 (concatMap (\\x -> [x, x]) [1..])",
+            ints(&[1, 1, 2, 2]),
+        );
+        compare_haskell("head (drop 100 [0..])", Object::Integer(100));
+        compare_haskell("take 2 ([1] ++ [5..])", ints(&[1, 5]));
+        compare_haskell(
+            "take 2 [1, error \"boom\"]",
+            Object::Error("boom".to_owned()),
+        );
+        compare_haskell("take 1 (1 : error \"boom\")", ints(&[1]));
+    }
+
+    #[test]
+    fn test_list_comprehensions() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        let triple = |a, b, c| {
+            let fields = vec![Object::Integer(a), Object::Integer(b), Object::Integer(c)];
+            Object::Data("(,,)".to_owned(), fields)
+        };
+        compare_haskell("[x * x | x <- [1..5], x > 2]", ints(&[9, 16, 25]));
+        compare_haskell(
+            "[z | x <- [1..3], y <- [x..3], let z = x * 10 + y]",
+            ints(&[11, 12, 13, 22, 23, 33]),
+        );
+        compare_haskell(
+            "data M = J Integer | N\n[x | J x <- [J 1, N, J 3]]",
+            ints(&[1, 3]),
+        );
+        compare_haskell(
+            "show [(x, c) | x <- [1, 2], c <- \"ab\"]",
+            Object::String("[(1,'a'),(1,'b'),(2,'a'),(2,'b')]".to_owned()),
+        );
+        compare_haskell(
+            "take 2 [(a, b, c) | c <- [1..], b <- [1..c], a <- [1..b], a * a + b * b == c * c]",
+            Object::Array(vec![
+                triple(3, 4, 5),
+                triple(6, 8, 10),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_do_notation() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        let just = |o| Object::Data("Just".to_owned(), vec![o]);
+        let safe_div = "safeDiv _ 0 = Nothing\nsafeDiv x y = Just (x `div` y)\n";
+        let calc = "calc a b c = do\n  x <- safeDiv a b\n  let y = x * 2\n  z <- safeDiv y c\n  \
+                    return (x + z)\n";
+        compare_haskell(&format!("{}{}calc 10 2 5", safe_div, calc), just(Object::Integer(7)));
+        compare_haskell(
+            &format!("{}{}calc 10 2 0", safe_div, calc),
+            Object::Data("Nothing".to_owned(), vec![]),
+        );
+        compare_haskell(
+            "do { x <- [1, 2]; y <- [10, 20]; return (x + y) }",
+            ints(&[11, 21, 12, 22]),
+        );
+        // a failed pattern match calls `fail`
+        compare_haskell("do { (x:_) <- [[1], [], [3]]; return x }", ints(&[1, 3]));
+        compare_haskell(
+            "do { [] <- Just [1]; return 0 }",
+            Object::Data("Nothing".to_owned(), vec![]),
+        );
+        compare_haskell("sequence [Just 1, Just 2]", just(ints(&[1, 2])));
+        compare_haskell("do { print 1; return 2 }", Object::Integer(2));
+        compare_haskell("mapM_ print [1, 2]", Object::Data("()".to_owned(), vec![]));
+        let state = "data State s a = State (s -> (a, s))\n\
+                     runState (State f) = f\n\
+                     instance Monad (State s) where\n  \
+                     State m >>= k = State (\\s -> case m s of\n    \
+                     (a, s') -> runState (k a) s')\n  \
+                     return a = State (\\s -> (a, s))\n\
+                     get = State (\\s -> (s, s))\n\
+                     put s = State (\\_ -> ((), s))\n\
+                     tick = do\n  n <- get\n  put (n + 1)\n  return n\n";
+        compare_haskell(
+            &format!("{}runState (tick >> tick) 5", state),
+            Object::Data("(,)".to_owned(), vec![Object::Integer(6), Object::Integer(7)]),
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        compare_haskell("map (+ 1) [1, 2, 3]", ints(&[2, 3, 4]));
+        compare_haskell("map (10 -) [1, 2]", ints(&[9, 8]));
+        compare_haskell("map (`div` 2) [7, -7]", ints(&[3, -4]));
+        compare_haskell("map (subtract 1) [5, 6]", ints(&[4, 5]));
+        compare_haskell("(- 1)", Object::Integer(-1));
+        compare_haskell("map (0:) [[1], [2]]", Object::Array(vec![ints(&[0, 1]), ints(&[0, 2])]));
+        compare_haskell("(+) 1 2", Object::Integer(3));
+        compare_haskell("flip (-) 1 10", Object::Integer(9));
+        compare_haskell("3 `elem` [1, 2, 3]", Object::Boolean(true));
+        compare_haskell("4 `elem` [1, 2, 3]", Object::Boolean(false));
+        compare_haskell("(-7) `mod` 2 + 7 `mod` (-2)", Object::Integer(0));
+        compare_haskell(
+            "(minBound :: Int) `div` (-1)",
+            Object::Error(String::from("arithmetic overflow")),
+        );
+        compare_haskell("(minBound :: Int) `mod` (-1)", Object::Integer(0));
+        compare_haskell("let x = 2 in (x `compare`) 1", Object::Data("GT".to_owned(), vec![]));
+        compare_haskell("(`elem` \"abc\") 'z'", Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_syntax_extensions() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        let pragma = "{-# LANGUAGE LambdaCase, MultiWayIf, TupleSections, BlockArguments #-}\n";
+        let input = "sign = \\case\n\
+                     \x20 0 -> 0\n\
+                     \x20 n | n < 0 -> -1\n\
+                     \x20     | otherwise -> 1\n\
+                     clamp x = if | x > 9 -> 9\n\
+                     \x20            | x < 0 -> 0\n\
+                     \x20            | otherwise -> x\n\
+                     map sign [-5, 0, 5] ++ map clamp [-1, 4, 12]";
+        compare_haskell(&(pragma.to_owned() + input), ints(&[-1, 0, 1, 0, 4, 9]));
+        let pair = |a| {
+            Object::Data("(,)".to_owned(), vec![Object::Integer(a), Object::Boolean(true)])
+        };
+        compare_haskell(
+            &(pragma.to_owned() + "map (1,) [True] ++ map (, True) [2]"),
+            Object::Array(vec![pair(1), pair(2)]),
+        );
+        compare_haskell(&(pragma.to_owned() + "(, 2, ) 1 3 == (1, 2, 3)"), Object::Boolean(true));
+        compare_haskell(
+            &(pragma.to_owned() + "apply x f = f x\napply 2 \\x -> x * 3"),
+            Object::Integer(6),
+        );
+        compare_haskell(
+            &(pragma.to_owned() + "head do\n  x <- [1, 2]\n  [x * 10]"),
+            Object::Integer(10),
+        );
+        compare_haskell(
+            &(pragma.to_owned() + "f x = if | x -> 1\nf False"),
+            Object::Error("Non-exhaustive patterns in case".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_typed_holes() {
+        let found = |hole: &str| Object::Error(format!("Found hole: {}", hole));
+        compare_haskell("f x = x + _\nf 1", found("_"));
+        compare_haskell("map _step [1, 2]", found("_step"));
+        compare_haskell("let _n = 2 in _n * 3", Object::Integer(6));
+        compare_haskell("f _ = 1\nf 2", Object::Integer(1));
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        compare_haskell("read \"5\" :: Int", Object::Integer(5));
+        compare_haskell("read \"[1, 2]\" :: [Int]", ints(&[1, 2]));
+        compare_haskell("read \"2.5\" :: Double", Object::Float(2.5));
+        compare_haskell("fromIntegral 3 :: Double", Object::Float(3.0));
+        compare_haskell("fromIntegral (len [1, 2]) + 0.5", Object::Float(2.5));
+        compare_haskell("(maxBound :: Int) > 0", Object::Boolean(true));
+        let pragma = "{-# LANGUAGE ScopedTypeVariables #-}\n";
+        compare_haskell(
+            &(pragma.to_owned() + "half (n :: Double) = n / 2.0\nhalf 3"),
+            Object::Float(1.5),
+        );
+        compare_haskell(
+            &(pragma.to_owned() + "parse = \\(s :: String) -> (read s :: Int) + 1\nparse \"41\""),
+            Object::Integer(42),
+        );
+    }
+
+    #[test]
+    fn test_fixity_declarations() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        let input = "infixl 6 <+>\n\
+                     a <+> b = a * 10 + b\n\
+                     infixl 7 `times`\n\
+                     times a b = a * b\n\
+                     f z = z ^^^ 2 ^^^ 3\n\
+                     \x20 where\n\
+                     \x20   infixr 6 ^^^\n\
+                     \x20   x ^^^ y = x - y\n\
+                     [1 <+> 2 <+> 3, 2 `times` 3 <+> 4, f 10, 10 - 2 - 3]";
+        compare_haskell(input, ints(&[123, 64, 11, 5]));
+        compare_haskell(
+            "infix 4 ~~\na ~~ b = a == b\n1 ~~ 2 ~~ 3",
+            Object::Error(
+                "Precedence parsing error: cannot mix `~~` [infix 4] and `~~` [infix 4] \
+                 in the same infix expression"
+                    .to_owned(),
+            ),
+        );
+        compare_haskell(
+            "1 == 1 && 2 /= 3 || False",
+            Object::Boolean(true),
+        );
+    }
+}
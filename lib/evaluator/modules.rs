@@ -0,0 +1,1163 @@
+use crate::core;
+use crate::evaluator::deriving::derive_instances;
+use crate::evaluator::environment::*;
+use crate::evaluator::object::*;
+use crate::evaluator::{parse_prelude, Evaluator};
+use crate::lexer::token::*;
+use crate::lexer::*;
+use crate::parser::ast::*;
+use crate::parser::rename::*;
+use crate::parser::*;
+use crate::typecheck::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// What a module exports: its values with the module defining each of them,
+/// and the constructors or methods exported with its types and classes.
+#[derive(Debug, Clone, Default)]
+pub struct Exports {
+    values: HashMap<String, (String, Object)>,
+    types: HashMap<String, Vec<String>>,
+}
+
+/// The modules found on the search path, each loaded at most once.
+#[derive(Debug, Default)]
+pub struct Modules {
+    search_path: Vec<PathBuf>,
+    loaded: HashMap<String, Exports>,
+    loading: Vec<String>,
+    prelude: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Modules {
+    pub fn new() -> Self {
+        Modules {
+            search_path: vec![PathBuf::from(".")],
+            ..Modules::default()
+        }
+    }
+
+    pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+        self.search_path = search_path;
+    }
+
+    /// Makes the environment the prelude was evaluated in the module
+    /// `Prelude`, imported by every module that doesn't import it explicitly.
+    pub fn set_prelude(&mut self, env: &Rc<RefCell<Environment>>, prelude: &Program) {
+        let values = env
+            .borrow()
+            .bindings()
+            .into_iter()
+            .map(|(name, object)| (name, ("Prelude".to_owned(), object)))
+            .collect();
+        let types = declared_types(prelude);
+        self.loaded
+            .insert("Prelude".to_owned(), Exports { values, types });
+        self.prelude = Some(Rc::clone(env));
+    }
+
+    // `Data.Queue` is in `Data/Queue.hs` under one of the search directories
+    fn find(&self, name: &str) -> Result<PathBuf, String> {
+        let file = name.split('.').collect::<PathBuf>().with_extension("hs");
+        let candidates: Vec<PathBuf> = self.search_path.iter().map(|dir| dir.join(&file)).collect();
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(path.clone()),
+            None => Err(format!(
+                "Could not find module `{}`, looked for {}",
+                name,
+                candidates
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+// The names the imports of a module bring into scope
+#[derive(Default)]
+struct Scope {
+    // each name with the different entities it may refer to, by defining module
+    values: HashMap<String, Vec<(String, Object)>>,
+    types: HashMap<String, Vec<String>>,
+    // the values and types imported unqualified under each module name or
+    // alias, for `module M` exports
+    modules: HashMap<String, (Vec<String>, Vec<String>)>,
+}
+
+impl Scope {
+    fn bind(&mut self, name: String, origin: &str, object: &Object) {
+        let entities = self.values.entry(name).or_default();
+        if !entities.iter().any(|(module, _)| module == origin) {
+            entities.push((origin.to_owned(), object.clone()));
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Result<(String, Object), String>> {
+        match self.values.get(name)?.as_slice() {
+            [entity] => Some(Ok(entity.clone())),
+            entities => Some(Err(ambiguous(name, entities))),
+        }
+    }
+}
+
+// The names in scope the type checker knows the types of, those that are not
+// ambiguous
+fn imported(scope: &Scope) -> Vec<Imported> {
+    let mut imports = vec![];
+    for (name, entities) in &scope.values {
+        if let [(origin, object)] = entities.as_slice() {
+            imports.push(Imported {
+                name: name.clone(),
+                origin: origin.clone(),
+                builtin: matches!(object, Object::Builtin(..)),
+            });
+        }
+    }
+    imports
+}
+
+// The Monkey functions in scope, builtins included, which `f(x, y)` applies
+// to `x` and `y` rather than to the tuple
+fn monkey_functions(scope: &Scope) -> Vec<String> {
+    let mut functions = vec![];
+    for (name, entities) in &scope.values {
+        if let [(_, Object::Builtin(..) | Object::Function(..))] = entities.as_slice() {
+            functions.push(name.clone());
+        }
+    }
+    functions
+}
+
+// All the errors, one to a line, located in the tokens of their module
+fn type_errors(errors: Vec<TypeError>, tokens: &Tokens, format: ErrorFormat) -> String {
+    let errors: Vec<String> = errors
+        .into_iter()
+        .map(|e| e.locate(tokens).render(format))
+        .collect();
+    errors.join("\n")
+}
+
+// The warnings of the modules checked since they were last taken, located
+// in the tokens of the last
+fn warnings(types: &mut TypeEnv, tokens: &Tokens, format: ErrorFormat) -> Vec<String> {
+    types
+        .take_warnings()
+        .into_iter()
+        .map(|w| w.locate(tokens).render(format))
+        .collect()
+}
+
+fn ambiguous(name: &str, entities: &[(String, Object)]) -> String {
+    let unqualified = Ident(name.to_owned()).unqualified().to_owned();
+    let candidates: Vec<String> = entities
+        .iter()
+        .map(|(module, _)| format!("`{}.{}`", module, unqualified))
+        .collect();
+    format!(
+        "Ambiguous occurrence `{}`: it could refer to {}",
+        name,
+        candidates.join(" or ")
+    )
+}
+
+/// The types and classes a program declares, with their constructors and
+/// field labels or methods.
+pub fn declared_types(program: &Program) -> HashMap<String, Vec<String>> {
+    let mut types = HashMap::new();
+    for stmt in program {
+        match stmt {
+            Stmt::DataStmt(decl) => {
+                let mut members = vec![];
+                for con in &decl.constructors {
+                    members.push(con.name.0.clone());
+                    for Ident(label) in &con.labels {
+                        if !members.contains(label) {
+                            members.push(label.clone());
+                        }
+                    }
+                }
+                types.insert(decl.name.0.clone(), members);
+            }
+            Stmt::TypeStmt(Ident(name), ..) => {
+                types.insert(name.clone(), vec![]);
+            }
+            Stmt::ClassStmt(decl) => {
+                let mut methods = vec![];
+                for stmt in &decl.body {
+                    if let Stmt::SigStmt(names, ..) = stmt {
+                        methods.extend(names.iter().map(|Ident(name)| name.clone()));
+                    }
+                }
+                types.insert(decl.name.0.clone(), methods);
+            }
+            _ => {}
+        }
+    }
+    types
+}
+
+// The members of `T`, `T(..)` or `T(a, b)` among those `all` it has, or the
+// first listed one that is not
+fn members(members: &Members, all: &[String]) -> Result<Vec<String>, String> {
+    match members {
+        Members::Abstract => Ok(vec![]),
+        Members::All => Ok(all.to_vec()),
+        Members::Listed(names) => names
+            .iter()
+            .map(|Ident(name)| match all.contains(name) {
+                true => Ok(name.clone()),
+                false => Err(name.clone()),
+            })
+            .collect(),
+    }
+}
+
+impl Evaluator {
+    pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+        self.modules.set_search_path(search_path);
+    }
+
+    /// Reports type errors as text, the default, or as JSON for tools.
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.error_format = format;
+    }
+
+    /// The warnings of the modules and lines checked since the last call,
+    /// such as pattern matches that miss values, in the error format.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Evaluates a module after loading the modules it imports from the
+    /// search path, and returns the value of its last statement.
+    pub fn eval_module(&mut self, module: Module) -> Object {
+        self.eval_module_with_spans(module, Tokens::new(&[]))
+    }
+
+    /// `eval_module` for a module parsed from the tokens, whose spans locate
+    /// the names that are not in scope.
+    pub fn eval_module_with_spans(&mut self, module: Module, tokens: Tokens) -> Object {
+        self.modules.loading.push(module.name.0.clone());
+        let result = self.load_module(module, tokens);
+        self.modules.loading.pop();
+        match result {
+            Ok((object, _)) => object,
+            Err(err) => Object::Error(err),
+        }
+    }
+
+    /// The Core a module desugars into once it type checks, after loading
+    /// the modules it imports, linted. The module itself is not evaluated.
+    pub fn core_module(&mut self, module: Module, tokens: Tokens) -> Result<core::Program, String> {
+        self.modules.loading.push(module.name.0.clone());
+        let result = self.desugar_module(module, tokens);
+        self.modules.loading.pop();
+        result
+    }
+
+    fn desugar_module(&mut self, module: Module, tokens: Tokens) -> Result<core::Program, String> {
+        let scope = self.import_all(&module.imports)?;
+        // Core has the methods the evaluator derives as it loads a module
+        let datatype = |tycon: &str| self.classes.datatype(tycon).cloned();
+        let body = derive_instances(module.body, datatype, &mut self.fixities)?;
+        let module = self.rename(Module { body, ..module }, &scope, tokens)?;
+        let body = self.typecheck(&module, &scope, &tokens)?;
+        let program = core::desugar_program(&self.types, &body, monkey_functions(&scope));
+        let names = scope.values.keys().cloned();
+        let globals = core::Globals::new(&self.types, &module.name.0, names);
+        core::lint(&program, &globals).map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            errors.join("\n")
+        })?;
+        Ok(program)
+    }
+
+    /// Evaluates a line of an interactive session, in the environment of the
+    /// lines before it, once it type checks. Its numbers are overloaded as
+    /// in a module, defaulted by GHCi's rules.
+    pub fn eval_interactive(&mut self, program: Program) -> Object {
+        match self.check_interactive(program) {
+            Ok(program) => self.eval_program(program),
+            Err(err) => Object::Error(err),
+        }
+    }
+
+    fn check_interactive(&mut self, program: Program) -> Result<Program, String> {
+        let program = self.fixities.resolve_program(program)?;
+        self.check_prelude()?;
+        let scope = self.import_all(&[])?;
+        let format = self.error_format;
+        let program = self
+            .types
+            .check_interactive(&program, &imported(&scope))
+            .map_err(|errors| {
+                // the line typed is all there is to point at
+                let errors = errors.into_iter().map(|e| TypeError { span: None, ..e });
+                type_errors(errors.collect(), &Tokens::new(&[]), format)
+            })?;
+        let mut warnings = warnings(&mut self.types, &Tokens::new(&[]), format);
+        self.warnings.append(&mut warnings);
+        Ok(program)
+    }
+
+    fn load_module(&mut self, module: Module, tokens: Tokens) -> Result<(Object, Exports), String> {
+        let scope = self.import_all(&module.imports)?;
+        let module = self.rename(module, &scope, tokens)?;
+        let body = self.typecheck(&module, &scope, &tokens)?;
+        let env = Rc::new(RefCell::new(Environment::new_with_outer(
+            self.scope_env(&scope),
+        )));
+        let old_env = std::mem::replace(&mut self.env, Rc::clone(&env));
+        let object = self.eval_program(body);
+        self.env = old_env;
+        if let Object::Error(err) = object {
+            return Err(err);
+        }
+        let exports = self.exports(&module, &env.borrow(), &scope)?;
+        Ok((object, exports))
+    }
+
+    // The exports of a module, loading it the first time
+    fn import(&mut self, name: &str) -> Result<Exports, String> {
+        if let Some(exports) = self.modules.loaded.get(name) {
+            return Ok(exports.clone());
+        }
+        if let Some(start) = self.modules.loading.iter().position(|m| m == name) {
+            let mut cycle = self.modules.loading[start..].to_vec();
+            cycle.push(name.to_owned());
+            return Err(format!(
+                "Module imports form a cycle: {}",
+                cycle.join(" imports ")
+            ));
+        }
+        let path = self.modules.find(name)?;
+        let source = fs::read_to_string(&path)
+            .map_err(|err| format!("Could not read `{}`: {}", path.display(), err))?;
+        let syntax_error = || format!("Syntax error in module `{}` ({})", name, path.display());
+        let (_, (tokens, spans)) =
+            Lexer::lex_tokens_with_spans(&source).map_err(|_| syntax_error())?;
+        let (module, errors) = Parser::parse_module_recovering(Tokens::with_spans(&tokens, &spans));
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(format!("{}: {}", syntax_error(), errors.join("; ")));
+        }
+        if module.name.0 != name {
+            return Err(format!(
+                "File `{}` declares module `{}`, not `{}`",
+                path.display(),
+                module.name.0,
+                name
+            ));
+        }
+        self.modules.loading.push(name.to_owned());
+        let result = self.load_module(module, Tokens::with_spans(&tokens, &spans));
+        self.modules.loading.pop();
+        let (_, exports) = result?;
+        self.modules.loaded.insert(name.to_owned(), exports.clone());
+        Ok(exports)
+    }
+
+    // Resolves the names of a module against its imports, all the errors
+    // one to a line. The operators are associated first, by the fixities of
+    // the names as written.
+    fn rename(&mut self, module: Module, scope: &Scope, tokens: Tokens) -> Result<Module, String> {
+        let body = self.fixities.resolve_program(module.body)?;
+        let mut renamer = Renamer::new().with_spans(tokens);
+        for (name, entities) in &scope.values {
+            for (origin, object) in entities {
+                let binder = match object {
+                    Object::Builtin(..) => Binder::Builtin,
+                    _ => Binder::Imported(origin.clone()),
+                };
+                renamer.import(name, origin, binder);
+            }
+        }
+        match renamer.rename_module(Module { body, ..module }) {
+            Ok(resolved) => Ok(resolved.module),
+            Err(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                Err(errors.join("\n"))
+            }
+        }
+    }
+
+    // Rejects an ill-typed module before it runs, all the errors one to a
+    // line, or gives its body elaborated into dictionary passing. The
+    // prelude is checked the first time, for the types of its names; it
+    // runs as it is.
+    fn typecheck(
+        &mut self,
+        module: &Module,
+        scope: &Scope,
+        tokens: &Tokens,
+    ) -> Result<Program, String> {
+        self.check_prelude()?;
+        let Ident(ref name) = module.name;
+        let format = self.error_format;
+        let body = self
+            .types
+            .check_module(name, &module.body, &imported(scope))
+            .map_err(|errors| type_errors(errors, tokens, format))?;
+        let mut warnings = warnings(&mut self.types, tokens, format);
+        self.warnings.append(&mut warnings);
+        Ok(body)
+    }
+
+    fn check_prelude(&mut self) -> Result<(), String> {
+        if !self.types.is_checked("Prelude") {
+            let prelude = self.fixities.resolve_program(parse_prelude())?;
+            self.types
+                .check_module("Prelude", &prelude, &[])
+                .map_err(|errors| type_errors(errors, &Tokens::new(&[]), ErrorFormat::Human))?;
+            // its partial functions, such as `head`, are meant
+            self.types.take_warnings();
+        }
+        Ok(())
+    }
+
+    // `Prelude` is imported implicitly unless a declaration imports it
+    fn import_all(&mut self, imports: &[Import]) -> Result<Scope, String> {
+        let mut scope = Scope::default();
+        let prelude = Import {
+            module: Ident("Prelude".to_owned()),
+            qualified: false,
+            alias: None,
+            hiding: false,
+            entities: None,
+        };
+        let implicit = match imports.iter().any(|import| import.module.0 == "Prelude") {
+            true => None,
+            false => Some(&prelude),
+        };
+        for import in implicit.into_iter().chain(imports) {
+            let Ident(ref module) = import.module;
+            let exports = self.import(module)?;
+            let (values, types) = select(import, &exports)?;
+            let Ident(ref qualifier) = *import.alias.as_ref().unwrap_or(&import.module);
+            for name in values {
+                let (origin, object) = &exports.values[&name];
+                scope.bind(format!("{}.{}", qualifier, name), origin, object);
+                if !import.qualified {
+                    scope.bind(name.clone(), origin, object);
+                    scope
+                        .modules
+                        .entry(qualifier.clone())
+                        .or_default()
+                        .0
+                        .push(name);
+                }
+            }
+            for (name, members) in types {
+                scope
+                    .types
+                    .insert(format!("{}.{}", qualifier, name), members.clone());
+                if !import.qualified {
+                    scope.types.insert(name.clone(), members);
+                    scope
+                        .modules
+                        .entry(qualifier.clone())
+                        .or_default()
+                        .1
+                        .push(name);
+                }
+            }
+        }
+        Ok(scope)
+    }
+
+    // The environment of the imported names, in front of the prelude's own.
+    // Prelude names that are not imported unqualified are hidden, and names
+    // imported from different modules are an error when they are used.
+    fn scope_env(&self, scope: &Scope) -> Rc<RefCell<Environment>> {
+        let prelude = self.modules.prelude.as_ref().expect("prelude is loaded");
+        let mut env = Environment::new_with_outer(Rc::clone(prelude));
+        for name in self.modules.loaded["Prelude"].values.keys() {
+            if !scope.values.contains_key(name) {
+                env.set(
+                    name,
+                    Object::Error(format!("identifier not found: {}", name)),
+                );
+            }
+        }
+        for name in scope.values.keys() {
+            match scope.lookup(name) {
+                Some(Ok((_, object))) => env.set(name, object),
+                Some(Err(err)) => env.set(name, Object::Error(err)),
+                None => {}
+            }
+        }
+        Rc::new(RefCell::new(env))
+    }
+
+    fn exports(
+        &self,
+        module: &Module,
+        env: &Environment,
+        scope: &Scope,
+    ) -> Result<Exports, String> {
+        let Ident(ref name) = module.name;
+        let own: HashMap<String, Object> = env.bindings().into_iter().collect();
+        let own_types = declared_types(&module.body);
+        let mut exports = Exports::default();
+        // without an export list a module exports what it defines
+        let everything = [Entity::Module(module.name.clone())];
+        let entities = module.exports.as_deref().unwrap_or(&everything);
+        let lookup = |var: &str| match own.get(var) {
+            Some(object) => Ok((name.clone(), object.clone())),
+            None => match scope.lookup(var) {
+                Some(entity) => entity,
+                None => Err(format!(
+                    "Module `{}` exports `{}`, which is not in scope",
+                    name, var
+                )),
+            },
+        };
+        for entity in entities {
+            match entity {
+                Entity::Var(var) => {
+                    let entity = lookup(&var.0)?;
+                    exports.values.insert(var.unqualified().to_owned(), entity);
+                }
+                Entity::Type(ty, listed) => {
+                    let all = own_types
+                        .get(&ty.0)
+                        .or_else(|| scope.types.get(&ty.0))
+                        .ok_or_else(|| {
+                            format!(
+                                "Module `{}` exports `{}`, which is not a type or class in scope",
+                                name, ty.0
+                            )
+                        })?;
+                    let members = members(listed, all).map_err(|member| {
+                        format!("`{}` is not a constructor or method of `{}`", member, ty.0)
+                    })?;
+                    for member in &members {
+                        exports.values.insert(member.clone(), lookup(member)?);
+                    }
+                    exports.types.insert(ty.unqualified().to_owned(), members);
+                }
+                Entity::Module(Ident(module)) if module == name => {
+                    for (var, object) in &own {
+                        exports
+                            .values
+                            .insert(var.clone(), (name.clone(), object.clone()));
+                    }
+                    exports.types.extend(own_types.clone());
+                }
+                Entity::Module(Ident(module)) => {
+                    let (values, types) = scope.modules.get(module).ok_or_else(|| {
+                        format!("The export item `module {}` is not imported", module)
+                    })?;
+                    for var in values {
+                        exports.values.insert(var.clone(), lookup(var)?);
+                    }
+                    for ty in types {
+                        exports.types.insert(ty.clone(), scope.types[ty].clone());
+                    }
+                }
+            }
+        }
+        Ok(exports)
+    }
+}
+
+// The values and the types with their members an import declaration takes
+// from the exports of a module
+type Selection = (Vec<String>, Vec<(String, Vec<String>)>);
+
+fn select(import: &Import, exports: &Exports) -> Result<Selection, String> {
+    let Ident(ref module) = import.module;
+    let all_types = || {
+        exports
+            .types
+            .iter()
+            .map(|(ty, members)| (ty.clone(), members.clone()))
+    };
+    let entities = match import.entities {
+        None => {
+            return Ok((
+                exports.values.keys().cloned().collect(),
+                all_types().collect(),
+            ))
+        }
+        Some(ref entities) => entities,
+    };
+    let not_exported = |name: &str| format!("Module `{}` does not export `{}`", module, name);
+    let mut values = vec![];
+    let mut types = vec![];
+    for entity in entities {
+        match entity {
+            Entity::Var(Ident(var)) if exports.values.contains_key(var) => values.push(var.clone()),
+            Entity::Var(Ident(var)) if import.hiding => {}
+            Entity::Var(Ident(var)) => return Err(not_exported(var)),
+            Entity::Type(ty, listed) => match exports.types.get(&ty.0) {
+                Some(all) => {
+                    let members = members(listed, all)
+                        .map_err(|member| not_exported(&format!("{}({})", ty.0, member)))?;
+                    values.extend(members.iter().cloned());
+                    types.push((ty.0.clone(), members));
+                }
+                // `hiding (C)` also hides a constructor `C`
+                None if import.hiding => values.push(ty.0.clone()),
+                None => return Err(not_exported(&ty.0)),
+            },
+            Entity::Module(Ident(name)) => {
+                return Err(format!(
+                    "`module {}` can only appear in an export list",
+                    name
+                ))
+            }
+        }
+    }
+    if !import.hiding {
+        return Ok((values, types));
+    }
+    let hidden_types: Vec<String> = types.into_iter().map(|(ty, _)| ty).collect();
+    Ok((
+        exports
+            .values
+            .keys()
+            .filter(|var| !values.contains(var))
+            .cloned()
+            .collect(),
+        all_types()
+            .filter(|(ty, _)| !hidden_types.contains(ty))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::builtins::BuiltinsFunctions;
+    use crate::parser::fixity::Fixities;
+
+    fn eval(input: &str) -> Object {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let tokens = Tokens::with_spans(&tokens, &spans);
+        let (_, module) = Parser::parse_module(tokens).unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_search_path(vec![PathBuf::from("examples/modules")]);
+        evaluator.eval_module_with_spans(module, tokens)
+    }
+
+    fn error(message: &str) -> Object {
+        Object::Error(message.to_owned())
+    }
+
+    fn core(input: &str) -> Result<String, String> {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let tokens = Tokens::with_spans(&tokens, &spans);
+        let (_, module) = Parser::parse_module(tokens).unwrap();
+        let mut evaluator = Evaluator::new();
+        let program = evaluator.core_module(module, tokens)?;
+        Ok(program.to_string())
+    }
+
+    #[test]
+    fn test_imports() {
+        let ints = |xs: &[i64]| Object::Array(xs.iter().map(|&x| Object::Integer(x)).collect());
+        let queue = "Q.toList (Q.push 2 (Q.push 1 Q.empty))";
+        assert_eq!(
+            eval(&format!("import qualified Data.Queue as Q\n{}", queue)),
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            eval(&format!("import Data.Queue as Q\n{}", queue)),
+            ints(&[1, 2])
+        );
+        assert_eq!(
+            eval("import Data.Queue (empty, toList)\ntoList empty"),
+            ints(&[])
+        );
+        assert_eq!(eval("import Shapes\narea (Square 3.0)"), Object::Float(9.0));
+        assert_eq!(
+            eval("import Shapes (size)\nsize [1, 2]"),
+            Object::Integer(2)
+        );
+        // a name defined in several modules is only an error when it is used
+        assert_eq!(eval("import Shapes\nimport Sizes\n1"), Object::Integer(1));
+        assert_eq!(
+            eval("import Shapes\nimport Sizes (size)\nSizes.size"),
+            Object::Integer(42)
+        );
+        assert_eq!(
+            eval("import Prelude hiding (map)\nmap f xs = 0\nmap 1 2"),
+            Object::Integer(0)
+        );
+        assert_eq!(
+            eval("import qualified Prelude as P\nP.map P.even [1, 2]"),
+            Object::Array(vec![Object::Boolean(false), Object::Boolean(true)])
+        );
+        // `module Shapes` re-exports what `Geometry` imports from `Shapes`
+        assert_eq!(
+            eval("import Geometry\n(area (Square 1.0), perimeter (Square 1.0))"),
+            Object::Data(
+                "(,)".to_owned(),
+                vec![Object::Float(1.0), Object::Float(4.0)]
+            )
+        );
+    }
+
+    #[test]
+    fn test_import_errors() {
+        // qualified names need an import
+        assert_eq!(
+            eval("module M where\nData.Queue.empty"),
+            error("2:1: identifier not found: Data.Queue.empty")
+        );
+        assert_eq!(
+            eval("import Data.Queue\nreverse' [1]"),
+            error("2:1: identifier not found: reverse'")
+        );
+        assert_eq!(
+            eval("import Data.Queue\nQueue [] []"),
+            error("2:1: identifier not found: Queue")
+        );
+        assert_eq!(
+            eval("import Prelude hiding (map)\nmap (+ 1) [1]"),
+            error("2:1: identifier not found: map")
+        );
+        assert_eq!(
+            eval("import qualified Data.Queue as Q\nempty"),
+            error("2:1: identifier not found: empty")
+        );
+        assert_eq!(
+            eval("import Shapes\nimport Sizes\nsize"),
+            error(
+                "3:1: Ambiguous occurrence `size`: it could refer to `Shapes.size` or `Sizes.size`"
+            )
+        );
+        // every scope error is reported before anything runs
+        assert_eq!(
+            eval("import Data.Queue\nmain = print (g 1)\ng = reverse'\nh = Queue"),
+            error("3:5: identifier not found: reverse'\n4:5: identifier not found: Queue")
+        );
+        assert_eq!(
+            eval("import Data.Queue (reverse')\n1"),
+            error("Module `Data.Queue` does not export `reverse'`")
+        );
+        assert_eq!(
+            eval("import Shapes (Shape(Triangle))\n1"),
+            error("Module `Shapes` does not export `Shape(Triangle)`")
+        );
+        assert_eq!(
+            eval("import Data.Missing\n1"),
+            error(
+                "Could not find module `Data.Missing`, \
+                 looked for examples/modules/Data/Missing.hs"
+            )
+        );
+        assert_eq!(
+            eval("import Cycle.Left\n1"),
+            error("Module imports form a cycle: Cycle.Left imports Cycle.Right imports Cycle.Left")
+        );
+        assert_eq!(
+            eval("module M (missing) where\nx = 1"),
+            error("Module `M` exports `missing`, which is not in scope")
+        );
+    }
+
+    #[test]
+    fn test_dictionary_passing() {
+        let string = |s: &str| Object::String(s.to_owned());
+        // methods that only mention the class in their result find their
+        // instance by the type they are used at
+        assert_eq!(
+            eval("lo :: Char\nlo = minBound\n[lo, maxBound]"),
+            Object::Array(vec![Object::Char('\0'), Object::Char('\u{10FFFF}')])
+        );
+        assert_eq!(
+            eval("m = do { x <- Just 3; y <- return 4; return (x + y) }\nshow m"),
+            string("Just 7")
+        );
+        assert_eq!(eval("read \"[1,2]\" :: [Double]"), eval("[1.0, 2.0]"));
+        // the dictionaries of an instance's context, and of the constraints
+        // of overloaded definitions
+        assert_eq!(
+            eval(
+                "class Pretty a where\n\
+                 \x20 pretty :: a -> String\n\
+                 \x20 prettyList :: [a] -> String\n\
+                 \x20 prettyList xs = concatMap pretty xs\n\
+                 instance Pretty Bool where\n\
+                 \x20 pretty True = \"T\"\n\
+                 \x20 pretty False = \"F\"\n\
+                 instance Pretty a => Pretty [a] where\n\
+                 \x20 pretty xs = \"<\" ++ prettyList xs ++ \">\"\n\
+                 twice x = pretty x ++ pretty x\n\
+                 twice [[True], [False, True]]"
+            ),
+            string("<<T><FT>><<T><FT>>")
+        );
+        assert_eq!(
+            eval(
+                "data Tree a = Leaf | Node (Tree a) a (Tree a)\n\
+                 insert x Leaf = Node Leaf x Leaf\n\
+                 insert x t@(Node l y r)\n\
+                 \x20 | x < y = Node (insert x l) y r\n\
+                 \x20 | x > y = Node l y (insert x r)\n\
+                 \x20 | otherwise = t\n\
+                 toList Leaf = []\n\
+                 toList (Node l x r) = toList l ++ [x] ++ toList r\n\
+                 fromList [] = Leaf\n\
+                 fromList (x:xs) = insert x (fromList xs)\n\
+                 toList (fromList \"banana\")"
+            ),
+            eval("\"abn\"")
+        );
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        assert_eq!(eval("1 / 2"), Object::Float(0.5));
+        assert_eq!(eval("half x = x / 2\nhalf 3"), Object::Float(1.5));
+        assert_eq!(eval("default (Double)\nn = 3\nshow n"), Object::String("3.0".to_owned()));
+        // a literal pattern matches a number of any type
+        assert_eq!(
+            eval("isZero 0 = True\nisZero _ = False\n(isZero (0 :: Double), isZero 1.5)"),
+            eval("(True, False)")
+        );
+        // literals are the prelude's whatever the module hides, and a
+        // class's own instances give them their value
+        assert_eq!(
+            eval("import Prelude hiding (fromInteger)\nf x = x + 1\nf 2.5"),
+            Object::Float(3.5)
+        );
+        assert_eq!(
+            eval(
+                "data V = V Integer deriving Show\n\
+                 instance Num V where\n\
+                 \x20 fromInteger n = V (n * 2)\n\
+                 v :: V\n\
+                 v = 21\n\
+                 show v"
+            ),
+            Object::String("V 42".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_char_enumeration() {
+        let string = |s: &str| Object::String(s.to_owned());
+        assert_eq!(eval("show ['a'..'e']"), string("\"abcde\""));
+        assert_eq!(eval("show (succ 'a', pred 'b')"), string("('b','a')"));
+        assert_eq!(
+            eval("show (['a', 'c'..'g'], take 3 ['x'..])"),
+            string("(\"aceg\",\"xyz\")")
+        );
+    }
+
+    #[test]
+    fn test_nested_generators() {
+        // the `$ok` of an inner generator is not the overloaded outer one
+        let string = |s: &str| Object::String(s.to_owned());
+        assert_eq!(
+            eval("show [x + y | x <- [1, 2], y <- [3, 4]]"),
+            string("[4,5,5,6]")
+        );
+        assert_eq!(
+            eval("show [x + 1 | xs <- [[1, 2], [3]], x <- xs]"),
+            string("[2,3,4]")
+        );
+        assert_eq!(
+            eval("show [(x, y) | x <- [1..4], y <- [x..4], x + y == 5]"),
+            string("[(1,4),(2,3)]")
+        );
+        assert_eq!(
+            eval("show (do { Just x <- [Just 1]; Just y <- [Just 10]; return (x + y) })"),
+            string("[11]")
+        );
+    }
+
+    #[test]
+    fn test_pattern_bindings() {
+        // each variable of a pattern binding has a type of its own
+        let string = |s: &str| Object::String(s.to_owned());
+        assert_eq!(
+            eval("(f, g) = (\\x -> x, \\x -> x)\nshow (f 1, g 'a')"),
+            string("(1,'a')")
+        );
+        assert_eq!(
+            eval("m :: Maybe Int\nm = do { let { (a, b) = (5, 6) }; return (a - b) }\nshow m"),
+            string("Just (-1)")
+        );
+        assert_eq!(
+            eval("a :: Int\n(a, b) = (1, True)\nshow (a + 1, not b)"),
+            string("(2,False)")
+        );
+    }
+
+    #[test]
+    fn test_type_synonyms() {
+        assert_eq!(
+            eval(
+                "module Main (Name, greet) where\n\
+                 type Name = String\n\
+                 greet :: Name -> Name\n\
+                 greet n = \"hi \" ++ n\n\
+                 greet \"bob\""
+            ),
+            Object::String("hi bob".to_owned())
+        );
+        assert_eq!(
+            eval(
+                "type Pair a = (a, a)\n\
+                 swap :: Pair a -> Pair a\n\
+                 swap (x, y) = (y, x)\n\
+                 swap 'a'"
+            ),
+            error(
+                "4:6: Couldn't match expected type `(a, a)` with actual type `Char`\n  \
+                 In the first argument of `swap`, namely `'a'`\n  \
+                 In the expression: swap 'a'"
+            )
+        );
+    }
+
+    #[test]
+    fn test_interactive() {
+        let mut evaluator = Evaluator::new();
+        let mut eval = |input: &str| {
+            let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).unwrap();
+            evaluator.eval_interactive(program)
+        };
+        assert_eq!(eval("1 / 2"), Object::Float(0.5));
+        // the extended rules default the type of an empty list to show, and
+        // run an action of any monad in IO
+        assert_eq!(eval("show []"), Object::String("[]".to_owned()));
+        assert_eq!(eval("return 1"), Object::Integer(1));
+        // the names of the earlier lines are in scope
+        assert_eq!(eval("half x = x / 2"), Object::Null);
+        assert_eq!(eval("half 5"), Object::Float(2.5));
+        assert_eq!(
+            eval("half \"a\""),
+            error(
+                "No instance for (Fractional [Char]) arising from a use of `half`\n  \
+                 In the expression: half \"a\""
+            )
+        );
+    }
+
+    #[test]
+    fn test_type_errors() {
+        // kind errors are at the signature or the instance head
+        assert_eq!(
+            eval("data Maybe2 a = N | J a\n\nx :: Maybe2 Int Int\nx = N"),
+            error(
+                "3:6: Expected kind `* -> *`, but `Maybe2 Int` has kind `*`\n  \
+                 In the definition of `x`"
+            )
+        );
+        assert_eq!(
+            eval(
+                "class MyFunctor f where\n  fmap' :: (a -> b) -> f a -> f b\n\
+                 instance MyFunctor Int where\n  fmap' f x = x"
+            ),
+            error(
+                "3:10: Expected kind `* -> *`, but `Int` has kind `*`\n  \
+                 In the instance declaration for `MyFunctor Int`"
+            )
+        );
+        assert_eq!(
+            eval("x = 1 + \"a\"\nmain = print x"),
+            error(
+                "1:5: No instance for (Num [Char]) arising from a use of `+`\n  \
+                 In the expression: 1 + \"a\"\n  \
+                 In the definition of `x`"
+            )
+        );
+        // the types of imported names come from the module defining them
+        assert_eq!(
+            eval("import Data.Queue\ntoList (push 'a' empty) ++ [True]"),
+            error(
+                "2:28: Couldn't match expected type `[Char]` with actual type `[Bool]`\n  \
+                 In the second argument of `(++)`, namely `[True]`\n  \
+                 In the expression: toList (push 'a' empty) ++ [True]"
+            )
+        );
+        assert_eq!(
+            eval("f :: Int -> Int\nf x = x + _\nf 1"),
+            error(
+                "2:11: Found hole: _ :: Integer\n  \
+                 Relevant bindings include\n    \
+                 x :: Integer\n  \
+                 Valid hole fits include\n    \
+                 x :: Integer\n    \
+                 maxBound :: Bounded a => a\n    \
+                 minBound :: Bounded a => a\n  \
+                 In the definition of `f`"
+            )
+        );
+        // `maxBound` fits any type but a function's, with no `Bounded`
+        // instance, while `show` fits with the `Show a` of the signature
+        assert_eq!(
+            eval("f :: Show a => a -> String\nf x = _ x\n_ + 1"),
+            error(
+                "2:7: Found hole: _ :: a -> [Char]\n  \
+                 Relevant bindings include\n    \
+                 x :: a\n  \
+                 Valid hole fits include\n    \
+                 f :: Show a => a -> [Char]\n    \
+                 show :: Show a => a -> [Char]\n  \
+                 In the definition of `f`\n\
+                 3:1: Found hole: _ :: Integer\n  \
+                 Valid hole fits include\n    \
+                 maxBound :: Bounded a => a\n    \
+                 minBound :: Bounded a => a"
+            )
+        );
+        assert_eq!(
+            eval("data T = T\nT == T"),
+            error(
+                "2:1: No instance for (Eq T) arising from a use of `==`\n  \
+                 In the expression: T == T"
+            )
+        );
+        // the error is where the parser found its expression, even when the
+        // same text is earlier in the definition
+        assert_eq!(
+            eval("g = (\"a\", not \"a\")"),
+            error(
+                "1:15: Couldn't match expected type `Bool` with actual type `[Char]`\n  \
+                 In the first argument of `not`, namely `\"a\"`\n  \
+                 In the expression: not \"a\"\n  \
+                 In the definition of `g`"
+            )
+        );
+        assert_eq!(
+            eval("g = (1 + 2, [1 + 2, True])"),
+            error(
+                "1:14: No instance for (Num Bool) arising from a use of `+`\n  \
+                 In the expression: 1 + 2\n  \
+                 In the definition of `g`"
+            )
+        );
+        assert_eq!(
+            eval("f xs = ys\n  where\n    ys :: [a]\n    ys = xs"),
+            error(
+                "4:10: Couldn't match expected type `[a]` with actual type `b`: \
+                 the type variable `a` would escape its scope\n  \
+                 In the expression: xs\n  \
+                 In the definition of `ys`\n  \
+                 In the definition of `f`"
+            )
+        );
+        assert_eq!(
+            eval("data T = A deriving Eq\ninstance Show T\ninstance Eq T\ninstance Show T"),
+            error(
+                "3:10: Duplicate instance declarations:\n  \
+                 instance Eq T -- Derived in the declaration of `T`\n  \
+                 instance Eq T -- Defined at 3:10\n\
+                 4:10: Duplicate instance declarations:\n  \
+                 instance Show T -- Defined at 2:10\n  \
+                 instance Show T -- Defined at 4:10"
+            )
+        );
+        // without `NegativeLiterals`, a function followed by `-1` is
+        // subtracted from
+        assert_eq!(
+            eval("square x = x * x\nsquare -1"),
+            error(
+                "2:1: Cannot subtract from a function: `square -1` is `square - 1`, \
+                 write `square (-1)` to apply `square` to a negative number\n  \
+                 In the expression: square - 1"
+            )
+        );
+        // Monkey functions are checked as the program runs
+        assert_eq!(
+            eval("let f = fn(x) { x * 2 };\nf(1) + f(2)"),
+            Object::Integer(6)
+        );
+    }
+
+    #[test]
+    fn test_core() {
+        assert_eq!(
+            core("zip' (x : xs) (y : ys) = (x, y) : zip' xs ys\nzip' _ _ = []").unwrap(),
+            "Rec {\n\
+             zip' = \\$ds1 $ds2 ->\n    \
+             let $fail3 = [] in\n    \
+             case $ds1 of\n      \
+             (:) $ds4 $ds5 ->\n        \
+             case $ds2 of\n          \
+             (:) $ds6 $ds7 ->\n            \
+             let x#1 = $ds4 in\n            \
+             let xs#2 = $ds5 in\n            \
+             let y#3 = $ds6 in\n            \
+             let ys#4 = $ds7 in\n            \
+             (:) ((,) x#1 y#3) (zip' xs#2 ys#4)\n          \
+             _ -> $fail3\n      \
+             _ -> $fail3\n\
+             }"
+        );
+        assert_eq!(
+            core(
+                "class Size a where\n  size :: a -> Int\n  big :: a -> Bool\n  \
+                 big x = size x > 9\ninstance Size [a] where\n  size _ = 1"
+            )
+            .unwrap(),
+            "big = \\$dict1 ->\n    \
+             case $dict1 of\n      \
+             Size:Size $ds2 $ds3 -> $ds2\n\n\
+             size = \\$dict4 ->\n    \
+             case $dict4 of\n      \
+             Size:Size $ds5 $ds6 -> $ds6\n\n\
+             $dmbig = \\$this x#1 -> (>) ($fOrdInteger $fEqInteger) (size $this x#1) 9\n\n\
+             $dmsize = \\$this -> \
+             $error \"No instance nor default method for class operation size\"\n\n\
+             $fSize[] = letrec\n    \
+             $this = Size:Size ($dmbig $this) (\\$ds7 -> 1) in\n  \
+             $this"
+        );
+        // a Monkey function takes the components of its arguments, any
+        // other the tuple
+        assert_eq!(
+            core("let add = fn(x, y) { x + y };\nadd(1, 2) == fst (1, 2)").unwrap(),
+            "add = \\x#1 y#2 -> (+) x#1 y#2\n\n\
+             $it1 = (==) $dynEq (add 1 2) (fst ((,) 1 2))"
+        );
+        // derived instances are written out, but `Read` is built in
+        assert_eq!(
+            core("data T = A | B deriving (Eq, Read)\nx = A == read \"B\"").unwrap(),
+            "$fReadT = Read:Read ($read \"T\")\n\n\
+             $fEqT = letrec\n    \
+             $this = Eq:Eq ($dm/= $this) (\\$ds1 $ds2 ->\n         \
+             let $fail3 = False in\n         \
+             case $ds1 of\n           \
+             A ->\n             \
+             case $ds2 of\n               \
+             A -> True\n               \
+             _ -> $fail3\n           \
+             B ->\n             \
+             case $ds2 of\n               \
+             B -> True\n               \
+             _ -> $fail3) in\n  \
+             $this\n\n\
+             x = (==) $fEqT A (read $fReadT \"B\")"
+        );
+    }
+
+    #[test]
+    fn test_prelude_core() {
+        let mut fixities = Fixities::new();
+        let prelude = fixities.resolve_program(parse_prelude()).unwrap();
+        let prelude = derive_instances(prelude, |_| None, &mut fixities).unwrap();
+        let mut types = TypeEnv::new();
+        let body = types.check_module("Prelude", &prelude, &[]).unwrap();
+        let builtins: Vec<String> = BuiltinsFunctions::new()
+            .get_builtins()
+            .into_iter()
+            .map(|(Ident(name), _)| name)
+            .collect();
+        let program = core::desugar_program(&types, &body, builtins.clone());
+        let globals = core::Globals::new(&types, "Prelude", builtins);
+        assert_eq!(core::lint(&program, &globals), Ok(()));
+    }
+}
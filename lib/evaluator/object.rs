@@ -0,0 +1,209 @@
+use crate::evaluator::environment::*;
+use crate::parser::ast::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub enum Object {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Char(char),
+    String(String),
+    Array(Vec<Object>),
+    Hash(HashMap<Object, Object>),
+    Function(Vec<Ident>, Program, Rc<RefCell<Environment>>),
+    Builtin(String, usize, BuiltinFunction),
+    Data(String, Vec<Object>),
+    Constructor(String, usize),
+    Closure(Vec<Clause>, Rc<RefCell<Environment>>),
+    Method(String, String),
+    Partial(Box<Object>, Vec<Object>),
+    /// A method application whose instance depends on the type of its result.
+    Overloaded(String, String, Vec<Object>),
+    Reader(Type),
+    Dictionary(Rc<Dictionary>),
+    /// A list cell whose tail may not be evaluated yet.
+    Cons(Box<Object>, Box<Object>),
+    Thunk(Rc<RefCell<Thunk>>),
+    Null,
+    ReturnValue(Box<Object>),
+    Error(String),
+}
+
+/// The methods of an instance, which a type-checked module passes to its
+/// overloaded functions: the instance of the class for a type constructor,
+/// with the dictionaries for its context and for the class's superclasses.
+/// Without a type constructor, each method picks the instance from its
+/// arguments as in a module that is not checked.
+#[derive(PartialEq, Debug)]
+pub struct Dictionary {
+    pub class: String,
+    pub tycon: Option<String>,
+    pub context: Vec<Object>,
+    pub superclasses: Vec<Object>,
+}
+
+pub type BuiltinFunction = fn(Vec<Object>) -> Result<Object, String>;
+
+/// A suspended list, evaluated at most once when it is first needed.
+#[derive(Debug, Clone)]
+pub enum Thunk {
+    Pending(Expr, Rc<RefCell<Environment>>),
+    /// The first list followed by the second, as `xs ++ ys`.
+    Append(Object, Object),
+    /// The list with its overloaded elements resolved at the given type.
+    Resolve(Object, Vec<String>),
+    /// A function applied to arguments, such as the equation of a constant
+    /// with guards or a `where` applied to none.
+    Apply(Object, Vec<Object>),
+    Done(Object),
+}
+
+impl Object {
+    pub fn thunk(thunk: Thunk) -> Self {
+        Object::Thunk(Rc::new(RefCell::new(thunk)))
+    }
+
+    pub fn is_returned(&self) -> bool {
+        matches!(*self, Object::ReturnValue(_))
+    }
+
+    pub fn returned(self) -> Self {
+        match self {
+            Object::ReturnValue(o) => *o,
+            o => o,
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Object::Integer(ref i) => write!(f, "{}", i),
+            Object::Float(ref x) => write!(f, "{:?}", x),
+            Object::Char(ref c) => write!(f, "{}", c),
+            Object::Boolean(ref b) => {
+                if *b {
+                    write!(f, "true")
+                } else {
+                    write!(f, "false")
+                }
+            }
+            Object::String(ref s) => write!(f, "{}", s),
+            Object::Array(ref v) => {
+                let mut fmt_string = String::new();
+                fmt_string.push('[');
+                for (i, o) in v.iter().enumerate() {
+                    fmt_string.push_str(format!("{}", o).as_str());
+                    if i < v.len() - 1 {
+                        fmt_string.push_str(", ");
+                    }
+                }
+                fmt_string.push(']');
+                write!(f, "{}", fmt_string)
+            }
+            Object::Hash(ref hashmap) => {
+                let mut fmt_string = String::new();
+                fmt_string.push('{');
+                for (i, (k, v)) in hashmap.iter().enumerate() {
+                    fmt_string.push_str(format!("{} : {}", k, v).as_str());
+                    if i < hashmap.len() - 1 {
+                        fmt_string.push_str(", ");
+                    }
+                }
+                fmt_string.push('}');
+                write!(f, "{}", fmt_string)
+            }
+            Object::Function(_, _, _) => write!(f, "[function]"),
+            Object::Builtin(ref name, _, _) => write!(f, "[built-in function: {}]", *name),
+            Object::Data(ref name, ref fields) if Ident(name.clone()).tuple_arity().is_some() => {
+                let fields: Vec<String> = fields.iter().map(|o| o.to_string()).collect();
+                write!(f, "({})", fields.join(", "))
+            }
+            Object::Data(ref name, ref fields) => {
+                write!(f, "{}", name)?;
+                for field in fields {
+                    match field {
+                        Object::Data(ref con, ref args)
+                            if !args.is_empty() && Ident(con.clone()).tuple_arity().is_none() =>
+                        {
+                            write!(f, " ({})", field)?
+                        }
+                        _ => write!(f, " {}", field)?,
+                    }
+                }
+                Ok(())
+            }
+            Object::Constructor(ref name, _) => write!(f, "[constructor: {}]", name),
+            Object::Closure(_, _) | Object::Partial(_, _) | Object::Reader(_) => {
+                write!(f, "[function]")
+            }
+            Object::Overloaded(_, ref name, _) => write!(f, "[overloaded: {}]", name),
+            Object::Cons(ref head, ref tail) => write!(f, "{} : {}", head, tail),
+            Object::Thunk(ref thunk) => match *thunk.borrow() {
+                Thunk::Done(ref o) => write!(f, "{}", o),
+                _ => write!(f, "..."),
+            },
+            Object::Method(_, ref name) => write!(f, "[method: {}]", name),
+            Object::Dictionary(ref dict) => write!(f, "[dictionary: {}]", dict.class),
+            Object::Null => write!(f, "null"),
+            Object::ReturnValue(ref o) => write!(f, "{}", *o),
+            Object::Error(ref s) => write!(f, "Error: {}", s),
+        }
+    }
+}
+
+// Builtins are compared by name, function pointers have no stable identity
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::Float(a), Object::Float(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::Char(a), Object::Char(b)) => a == b,
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::Array(a), Object::Array(b)) => a == b,
+            (Object::Hash(a), Object::Hash(b)) => a == b,
+            (Object::Function(p1, b1, e1), Object::Function(p2, b2, e2)) => {
+                p1 == p2 && b1 == b2 && e1 == e2
+            }
+            (Object::Builtin(n1, a1, _), Object::Builtin(n2, a2, _)) => n1 == n2 && a1 == a2,
+            (Object::Data(n1, f1), Object::Data(n2, f2)) => n1 == n2 && f1 == f2,
+            (Object::Constructor(n1, a1), Object::Constructor(n2, a2)) => n1 == n2 && a1 == a2,
+            (Object::Closure(c1, e1), Object::Closure(c2, e2)) => c1 == c2 && Rc::ptr_eq(e1, e2),
+            (Object::Method(c1, m1), Object::Method(c2, m2)) => c1 == c2 && m1 == m2,
+            (Object::Partial(f1, a1), Object::Partial(f2, a2)) => f1 == f2 && a1 == a2,
+            (Object::Overloaded(c1, m1, a1), Object::Overloaded(c2, m2, a2)) => {
+                c1 == c2 && m1 == m2 && a1 == a2
+            }
+            (Object::Reader(t1), Object::Reader(t2)) => t1 == t2,
+            (Object::Dictionary(d1), Object::Dictionary(d2)) => d1 == d2,
+            (Object::Cons(h1, t1), Object::Cons(h2, t2)) => h1 == h2 && t1 == t2,
+            (Object::Thunk(t1), Object::Thunk(t2)) => Rc::ptr_eq(t1, t2),
+            (Object::Null, Object::Null) => true,
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => a == b,
+            (Object::Error(a), Object::Error(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Object {}
+
+#[allow(clippy::all)]
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Object::Integer(ref i) => i.hash(state),
+            Object::Boolean(ref b) => b.hash(state),
+            Object::String(ref s) => s.hash(state),
+            Object::Char(ref c) => c.hash(state),
+            Object::Data(ref name, _) => name.hash(state),
+            _ => "".hash(state),
+        }
+    }
+}
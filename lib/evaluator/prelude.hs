@@ -0,0 +1,343 @@
+-- The standard prelude, loaded by every evaluator before the user program.
+
+-- The types of the functions built into the evaluator
+primPrint :: a -> ()
+len :: [a] -> Int
+head :: [a] -> a
+tail :: [a] -> [a]
+cons :: a -> [a] -> [a]
+(++) :: [a] -> [a] -> [a]
+primEq :: a -> a -> Bool
+primCompare :: a -> a -> Ordering
+primShow :: a -> String
+primChr :: Int -> Char
+primOrd :: Char -> Int
+primIntToDouble :: Integer -> Double
+primOne :: a -> a
+primEnumFromTo :: a -> a -> [a]
+primEnumFromThenTo :: a -> a -> a -> [a]
+primDiv, primMod :: Integer -> Integer -> Integer
+error :: String -> a
+(+), (-), (*) :: Num a => a -> a -> a
+(/) :: Fractional a => a -> a -> a
+
+data Bool = False | True
+  deriving (Eq, Ord, Show, Read, Enum, Bounded, Ix)
+
+data Ordering = LT | EQ | GT
+  deriving (Eq, Ord, Show, Read, Enum, Bounded, Ix)
+
+not True = False
+not False = True
+
+True && x = x
+False && _ = False
+
+True || _ = True
+False || x = x
+
+otherwise = True
+
+f $ x = f x
+
+(.) f g = \x -> f (g x)
+
+map _ [] = []
+map f (x:xs) = f x : map f xs
+
+concatMap _ [] = []
+concatMap f (x:xs) = f x ++ concatMap f xs
+
+take n _ | n <= 0 = []
+take _ [] = []
+take n (x:xs) = x : take (n - 1) xs
+
+fst (x, _) = x
+
+snd (_, y) = y
+
+curry f x y = f (x, y)
+
+uncurry f (x, y) = f x y
+
+drop n xs | n <= 0 = xs
+drop _ [] = []
+drop n (_:xs) = drop (n - 1) xs
+
+flip f x y = f y x
+
+negate x = - x
+
+subtract x y = y - x
+
+x `div` y = primDiv x y
+
+x `mod` y = primMod x y
+
+even n = n `mod` 2 == 0
+
+odd n = not (even n)
+
+infixr 8 ^
+
+-- by repeated squaring, for a natural exponent. The Prelude's literals are
+-- not overloaded as it runs, so the `1` of `x ^ 0` comes from `x`.
+x ^ n
+  | n < 0 = error "Negative exponent"
+  | n == 0 = primOne x
+  | n == 1 = x
+  | even n = let y = x ^ (n `div` 2) in y * y
+  | otherwise = x * x ^ (n - 1)
+
+_ `elem` [] = False
+x `elem` (y:ys) = x == y || x `elem` ys
+
+data Maybe a = Nothing | Just a
+  deriving (Eq, Ord, Show, Read)
+
+maybe n _ Nothing = n
+maybe _ f (Just x) = f x
+
+-- Actions run when they are evaluated, so an `IO` value only carries the
+-- result
+data IO a = IO a
+
+print x = IO (primPrint x)
+
+putStrLn s = IO (primPrint s)
+
+class Eq a where
+  (==), (/=) :: a -> a -> Bool
+  x == y = not (x /= y)
+  x /= y = not (x == y)
+
+class Eq a => Ord a where
+  compare :: a -> a -> Ordering
+  (<), (<=), (>), (>=) :: a -> a -> Bool
+  max, min :: a -> a -> a
+  compare x y = if x == y then EQ else if x <= y then LT else GT
+  x < y = case compare x y of
+    LT -> True
+    _ -> False
+  x <= y = case compare x y of
+    GT -> False
+    _ -> True
+  x > y = case compare x y of
+    GT -> True
+    _ -> False
+  x >= y = case compare x y of
+    LT -> False
+    _ -> True
+  max x y = if x <= y then y else x
+  min x y = if x <= y then x else y
+
+class Show a where
+  showsPrec :: Int -> a -> String -> String
+  show :: a -> String
+  showList :: [a] -> String -> String
+  showsPrec _ x s = show x ++ s
+  show x = showsPrec 0 x ""
+  showList [] s = "[]" ++ s
+  showList (x:xs) s = "[" ++ showsPrec 0 x (showListRest xs s)
+
+showListRest [] s = "]" ++ s
+showListRest (x:xs) s = "," ++ showsPrec 0 x (showListRest xs s)
+
+showString str s = str ++ s
+
+showParen b p s = if b then "(" ++ p (")" ++ s) else p s
+
+class Monad m where
+  (>>=) :: m a -> (a -> m b) -> m b
+  (>>) :: m a -> m b -> m b
+  return :: a -> m a
+  fail :: String -> m a
+  m >> k = m >>= \_ -> k
+  fail s = error s
+
+f =<< m = m >>= f
+
+sequence [] = return []
+sequence (m:ms) = m >>= \x -> sequence ms >>= \xs -> return (x : xs)
+
+sequence_ [] = return ()
+sequence_ (m:ms) = m >> sequence_ ms
+
+mapM f xs = sequence (map f xs)
+
+mapM_ f xs = sequence_ (map f xs)
+
+forM xs f = mapM f xs
+
+forM_ xs f = mapM_ f xs
+
+when b m = if b then m else return ()
+
+unless b m = if b then return () else m
+
+class Read a where
+  read :: String -> a
+
+class Num a where
+  fromInteger :: Integer -> a
+
+fromIntegral n = fromInteger n
+
+-- Rationals are represented by doubles
+class Num a => Fractional a where
+  fromRational :: Double -> a
+
+class Enum a where
+  succ, pred :: a -> a
+  toEnum :: Int -> a
+  fromEnum :: a -> Int
+  enumFrom :: a -> [a]
+  enumFromThen :: a -> a -> [a]
+  enumFromTo :: a -> a -> [a]
+  enumFromThenTo :: a -> a -> a -> [a]
+  succ x = toEnum (fromEnum x + 1)
+  pred x = toEnum (fromEnum x - 1)
+  enumFrom x = map toEnum (numericEnumFrom (fromEnum x))
+  enumFromThen x y = map toEnum (numericEnumFromThen (fromEnum x) (fromEnum y))
+  enumFromTo x y = map toEnum (numericEnumFromTo (fromEnum x) (fromEnum y))
+  enumFromThenTo x y z = map toEnum (numericEnumFromThenTo (fromEnum x) (fromEnum y) (fromEnum z))
+
+numericEnumFrom n = n : numericEnumFrom (n + 1)
+
+numericEnumFromThen n n' = n : numericEnumFromThen n' (n' + n' - n)
+
+numericEnumFromTo n m = primEnumFromTo n m
+
+numericEnumFromThenTo n n' m = primEnumFromThenTo n n' m
+
+class Bounded a where
+  minBound, maxBound :: a
+
+class Ord a => Ix a where
+  range :: (a, a) -> [a]
+  index :: (a, a) -> a -> Int
+  inRange :: (a, a) -> a -> Bool
+  rangeSize :: (a, a) -> Int
+  rangeSize b@(_, u) = if inRange b u then index b u + 1 else 0
+
+instance Eq Integer where
+  (==) = primEq
+
+instance Ord Integer where
+  compare = primCompare
+
+instance Show Integer where
+  showsPrec d n s = if d > 6 && n < 0 then "(" ++ primShow n ++ ")" ++ s else primShow n ++ s
+
+instance Eq Double where
+  (==) = primEq
+
+instance Ord Double where
+  compare = primCompare
+
+instance Show Double where
+  showsPrec d n s = if d > 6 && n < 0.0 then "(" ++ primShow n ++ ")" ++ s else primShow n ++ s
+
+instance Eq Char where
+  (==) = primEq
+
+instance Ord Char where
+  compare = primCompare
+
+instance Show Char where
+  show = primShow
+  showList cs s = primShow cs ++ s
+
+instance Eq a => Eq [a] where
+  [] == [] = True
+  (x:xs) == (y:ys) = x == y && xs == ys
+  _ == _ = False
+
+instance Ord a => Ord [a] where
+  compare [] [] = EQ
+  compare [] _ = LT
+  compare _ [] = GT
+  compare (x:xs) (y:ys) = case compare x y of
+    EQ -> compare xs ys
+    other -> other
+
+instance Show a => Show [a] where
+  showsPrec _ xs s = showList xs s
+
+instance Monad Maybe where
+  Just x >>= k = k x
+  Nothing >>= _ = Nothing
+  return x = Just x
+  fail _ = Nothing
+
+instance Monad [] where
+  xs >>= f = concatMap f xs
+  return x = [x]
+  fail _ = []
+
+instance Monad IO where
+  IO x >>= k = k x
+  return x = IO x
+
+instance Num Integer where
+  fromInteger n = n
+
+instance Num Double where
+  fromInteger n = primIntToDouble n
+
+instance Fractional Double where
+  fromRational x = x
+
+-- Read instances of the builtin types are built in
+deriving instance Read Integer
+deriving instance Read Double
+deriving instance Read Char
+deriving instance Read a => Read [a]
+
+instance Enum Integer where
+  succ n = n + 1
+  pred n = n - 1
+  toEnum n = n
+  fromEnum n = n
+  enumFrom = numericEnumFrom
+  enumFromThen = numericEnumFromThen
+  enumFromTo = numericEnumFromTo
+  enumFromThenTo = numericEnumFromThenTo
+
+instance Enum Double where
+  succ x = x + 1.0
+  pred x = x - 1.0
+  enumFrom x = x : enumFrom (x + 1.0)
+  enumFromThen x y = x : enumFromThen y (y + y - x)
+  enumFromTo x y = numericEnumFromThenTo x (x + 1.0) y
+  enumFromThenTo = numericEnumFromThenTo
+
+-- spelled out, since the prelude's defaults can't tell `toEnum` the type
+-- to return
+instance Enum Char where
+  toEnum = primChr
+  fromEnum = primOrd
+  succ c = primChr (primOrd c + 1)
+  pred c = primChr (primOrd c - 1)
+  enumFrom c = enumFromTo c (primChr 1114111)
+  enumFromThen c d = enumFromThenTo c d (primChr (if d >= c then 1114111 else 0))
+  enumFromTo c d = map primChr (numericEnumFromTo (primOrd c) (primOrd d))
+  enumFromThenTo c d e = map primChr (numericEnumFromThenTo (primOrd c) (primOrd d) (primOrd e))
+
+instance Bounded Int where
+  minBound = -9223372036854775807 - 1
+  maxBound = 9223372036854775807
+
+instance Bounded Char where
+  minBound = '\0'
+  maxBound = primChr 1114111
+
+instance Ix Integer where
+  range (l, u) = numericEnumFromTo l u
+  index b@(l, _) i = if inRange b i then i - l else error "Ix.index: index out of range"
+  inRange (l, u) i = l <= i && i <= u
+
+instance Ix Char where
+  range (l, u) = map primChr (numericEnumFromTo (primOrd l) (primOrd u))
+  index b@(l, _) i = if inRange b i then primOrd i - primOrd l else error "Ix.index: index out of range"
+  inRange (l, u) i = l <= i && i <= u
@@ -1,5 +1,7 @@
 extern crate nom;
 
+pub mod core;
 pub mod evaluator;
 pub mod lexer;
 pub mod parser;
+pub mod typecheck;
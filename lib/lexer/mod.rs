@@ -2,11 +2,14 @@ use nom::{
     IResult,
     Parser,
     branch::*,
-    bytes::complete::{tag, take},
-    character::complete::{alpha1, alphanumeric1, char, digit1, multispace0, none_of},
-    combinator::{map, recognize, value},
-    multi::many0,
-    sequence::{delimited, preceded, pair},
+    bytes::complete::{is_not, tag, take, take_while, take_while1},
+    character::complete::{
+        alpha1, alphanumeric1, anychar, char, digit1, multispace0, multispace1, none_of, one_of,
+        satisfy,
+    },
+    combinator::{map, not, opt, recognize, value},
+    multi::{many0, many1, separated_list1},
+    sequence::{delimited, pair, preceded, tuple},
 };
 
 
@@ -15,83 +18,111 @@ use std::str;
 pub mod token;
 use crate::lexer::token::*;
 
-macro_rules! syntax_tag {
-    ($func_name: ident, $tag_string: literal, $output_token: expr) => {
-        fn $func_name<'a>(s: &'a str) -> IResult<&str, Token> {
-            map(tag($tag_string), |_| $output_token)(s)
-        }
-    };
-}
-
 macro_rules! syntax_char {
     ($func_name: ident, $tag_string: literal, $output_token: expr) => {
-        fn $func_name<'a>(s: &'a str) -> IResult<&str, Token> {
+        fn $func_name(s: &str) -> IResult<&str, Token> {
             map(char($tag_string), |_| $output_token)(s)
         }
     };
 }
 
 // operators
-syntax_tag! {equal_operator, "==", Token::Equal}
-syntax_tag! {not_equal_operator, "!=", Token::NotEqual}
-syntax_char! {assign_operator, '=', Token::Assign}
-syntax_char! {plus_operator, '+', Token::Plus}
-syntax_char! {minus_operator, '-', Token::Minus}
-syntax_char! {multiply_operator, '*', Token::Multiply}
-syntax_char! {divide_operator, '/', Token::Divide}
-syntax_char! {not_operator, '!', Token::Not}
-syntax_tag! {greater_operator_equal, ">=", Token::GreaterThanEqual}
-syntax_tag! {lesser_operator_equal, "<=", Token::LessThanEqual}
-syntax_char! {greater_operator, '>', Token::GreaterThan}
-syntax_char! {lesser_operator, '<', Token::LessThan}
+fn is_symbol_char(c: char) -> bool {
+    "!#$%&*+./<=>?@\\^|-~:".contains(c)
+}
 
+fn reserved_operator(s: &str) -> Option<Token> {
+    let token = match s {
+        "==" => Token::Equal,
+        "!=" | "/=" => Token::NotEqual,
+        "=>" => Token::FatArrow,
+        "=" => Token::Assign,
+        "+" => Token::Plus,
+        "->" => Token::Arrow,
+        "-" => Token::Minus,
+        "*" => Token::Multiply,
+        "/" => Token::Divide,
+        ">=" => Token::GreaterThanEqual,
+        "<=" => Token::LessThanEqual,
+        "<-" => Token::LeftArrow,
+        ">" => Token::GreaterThan,
+        "<" => Token::LessThan,
+        "|" => Token::Pipe,
+        "\\" => Token::Backslash,
+        "@" => Token::At,
+        ".." => Token::Range,
+        ":" => Token::Colon,
+        "::" => Token::DoubleColon,
+        _ => return None,
+    };
+    Some(token)
+}
+
+// The longest run of symbol characters is one operator, except that runs
+// starting with `=` are split after their longest reserved prefix when a
+// sign follows, as in `x =-1`.
 pub fn lex_operator(input: &str) -> IResult<&str, Token> {
-    alt((
-        equal_operator,
-        not_equal_operator,
-        assign_operator,
-        plus_operator,
-        minus_operator,
-        multiply_operator,
-        divide_operator,
-        not_operator,
-        greater_operator_equal,
-        lesser_operator_equal,
-        greater_operator,
-        lesser_operator,
-    ))(input)
+    let (rest, symbols) = take_while1(is_symbol_char)(input)?;
+    if let Some(token) = reserved_operator(symbols) {
+        return Ok((rest, token));
+    }
+    if symbols.starts_with('=') {
+        for end in (1..symbols.len()).rev() {
+            match reserved_operator(&symbols[..end]) {
+                Some(token) if symbols[end..].starts_with(['-', '+']) => {
+                    return Ok((&input[end..], token))
+                }
+                Some(_) => break,
+                None => {}
+            }
+        }
+    }
+    Ok((rest, Token::Operator(symbols.to_owned())))
 }
 
 // punctuations
 syntax_char! {comma_punctuation, ',', Token::Comma}
 syntax_char! {semicolon_punctuation, ';', Token::SemiColon}
-syntax_char! {colon_punctuation, ':', Token::Colon}
 syntax_char! {lparen_punctuation, '(', Token::LParen}
 syntax_char! {rparen_punctuation, ')', Token::RParen}
 syntax_char! {lbrace_punctuation, '{', Token::LBrace}
 syntax_char! {rbrace_punctuation, '}', Token::RBrace}
 syntax_char! {lbracket_punctuation, '[', Token::LBracket}
 syntax_char! {rbracket_punctuation, ']', Token::RBracket}
+syntax_char! {backquote_punctuation, '`', Token::Backquote}
 
 pub fn lex_punctuations(input: &str) -> IResult<&str, Token> {
     alt((
         comma_punctuation,
         semicolon_punctuation,
-        colon_punctuation,
         lparen_punctuation,
         rparen_punctuation,
         lbrace_punctuation,
         rbrace_punctuation,
         lbracket_punctuation,
         rbracket_punctuation,
+        backquote_punctuation,
     ))(input)
 }
 
+fn escaped_char(input: &str) -> IResult<&str, char> {
+    preceded(
+        char('\\'),
+        alt((
+            value('\n', char('n')),
+            value('\t', char('t')),
+            value('\r', char('r')),
+            value('\0', char('0')),
+            anychar,
+        )),
+    )(input)
+}
+
 fn string(input: &str) -> IResult<&str, String> {
     delimited(
         char('"'),
         map(
-            many0(preceded(char('\\'), char('"')).or(none_of("\""))),
+            many0(escaped_char.or(none_of("\""))),
             |chars: Vec<char>| chars.into_iter().collect(),
         ),
         char('"'),
@@ -102,31 +133,97 @@ fn lex_string(input: &str) -> IResult<&str, Token> {
     map(string, Token::StringLiteral)(input)
 }
 
+fn lex_char(input: &str) -> IResult<&str, Token> {
+    map(
+        delimited(char('\''), alt((escaped_char, none_of("'\\"))), char('\'')),
+        Token::CharLiteral,
+    )(input)
+}
+
+fn keyword_or_ident(s: &str) -> Token {
+    match s {
+        "let" => Token::Let,
+        "in" => Token::In,
+        "fn" => Token::Function,
+        "if" => Token::If,
+        "then" => Token::Then,
+        "else" => Token::Else,
+        "case" => Token::Case,
+        "of" => Token::Of,
+        "do" => Token::Do,
+        "where" => Token::Where,
+        "data" => Token::Data,
+        "newtype" => Token::Newtype,
+        "type" => Token::Type,
+        "class" => Token::Class,
+        "instance" => Token::Instance,
+        "deriving" => Token::Deriving,
+        "default" => Token::Default,
+        "infix" => Token::Infix,
+        "infixl" => Token::Infixl,
+        "infixr" => Token::Infixr,
+        "module" => Token::Module,
+        "import" => Token::Import,
+        "return" => Token::Return,
+        "true" => Token::BoolLiteral(true),
+        "false" => Token::BoolLiteral(false),
+        _ => Token::Ident(s.to_string()),
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '\''
+}
+
+fn conid(input: &str) -> IResult<&str, &str> {
+    recognize(pair(satisfy(|c| c.is_ascii_uppercase()), take_while(is_ident_char)))(input)
+}
+
+// A name qualified by a module, as in `Data.Map` or `M.lookup`, is a single
+// token of the kind of its last part
+fn lex_qualified_ident(input: &str) -> IResult<&str, Token> {
+    let varid = recognize(pair(alt((alpha1, tag("_"))), take_while(is_ident_char)));
+    map(
+        recognize(pair(many1(pair(conid, char('.'))), alt((conid, varid)))),
+        |s: &str| match s.rsplit('.').next() {
+            Some(name) if name.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                Token::TypeIdent(s.to_string())
+            }
+            _ => Token::Ident(s.to_string()),
+        },
+    )(input)
+}
+
 // Reserved or ident
 fn lex_reserved_ident(input: &str) -> IResult<&str, Token> {
     alt((
-        value(Token::Let, tag("let")),
-        value(Token::Function, tag("fn")),
-        value(Token::If, tag("if")),
-        value(Token::Else, tag("else")),
-        value(Token::Return, tag("return")),
-        value(Token::BoolLiteral(true), tag("true")),
-        value(Token::BoolLiteral(false), tag("false")),
+        lex_qualified_ident,
+        map(conid, |s: &str| Token::TypeIdent(s.to_string())),
         map(
-            recognize(pair(
-                alt((alpha1, tag("_"))),
-                many0(alt((alphanumeric1, tag("_")))),
-            )),
-            |s: &str| Token::Ident(s.to_string()),
+            recognize(pair(alt((alpha1, tag("_"))), take_while(is_ident_char))),
+            keyword_or_ident,
         ),
     ))(input)
 }
 
-// Integers parsing
-fn lex_integer(input: &str) -> IResult<&str, Token> {
-    map(digit1, |s: &str| {
-        Token::IntLiteral(s.parse::<i64>().unwrap())
-    })(input)
+// Integers and floats parsing
+fn lex_number(input: &str) -> IResult<&str, Token> {
+    let exponent = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+    alt((
+        map(
+            recognize(tuple((
+                digit1,
+                alt((
+                    recognize(pair(preceded(char('.'), digit1), opt(exponent))),
+                    recognize(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+                )),
+            ))),
+            |s: &str| Token::FloatLiteral(s.parse::<f64>().unwrap()),
+        ),
+        map(digit1, |s: &str| {
+            Token::IntLiteral(s.parse::<i64>().unwrap())
+        }),
+    ))(input)
 }
 
 // Illegal tokens
@@ -136,17 +233,112 @@ fn lex_illegal(input: &str) -> IResult<&str, Token> {
 
 fn lex_token(input: &str) -> IResult<&str, Token> {
     alt((
+        lex_language_pragma,
         lex_operator,
         lex_punctuations,
         lex_string,
+        lex_char,
         lex_reserved_ident,
-        lex_integer,
+        lex_number,
         lex_illegal,
     ))(input)
 }
 
+// Comments: `-- ...` up to the end of the line and nested `{- ... -}` blocks
+fn line_comment(input: &str) -> IResult<&str, &str> {
+    recognize(pair(tag("--"), opt(is_not("\n"))))(input)
+}
+
+fn block_comment(input: &str) -> IResult<&str, &str> {
+    let (mut rest, _) = tag("{-")(input)?;
+    let mut depth = 1;
+    while depth > 0 {
+        if let Some(r) = rest.strip_prefix("-}") {
+            depth -= 1;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("{-") {
+            depth += 1;
+            rest = r;
+        } else {
+            rest = anychar(rest)?.0;
+        }
+    }
+    Ok((rest, &input[..input.len() - rest.len()]))
+}
+
+// `{-# LANGUAGE A, B #-}`; other pragmas are comments
+fn lex_language_pragma(input: &str) -> IResult<&str, Token> {
+    let separator = tuple((multispace0, tag(","), multispace0));
+    map(
+        delimited(
+            tuple((tag("{-#"), multispace0, tag("LANGUAGE"), multispace1)),
+            separated_list1(separator, alphanumeric1),
+            pair(multispace0, tag("#-}")),
+        ),
+        |names: Vec<&str>| Token::LanguagePragma(names.into_iter().map(str::to_owned).collect()),
+    )(input)
+}
+
+fn skip_trivia(input: &str) -> IResult<&str, ()> {
+    let comment = preceded(not(lex_language_pragma), block_comment);
+    value((), many0(alt((multispace1, line_comment, comment))))(input)
+}
+
 fn lex_tokens(input: &str) -> IResult<&str, Vec<Token>> {
-    many0(delimited(multispace0, lex_token, multispace0))(input)
+    many0(delimited(skip_trivia, lex_token, skip_trivia))(input)
+}
+
+fn lex_spanned_tokens(input: &str) -> IResult<&str, (Vec<Token>, Vec<Span>)> {
+    let mut tokens = vec![];
+    let mut spans = vec![];
+    let mut tracker = PositionTracker::default();
+    let (mut rest, _) = skip_trivia(input)?;
+    while !rest.is_empty() {
+        let start = input.len() - rest.len();
+        let (after, token) = lex_token(rest)?;
+        let end = input.len() - after.len();
+        spans.push(tracker.span(input, start, end));
+        tokens.push(token);
+        rest = skip_trivia(after)?.0;
+    }
+    let end = input.len();
+    tokens.push(Token::EOF);
+    spans.push(tracker.span(input, end, end));
+    Ok((rest, (tokens, spans)))
+}
+
+/// Converts byte offsets into lines and columns, walking the source only once.
+#[derive(Default)]
+struct PositionTracker {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl PositionTracker {
+    fn span(&mut self, input: &str, start: usize, end: usize) -> Span {
+        if self.line == 0 {
+            self.line = 1;
+            self.column = 1;
+        }
+        for c in input[self.offset..start].chars() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                '\t' => self.column += 8 - (self.column - 1) % 8,
+                _ => self.column += 1,
+            }
+        }
+        self.offset = start;
+        Span {
+            line: self.line,
+            column: self.column,
+            start,
+            end,
+        }
+    }
 }
 
 pub struct Lexer;
@@ -156,6 +348,11 @@ impl Lexer {
         lex_tokens(input)
             .map(|(slice, result)| (slice, [&result[..], &vec![Token::EOF][..]].concat()))
     }
+
+    /// Like `lex_tokens`, but also returns the source span of every token.
+    pub fn lex_tokens_with_spans(input: &str) -> IResult<&str, (Vec<Token>, Vec<Span>)> {
+        lex_spanned_tokens(input)
+    }
 }
 
 #[cfg(test)]
@@ -273,7 +470,7 @@ mod tests {
             Token::RParen,
             Token::LBrace,
             Token::Return,
-            Token::Not,
+            Token::Operator("!".to_owned()),
             Token::Ident("a".to_owned()),
             Token::SemiColon,
             Token::RBrace,
@@ -397,4 +594,78 @@ mod tests {
         ];
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn qualified_names() {
+        let input = "import Data.Map as M\nM.lookup M.Just f.g [A..B]";
+        let (_, result) = Lexer::lex_tokens(input).unwrap();
+        let expected = vec![
+            Token::Import,
+            Token::TypeIdent("Data.Map".to_owned()),
+            Token::Ident("as".to_owned()),
+            Token::TypeIdent("M".to_owned()),
+            Token::Ident("M.lookup".to_owned()),
+            Token::TypeIdent("M.Just".to_owned()),
+            Token::Ident("f".to_owned()),
+            Token::Operator(".".to_owned()),
+            Token::Ident("g".to_owned()),
+            Token::LBracket,
+            Token::TypeIdent("A".to_owned()),
+            Token::Range,
+            Token::TypeIdent("B".to_owned()),
+            Token::RBracket,
+            Token::EOF,
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn operator_tokens() {
+        let input = "infixl 6 <+>; a >>= b :+ c .. `div` !!x =-1 =<< do";
+        let (_, result) = Lexer::lex_tokens(input).unwrap();
+        let op = |s: &str| Token::Operator(s.to_owned());
+        let ident = |s: &str| Token::Ident(s.to_owned());
+        let expected = vec![
+            Token::Infixl,
+            Token::IntLiteral(6),
+            op("<+>"),
+            Token::SemiColon,
+            ident("a"),
+            op(">>="),
+            ident("b"),
+            op(":+"),
+            ident("c"),
+            Token::Range,
+            Token::Backquote,
+            ident("div"),
+            Token::Backquote,
+            op("!!"),
+            ident("x"),
+            Token::Assign,
+            Token::Minus,
+            Token::IntLiteral(1),
+            op("=<<"),
+            Token::Do,
+            Token::EOF,
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn language_pragmas() {
+        let input = "{-# LANGUAGE LambdaCase #-}\n{-#LANGUAGE TupleSections ,BlockArguments#-}\n\
+                     {-# INLINE f #-} {- {-# LANGUAGE MultiWayIf #-} -} f";
+        let (_, result) = Lexer::lex_tokens(input).unwrap();
+        let pragma = |names: &[&str]| {
+            Token::LanguagePragma(names.iter().map(|name| name.to_string()).collect())
+        };
+        let expected = vec![
+            pragma(&["LambdaCase"]),
+            pragma(&["TupleSections", "BlockArguments"]),
+            Token::Ident("f".to_owned()),
+            Token::EOF,
+        ];
+        assert_eq!(result, expected);
+        assert_eq!(expected[1].to_string(), "{-# LANGUAGE TupleSections, BlockArguments #-}");
+    }
 }
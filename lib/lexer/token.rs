@@ -1,4 +1,5 @@
 use nom::*;
+use std::fmt;
 use std::iter::Enumerate;
 use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
 
@@ -15,6 +16,8 @@ pub enum Token {
     FloatLiteral(f64),
     BoolLiteral(bool),
     // operators
+    Operator(String),
+    Assign,
     Range,
     Plus,
     Minus,
@@ -26,55 +29,261 @@ pub enum Token {
     LessThanEqual,
     GreaterThan,
     LessThan,
-    Not, // !
     Arrow, // ->
+    LeftArrow, // <-
+    FatArrow, // =>
+    Pipe, // |
+    Backslash, // \
+    At, // @
     Colon,
     DoubleColon, // ::
     // punctuations
     Comma,
+    SemiColon,
     LParen,
     RParen,
     LBrace,
     RBrace,
     LBracket,
     RBracket,
+    Backquote,
+    /// `{-# LANGUAGE LambdaCase, TupleSections #-}`
+    LanguagePragma(Vec<String>),
     //reserved words
     Int,
     Char,
+    Let,
+    In,
+    If,
+    Then,
+    Else,
+    Case,
+    Of,
+    Do,
+    Where,
+    Data,
+    Newtype,
+    Type,
+    Class,
+    Instance,
+    Deriving,
+    Default,
+    Infix,
+    Infixl,
+    Infixr,
+    Module,
+    Import,
+    Function,
+    Return,
+}
+
+/// The source text of a token, or a description of `Illegal` and `EOF`.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Token::Illegal => "illegal character",
+            Token::EOF => "end of input",
+            Token::Ident(name) | Token::TypeIdent(name) | Token::Operator(name) => name,
+            Token::StringLiteral(s) => return write!(f, "{:?}", s),
+            Token::CharLiteral(c) => return write!(f, "{:?}", c),
+            Token::IntLiteral(i) => return write!(f, "{}", i),
+            Token::FloatLiteral(x) => return write!(f, "{}", x),
+            Token::BoolLiteral(b) => return write!(f, "{}", b),
+            Token::LanguagePragma(names) => {
+                return write!(f, "{{-# LANGUAGE {} #-}}", names.join(", "))
+            }
+            Token::Assign => "=",
+            Token::Range => "..",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Divide => "/",
+            Token::Multiply => "*",
+            Token::Equal => "==",
+            Token::NotEqual => "/=",
+            Token::GreaterThanEqual => ">=",
+            Token::LessThanEqual => "<=",
+            Token::GreaterThan => ">",
+            Token::LessThan => "<",
+            Token::Arrow => "->",
+            Token::LeftArrow => "<-",
+            Token::FatArrow => "=>",
+            Token::Pipe => "|",
+            Token::Backslash => "\\",
+            Token::At => "@",
+            Token::Colon => ":",
+            Token::DoubleColon => "::",
+            Token::Comma => ",",
+            Token::SemiColon => ";",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Backquote => "`",
+            Token::Int => "int",
+            Token::Char => "char",
+            Token::Let => "let",
+            Token::In => "in",
+            Token::If => "if",
+            Token::Then => "then",
+            Token::Else => "else",
+            Token::Case => "case",
+            Token::Of => "of",
+            Token::Do => "do",
+            Token::Where => "where",
+            Token::Data => "data",
+            Token::Newtype => "newtype",
+            Token::Type => "type",
+            Token::Class => "class",
+            Token::Instance => "instance",
+            Token::Deriving => "deriving",
+            Token::Default => "default",
+            Token::Infix => "infix",
+            Token::Infixl => "infixl",
+            Token::Infixr => "infixr",
+            Token::Module => "module",
+            Token::Import => "import",
+            Token::Function => "fn",
+            Token::Return => "return",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// A syntactic extension of Haskell 2010, enabled by a `LANGUAGE` pragma.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Extension {
+    /// `\case alts`
+    LambdaCase,
+    /// `if | c1 -> e1 | c2 -> e2`
+    MultiWayIf,
+    /// `(, x)` and `(x,)`
+    TupleSections,
+    /// `do`, `\x -> e`, `case`, `if` and `let` as arguments without parentheses
+    BlockArguments,
+    /// `-1` without a space is a literal, so that `f -1` applies `f`
+    NegativeLiterals,
+    /// `forall a.` in a signature scopes `a` over the definition, and
+    /// patterns can have signatures as in `\(x :: Int) -> x`
+    ScopedTypeVariables,
+}
+
+impl Extension {
+    pub const ALL: [Extension; 6] = [
+        Extension::LambdaCase,
+        Extension::MultiWayIf,
+        Extension::TupleSections,
+        Extension::BlockArguments,
+        Extension::NegativeLiterals,
+        Extension::ScopedTypeVariables,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Extension::LambdaCase => "LambdaCase",
+            Extension::MultiWayIf => "MultiWayIf",
+            Extension::TupleSections => "TupleSections",
+            Extension::BlockArguments => "BlockArguments",
+            Extension::NegativeLiterals => "NegativeLiterals",
+            Extension::ScopedTypeVariables => "ScopedTypeVariables",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Extension::ALL.into_iter().find(|e| e.name() == name)
+    }
+}
+
+/// The extensions enabled for a stream of tokens.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Extensions(u8);
+
+impl Extensions {
+    pub fn contains(self, extension: Extension) -> bool {
+        self.0 & 1 << extension as u8 != 0
+    }
+
+    pub fn insert(&mut self, extension: Extension) {
+        self.0 |= 1 << extension as u8;
+    }
+}
+
+/// Source position of a token: 1-based line and column plus the byte range.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(C)]
 pub struct Tokens<'a> {
     pub tok: &'a [Token],
+    pub span: &'a [Span],
     pub start: usize,
     pub end: usize,
+    pub extensions: Extensions,
 }
 
 impl<'a> Tokens<'a> {
     pub fn new(vec: &'a [Token]) -> Self {
         Tokens {
             tok: vec,
+            span: &[],
             start: 0,
             end: vec.len(),
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Token stream carrying source positions, which enables the layout rule.
+    pub fn with_spans(vec: &'a [Token], span: &'a [Span]) -> Self {
+        Tokens {
+            tok: vec,
+            span,
+            start: 0,
+            end: vec.len(),
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// The same tokens, parsed with the given extensions enabled.
+    pub fn with_extensions(self, extensions: Extensions) -> Self {
+        Tokens { extensions, ..self }
+    }
+
+    pub fn span_at(&self, index: usize) -> Option<Span> {
+        self.span.get(index).copied()
+    }
+
+    fn span_slice(&self, range: Range<usize>) -> &'a [Span] {
+        if self.span.is_empty() {
+            self.span
+        } else {
+            &self.span[range]
         }
     }
 }
 
-impl<'a> InputLength for Tokens<'a> {
+impl InputLength for Tokens<'_> {
     #[inline]
     fn input_len(&self) -> usize {
         self.tok.len()
     }
 }
 
-impl<'a> InputTake for Tokens<'a> {
+impl InputTake for Tokens<'_> {
     #[inline]
     fn take(&self, count: usize) -> Self {
         Tokens {
             tok: &self.tok[0..count],
+            span: self.span_slice(0..count),
             start: 0,
             end: count,
+            extensions: self.extensions,
         }
     }
 
@@ -83,13 +292,17 @@ impl<'a> InputTake for Tokens<'a> {
         let (prefix, suffix) = self.tok.split_at(count);
         let first = Tokens {
             tok: prefix,
+            span: self.span_slice(0..count),
             start: 0,
             end: prefix.len(),
+            extensions: self.extensions,
         };
         let second = Tokens {
             tok: suffix,
+            span: self.span_slice(count..self.tok.len()),
             start: 0,
             end: suffix.len(),
+            extensions: self.extensions,
         };
         (second, first)
     }
@@ -102,38 +315,42 @@ impl InputLength for Token {
     }
 }
 
-impl<'a> Slice<Range<usize>> for Tokens<'a> {
+impl Slice<Range<usize>> for Tokens<'_> {
     #[inline]
     fn slice(&self, range: Range<usize>) -> Self {
         Tokens {
             tok: self.tok.slice(range.clone()),
+            span: self.span_slice(range.clone()),
             start: self.start + range.start,
             end: self.start + range.end,
+            extensions: self.extensions,
         }
     }
 }
 
-impl<'a> Slice<RangeTo<usize>> for Tokens<'a> {
+impl Slice<RangeTo<usize>> for Tokens<'_> {
     #[inline]
     fn slice(&self, range: RangeTo<usize>) -> Self {
         self.slice(0..range.end)
     }
 }
 
-impl<'a> Slice<RangeFrom<usize>> for Tokens<'a> {
+impl Slice<RangeFrom<usize>> for Tokens<'_> {
     #[inline]
     fn slice(&self, range: RangeFrom<usize>) -> Self {
         self.slice(range.start..self.end - self.start)
     }
 }
 
-impl<'a> Slice<RangeFull> for Tokens<'a> {
+impl Slice<RangeFull> for Tokens<'_> {
     #[inline]
     fn slice(&self, _: RangeFull) -> Self {
         Tokens {
             tok: self.tok,
+            span: self.span,
             start: self.start,
             end: self.end,
+            extensions: self.extensions,
         }
     }
 }
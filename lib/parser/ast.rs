@@ -1,18 +1,92 @@
+use crate::lexer::token::Span;
+use crate::parser::error::ParseError;
+use std::fmt;
+
 pub type Program = Vec<Stmt>;
 
+/// A source file: `module M (exports) where`, its imports and the body. A
+/// file without a header is the module `Main`, exporting everything.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Module {
+    pub name: Ident,
+    /// `None` when there is no export list.
+    pub exports: Option<Vec<Entity>>,
+    pub imports: Vec<Import>,
+    pub body: Program,
+}
+
+/// `import qualified M as N hiding (entities)`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Import {
+    pub module: Ident,
+    pub qualified: bool,
+    pub alias: Option<Ident>,
+    pub hiding: bool,
+    /// `None` imports everything the module exports.
+    pub entities: Option<Vec<Entity>>,
+}
+
+/// An item of an export or import list.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Entity {
+    /// A function, method or constructor name: `x`, `(+)` or `Just`
+    Var(Ident),
+    /// A type or class with some of its constructors or methods: `T`,
+    /// `T(..)` or `T(A, B)`
+    Type(Ident, Members),
+    /// `module M` re-exports what is imported from `M`
+    Module(Ident),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Members {
+    Abstract,
+    All,
+    Listed(Vec<Ident>),
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Stmt {
     LetStmt(Ident, Expr),
     ReturnStmt(Expr),
     ExprStmt(Expr),
+    /// `f, g :: C a => t`, with where its type is when the parser has the
+    /// spans
+    SigStmt(Vec<Ident>, Context, Type, Option<Span>),
+    FunStmt(Ident, Vec<Clause>),
+    /// `(a, b) = e where decls`, a pattern binding, evaluated through
+    /// `desugar::desugar_pattern_binding`
+    PatStmt(Pattern, Rhs, Program),
+    DataStmt(DataDecl),
+    /// `type Pair a = (a, a)`, a type synonym, which types written with it
+    /// are expanded to by the checker
+    TypeStmt(Ident, Vec<Ident>, Type),
+    ClassStmt(ClassDecl),
+    InstanceStmt(InstanceDecl),
+    DerivingStmt(InstanceDecl),
+    /// `default (Integer, Double)`: the types an ambiguous type variable
+    /// with a numeric constraint is tried at, in order
+    DefaultStmt(Vec<Type>),
+    /// `infixl 6 +, -`
+    FixityStmt(Fixity, Vec<Ident>),
+    /// A declaration that failed to parse, kept in the partial AST of
+    /// `Parser::parse_tokens_recovering`.
+    ErrorStmt(ParseError),
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Expr {
     IdentExpr(Ident),
+    /// `_` or `_name`, a typed hole standing for an expression still to be
+    /// written. A `_name` that is bound is an ordinary variable, as in GHC.
+    HoleExpr(Ident),
     LitExpr(Literal),
     PrefixExpr(Prefix, Box<Expr>),
     InfixExpr(Infix, Box<Expr>, Box<Expr>),
+    /// `e1 op1 e2 op2 e3 ...` as parsed, before `fixity::Fixities` associates
+    /// the operators into `InfixExpr`s. An operand `PrefixExpr` is a negation
+    /// sign still to be associated, as in `- e1 op1 e2`.
+    InfixChain(Box<Expr>, Vec<(Infix, Expr)>),
     IfExpr {
         cond: Box<Expr>,
         consequence: Program,
@@ -32,23 +106,159 @@ pub enum Expr {
         array: Box<Expr>,
         index: Box<Expr>,
     },
+    LambdaExpr {
+        params: Vec<Pattern>,
+        body: Box<Expr>,
+    },
+    CaseExpr {
+        scrutinee: Box<Expr>,
+        alts: Vec<Alt>,
+    },
+    /// `\case alts` (LambdaCase), evaluated through
+    /// `desugar::desugar_lambda_case`.
+    LambdaCaseExpr(Vec<Alt>),
+    /// `if | c1 -> e1 | c2 -> e2` (MultiWayIf), evaluated through
+    /// `desugar::desugar_multi_way_if`.
+    MultiIfExpr(Vec<(Expr, Expr)>),
+    LetExpr {
+        bindings: Program,
+        body: Box<Expr>,
+    },
+    /// Tuples of two or more components, or the unit `()` when empty.
+    TupleExpr(Vec<Expr>),
+    /// `(, e)` (TupleSections), a tuple missing some of its components,
+    /// evaluated through `desugar::desugar_tuple_section`.
+    TupleSectionExpr(Vec<Option<Expr>>),
+    /// `(e op)`, the operator applied to its left operand.
+    LeftSectionExpr(Box<Expr>, Infix),
+    /// `(op e)`, a function of the left operand.
+    RightSectionExpr(Infix, Box<Expr>),
+    /// `[e | quals]`, evaluated through `desugar::desugar_comprehension`.
+    ListCompExpr {
+        expr: Box<Expr>,
+        qualifiers: Vec<Qualifier>,
+    },
+    /// `do { stmts }`, evaluated through `desugar::desugar_do`. The last
+    /// statement is a `Guard`, the expression whose action ends the block.
+    DoExpr(Vec<Qualifier>),
+    /// `e :: context => type`, fixing the type of an overloaded `e` as in
+    /// `read s :: Int`.
+    TypedExpr(Box<Expr>, Context, Type),
+    /// The dictionary of the methods of an instance, which the type checker
+    /// passes to overloaded functions when it elaborates a module.
+    DictExpr(Dict),
+    /// An expression with the span of its tokens, which the parser adds
+    /// when it has the spans so that type errors point at the source. The
+    /// type checker leaves them out of the module it elaborates.
+    Located(Span, Box<Expr>),
+}
+
+impl Expr {
+    /// The expression without the spans around it.
+    pub fn unlocated(&self) -> &Expr {
+        match self {
+            Expr::Located(_, expr) => expr.unlocated(),
+            expr => expr,
+        }
+    }
+
+    /// The span of the expression, if the parser recorded it.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::Located(span, _) => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+/// The evidence for a class constraint such as `Show [Int]`, as an argument
+/// of the definitions overloaded on it.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Dict {
+    /// The instance for a type constructor, with the dictionaries for the
+    /// instance's context and for the class's superclasses
+    Instance {
+        class: Ident,
+        tycon: Ident,
+        context: Vec<Dict>,
+        superclasses: Vec<Dict>,
+    },
+    /// A dictionary parameter of the enclosing definition
+    Param(Ident),
+    /// The dictionary of a superclass, taken from a dictionary of one of its
+    /// subclasses, the second class
+    Super(Ident, Ident, Box<Dict>),
+    /// For a type only known as the program runs, a Monkey value's: each
+    /// method picks the instance from its arguments.
+    Dynamic(Ident),
+}
+
+/// A generator `p <- xs`, a boolean guard or a `let` of a list comprehension,
+/// which are also the statements of a `do` block: `p <- e`, an action `e` and
+/// a `let`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Qualifier {
+    Generator(Pattern, Expr),
+    Guard(Expr),
+    LetQual(Program),
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Literal {
     IntLiteral(i64),
+    FloatLiteral(f64),
     BoolLiteral(bool),
     StringLiteral(String),
+    CharLiteral(char),
 }
 
-#[derive(PartialEq, Debug, Eq, Clone)]
+#[derive(PartialEq, Debug, Eq, Hash, Clone)]
 pub struct Ident(pub String);
 
+impl Ident {
+    /// Constructors and type names start with an upper case letter or, for
+    /// operators, with a colon. Tuple constructors are written `(,)`.
+    pub fn is_constructor(&self) -> bool {
+        self.unqualified()
+            .starts_with(|c: char| c.is_ascii_uppercase() || c == ':' || c == '(')
+    }
+
+    /// The module qualifying a name such as `M.lookup` or `Data.Map.Map`.
+    pub fn qualifier(&self) -> Option<&str> {
+        match self.0.starts_with(|c: char| c.is_ascii_uppercase()) {
+            true => self.0.rsplit_once('.').map(|(module, _)| module),
+            false => None,
+        }
+    }
+
+    /// The name without its qualifier.
+    pub fn unqualified(&self) -> &str {
+        match self.qualifier() {
+            Some(module) => &self.0[module.len() + 1..],
+            None => &self.0,
+        }
+    }
+
+    /// The constructor of tuples with the given number of components.
+    pub fn tuple_con(arity: usize) -> Self {
+        Ident(format!("({})", ",".repeat(arity.saturating_sub(1))))
+    }
+
+    /// The number of components of a tuple constructor such as `(,,)`.
+    pub fn tuple_arity(&self) -> Option<usize> {
+        let commas = self.0.strip_prefix('(')?.strip_suffix(')')?;
+        match commas.chars().all(|c| c == ',') {
+            true if commas.is_empty() => Some(0),
+            true => Some(commas.len() + 1),
+            false => None,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Prefix {
-    PrefixPlus,
+    /// `- e`, the only prefix operator of Haskell, meaning `negate e`
     PrefixMinus,
-    Not,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -63,15 +273,218 @@ pub enum Infix {
     LessThanEqual,
     GreaterThan,
     LessThan,
+    Op(Ident),
+}
+
+impl Infix {
+    /// The name the operator is bound to in the environment.
+    pub fn name(&self) -> &str {
+        match self {
+            Infix::Plus => "+",
+            Infix::Minus => "-",
+            Infix::Divide => "/",
+            Infix::Multiply => "*",
+            Infix::Equal => "==",
+            Infix::NotEqual => "/=",
+            Infix::GreaterThanEqual => ">=",
+            Infix::LessThanEqual => "<=",
+            Infix::GreaterThan => ">",
+            Infix::LessThan => "<",
+            Infix::Op(Ident(name)) => name,
+        }
+    }
+
+    /// The operator bound to a name, the inverse of `name`.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "+" => Infix::Plus,
+            "-" => Infix::Minus,
+            "/" => Infix::Divide,
+            "*" => Infix::Multiply,
+            "==" => Infix::Equal,
+            "/=" => Infix::NotEqual,
+            ">=" => Infix::GreaterThanEqual,
+            "<=" => Infix::LessThanEqual,
+            ">" => Infix::GreaterThan,
+            "<" => Infix::LessThan,
+            name => Infix::Op(Ident(name.to_owned())),
+        }
+    }
 }
 
+/// Binding power in the expression parser. Binary operators all share one
+/// level, their fixities only apply once the chain is associated.
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub enum Precedence {
     PLowest,
-    PEquals,
-    PLessGreater,
-    PSum,
-    PProduct,
+    POperator,
     PCall,
     PIndex,
 }
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Assoc {
+    Left,
+    Right,
+    Non,
+}
+
+/// How tightly an operator binds, from 0 to 9, and which way it associates.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Fixity {
+    pub assoc: Assoc,
+    pub precedence: u8,
+}
+
+impl Fixity {
+    /// The fixity of the negation `- e`, which binds like binary `-`.
+    pub const NEGATION: Fixity = Fixity {
+        assoc: Assoc::Left,
+        precedence: 6,
+    };
+}
+
+impl fmt::Display for Fixity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match self.assoc {
+            Assoc::Left => "infixl",
+            Assoc::Right => "infixr",
+            Assoc::Non => "infix",
+        };
+        write!(f, "{} {}", keyword, self.precedence)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Pattern {
+    WildPat,
+    VarPat(Ident),
+    LitPat(Literal),
+    ConPat(Ident, Vec<Pattern>),
+    ListPat(Vec<Pattern>),
+    TuplePat(Vec<Pattern>),
+    AsPat(Ident, Box<Pattern>),
+    /// `(p :: t)` (ScopedTypeVariables)
+    SigPat(Box<Pattern>, Type),
+}
+
+impl Pattern {
+    /// The variables the pattern binds, from left to right.
+    pub fn vars(&self) -> Vec<&Ident> {
+        match self {
+            Pattern::WildPat | Pattern::LitPat(_) => vec![],
+            Pattern::VarPat(var) => vec![var],
+            Pattern::ConPat(_, pats) | Pattern::ListPat(pats) | Pattern::TuplePat(pats) => {
+                pats.iter().flat_map(Pattern::vars).collect()
+            }
+            Pattern::AsPat(var, pat) => [vec![var], pat.vars()].concat(),
+            Pattern::SigPat(pat, _) => pat.vars(),
+        }
+    }
+}
+
+/// One equation of a function: `f p1 p2 | guard = e where decls`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Clause {
+    pub params: Vec<Pattern>,
+    pub rhs: Rhs,
+    pub wheres: Program,
+}
+
+/// One alternative of a case expression: `p | guard -> e where decls`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Alt {
+    pub pat: Pattern,
+    pub rhs: Rhs,
+    pub wheres: Program,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Rhs {
+    Plain(Expr),
+    Guarded(Vec<(Expr, Expr)>),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Type {
+    VarType(Ident),
+    ConType(Ident),
+    AppType(Box<Type>, Box<Type>),
+    FnType(Box<Type>, Box<Type>),
+    ListType(Box<Type>),
+    TupleType(Vec<Type>),
+    /// `forall a b. t` at the head of a signature, whose variables are in
+    /// scope in the definition with `ScopedTypeVariables`.
+    ForallType(Vec<Ident>, Box<Type>),
+}
+
+impl Type {
+    /// The constructor and the arguments of `T a b`.
+    pub fn split(&self) -> (&Type, Vec<&Type>) {
+        let mut args = vec![];
+        let mut ty = self;
+        while let Type::AppType(f, arg) = ty {
+            args.push(&**arg);
+            ty = f;
+        }
+        args.reverse();
+        (ty, args)
+    }
+}
+
+impl Dict {
+    /// The name an instance's methods find the dictionary for the `i`th
+    /// constraint of the instance's context by.
+    pub fn context(i: usize) -> Ident {
+        Ident(format!("$ctx{}", i))
+    }
+
+    /// The name a default method finds the dictionary of the instance it is
+    /// used for by.
+    pub fn this() -> Ident {
+        Ident("$this".to_owned())
+    }
+}
+
+/// A class constraint such as `Eq a` or `Show (f a)`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pred {
+    pub class: Ident,
+    pub ty: Type,
+}
+
+pub type Context = Vec<Pred>;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct DataDecl {
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub constructors: Vec<ConDecl>,
+    pub deriving: Vec<Ident>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ConDecl {
+    pub name: Ident,
+    pub fields: Vec<Type>,
+    /// Field names of a record constructor, empty for positional fields.
+    pub labels: Vec<Ident>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ClassDecl {
+    pub context: Context,
+    pub name: Ident,
+    pub var: Ident,
+    pub body: Program,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct InstanceDecl {
+    pub context: Context,
+    pub class: Ident,
+    pub ty: Type,
+    pub body: Program,
+    /// Where the head `C t` is, when the parser has the spans
+    pub span: Option<Span>,
+}
@@ -0,0 +1,237 @@
+use crate::parser::ast::*;
+use crate::parser::desugar::desugar_pattern_binding;
+use crate::parser::visit::{self, Visitor};
+use std::collections::{HashMap, HashSet};
+
+/// A step of a program once its bindings are in dependency order.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Group {
+    /// A statement that binds no value: an expression, a signature or a
+    /// type, class or instance declaration
+    Stmt(Stmt),
+    /// Bindings that are defined in terms of each other, a strongly
+    /// connected component of the dependency graph. `recursive` is false only
+    /// for a single binding that doesn't mention itself.
+    Bindings { bindings: Program, recursive: bool },
+}
+
+/// Orders the bindings of a program, a `let` or a `where` so that every
+/// binding comes after the bindings it uses, as the Haskell report asks in
+/// section 4.5.1. Bindings that don't depend on each other keep their order.
+/// A pattern binding is ordered as the bindings it desugars into.
+///
+/// A program can still be a script: an expression, or a second definition
+/// of a name as in GHCi, only sees the bindings above it, so each run of
+/// bindings between them is ordered on its own.
+pub fn dependency_order(program: Program) -> Vec<Group> {
+    let mut groups = vec![];
+    let mut run: Program = vec![];
+    let program = program.into_iter().flat_map(|stmt| match stmt {
+        Stmt::PatStmt(pat, rhs, wheres) => desugar_pattern_binding(pat, rhs, wheres),
+        stmt => vec![stmt],
+    });
+    for stmt in program {
+        match stmt {
+            Stmt::LetStmt(Ident(ref name), _) | Stmt::FunStmt(Ident(ref name), _) => {
+                if run.iter().any(|s| bound_name(s) == Some(name)) {
+                    groups.extend(components(std::mem::take(&mut run)));
+                }
+                run.push(stmt);
+            }
+            Stmt::ExprStmt(_) | Stmt::ReturnStmt(_) | Stmt::ErrorStmt(_) => {
+                groups.extend(components(std::mem::take(&mut run)));
+                groups.push(Group::Stmt(stmt));
+            }
+            // declarations take effect before the program runs
+            stmt => groups.push(Group::Stmt(stmt)),
+        }
+    }
+    groups.extend(components(run));
+    groups
+}
+
+fn bound_name(stmt: &Stmt) -> Option<&String> {
+    match stmt {
+        Stmt::LetStmt(Ident(name), _) | Stmt::FunStmt(Ident(name), _) => Some(name),
+        _ => None,
+    }
+}
+
+// The names a binding mentions. Local binders that shadow a name of the
+// run count as a use of it, which can only merge components; the renamer
+// has made local names unique by the time a module gets here.
+#[derive(Default)]
+struct Mentions<'ast> {
+    names: HashSet<&'ast str>,
+}
+
+impl<'ast> Visitor<'ast> for Mentions<'ast> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::IdentExpr(Ident(name)) | Expr::HoleExpr(Ident(name)) = expr {
+            self.names.insert(name);
+        }
+        visit::visit_expr(self, expr)
+    }
+
+    fn visit_infix(&mut self, op: &'ast Infix) {
+        self.names.insert(op.name());
+        visit::visit_infix(self, op)
+    }
+}
+
+// Tarjan's algorithm, started from the bindings in source order so that a
+// component is emitted as soon as everything it uses has been.
+fn components(run: Program) -> Vec<Group> {
+    let index: HashMap<&str, usize> = run
+        .iter()
+        .enumerate()
+        .filter_map(|(i, stmt)| bound_name(stmt).map(|name| (name.as_str(), i)))
+        .collect();
+    let edges: Vec<Vec<usize>> = run
+        .iter()
+        .map(|stmt| {
+            let mut mentions = Mentions::default();
+            match stmt {
+                Stmt::FunStmt(_, clauses) => clauses.iter().for_each(|c| mentions.visit_clause(c)),
+                stmt => mentions.visit_stmt(stmt),
+            }
+            let mut edges: Vec<usize> = mentions
+                .names
+                .iter()
+                .filter_map(|name| index.get(name).copied())
+                .collect();
+            edges.sort_unstable();
+            edges
+        })
+        .collect();
+    let mut tarjan = Tarjan {
+        edges: &edges,
+        index: vec![None; run.len()],
+        low: vec![0; run.len()],
+        stack: vec![],
+        on_stack: vec![false; run.len()],
+        next: 0,
+        components: vec![],
+    };
+    for node in 0..run.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.connect(node);
+        }
+    }
+    let components = tarjan.components;
+    let mut stmts: Vec<Option<Stmt>> = run.into_iter().map(Some).collect();
+    components
+        .into_iter()
+        .map(|mut component| {
+            component.sort_unstable();
+            let recursive = component.len() > 1 || edges[component[0]].contains(&component[0]);
+            let bindings = component.iter().filter_map(|&i| stmts[i].take()).collect();
+            Group::Bindings {
+                bindings,
+                recursive,
+            }
+        })
+        .collect()
+}
+
+struct Tarjan<'a> {
+    edges: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn connect(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        for &next in &self.edges[node] {
+            match self.index[next] {
+                None => {
+                    self.connect(next);
+                    self.low[node] = self.low[node].min(self.low[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.low[node] = self.low[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+        if Some(self.low[node]) == self.index[node] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::Tokens;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    // The names of each group, recursive ones in brackets
+    fn order(input: &str) -> Vec<String> {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).unwrap();
+        dependency_order(program)
+            .into_iter()
+            .map(|group| match group {
+                Group::Stmt(Stmt::SigStmt(names, ..)) => format!("::{}", names[0].0),
+                Group::Stmt(_) => "stmt".to_owned(),
+                Group::Bindings {
+                    bindings,
+                    recursive,
+                } => {
+                    let names: Vec<&str> =
+                        bindings.iter().filter_map(bound_name).map(String::as_str).collect();
+                    match recursive {
+                        true => format!("[{}]", names.join(" ")),
+                        false => names.join(" "),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        assert_eq!(order("x = a + 1\ny = 2\na = f y\nf n = n"), ["y", "f", "a", "x"]);
+        assert_eq!(
+            order("even' 0 = True\neven' n = odd' (n - 1)\nodd' 0 = False\nodd' n = even' (n - 1)"),
+            ["[even' odd']"]
+        );
+        assert_eq!(
+            order("xs = 1 : ys\nlen' = 3\nys = 2 : xs\nones = 1 : ones"),
+            ["[xs ys]", "len'", "[ones]"]
+        );
+        assert_eq!(
+            order("x <+> y = x\nz = 1 <+> 2\nsq :: Int -> Int\nw = sq 2\nsq n = n * n"),
+            ["::sq", "<+>", "z", "sq", "w"]
+        );
+    }
+
+    #[test]
+    fn test_script_order() {
+        assert_eq!(
+            order("let x = fn () { a; };\nlet a = 10;\nx();\nlet b = c;\nlet c = 1;"),
+            ["a", "x", "stmt", "c", "b"]
+        );
+        // a second definition starts a new run
+        assert_eq!(order("x = 1\ny = x\nx = y + 1\n"), ["x", "y", "x"]);
+    }
+}
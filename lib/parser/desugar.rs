@@ -0,0 +1,347 @@
+use crate::parser::ast::*;
+
+/// Translates a list comprehension into `concatMap`, `if` and `let` as in
+/// section 3.11 of the Haskell report, so that it is as lazy as the lists it
+/// draws from. Elements not matching the pattern of a generator are skipped.
+pub fn desugar_comprehension(expr: Expr, qualifiers: Vec<Qualifier>) -> Expr {
+    let mut qualifiers = qualifiers.into_iter();
+    let qualifier = match qualifiers.next() {
+        Some(qualifier) => qualifier,
+        None => return Expr::ArrayExpr(vec![expr]),
+    };
+    let rest = desugar_comprehension(expr, qualifiers.collect());
+    match qualifier {
+        Qualifier::Guard(cond) => Expr::IfExpr {
+            cond: Box::new(cond),
+            consequence: vec![Stmt::ExprStmt(rest)],
+            alternative: Some(vec![Stmt::ExprStmt(Expr::ArrayExpr(vec![]))]),
+        },
+        Qualifier::Generator(pat, list) => {
+            // `$ok` can't be written in a program, so it captures nothing
+            let ok = Ident("$ok".to_owned());
+            let clause = |params, body| Clause {
+                params: vec![params],
+                rhs: Rhs::Plain(body),
+                wheres: vec![],
+            };
+            let ok_fn = Stmt::FunStmt(
+                ok.clone(),
+                vec![
+                    clause(pat, rest),
+                    clause(Pattern::WildPat, Expr::ArrayExpr(vec![])),
+                ],
+            );
+            let concat_map = Expr::CallExpr {
+                function: Box::new(Expr::IdentExpr(Ident("concatMap".to_owned()))),
+                arguments: vec![Expr::IdentExpr(ok)],
+            };
+            Expr::LetExpr {
+                bindings: vec![ok_fn],
+                body: Box::new(Expr::CallExpr {
+                    function: Box::new(concat_map),
+                    arguments: vec![list],
+                }),
+            }
+        }
+        Qualifier::LetQual(bindings) => Expr::LetExpr {
+            bindings,
+            body: Box::new(rest),
+        },
+    }
+}
+
+/// Translates the statements of a `do` block into `>>=`, `>>` and `fail` as in
+/// section 3.14 of the Haskell report, so that it works in any `Monad`. A
+/// generator whose pattern can fail calls `fail` on the values it rejects.
+pub fn desugar_do(stmts: Vec<Qualifier>) -> Expr {
+    let mut stmts = stmts.into_iter();
+    let stmt = match stmts.next() {
+        Some(stmt) => stmt,
+        None => return error("Empty 'do' block"),
+    };
+    if stmts.len() == 0 {
+        return match stmt {
+            Qualifier::Guard(expr) => expr,
+            _ => error("The last statement in a 'do' block must be an expression"),
+        };
+    }
+    let rest = desugar_do(stmts.collect());
+    let op = |name: &str, left, right| {
+        Expr::InfixExpr(Infix::Op(Ident(name.to_owned())), Box::new(left), Box::new(right))
+    };
+    match stmt {
+        Qualifier::Guard(action) => op(">>", action, rest),
+        Qualifier::Generator(pat @ (Pattern::VarPat(_) | Pattern::WildPat), action) => {
+            let k = Expr::LambdaExpr {
+                params: vec![pat],
+                body: Box::new(rest),
+            };
+            op(">>=", action, k)
+        }
+        Qualifier::Generator(pat, action) => {
+            let ok = Ident("$ok".to_owned());
+            let clause = |params, body| Clause {
+                params: vec![params],
+                rhs: Rhs::Plain(body),
+                wheres: vec![],
+            };
+            let fail = Expr::CallExpr {
+                function: Box::new(Expr::IdentExpr(Ident("fail".to_owned()))),
+                arguments: vec![Expr::LitExpr(Literal::StringLiteral(
+                    "Pattern match failure in do expression".to_owned(),
+                ))],
+            };
+            let ok_fn = Stmt::FunStmt(
+                ok.clone(),
+                vec![clause(pat, rest), clause(Pattern::WildPat, fail)],
+            );
+            Expr::LetExpr {
+                bindings: vec![ok_fn],
+                body: Box::new(op(">>=", action, Expr::IdentExpr(ok))),
+            }
+        }
+        Qualifier::LetQual(bindings) => Expr::LetExpr {
+            bindings,
+            body: Box::new(rest),
+        },
+    }
+}
+
+/// `\case alts` is `\x -> case x of alts`.
+pub fn desugar_lambda_case(alts: Vec<Alt>) -> Expr {
+    // `$x` can't be written in a program, so it captures nothing
+    let x = Ident("$x".to_owned());
+    Expr::LambdaExpr {
+        params: vec![Pattern::VarPat(x.clone())],
+        body: Box::new(Expr::CaseExpr {
+            scrutinee: Box::new(Expr::IdentExpr(x)),
+            alts,
+        }),
+    }
+}
+
+/// `if | c1 -> e1 | c2 -> e2` is `case () of _ | c1 -> e1 | c2 -> e2`, which
+/// fails like any case when no guard holds.
+pub fn desugar_multi_way_if(guards: Vec<(Expr, Expr)>) -> Expr {
+    Expr::CaseExpr {
+        scrutinee: Box::new(Expr::TupleExpr(vec![])),
+        alts: vec![Alt {
+            pat: Pattern::WildPat,
+            rhs: Rhs::Guarded(guards),
+            wheres: vec![],
+        }],
+    }
+}
+
+/// `(, x, )` is `\$0 $2 -> ($0, x, $2)`, a parameter for every missing
+/// component.
+pub fn desugar_tuple_section(components: Vec<Option<Expr>>) -> Expr {
+    let mut params = vec![];
+    let exprs = components
+        .into_iter()
+        .enumerate()
+        .map(|(i, component)| {
+            component.unwrap_or_else(|| {
+                let param = Ident(format!("${}", i));
+                params.push(Pattern::VarPat(param.clone()));
+                Expr::IdentExpr(param)
+            })
+        })
+        .collect();
+    Expr::LambdaExpr {
+        params,
+        body: Box::new(Expr::TupleExpr(exprs)),
+    }
+}
+
+/// `(a, b) = e` is `$pat_a = e` with `a = case $pat_a of ($a, _) -> $a` and
+/// `b = case $pat_a of (_, $b) -> $b`, as in section 4.4.3.2 of the Haskell
+/// report: the value is shared, and matched when a variable is first used.
+pub fn desugar_pattern_binding(pat: Pattern, rhs: Rhs, wheres: Program) -> Program {
+    let vars: Vec<Ident> = pat.vars().into_iter().cloned().collect();
+    let Some(Ident(first)) = vars.first() else {
+        return vec![];
+    };
+    // `$pat_a` can't be written in a program, so it captures nothing
+    let value = Ident(format!("$pat_{}", first));
+    let binding = match rhs {
+        Rhs::Plain(expr) if wheres.is_empty() => Stmt::LetStmt(value.clone(), expr),
+        rhs => Stmt::FunStmt(
+            value.clone(),
+            vec![Clause {
+                params: vec![],
+                rhs,
+                wheres,
+            }],
+        ),
+    };
+    let mut program = vec![binding];
+    for var in vars {
+        // the variable matched is renamed so that its binding is not recursive
+        let matched = Ident(format!("${}", var.0));
+        let alt = Alt {
+            pat: project(&pat, &var, &matched),
+            rhs: Rhs::Plain(Expr::IdentExpr(matched)),
+            wheres: vec![],
+        };
+        let scrutinee = Box::new(Expr::IdentExpr(value.clone()));
+        let alts = vec![alt];
+        program.push(Stmt::LetStmt(var, Expr::CaseExpr { scrutinee, alts }));
+    }
+    program
+}
+
+// The pattern binding only `var`, as `matched`
+fn project(pat: &Pattern, var: &Ident, matched: &Ident) -> Pattern {
+    let project = |pat: &Pattern| project(pat, var, matched);
+    let project_all = |pats: &[Pattern]| pats.iter().map(project).collect();
+    match pat {
+        Pattern::VarPat(v) if v == var => Pattern::VarPat(matched.clone()),
+        Pattern::VarPat(_) => Pattern::WildPat,
+        Pattern::AsPat(v, pat) if v == var => {
+            Pattern::AsPat(matched.clone(), Box::new(project(pat)))
+        }
+        Pattern::AsPat(_, pat) => project(pat),
+        Pattern::ConPat(con, pats) => Pattern::ConPat(con.clone(), project_all(pats)),
+        Pattern::ListPat(pats) => Pattern::ListPat(project_all(pats)),
+        Pattern::TuplePat(pats) => Pattern::TuplePat(project_all(pats)),
+        Pattern::SigPat(pat, ty) => Pattern::SigPat(Box::new(project(pat)), ty.clone()),
+        pat => pat.clone(),
+    }
+}
+
+// A call of `error`, for blocks the parser would not have produced
+fn error(message: &str) -> Expr {
+    Expr::CallExpr {
+        function: Box::new(Expr::IdentExpr(Ident("error".to_owned()))),
+        arguments: vec![Expr::LitExpr(Literal::StringLiteral(message.to_owned()))],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Expr {
+        Expr::IdentExpr(Ident(name.to_owned()))
+    }
+
+    #[test]
+    fn test_desugar_comprehension() {
+        assert_eq!(
+            desugar_comprehension(var("x"), vec![]),
+            Expr::ArrayExpr(vec![var("x")])
+        );
+        assert_eq!(
+            desugar_comprehension(var("x"), vec![Qualifier::Guard(var("b"))]),
+            Expr::IfExpr {
+                cond: Box::new(var("b")),
+                consequence: vec![Stmt::ExprStmt(Expr::ArrayExpr(vec![var("x")]))],
+                alternative: Some(vec![Stmt::ExprStmt(Expr::ArrayExpr(vec![]))]),
+            }
+        );
+        let generator = desugar_comprehension(
+            var("x"),
+            vec![Qualifier::Generator(
+                Pattern::VarPat(Ident("x".to_owned())),
+                var("xs"),
+            )],
+        );
+        let (bindings, body) = match generator {
+            Expr::LetExpr { bindings, body } => (bindings, body),
+            e => panic!("expected a let expression, got {:?}", e),
+        };
+        match bindings.as_slice() {
+            [Stmt::FunStmt(_, clauses)] => assert_eq!(clauses[1].params, vec![Pattern::WildPat]),
+            b => panic!("expected the generator function, got {:?}", b),
+        }
+        match *body {
+            Expr::CallExpr { arguments, .. } => assert_eq!(arguments, vec![var("xs")]),
+            e => panic!("expected a call of concatMap, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_desugar_do() {
+        let op = |name: &str, left, right| {
+            Expr::InfixExpr(Infix::Op(Ident(name.to_owned())), Box::new(left), Box::new(right))
+        };
+        assert_eq!(desugar_do(vec![Qualifier::Guard(var("m"))]), var("m"));
+        assert_eq!(
+            desugar_do(vec![Qualifier::Guard(var("m")), Qualifier::Guard(var("k"))]),
+            op(">>", var("m"), var("k"))
+        );
+        assert_eq!(
+            desugar_do(vec![
+                Qualifier::Generator(Pattern::VarPat(Ident("x".to_owned())), var("m")),
+                Qualifier::Guard(var("x")),
+            ]),
+            op(
+                ">>=",
+                var("m"),
+                Expr::LambdaExpr {
+                    params: vec![Pattern::VarPat(Ident("x".to_owned()))],
+                    body: Box::new(var("x")),
+                }
+            )
+        );
+        let failable = desugar_do(vec![
+            Qualifier::Generator(Pattern::ListPat(vec![]), var("m")),
+            Qualifier::Guard(var("k")),
+        ]);
+        match failable {
+            Expr::LetExpr { bindings, body } => {
+                match bindings.as_slice() {
+                    [Stmt::FunStmt(_, clauses)] => assert_eq!(clauses.len(), 2),
+                    b => panic!("expected the continuation, got {:?}", b),
+                }
+                assert_eq!(*body, op(">>=", var("m"), var("$ok")));
+            }
+            e => panic!("expected a let expression, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_desugar_pattern_binding() {
+        let pat = |name: &str| Pattern::VarPat(Ident(name.to_owned()));
+        let project = |name: &str, pattern| {
+            let alt = Alt {
+                pat: pattern,
+                rhs: Rhs::Plain(var(&format!("${}", name))),
+                wheres: vec![],
+            };
+            Stmt::LetStmt(
+                Ident(name.to_owned()),
+                Expr::CaseExpr {
+                    scrutinee: Box::new(var("$pat_a")),
+                    alts: vec![alt],
+                },
+            )
+        };
+        let as_c = Pattern::AsPat(Ident("b".to_owned()), Box::new(pat("c")));
+        assert_eq!(
+            desugar_pattern_binding(
+                Pattern::TuplePat(vec![pat("a"), as_c]),
+                Rhs::Plain(var("e")),
+                vec![],
+            ),
+            vec![
+                Stmt::LetStmt(Ident("$pat_a".to_owned()), var("e")),
+                project("a", Pattern::TuplePat(vec![pat("$a"), Pattern::WildPat])),
+                project(
+                    "b",
+                    Pattern::TuplePat(vec![
+                        Pattern::WildPat,
+                        Pattern::AsPat(Ident("$b".to_owned()), Box::new(Pattern::WildPat)),
+                    ])
+                ),
+                project("c", Pattern::TuplePat(vec![Pattern::WildPat, pat("$c")])),
+            ]
+        );
+        // a pattern with no variables binds nothing
+        assert_eq!(
+            desugar_pattern_binding(Pattern::WildPat, Rhs::Plain(var("e")), vec![]),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::lexer::token::*;
+use nom::error::ErrorKind;
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+
+/// What the parser would have accepted where it failed.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expected {
+    /// A particular token, such as `)` or `where`
+    Token(Token),
+    /// A kind of token, such as "an identifier"
+    Kind(&'static str),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(Token::EOF) => write!(f, "end of input"),
+            Expected::Token(Token::Backquote) => write!(f, "a backquote"),
+            Expected::Token(token) => write!(f, "`{}`", token),
+            Expected::Kind(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+/// A syntax error: the token the parser could not go past, the tokens it
+/// expected there and where the unexpected token is.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub unexpected: Token,
+    pub expected: Vec<Expected>,
+    /// `None` when the tokens have no source spans.
+    pub span: Option<Span>,
+    /// The language extension that would have made the input parse.
+    pub extension: Option<Extension>,
+    // the address of the unexpected token, so that the alternative that got
+    // furthest gives the error even when it parsed a layout slice of the
+    // input: all slices borrow the same token vector
+    position: usize,
+}
+
+impl ParseError {
+    pub fn new(input: Tokens) -> Self {
+        ParseError {
+            unexpected: input.tok.first().cloned().unwrap_or(Token::EOF),
+            expected: vec![],
+            span: input.span_at(0),
+            extension: None,
+            position: input.tok.as_ptr() as usize,
+        }
+    }
+
+    pub fn expected(input: Tokens, expected: Expected) -> Self {
+        ParseError {
+            expected: vec![expected],
+            ..ParseError::new(input)
+        }
+    }
+
+    /// Syntax of a language extension that is not enabled.
+    pub fn extension(input: Tokens, extension: Extension) -> Self {
+        ParseError {
+            extension: Some(extension),
+            ..ParseError::new(input)
+        }
+    }
+
+    /// A `LANGUAGE` pragma naming an extension the parser doesn't have.
+    pub fn unsupported(input: Tokens, name: &str) -> Self {
+        ParseError {
+            unexpected: Token::TypeIdent(name.to_owned()),
+            expected: Extension::ALL.iter().map(|e| Expected::Kind(e.name())).collect(),
+            ..ParseError::new(input)
+        }
+    }
+
+    /// Points an error found at the end of a slice of `input` at the token
+    /// of `input` that follows the slice, which has a source span.
+    pub fn within(self, input: Tokens) -> Self {
+        let offset = self.position.wrapping_sub(input.tok.as_ptr() as usize);
+        let index = offset / mem::size_of::<Token>();
+        match input.tok.get(index) {
+            Some(token) => ParseError {
+                unexpected: token.clone(),
+                span: input.span_at(index),
+                ..self
+            },
+            None => self,
+        }
+    }
+}
+
+impl PartialEq for ParseError {
+    fn eq(&self, other: &Self) -> bool {
+        self.unexpected == other.unexpected
+            && self.expected == other.expected
+            && self.span == other.span
+            && self.extension == other.extension
+    }
+}
+
+impl<'a> nom::error::ParseError<Tokens<'a>> for ParseError {
+    fn from_error_kind(input: Tokens<'a>, _: ErrorKind) -> Self {
+        ParseError::new(input)
+    }
+
+    fn append(_: Tokens<'a>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    // Of the errors of two alternatives, the one that got further, or both
+    // expectations when they failed at the same token
+    fn or(mut self, other: Self) -> Self {
+        match self.position.cmp(&other.position) {
+            Ordering::Greater => self,
+            Ordering::Less => other,
+            Ordering::Equal => {
+                for expected in other.expected {
+                    if !self.expected.contains(&expected) {
+                        self.expected.push(expected);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+/// `3:7: unexpected `)`, expected one of `]`, `,`` with the line and column
+/// when they are known, and the extension to enable if there is one.
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}:{}: ", span.line, span.column)?;
+        }
+        match self.unexpected {
+            Token::EOF => write!(f, "unexpected end of input")?,
+            ref token => write!(f, "unexpected `{}`", token)?,
+        }
+        let expected: Vec<String> = self.expected.iter().map(|e| e.to_string()).collect();
+        match expected.as_slice() {
+            [] => {}
+            [one] => write!(f, ", expected {}", one)?,
+            many => write!(f, ", expected one of {}", many.join(", "))?,
+        }
+        match self.extension {
+            Some(extension) => write!(
+                f,
+                "; enable extension {0} with {{-# LANGUAGE {0} #-}}",
+                extension.name()
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::ParseError as _;
+    use nom::Slice;
+
+    #[test]
+    fn test_furthest_error() {
+        let tokens = [Token::LParen, Token::Ident("x".to_owned()), Token::EOF];
+        let input = Tokens::new(&tokens);
+        let at_paren = ParseError::expected(input, Expected::Kind("an identifier"));
+        let at_x = ParseError::expected(input.slice(1..), Expected::Token(Token::RParen));
+        assert_eq!(at_paren.clone().or(at_x.clone()), at_x);
+        assert_eq!(at_x.clone().or(at_paren.clone()), at_x);
+        let both = at_paren
+            .clone()
+            .or(ParseError::expected(input, Expected::Token(Token::LBracket)));
+        assert_eq!(
+            both.expected,
+            vec![Expected::Kind("an identifier"), Expected::Token(Token::LBracket)]
+        );
+        assert_eq!(
+            both.to_string(),
+            "unexpected `(`, expected one of an identifier, `[`"
+        );
+        assert_eq!(at_x.to_string(), "unexpected `x`, expected `)`");
+    }
+}
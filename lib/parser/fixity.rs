@@ -0,0 +1,470 @@
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+// An operand of an operator chain with its negation sign, if any, and the
+// span of the negation
+type Operand = (Option<(Prefix, Option<Span>)>, Expr);
+
+/// The fixity declarations in scope, innermost scope last. Operators
+/// without a declaration are `infixl 9`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Fixities {
+    scopes: Vec<HashMap<String, Fixity>>,
+}
+
+impl Default for Fixities {
+    /// The fixities of the Prelude operators, as listed in the report.
+    fn default() -> Self {
+        let table: [(Assoc, u8, &[&str]); 12] = [
+            (Assoc::Right, 9, &["."]),
+            (Assoc::Left, 9, &["!!"]),
+            (Assoc::Right, 8, &["^", "^^", "**"]),
+            (Assoc::Left, 7, &["*", "/", "div", "mod", "rem", "quot"]),
+            (Assoc::Left, 6, &["+", "-"]),
+            (Assoc::Right, 5, &[":", "++"]),
+            (Assoc::Non, 4, &["==", "/=", "<", "<=", ">=", ">", "elem", "notElem"]),
+            (Assoc::Right, 3, &["&&"]),
+            (Assoc::Right, 2, &["||"]),
+            (Assoc::Left, 1, &[">>", ">>="]),
+            (Assoc::Right, 1, &["=<<"]),
+            (Assoc::Right, 0, &["$", "$!", "seq"]),
+        ];
+        let mut prelude = HashMap::new();
+        for (assoc, precedence, names) in table {
+            for name in names {
+                prelude.insert(name.to_string(), Fixity { assoc, precedence });
+            }
+        }
+        Fixities {
+            scopes: vec![prelude],
+        }
+    }
+}
+
+impl Fixities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Fixity {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .unwrap_or(Fixity {
+                assoc: Assoc::Left,
+                precedence: 9,
+            })
+    }
+
+    /// Brings the fixity declarations of a program into the innermost scope.
+    /// Those of a class body belong to the enclosing scope.
+    pub fn declare(&mut self, decls: &Program) {
+        for decl in decls {
+            match decl {
+                Stmt::FixityStmt(fixity, names) => {
+                    let scope = self.scopes.last_mut().expect("a scope");
+                    for Ident(name) in names {
+                        scope.insert(name.clone(), *fixity);
+                    }
+                }
+                Stmt::ClassStmt(class) => self.declare(&class.body),
+                _ => {}
+            }
+        }
+    }
+
+    /// Associates the operator chains of a program. Its fixity declarations
+    /// stay in scope for the programs resolved after it, as in a REPL.
+    pub fn resolve_program(&mut self, program: Program) -> Result<Program, String> {
+        self.declare(&program);
+        self.resolve_stmts(program)
+    }
+
+    /// Opens the scope of a `let` or `where` block with its declarations.
+    pub fn push_scope(&mut self, decls: &Program) {
+        self.scopes.push(HashMap::new());
+        self.declare(decls);
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // Resolves a `let` or `where` block together with the code it scopes
+    // over, with the block's own fixity declarations in scope.
+    fn scoped<T>(
+        &mut self,
+        decls: Program,
+        body: T,
+        resolve: fn(&mut Self, T) -> Result<T, String>,
+    ) -> Result<(Program, T), String> {
+        self.push_scope(&decls);
+        let result = self
+            .resolve_stmts(decls)
+            .and_then(|decls| Ok((decls, resolve(self, body)?)));
+        self.pop_scope();
+        result
+    }
+
+    fn resolve_stmts(&mut self, stmts: Program) -> Result<Program, String> {
+        stmts.into_iter().map(|stmt| self.resolve_stmt(stmt)).collect()
+    }
+
+    fn resolve_stmt(&mut self, stmt: Stmt) -> Result<Stmt, String> {
+        Ok(match stmt {
+            Stmt::LetStmt(name, expr) => Stmt::LetStmt(name, self.resolve_expr(expr)?),
+            Stmt::ReturnStmt(expr) => Stmt::ReturnStmt(self.resolve_expr(expr)?),
+            Stmt::ExprStmt(expr) => Stmt::ExprStmt(self.resolve_expr(expr)?),
+            Stmt::FunStmt(name, clauses) => Stmt::FunStmt(
+                name,
+                clauses
+                    .into_iter()
+                    .map(|clause| self.resolve_clause(clause))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Stmt::PatStmt(pat, rhs, wheres) => {
+                let (wheres, rhs) = self.scoped(wheres, rhs, Self::resolve_rhs)?;
+                Stmt::PatStmt(pat, rhs, wheres)
+            }
+            Stmt::ClassStmt(mut class) => {
+                class.body = self.resolve_stmts(class.body)?;
+                Stmt::ClassStmt(class)
+            }
+            Stmt::InstanceStmt(mut instance) => {
+                instance.body = self.resolve_stmts(instance.body)?;
+                Stmt::InstanceStmt(instance)
+            }
+            stmt @ (Stmt::SigStmt(..)
+            | Stmt::DataStmt(_)
+            | Stmt::TypeStmt(..)
+            | Stmt::DerivingStmt(_)
+            | Stmt::FixityStmt(..)
+            | Stmt::DefaultStmt(_)
+            | Stmt::ErrorStmt(_)) => stmt,
+        })
+    }
+
+    fn resolve_clause(&mut self, clause: Clause) -> Result<Clause, String> {
+        let (wheres, rhs) = self.scoped(clause.wheres, clause.rhs, Self::resolve_rhs)?;
+        Ok(Clause {
+            params: clause.params,
+            rhs,
+            wheres,
+        })
+    }
+
+    fn resolve_alt(&mut self, alt: Alt) -> Result<Alt, String> {
+        let (wheres, rhs) = self.scoped(alt.wheres, alt.rhs, Self::resolve_rhs)?;
+        Ok(Alt {
+            pat: alt.pat,
+            rhs,
+            wheres,
+        })
+    }
+
+    fn resolve_rhs(&mut self, rhs: Rhs) -> Result<Rhs, String> {
+        Ok(match rhs {
+            Rhs::Plain(expr) => Rhs::Plain(self.resolve_expr(expr)?),
+            Rhs::Guarded(guards) => Rhs::Guarded(
+                guards
+                    .into_iter()
+                    .map(|(guard, expr)| Ok((self.resolve_expr(guard)?, self.resolve_expr(expr)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+        })
+    }
+
+    fn resolve_exprs(&mut self, exprs: Vec<Expr>) -> Result<Vec<Expr>, String> {
+        exprs.into_iter().map(|expr| self.resolve_expr(expr)).collect()
+    }
+
+    fn resolve_box(&mut self, mut expr: Box<Expr>) -> Result<Box<Expr>, String> {
+        *expr = self.resolve_expr(*expr)?;
+        Ok(expr)
+    }
+
+    pub fn resolve_expr(&mut self, expr: Expr) -> Result<Expr, String> {
+        Ok(match expr {
+            Expr::InfixChain(first, rest) => {
+                let first = self.resolve_operand(*first)?;
+                let rest = rest
+                    .into_iter()
+                    .map(|(op, expr)| Ok((op, self.resolve_operand(expr)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.negate(first, None, &mut rest.into_iter().peekable())?
+            }
+            Expr::PrefixExpr(prefix, expr) => Expr::PrefixExpr(prefix, self.resolve_box(expr)?),
+            Expr::InfixExpr(op, left, right) => {
+                Expr::InfixExpr(op, self.resolve_box(left)?, self.resolve_box(right)?)
+            }
+            Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            } => Expr::IfExpr {
+                cond: self.resolve_box(cond)?,
+                consequence: self.resolve_stmts(consequence)?,
+                alternative: alternative.map(|alt| self.resolve_stmts(alt)).transpose()?,
+            },
+            Expr::FnExpr { params, body } => Expr::FnExpr {
+                params,
+                body: self.resolve_stmts(body)?,
+            },
+            Expr::CallExpr {
+                function,
+                arguments,
+            } => Expr::CallExpr {
+                function: self.resolve_box(function)?,
+                arguments: self.resolve_exprs(arguments)?,
+            },
+            Expr::ArrayExpr(exprs) => Expr::ArrayExpr(self.resolve_exprs(exprs)?),
+            Expr::HashExpr(pairs) => Expr::HashExpr(
+                pairs
+                    .into_iter()
+                    .map(|(key, expr)| Ok((key, self.resolve_expr(expr)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+            Expr::IndexExpr { array, index } => Expr::IndexExpr {
+                array: self.resolve_box(array)?,
+                index: self.resolve_box(index)?,
+            },
+            Expr::LambdaExpr { params, body } => Expr::LambdaExpr {
+                params,
+                body: self.resolve_box(body)?,
+            },
+            Expr::CaseExpr { scrutinee, alts } => Expr::CaseExpr {
+                scrutinee: self.resolve_box(scrutinee)?,
+                alts: alts
+                    .into_iter()
+                    .map(|alt| self.resolve_alt(alt))
+                    .collect::<Result<_, _>>()?,
+            },
+            Expr::LambdaCaseExpr(alts) => Expr::LambdaCaseExpr(
+                alts.into_iter()
+                    .map(|alt| self.resolve_alt(alt))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::MultiIfExpr(guards) => Expr::MultiIfExpr(
+                guards
+                    .into_iter()
+                    .map(|(guard, expr)| Ok((self.resolve_expr(guard)?, self.resolve_expr(expr)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+            Expr::TupleSectionExpr(components) => Expr::TupleSectionExpr(
+                components
+                    .into_iter()
+                    .map(|component| component.map(|expr| self.resolve_expr(expr)).transpose())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::LetExpr { bindings, body } => {
+                let (bindings, body) = self.scoped(bindings, body, Self::resolve_box)?;
+                Expr::LetExpr { bindings, body }
+            }
+            Expr::TupleExpr(exprs) => Expr::TupleExpr(self.resolve_exprs(exprs)?),
+            Expr::LeftSectionExpr(expr, op) => Expr::LeftSectionExpr(self.resolve_box(expr)?, op),
+            Expr::RightSectionExpr(op, expr) => Expr::RightSectionExpr(op, self.resolve_box(expr)?),
+            Expr::ListCompExpr { expr, qualifiers } => Expr::ListCompExpr {
+                expr: self.resolve_box(expr)?,
+                qualifiers: qualifiers
+                    .into_iter()
+                    .map(|qualifier| self.resolve_qualifier(qualifier))
+                    .collect::<Result<_, _>>()?,
+            },
+            Expr::DoExpr(stmts) => Expr::DoExpr(
+                stmts
+                    .into_iter()
+                    .map(|stmt| self.resolve_qualifier(stmt))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::TypedExpr(expr, context, ty) => {
+                Expr::TypedExpr(self.resolve_box(expr)?, context, ty)
+            }
+            Expr::Located(span, expr) => Expr::Located(span, self.resolve_box(expr)?),
+            expr @ (Expr::IdentExpr(_)
+            | Expr::HoleExpr(_)
+            | Expr::LitExpr(_)
+            | Expr::DictExpr(_)) => expr,
+        })
+    }
+
+    // An operand of a chain and its negation sign, told apart before the
+    // operand is resolved as `(- e)` resolves to a `PrefixExpr` too
+    fn resolve_operand(&mut self, expr: Expr) -> Result<Operand, String> {
+        Ok(match expr {
+            Expr::PrefixExpr(prefix, expr) => (Some((prefix, None)), self.resolve_expr(*expr)?),
+            Expr::Located(span, expr) if matches!(*expr, Expr::PrefixExpr(..)) => {
+                let (prefix, expr) = self.resolve_operand(*expr)?;
+                (prefix.map(|(prefix, _)| (prefix, Some(span))), expr)
+            }
+            expr => (None, self.resolve_expr(expr)?),
+        })
+    }
+
+    fn resolve_qualifier(&mut self, qualifier: Qualifier) -> Result<Qualifier, String> {
+        Ok(match qualifier {
+            Qualifier::Generator(pat, expr) => Qualifier::Generator(pat, self.resolve_expr(expr)?),
+            Qualifier::Guard(expr) => Qualifier::Guard(self.resolve_expr(expr)?),
+            Qualifier::LetQual(decls) => {
+                Qualifier::LetQual(self.scoped(decls, (), |_, ()| Ok(()))?.0)
+            }
+        })
+    }
+
+    // `parseNeg` of section 10.6 of the report: a negation takes the operators
+    // binding tighter than itself, and can't follow one binding as tightly.
+    fn negate(
+        &self,
+        (prefix, operand): Operand,
+        outer: Option<(&Infix, Fixity)>,
+        rest: &mut Peekable<IntoIter<(Infix, Operand)>>,
+    ) -> Result<Expr, String> {
+        let prefix = match (prefix, outer) {
+            (None, _) => return self.associate(operand, outer, rest),
+            (Some(_), Some((outer_op, outer_fixity)))
+                if outer_fixity.precedence >= Fixity::NEGATION.precedence =>
+            {
+                return Err(format!(
+                    "Precedence parsing error: cannot mix `{}` [{}] and prefix `-` [{}] \
+                     in the same infix expression",
+                    outer_op.name(),
+                    outer_fixity,
+                    Fixity::NEGATION
+                ));
+            }
+            (Some(prefix), _) => prefix,
+        };
+        let (prefix, sign) = prefix;
+        let operand = self.associate(operand, Some((&Infix::Minus, Fixity::NEGATION)), rest)?;
+        let end = operand.span();
+        let negation = located(sign, end, Expr::PrefixExpr(prefix, Box::new(operand)));
+        self.associate(negation, outer, rest)
+    }
+
+    // `parse1` of the report: `left` is the left operand of the operators
+    // that follow, which bind tighter than `outer`, the operator to its left
+    // if any.
+    fn associate(
+        &self,
+        mut left: Expr,
+        outer: Option<(&Infix, Fixity)>,
+        rest: &mut Peekable<IntoIter<(Infix, Operand)>>,
+    ) -> Result<Expr, String> {
+        while let Some((op, _)) = rest.peek() {
+            let fixity = self.get(op.name());
+            if let Some((outer_op, outer_fixity)) = outer {
+                if outer_fixity.precedence == fixity.precedence
+                    && (outer_fixity.assoc != fixity.assoc || fixity.assoc == Assoc::Non)
+                {
+                    return Err(format!(
+                        "Precedence parsing error: cannot mix `{}` [{}] and `{}` [{}] \
+                         in the same infix expression",
+                        outer_op.name(),
+                        outer_fixity,
+                        op.name(),
+                        fixity
+                    ));
+                }
+                if outer_fixity.precedence > fixity.precedence
+                    || (outer_fixity.precedence == fixity.precedence
+                        && fixity.assoc == Assoc::Left)
+                {
+                    return Ok(left);
+                }
+            }
+            let (op, first) = rest.next().expect("peeked");
+            let right = self.negate(first, Some((&op, fixity)), rest)?;
+            let (start, end) = (left.span(), right.span());
+            left = located(start, end, Expr::InfixExpr(op, Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+}
+
+// An expression from the first token of one span to the last of another,
+// when both are known
+fn located(first: Option<Span>, last: Option<Span>, expr: Expr) -> Expr {
+    match (first, last) {
+        (Some(first), Some(last)) => Expr::Located(
+            Span {
+                end: last.end,
+                ..first
+            },
+            Box::new(expr),
+        ),
+        _ => expr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Expr {
+        Expr::IdentExpr(Ident(name.to_owned()))
+    }
+
+    fn op(name: &str) -> Infix {
+        Infix::from_name(name)
+    }
+
+    fn chain(first: &str, rest: &[(&str, &str)]) -> Expr {
+        let rest = rest.iter().map(|(o, e)| (op(o), var(e))).collect();
+        Expr::InfixChain(Box::new(var(first)), rest)
+    }
+
+    fn infix(o: &str, left: Expr, right: Expr) -> Expr {
+        Expr::InfixExpr(op(o), Box::new(left), Box::new(right))
+    }
+
+    #[test]
+    fn test_associate() {
+        let mut fixities = Fixities::new();
+        assert_eq!(
+            fixities.resolve_expr(chain("a", &[("+", "b"), ("*", "c"), ("-", "d")])),
+            Ok(infix("-", infix("+", var("a"), infix("*", var("b"), var("c"))), var("d")))
+        );
+        assert_eq!(
+            fixities.resolve_expr(chain("a", &[("++", "b"), ("++", "c")])),
+            Ok(infix("++", var("a"), infix("++", var("b"), var("c"))))
+        );
+        assert_eq!(
+            fixities.resolve_expr(chain("a", &[("==", "b"), ("&&", "c"), ("<", "d")])),
+            Ok(infix(
+                "&&",
+                infix("==", var("a"), var("b")),
+                infix("<", var("c"), var("d"))
+            ))
+        );
+        assert_eq!(
+            fixities.resolve_expr(chain("a", &[("<+>", "b"), ("+", "c")])),
+            Ok(infix("+", infix("<+>", var("a"), var("b")), var("c")))
+        );
+    }
+
+    #[test]
+    fn test_fixity_errors() {
+        let mut fixities = Fixities::new();
+        assert_eq!(
+            fixities.resolve_expr(chain("a", &[("==", "b"), ("<", "c")])),
+            Err("Precedence parsing error: cannot mix `==` [infix 4] and `<` [infix 4] \
+                 in the same infix expression"
+                .to_owned())
+        );
+        let program = vec![
+            Stmt::FixityStmt(
+                Fixity {
+                    assoc: Assoc::Right,
+                    precedence: 6,
+                },
+                vec![Ident("<+>".to_owned())],
+            ),
+            Stmt::ExprStmt(chain("a", &[("<+>", "b"), ("+", "c")])),
+        ];
+        assert!(fixities.resolve_program(program).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+use crate::parser::ast::*;
+
+/// A traversal that consumes the AST and builds a new one. Every method
+/// rebuilds its node from the folded children by default, by calling the
+/// function of the same name, so a rewrite only overrides the nodes it
+/// replaces. The nodes are visited in the same order as by `visit::Visitor`.
+pub trait Fold {
+    fn fold_module(&mut self, module: Module) -> Module {
+        fold_module(self, module)
+    }
+
+    fn fold_import(&mut self, import: Import) -> Import {
+        fold_import(self, import)
+    }
+
+    fn fold_entity(&mut self, entity: Entity) -> Entity {
+        fold_entity(self, entity)
+    }
+
+    /// A sequence of statements or declarations: a program or the body of a
+    /// block, class or instance, the bindings of a `let` or a `where`.
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_clause(&mut self, clause: Clause) -> Clause {
+        fold_clause(self, clause)
+    }
+
+    fn fold_rhs(&mut self, rhs: Rhs) -> Rhs {
+        fold_rhs(self, rhs)
+    }
+
+    fn fold_data_decl(&mut self, data: DataDecl) -> DataDecl {
+        fold_data_decl(self, data)
+    }
+
+    fn fold_con_decl(&mut self, con: ConDecl) -> ConDecl {
+        fold_con_decl(self, con)
+    }
+
+    fn fold_class_decl(&mut self, class: ClassDecl) -> ClassDecl {
+        fold_class_decl(self, class)
+    }
+
+    fn fold_instance_decl(&mut self, instance: InstanceDecl) -> InstanceDecl {
+        fold_instance_decl(self, instance)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_alt(&mut self, alt: Alt) -> Alt {
+        fold_alt(self, alt)
+    }
+
+    fn fold_qualifier(&mut self, qualifier: Qualifier) -> Qualifier {
+        fold_qualifier(self, qualifier)
+    }
+
+    fn fold_pattern(&mut self, pat: Pattern) -> Pattern {
+        fold_pattern(self, pat)
+    }
+
+    fn fold_type(&mut self, ty: Type) -> Type {
+        fold_type(self, ty)
+    }
+
+    fn fold_pred(&mut self, pred: Pred) -> Pred {
+        fold_pred(self, pred)
+    }
+
+    fn fold_infix(&mut self, op: Infix) -> Infix {
+        fold_infix(self, op)
+    }
+
+    fn fold_literal(&mut self, literal: Literal) -> Literal {
+        literal
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+}
+
+fn fold_idents<F: Fold + ?Sized>(f: &mut F, idents: Vec<Ident>) -> Vec<Ident> {
+    idents
+        .into_iter()
+        .map(|ident| f.fold_ident(ident))
+        .collect()
+}
+
+fn fold_exprs<F: Fold + ?Sized>(f: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs.into_iter().map(|expr| f.fold_expr(expr)).collect()
+}
+
+fn fold_patterns<F: Fold + ?Sized>(f: &mut F, pats: Vec<Pattern>) -> Vec<Pattern> {
+    pats.into_iter().map(|pat| f.fold_pattern(pat)).collect()
+}
+
+fn fold_context<F: Fold + ?Sized>(f: &mut F, context: Context) -> Context {
+    context.into_iter().map(|pred| f.fold_pred(pred)).collect()
+}
+
+fn fold_entities<F: Fold + ?Sized>(
+    f: &mut F,
+    entities: Option<Vec<Entity>>,
+) -> Option<Vec<Entity>> {
+    entities.map(|entities| entities.into_iter().map(|e| f.fold_entity(e)).collect())
+}
+
+fn fold_box<F: Fold + ?Sized>(f: &mut F, expr: Expr) -> Box<Expr> {
+    Box::new(f.fold_expr(expr))
+}
+
+pub fn fold_module<F: Fold + ?Sized>(f: &mut F, module: Module) -> Module {
+    Module {
+        name: f.fold_ident(module.name),
+        exports: fold_entities(f, module.exports),
+        imports: module
+            .imports
+            .into_iter()
+            .map(|import| f.fold_import(import))
+            .collect(),
+        body: f.fold_program(module.body),
+    }
+}
+
+pub fn fold_import<F: Fold + ?Sized>(f: &mut F, import: Import) -> Import {
+    Import {
+        module: f.fold_ident(import.module),
+        alias: import.alias.map(|alias| f.fold_ident(alias)),
+        entities: fold_entities(f, import.entities),
+        ..import
+    }
+}
+
+pub fn fold_entity<F: Fold + ?Sized>(f: &mut F, entity: Entity) -> Entity {
+    match entity {
+        Entity::Var(name) => Entity::Var(f.fold_ident(name)),
+        Entity::Module(name) => Entity::Module(f.fold_ident(name)),
+        Entity::Type(name, members) => {
+            let name = f.fold_ident(name);
+            let members = match members {
+                Members::Listed(members) => Members::Listed(fold_idents(f, members)),
+                members => members,
+            };
+            Entity::Type(name, members)
+        }
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(f: &mut F, program: Program) -> Program {
+    program.into_iter().map(|stmt| f.fold_stmt(stmt)).collect()
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::LetStmt(name, expr) => Stmt::LetStmt(f.fold_ident(name), f.fold_expr(expr)),
+        Stmt::ReturnStmt(expr) => Stmt::ReturnStmt(f.fold_expr(expr)),
+        Stmt::ExprStmt(expr) => Stmt::ExprStmt(f.fold_expr(expr)),
+        Stmt::SigStmt(names, context, ty, span) => {
+            let names = fold_idents(f, names);
+            let context = fold_context(f, context);
+            Stmt::SigStmt(names, context, f.fold_type(ty), span)
+        }
+        Stmt::FunStmt(name, clauses) => {
+            let name = f.fold_ident(name);
+            let clauses = clauses.into_iter().map(|c| f.fold_clause(c)).collect();
+            Stmt::FunStmt(name, clauses)
+        }
+        Stmt::PatStmt(pat, rhs, wheres) => {
+            let pat = f.fold_pattern(pat);
+            Stmt::PatStmt(pat, f.fold_rhs(rhs), f.fold_program(wheres))
+        }
+        Stmt::DataStmt(data) => Stmt::DataStmt(f.fold_data_decl(data)),
+        Stmt::TypeStmt(name, params, ty) => {
+            let name = f.fold_ident(name);
+            Stmt::TypeStmt(name, fold_idents(f, params), f.fold_type(ty))
+        }
+        Stmt::ClassStmt(class) => Stmt::ClassStmt(f.fold_class_decl(class)),
+        Stmt::InstanceStmt(instance) => Stmt::InstanceStmt(f.fold_instance_decl(instance)),
+        Stmt::DerivingStmt(instance) => Stmt::DerivingStmt(f.fold_instance_decl(instance)),
+        Stmt::FixityStmt(fixity, names) => Stmt::FixityStmt(fixity, fold_idents(f, names)),
+        Stmt::DefaultStmt(types) => {
+            Stmt::DefaultStmt(types.into_iter().map(|ty| f.fold_type(ty)).collect())
+        }
+        Stmt::ErrorStmt(error) => Stmt::ErrorStmt(error),
+    }
+}
+
+pub fn fold_clause<F: Fold + ?Sized>(f: &mut F, clause: Clause) -> Clause {
+    Clause {
+        params: fold_patterns(f, clause.params),
+        rhs: f.fold_rhs(clause.rhs),
+        wheres: f.fold_program(clause.wheres),
+    }
+}
+
+pub fn fold_rhs<F: Fold + ?Sized>(f: &mut F, rhs: Rhs) -> Rhs {
+    match rhs {
+        Rhs::Plain(expr) => Rhs::Plain(f.fold_expr(expr)),
+        Rhs::Guarded(guards) => Rhs::Guarded(
+            guards
+                .into_iter()
+                .map(|(guard, expr)| (f.fold_expr(guard), f.fold_expr(expr)))
+                .collect(),
+        ),
+    }
+}
+
+pub fn fold_data_decl<F: Fold + ?Sized>(f: &mut F, data: DataDecl) -> DataDecl {
+    DataDecl {
+        name: f.fold_ident(data.name),
+        params: fold_idents(f, data.params),
+        constructors: data
+            .constructors
+            .into_iter()
+            .map(|con| f.fold_con_decl(con))
+            .collect(),
+        deriving: fold_idents(f, data.deriving),
+    }
+}
+
+pub fn fold_con_decl<F: Fold + ?Sized>(f: &mut F, con: ConDecl) -> ConDecl {
+    ConDecl {
+        name: f.fold_ident(con.name),
+        fields: con.fields.into_iter().map(|ty| f.fold_type(ty)).collect(),
+        labels: fold_idents(f, con.labels),
+    }
+}
+
+pub fn fold_class_decl<F: Fold + ?Sized>(f: &mut F, class: ClassDecl) -> ClassDecl {
+    ClassDecl {
+        context: fold_context(f, class.context),
+        name: f.fold_ident(class.name),
+        var: f.fold_ident(class.var),
+        body: f.fold_program(class.body),
+    }
+}
+
+pub fn fold_instance_decl<F: Fold + ?Sized>(f: &mut F, instance: InstanceDecl) -> InstanceDecl {
+    InstanceDecl {
+        context: fold_context(f, instance.context),
+        class: f.fold_ident(instance.class),
+        ty: f.fold_type(instance.ty),
+        body: f.fold_program(instance.body),
+        span: instance.span,
+    }
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(f: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::IdentExpr(name) => Expr::IdentExpr(f.fold_ident(name)),
+        Expr::HoleExpr(name) => Expr::HoleExpr(f.fold_ident(name)),
+        Expr::LitExpr(literal) => Expr::LitExpr(f.fold_literal(literal)),
+        Expr::PrefixExpr(prefix, operand) => Expr::PrefixExpr(prefix, fold_box(f, *operand)),
+        Expr::InfixExpr(op, left, right) => {
+            let left = fold_box(f, *left);
+            let op = f.fold_infix(op);
+            Expr::InfixExpr(op, left, fold_box(f, *right))
+        }
+        Expr::InfixChain(first, rest) => {
+            let first = fold_box(f, *first);
+            let rest = rest
+                .into_iter()
+                .map(|(op, operand)| (f.fold_infix(op), f.fold_expr(operand)))
+                .collect();
+            Expr::InfixChain(first, rest)
+        }
+        Expr::IfExpr {
+            cond,
+            consequence,
+            alternative,
+        } => Expr::IfExpr {
+            cond: fold_box(f, *cond),
+            consequence: f.fold_program(consequence),
+            alternative: alternative.map(|alternative| f.fold_program(alternative)),
+        },
+        Expr::FnExpr { params, body } => Expr::FnExpr {
+            params: fold_idents(f, params),
+            body: f.fold_program(body),
+        },
+        Expr::CallExpr {
+            function,
+            arguments,
+        } => Expr::CallExpr {
+            function: fold_box(f, *function),
+            arguments: fold_exprs(f, arguments),
+        },
+        Expr::ArrayExpr(exprs) => Expr::ArrayExpr(fold_exprs(f, exprs)),
+        Expr::TupleExpr(exprs) => Expr::TupleExpr(fold_exprs(f, exprs)),
+        Expr::HashExpr(pairs) => Expr::HashExpr(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (f.fold_literal(key), f.fold_expr(value)))
+                .collect(),
+        ),
+        Expr::IndexExpr { array, index } => Expr::IndexExpr {
+            array: fold_box(f, *array),
+            index: fold_box(f, *index),
+        },
+        Expr::LambdaExpr { params, body } => Expr::LambdaExpr {
+            params: fold_patterns(f, params),
+            body: fold_box(f, *body),
+        },
+        Expr::CaseExpr { scrutinee, alts } => Expr::CaseExpr {
+            scrutinee: fold_box(f, *scrutinee),
+            alts: alts.into_iter().map(|alt| f.fold_alt(alt)).collect(),
+        },
+        Expr::LambdaCaseExpr(alts) => {
+            Expr::LambdaCaseExpr(alts.into_iter().map(|alt| f.fold_alt(alt)).collect())
+        }
+        Expr::MultiIfExpr(guards) => Expr::MultiIfExpr(
+            guards
+                .into_iter()
+                .map(|(guard, expr)| (f.fold_expr(guard), f.fold_expr(expr)))
+                .collect(),
+        ),
+        Expr::TupleSectionExpr(components) => Expr::TupleSectionExpr(
+            components
+                .into_iter()
+                .map(|component| component.map(|expr| f.fold_expr(expr)))
+                .collect(),
+        ),
+        Expr::LetExpr { bindings, body } => Expr::LetExpr {
+            bindings: f.fold_program(bindings),
+            body: fold_box(f, *body),
+        },
+        Expr::LeftSectionExpr(operand, op) => {
+            let operand = fold_box(f, *operand);
+            Expr::LeftSectionExpr(operand, f.fold_infix(op))
+        }
+        Expr::RightSectionExpr(op, operand) => {
+            let op = f.fold_infix(op);
+            Expr::RightSectionExpr(op, fold_box(f, *operand))
+        }
+        Expr::ListCompExpr { expr, qualifiers } => Expr::ListCompExpr {
+            expr: fold_box(f, *expr),
+            qualifiers: qualifiers
+                .into_iter()
+                .map(|qualifier| f.fold_qualifier(qualifier))
+                .collect(),
+        },
+        Expr::DoExpr(stmts) => Expr::DoExpr(
+            stmts
+                .into_iter()
+                .map(|stmt| f.fold_qualifier(stmt))
+                .collect(),
+        ),
+        Expr::TypedExpr(expr, context, ty) => {
+            let expr = fold_box(f, *expr);
+            let context = fold_context(f, context);
+            Expr::TypedExpr(expr, context, f.fold_type(ty))
+        }
+        expr @ Expr::DictExpr(_) => expr,
+        Expr::Located(span, expr) => Expr::Located(span, fold_box(f, *expr)),
+    }
+}
+
+pub fn fold_alt<F: Fold + ?Sized>(f: &mut F, alt: Alt) -> Alt {
+    Alt {
+        pat: f.fold_pattern(alt.pat),
+        rhs: f.fold_rhs(alt.rhs),
+        wheres: f.fold_program(alt.wheres),
+    }
+}
+
+pub fn fold_qualifier<F: Fold + ?Sized>(f: &mut F, qualifier: Qualifier) -> Qualifier {
+    match qualifier {
+        Qualifier::Generator(pat, expr) => {
+            let pat = f.fold_pattern(pat);
+            Qualifier::Generator(pat, f.fold_expr(expr))
+        }
+        Qualifier::Guard(expr) => Qualifier::Guard(f.fold_expr(expr)),
+        Qualifier::LetQual(decls) => Qualifier::LetQual(f.fold_program(decls)),
+    }
+}
+
+pub fn fold_pattern<F: Fold + ?Sized>(f: &mut F, pat: Pattern) -> Pattern {
+    match pat {
+        Pattern::WildPat => Pattern::WildPat,
+        Pattern::VarPat(name) => Pattern::VarPat(f.fold_ident(name)),
+        Pattern::LitPat(literal) => Pattern::LitPat(f.fold_literal(literal)),
+        Pattern::ConPat(con, args) => {
+            let con = f.fold_ident(con);
+            Pattern::ConPat(con, fold_patterns(f, args))
+        }
+        Pattern::ListPat(pats) => Pattern::ListPat(fold_patterns(f, pats)),
+        Pattern::TuplePat(pats) => Pattern::TuplePat(fold_patterns(f, pats)),
+        Pattern::AsPat(name, pat) => {
+            let name = f.fold_ident(name);
+            Pattern::AsPat(name, Box::new(f.fold_pattern(*pat)))
+        }
+        Pattern::SigPat(pat, ty) => {
+            let pat = Box::new(f.fold_pattern(*pat));
+            Pattern::SigPat(pat, f.fold_type(ty))
+        }
+    }
+}
+
+pub fn fold_type<F: Fold + ?Sized>(f: &mut F, ty: Type) -> Type {
+    match ty {
+        Type::VarType(name) => Type::VarType(f.fold_ident(name)),
+        Type::ConType(name) => Type::ConType(f.fold_ident(name)),
+        Type::AppType(left, right) => {
+            let left = Box::new(f.fold_type(*left));
+            Type::AppType(left, Box::new(f.fold_type(*right)))
+        }
+        Type::FnType(left, right) => {
+            let left = Box::new(f.fold_type(*left));
+            Type::FnType(left, Box::new(f.fold_type(*right)))
+        }
+        Type::ListType(ty) => Type::ListType(Box::new(f.fold_type(*ty))),
+        Type::TupleType(types) => {
+            Type::TupleType(types.into_iter().map(|ty| f.fold_type(ty)).collect())
+        }
+        Type::ForallType(vars, ty) => {
+            let vars = fold_idents(f, vars);
+            Type::ForallType(vars, Box::new(f.fold_type(*ty)))
+        }
+    }
+}
+
+pub fn fold_pred<F: Fold + ?Sized>(f: &mut F, pred: Pred) -> Pred {
+    Pred {
+        class: f.fold_ident(pred.class),
+        ty: f.fold_type(pred.ty),
+    }
+}
+
+pub fn fold_infix<F: Fold + ?Sized>(f: &mut F, op: Infix) -> Infix {
+    match op {
+        Infix::Op(name) => Infix::Op(f.fold_ident(name)),
+        op => op,
+    }
+}
+
+/// Drops the spans the parser records on expressions and instances, for the
+/// passes and comparisons that have no use for them.
+pub struct Unlocate;
+
+impl Fold for Unlocate {
+    fn fold_instance_decl(&mut self, instance: InstanceDecl) -> InstanceDecl {
+        InstanceDecl {
+            span: None,
+            ..fold_instance_decl(self, instance)
+        }
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match fold_stmt(self, stmt) {
+            Stmt::SigStmt(names, context, ty, _) => Stmt::SigStmt(names, context, ty, None),
+            stmt => stmt,
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match fold_expr(self, expr) {
+            Expr::Located(_, expr) => *expr,
+            expr => expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::Tokens;
+    use crate::lexer::Lexer;
+    use crate::parser::fixity::Fixities;
+    use crate::parser::pretty::Printer;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> Program {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).unwrap();
+        Unlocate.fold_program(Fixities::new().resolve_program(program).unwrap())
+    }
+
+    struct Identity;
+
+    impl Fold for Identity {}
+
+    // Adds up integer literals, bottom up.
+    struct ConstantFolding;
+
+    impl Fold for ConstantFolding {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match fold_expr(self, expr) {
+                Expr::InfixExpr(Infix::Plus, left, right) => match (*left, *right) {
+                    (
+                        Expr::LitExpr(Literal::IntLiteral(a)),
+                        Expr::LitExpr(Literal::IntLiteral(b)),
+                    ) => Expr::LitExpr(Literal::IntLiteral(a + b)),
+                    (left, right) => Expr::InfixExpr(Infix::Plus, Box::new(left), Box::new(right)),
+                },
+                expr => expr,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let input = "module M (T(..), f) where\n\
+                     import qualified Data.List as L (sort)\n\
+                     data T = A | B Int deriving Eq\n\
+                     instance Show T where { show A = \"A\" }\n\
+                     f :: Num a => a -> [a]\n\
+                     f x | x > 0 = [y | y <- [x, 1 + 2 + x]] where z = (1 + 2, L.sort [3])\n\
+                     g = do { let { n = 1 + 1 }; print (n + 2) }";
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, mut module) = Parser::parse_module(Tokens::with_spans(&tokens, &spans)).unwrap();
+        module.body = Fixities::new().resolve_program(module.body).unwrap();
+        assert_eq!(Identity.fold_module(module.clone()), module);
+        let folded = ConstantFolding.fold_module(Unlocate.fold_module(module));
+        assert_eq!(
+            Printer::new().module(&folded),
+            "module M (T(..), f) where\n\
+             import qualified Data.List as L (sort)\n\
+             data T = A | B Int deriving (Eq)\n\
+             instance Show T where { show A = \"A\" }\n\
+             f :: Num a => a -> [a]\n\
+             f x | x > 0 = [y | y <- [x, 3 + x]] where { z = (3, L.sort ([3])) }\n\
+             g = do { let { n = 2 }; print (n + 2) }"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_program() {
+        let program = ConstantFolding.fold_program(parse("let a = 1 + 2 + 3;\nh (x + (4 + 5))"));
+        assert_eq!(Printer::new().program(&program), "a = 6\nh (x + 9)");
+    }
+}
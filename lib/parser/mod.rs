@@ -1,49 +1,141 @@
 use nom::*;
 
 pub mod ast;
+pub mod depend;
+pub mod desugar;
+pub mod error;
+pub mod fixity;
+pub mod fold;
+pub mod pretty;
+pub mod rename;
+pub mod visit;
 use crate::lexer::token::*;
 use crate::parser::ast::*;
+use crate::parser::error::*;
 use nom::branch::*;
 use nom::bytes::complete::take;
-use nom::combinator::{map, opt, verify};
-use nom::error::{Error, ErrorKind};
-use nom::multi::many0;
+use nom::combinator::{cut, map, not, opt, value, verify};
+use nom::multi::{many0, many1, separated_list0, separated_list1};
 use nom::sequence::*;
+use nom::error::ParseError as _;
 use nom::Err;
 use std::result::Result::*;
 
+type IResult<I, O> = nom::IResult<I, O, ParseError>;
+
 macro_rules! tag_token (
     ($func_name:ident, $tag: expr) => (
         fn $func_name(tokens: Tokens) -> IResult<Tokens, Tokens> {
-            verify(take(1usize), |t: &Tokens| t.tok[0] == $tag)(tokens)
+            match tokens.tok.first() {
+                Some(t) if *t == $tag => Ok(take(1usize)(tokens)?),
+                _ => Err(Err::Error(ParseError::expected(tokens, Expected::Token($tag)))),
+            }
+        }
+    )
+  );
+
+// `qualified`, `as` and `hiding` are only keywords in import declarations
+macro_rules! special_ident (
+    ($func_name:ident, $name: literal) => (
+        fn $func_name(tokens: Tokens) -> IResult<Tokens, Ident> {
+            match parse_ident(tokens) {
+                Ok((rest, ident)) if ident.0 == $name => Ok((rest, ident)),
+                _ => {
+                    let expected = Expected::Token(Token::Ident($name.to_owned()));
+                    Err(Err::Error(ParseError::expected(tokens, expected)))
+                }
+            }
         }
     )
   );
+// The error of finding something other than `what` at the start of `input`
+fn expected<'a, O>(input: Tokens<'a>, what: &'static str) -> IResult<Tokens<'a>, O> {
+    Err(Err::Error(ParseError::expected(input, Expected::Kind(what))))
+}
+
+// The next token, or the error of finding the end of the input instead of `what`
+fn take_one<'a>(input: Tokens<'a>, what: &'static str) -> IResult<Tokens<'a>, Tokens<'a>> {
+    take(1usize)(input).or_else(|_: Err<ParseError>| expected(input, what))
+}
+
+// Syntax of an extension that is not enabled fails for good, with a hint
+fn extension(input: Tokens, extension: Extension) -> IResult<Tokens, ()> {
+    match input.extensions.contains(extension) {
+        true => Ok((input, ())),
+        false => Err(Err::Failure(ParseError::extension(input, extension))),
+    }
+}
+
+// The `LANGUAGE` pragmas at the start of the input, enabling their extensions
+// for the rest of it
+fn parse_pragmas(input: Tokens) -> IResult<Tokens, Extensions> {
+    let mut extensions = input.extensions;
+    let mut rest = input;
+    while let Some(Token::LanguagePragma(names)) = rest.tok.first() {
+        for name in names {
+            match Extension::from_name(name) {
+                Some(extension) => extensions.insert(extension),
+                None => return Err(Err::Failure(ParseError::unsupported(rest, name))),
+            }
+        }
+        rest = rest.slice(1..);
+    }
+    Ok((rest.with_extensions(extensions), extensions))
+}
+
 fn parse_literal(input: Tokens) -> IResult<Tokens, Literal> {
-    let (i1, t1) = take(1usize)(input)?;
+    let (i1, t1) = take_one(input, "a literal")?;
     if t1.tok.is_empty() {
-        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+        expected(input, "a literal")
     } else {
         match t1.tok[0].clone() {
             Token::IntLiteral(name) => Ok((i1, Literal::IntLiteral(name))),
+            Token::FloatLiteral(f) => Ok((i1, Literal::FloatLiteral(f))),
             Token::StringLiteral(s) => Ok((i1, Literal::StringLiteral(s))),
+            Token::CharLiteral(c) => Ok((i1, Literal::CharLiteral(c))),
             Token::BoolLiteral(b) => Ok((i1, Literal::BoolLiteral(b))),
-            _ => Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+            _ => expected(input, "a literal"),
         }
     }
 }
 fn parse_ident(input: Tokens) -> IResult<Tokens, Ident> {
-    let (i1, t1) = take(1usize)(input)?;
+    let (i1, t1) = take_one(input, "an identifier")?;
     if t1.tok.is_empty() {
-        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+        expected(input, "an identifier")
     } else {
         match t1.tok[0].clone() {
             Token::Ident(name) => Ok((i1, Ident(name))),
-            _ => Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+            // the `Monad` method, where a statement can't start
+            Token::Return => Ok((i1, Ident("return".to_owned()))),
+            _ => expected(input, "an identifier"),
+        }
+    }
+}
+fn parse_type_ident(input: Tokens) -> IResult<Tokens, Ident> {
+    let (i1, t1) = take_one(input, "a constructor")?;
+    if t1.tok.is_empty() {
+        expected(input, "a constructor")
+    } else {
+        match t1.tok[0].clone() {
+            Token::TypeIdent(name) => Ok((i1, Ident(name))),
+            _ => expected(input, "a constructor"),
+        }
+    }
+}
+// Operator symbols that can be used as variables, e.g. `(==)` in a signature
+fn parse_operator(input: Tokens) -> IResult<Tokens, Ident> {
+    let (i1, t1) = take_one(input, "an operator")?;
+    if t1.tok.is_empty() {
+        expected(input, "an operator")
+    } else {
+        match infix_op(&t1.tok[0]) {
+            (_, Some(op)) => Ok((i1, Ident(op.name().to_owned()))),
+            _ => expected(input, "an operator"),
         }
     }
 }
 tag_token!(let_tag, Token::Let);
+tag_token!(in_tag, Token::In);
 tag_token!(assign_tag, Token::Assign);
 tag_token!(semicolon_tag, Token::SemiColon);
 tag_token!(return_tag, Token::Return);
@@ -54,43 +146,283 @@ tag_token!(rparen_tag, Token::RParen);
 tag_token!(lbracket_tag, Token::LBracket);
 tag_token!(rbracket_tag, Token::RBracket);
 tag_token!(comma_tag, Token::Comma);
+tag_token!(range_tag, Token::Range);
+tag_token!(left_arrow_tag, Token::LeftArrow);
 tag_token!(colon_tag, Token::Colon);
-tag_token!(plus_tag, Token::Plus);
+tag_token!(double_colon_tag, Token::DoubleColon);
 tag_token!(minus_tag, Token::Minus);
-tag_token!(not_tag, Token::Not);
+tag_token!(arrow_tag, Token::Arrow);
+tag_token!(fat_arrow_tag, Token::FatArrow);
+tag_token!(pipe_tag, Token::Pipe);
+tag_token!(backslash_tag, Token::Backslash);
+tag_token!(at_tag, Token::At);
+tag_token!(backquote_tag, Token::Backquote);
 tag_token!(if_tag, Token::If);
+tag_token!(then_tag, Token::Then);
 tag_token!(else_tag, Token::Else);
+tag_token!(case_tag, Token::Case);
+tag_token!(of_tag, Token::Of);
+tag_token!(do_tag, Token::Do);
+tag_token!(module_tag, Token::Module);
+tag_token!(import_tag, Token::Import);
+special_ident!(qualified_tag, "qualified");
+special_ident!(as_tag, "as");
+special_ident!(hiding_tag, "hiding");
+tag_token!(where_tag, Token::Where);
+tag_token!(data_tag, Token::Data);
+tag_token!(newtype_tag, Token::Newtype);
+tag_token!(type_tag, Token::Type);
+tag_token!(class_tag, Token::Class);
+tag_token!(instance_tag, Token::Instance);
+tag_token!(deriving_tag, Token::Deriving);
+tag_token!(default_tag, Token::Default);
+tag_token!(infix_tag, Token::Infix);
+tag_token!(infixl_tag, Token::Infixl);
+tag_token!(infixr_tag, Token::Infixr);
 tag_token!(function_tag, Token::Function);
 tag_token!(eof_tag, Token::EOF);
 
-fn infix_op(t: &Token) -> (Precedence, Option<Infix>) {
-    match *t {
-        Token::Equal => (Precedence::PEquals, Some(Infix::Equal)),
-        Token::NotEqual => (Precedence::PEquals, Some(Infix::NotEqual)),
-        Token::LessThanEqual => (Precedence::PLessGreater, Some(Infix::LessThanEqual)),
-        Token::GreaterThanEqual => (Precedence::PLessGreater, Some(Infix::GreaterThanEqual)),
-        Token::LessThan => (Precedence::PLessGreater, Some(Infix::LessThan)),
-        Token::GreaterThan => (Precedence::PLessGreater, Some(Infix::GreaterThan)),
-        Token::Plus => (Precedence::PSum, Some(Infix::Plus)),
-        Token::Minus => (Precedence::PSum, Some(Infix::Minus)),
-        Token::Multiply => (Precedence::PProduct, Some(Infix::Multiply)),
-        Token::Divide => (Precedence::PProduct, Some(Infix::Divide)),
-        Token::LParen => (Precedence::PCall, None),
-        Token::LBracket => (Precedence::PIndex, None),
-        _ => (Precedence::PLowest, None),
+// A binary operator: a symbol, or a name in backquotes like `div`
+fn parse_infix_op(input: Tokens) -> IResult<Tokens, Infix> {
+    alt((
+        map(
+            delimited(backquote_tag, alt((parse_ident, parse_type_ident)), backquote_tag),
+            Infix::Op,
+        ),
+        parse_symbol_op,
+    ))(input)
+}
+
+fn parse_symbol_op(input: Tokens) -> IResult<Tokens, Infix> {
+    let (i1, t1) = take_one(input, "an operator")?;
+    match infix_op(&t1.tok[0]) {
+        (_, Some(op)) => Ok((i1, op)),
+        _ => expected(input, "an operator"),
     }
 }
 
+fn infix_op(t: &Token) -> (Precedence, Option<Infix>) {
+    let op = match *t {
+        Token::Equal => Infix::Equal,
+        Token::NotEqual => Infix::NotEqual,
+        Token::LessThanEqual => Infix::LessThanEqual,
+        Token::GreaterThanEqual => Infix::GreaterThanEqual,
+        Token::LessThan => Infix::LessThan,
+        Token::GreaterThan => Infix::GreaterThan,
+        Token::Plus => Infix::Plus,
+        Token::Minus => Infix::Minus,
+        Token::Multiply => Infix::Multiply,
+        Token::Divide => Infix::Divide,
+        Token::Colon => Infix::Op(Ident(":".to_owned())),
+        Token::Operator(ref op) => Infix::Op(Ident(op.clone())),
+        Token::LParen
+        | Token::Ident(_)
+        | Token::TypeIdent(_)
+        | Token::IntLiteral(_)
+        | Token::FloatLiteral(_)
+        | Token::StringLiteral(_)
+        | Token::CharLiteral(_)
+        | Token::BoolLiteral(_) => return (Precedence::PCall, None),
+        Token::LBracket => return (Precedence::PIndex, None),
+        _ => return (Precedence::PLowest, None),
+    };
+    (Precedence::POperator, Some(op))
+}
+
 fn parse_program(input: Tokens) -> IResult<Tokens, Program> {
-    terminated(many0(parse_stmt), eof_tag)(input)
+    preceded(parse_pragmas, terminated(parse_top_stmts, eof_tag))(input)
+}
+
+// Modules
+
+fn parse_module(input: Tokens) -> IResult<Tokens, Module> {
+    let (input, _) = parse_pragmas(input)?;
+    map(
+        terminated(
+            tuple((
+                opt(parse_module_header),
+                |i| parse_implicit_block(i, parse_import),
+                parse_top_stmts,
+            )),
+            eof_tag,
+        ),
+        |(header, imports, body)| {
+            let (name, exports) = header.unwrap_or((Ident("Main".to_owned()), None));
+            Module {
+                name,
+                exports,
+                imports,
+                body,
+            }
+        },
+    )(input)
 }
 
+fn parse_module_header(input: Tokens) -> IResult<Tokens, (Ident, Option<Vec<Entity>>)> {
+    delimited(
+        module_tag,
+        pair(parse_type_ident, opt(parse_entities)),
+        where_tag,
+    )(input)
+}
+
+fn parse_import(input: Tokens) -> IResult<Tokens, Import> {
+    map(
+        tuple((
+            import_tag,
+            opt(qualified_tag),
+            parse_type_ident,
+            opt(preceded(as_tag, parse_type_ident)),
+            opt(pair(opt(hiding_tag), parse_entities)),
+        )),
+        |(_, qualified, module, alias, entities)| Import {
+            module,
+            qualified: qualified.is_some(),
+            alias,
+            hiding: matches!(entities, Some((Some(_), _))),
+            entities: entities.map(|(_, entities)| entities),
+        },
+    )(input)
+}
+
+// Nothing else starts with `(` where an entity list can, so an error inside
+// one is not left for `opt` to discard
+fn parse_entities(input: Tokens) -> IResult<Tokens, Vec<Entity>> {
+    preceded(
+        lparen_tag,
+        cut(terminated(
+            terminated(separated_list0(comma_tag, parse_entity), opt(comma_tag)),
+            rparen_tag,
+        )),
+    )(input)
+}
+
+fn parse_entity(input: Tokens) -> IResult<Tokens, Entity> {
+    alt((
+        map(preceded(module_tag, parse_type_ident), Entity::Module),
+        map(pair(parse_type_ident, parse_members), |(name, members)| {
+            Entity::Type(name, members)
+        }),
+        map(parse_var, Entity::Var),
+    ))(input)
+}
+
+fn parse_members(input: Tokens) -> IResult<Tokens, Members> {
+    map(
+        opt(delimited(
+            lparen_tag,
+            alt((
+                value(None, range_tag),
+                map(
+                    separated_list0(comma_tag, alt((parse_var, parse_type_ident))),
+                    Some,
+                ),
+            )),
+            rparen_tag,
+        )),
+        |members| match members {
+            None => Members::Abstract,
+            Some(None) => Members::All,
+            Some(Some(names)) => Members::Listed(names),
+        },
+    )(input)
+}
+
+fn parse_top_stmts(input: Tokens) -> IResult<Tokens, Program> {
+    let (rest, groups) = parse_implicit_block(input, parse_stmt_group)?;
+    Ok((rest, merge_clauses(groups.into_iter().flatten().collect())))
+}
+
+fn parse_stmt_group(input: Tokens) -> IResult<Tokens, Vec<Stmt>> {
+    many1(parse_stmt)(input)
+}
+
+// `e :: type`, the signature covering the whole infix expression before it
 fn parse_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    parse_pratt_expr(input, Precedence::PLowest)
+    let (rest, (expr, sig)) = pair(
+        parse_infix_exp,
+        opt(preceded(double_colon_tag, parse_qual_type)),
+    )(input)?;
+    Ok(match sig {
+        Some((context, ty)) => {
+            let typed = Expr::TypedExpr(Box::new(expr), context, ty);
+            (rest, located(&input, &rest, typed))
+        }
+        None => (rest, expr),
+    })
+}
+
+// A negation is an operator of the chain it starts, even one without other
+// operators, so that `(- x) ^ 2` keeps its parentheses.
+fn parse_infix_exp(input: Tokens) -> IResult<Tokens, Expr> {
+    match parse_negation(input) {
+        Ok((i1, negation)) => {
+            let last_end = last_span_end(&input, &i1);
+            match go_parse_pratt_expr(i1, Precedence::PLowest, negation, last_end)? {
+                (rest, chain @ Expr::InfixChain(..)) => Ok((rest, chain)),
+                (rest, negation) => Ok((rest, Expr::InfixChain(Box::new(negation), vec![]))),
+            }
+        }
+        Err(Err::Error(_)) => parse_pratt_expr(input, Precedence::PLowest),
+        Err(err) => Err(err),
+    }
 }
 
 fn parse_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
-    alt((parse_let_stmt, parse_return_stmt, parse_expr_stmt))(input)
+    alt((
+        terminated(parse_decl_stmt, opt(semicolon_tag)),
+        parse_let_stmt,
+        parse_return_stmt,
+        parse_expr_stmt,
+    ))(input)
+}
+
+fn parse_decl_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    alt((
+        parse_data_stmt,
+        parse_newtype_stmt,
+        parse_type_stmt,
+        parse_class_stmt,
+        parse_instance_stmt,
+        parse_deriving_stmt,
+        parse_default_stmt,
+        parse_decl,
+    ))(input)
+}
+
+// Declarations allowed in `let`, `where`, class and instance blocks
+fn parse_decl(input: Tokens) -> IResult<Tokens, Stmt> {
+    alt((parse_sig_stmt, parse_fixity_stmt, parse_fun_stmt, parse_pat_stmt))(input)
+}
+
+fn parse_fixity_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((
+            alt((
+                value(Assoc::Left, infixl_tag),
+                value(Assoc::Right, infixr_tag),
+                value(Assoc::Non, infix_tag),
+            )),
+            opt(parse_fixity_precedence),
+            separated_list1(comma_tag, parse_infix_op),
+        )),
+        |(assoc, precedence, ops)| {
+            let fixity = Fixity {
+                assoc,
+                precedence: precedence.unwrap_or(9),
+            };
+            let names = ops.iter().map(|op| Ident(op.name().to_owned())).collect();
+            Stmt::FixityStmt(fixity, names)
+        },
+    )(input)
+}
+
+fn parse_fixity_precedence(input: Tokens) -> IResult<Tokens, u8> {
+    let (i1, t1) = take_one(input, "a precedence from 0 to 9")?;
+    match t1.tok[0] {
+        Token::IntLiteral(n @ 0..=9) => Ok((i1, n as u8)),
+        _ => expected(input, "a precedence from 0 to 9"),
+    }
 }
 
 fn parse_let_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
@@ -100,9 +432,10 @@ fn parse_let_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
             parse_ident,
             assign_tag,
             parse_expr,
+            not(in_tag),
             opt(semicolon_tag),
         )),
-        |(_, ident, _, expr, _)| Stmt::LetStmt(ident, expr),
+        |(_, ident, _, expr, _, _)| Stmt::LetStmt(ident, expr),
     )(input)
 }
 
@@ -123,28 +456,700 @@ fn parse_block_stmt(input: Tokens) -> IResult<Tokens, Program> {
     delimited(lbrace_tag, many0(parse_stmt), rbrace_tag)(input)
 }
 
+// Layout
+
+/// Parses the items of a `where`, `let` or `of` block, either between
+/// explicit braces and semicolons or laid out by indentation.
+fn parse_block<O>(
+    input: Tokens,
+    item: fn(Tokens) -> IResult<Tokens, O>,
+) -> IResult<Tokens, Vec<O>> {
+    if lbrace_tag(input).is_ok() {
+        parse_explicit_block(input, item)
+    } else {
+        parse_implicit_block(input, item)
+    }
+}
+
+fn parse_explicit_block<O>(
+    input: Tokens,
+    item: fn(Tokens) -> IResult<Tokens, O>,
+) -> IResult<Tokens, Vec<O>> {
+    let (mut rest, _) = lbrace_tag(input)?;
+    let mut items = vec![];
+    loop {
+        rest = many0(semicolon_tag)(rest)?.0;
+        if let Ok((after, _)) = rbrace_tag(rest) {
+            return Ok((after, items));
+        }
+        let (after, o) = item(rest)?;
+        items.push(o);
+        rest = after;
+        if semicolon_tag(rest).is_err() {
+            let (after, _) = rbrace_tag(rest)?;
+            return Ok((after, items));
+        }
+    }
+}
+
+/// The offside rule: every item starts in the column of the first one, and
+/// lines indented further continue the current item. A token that the item
+/// parser cannot consume closes the block, like the `parse-error(t)` rule of
+/// the Haskell report. Without source spans, items are separated by `;` only.
+fn parse_implicit_block<O>(
+    input: Tokens,
+    item: fn(Tokens) -> IResult<Tokens, O>,
+) -> IResult<Tokens, Vec<O>> {
+    let mut items = vec![];
+    let mut rest = input;
+    let column = match input.span_at(0) {
+        Some(span) => span.column,
+        None => loop {
+            match item(rest) {
+                Ok((after, o)) => {
+                    items.push(o);
+                    rest = after;
+                }
+                Err(Err::Error(_)) => return Ok((rest, items)),
+                Err(e) => return Err(e),
+            }
+            match semicolon_tag(rest) {
+                Ok((after, _)) => rest = after,
+                Err(_) => return Ok((rest, items)),
+            }
+        },
+    };
+    loop {
+        if rest.tok.is_empty() || rest.tok[0] == Token::EOF {
+            return Ok((rest, items));
+        }
+        let (end, next_item) = layout_region(&rest, column);
+        match item(rest.slice(0..end)) {
+            Ok((left, o)) => {
+                items.push(o);
+                let consumed = end - left.input_len();
+                rest = rest.slice(consumed..);
+                if consumed < end {
+                    return Ok((rest, items));
+                }
+            }
+            Err(Err::Error(_)) => return Ok((rest, items)),
+            Err(e) => return Err(e),
+        }
+        match semicolon_tag(rest) {
+            Ok((after, _)) => rest = after,
+            Err(_) if next_item => {}
+            Err(_) => return Ok((rest, items)),
+        }
+    }
+}
+
+/// Finds where the layout item starting at the first token ends. Returns the
+/// number of tokens in the item and whether another item of the same block
+/// starts right after it. A `;` ends the item unless it is in a bracket or
+/// in a block the item opens, as in `case m of Just x -> x; Nothing -> 0`.
+fn layout_region(input: &Tokens, column: usize) -> (usize, bool) {
+    // the brackets and implicit blocks open in the item, innermost last:
+    // `None` for a bracket and the column of its items for a block
+    let mut open: Vec<Option<usize>> = vec![];
+    for (i, token) in input.tok.iter().enumerate() {
+        if i > 0 {
+            let (previous, span) = (input.span[i - 1], input.span[i]);
+            // a line left of the items of a block closes it
+            while let Some(&Some(block)) = open.last() {
+                match span.line > previous.line && span.column < block {
+                    true => open.pop(),
+                    false => break,
+                };
+            }
+            if open.is_empty() {
+                if *token == Token::SemiColon {
+                    return (i, false);
+                }
+                let continues =
+                    matches!(token, Token::Then | Token::Else) && span.column == column;
+                if span.line > previous.line && span.column <= column && !continues {
+                    return (i, span.column == column);
+                }
+            }
+        }
+        match token {
+            Token::LParen | Token::LBracket | Token::LBrace => open.push(None),
+            // a bracket closes the blocks opened in it
+            Token::RParen | Token::RBracket | Token::RBrace => {
+                while let Some(Some(_)) = open.last() {
+                    open.pop();
+                }
+                if open.pop().is_none() {
+                    return (i, false);
+                }
+            }
+            Token::In if matches!(open.last(), Some(Some(_))) => {
+                open.pop();
+            }
+            Token::EOF => return (i, false),
+            _ => {}
+        }
+        let opens = match token {
+            Token::Of | Token::Where | Token::Let | Token::Do => true,
+            Token::Case => i > 0 && input.tok[i - 1] == Token::Backslash,
+            _ => false,
+        };
+        match input.tok.get(i + 1) {
+            Some(Token::LBrace | Token::EOF) | None => {}
+            Some(_) if opens => open.push(input.span_at(i + 1).map(|span| span.column)),
+            Some(_) => {}
+        }
+    }
+    (input.tok.len(), false)
+}
+
+// Consecutive equations of the same function make up a single declaration
+fn merge_clauses(stmts: Program) -> Program {
+    let mut merged: Program = vec![];
+    for stmt in stmts {
+        match (merged.last_mut(), stmt) {
+            (Some(Stmt::FunStmt(name, clauses)), Stmt::FunStmt(next, more)) if *name == next => {
+                clauses.extend(more)
+            }
+            (_, stmt) => merged.push(stmt),
+        }
+    }
+    merged
+}
+
+// Declarations
+
+fn parse_var(input: Tokens) -> IResult<Tokens, Ident> {
+    alt((
+        parse_ident,
+        delimited(lparen_tag, parse_operator, rparen_tag),
+    ))(input)
+}
+
+fn parse_varop(input: Tokens) -> IResult<Tokens, Ident> {
+    alt((
+        verify(parse_operator, |op: &Ident| !op.is_constructor()),
+        delimited(backquote_tag, parse_ident, backquote_tag),
+    ))(input)
+}
+
+fn parse_sig_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    let (i1, (names, _)) = pair(separated_list1(comma_tag, parse_var), double_colon_tag)(input)?;
+    let (i2, (context, ty)) = parse_qual_type(i1)?;
+    let span = span_between(&i1, &i2);
+    Ok((i2, Stmt::SigStmt(names, context, ty, span)))
+}
+
+fn parse_fun_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((parse_fun_lhs, parse_rhs(assign_tag), parse_wheres)),
+        |((name, params), rhs, wheres)| match rhs {
+            Rhs::Plain(expr) if params.is_empty() && wheres.is_empty() => {
+                Stmt::LetStmt(name, expr)
+            }
+            rhs => Stmt::FunStmt(
+                name,
+                vec![Clause {
+                    params,
+                    rhs,
+                    wheres,
+                }],
+            ),
+        },
+    )(input)
+}
+
+// `(a, b) = e`, where a variable alone would have made a `parse_fun_stmt`
+fn parse_pat_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((
+            verify(parse_pattern, |pat| !matches!(pat, Pattern::VarPat(_))),
+            parse_rhs(assign_tag),
+            parse_wheres,
+        )),
+        |(pat, rhs, wheres)| Stmt::PatStmt(pat, rhs, wheres),
+    )(input)
+}
+
+fn parse_fun_lhs(input: Tokens) -> IResult<Tokens, (Ident, Vec<Pattern>)> {
+    alt((
+        map(
+            tuple((parse_pattern_app, parse_varop, parse_pattern_app)),
+            |(left, op, right)| (op, vec![left, right]),
+        ),
+        pair(parse_var, many0(parse_apat)),
+    ))(input)
+}
+
+fn parse_rhs(
+    separator: fn(Tokens) -> IResult<Tokens, Tokens>,
+) -> impl FnMut(Tokens) -> IResult<Tokens, Rhs> {
+    move |input| {
+        alt((
+            map(preceded(separator, parse_expr), Rhs::Plain),
+            map(
+                many1(tuple((pipe_tag, parse_expr, separator, parse_expr))),
+                |guards| {
+                    Rhs::Guarded(
+                        guards
+                            .into_iter()
+                            .map(|(_, guard, _, expr)| (guard, expr))
+                            .collect(),
+                    )
+                },
+            ),
+        ))(input)
+    }
+}
+
+fn parse_wheres(input: Tokens) -> IResult<Tokens, Program> {
+    map(
+        opt(preceded(where_tag, |i| parse_block(i, parse_decl))),
+        |decls| merge_clauses(decls.unwrap_or_default()),
+    )(input)
+}
+
+fn parse_data_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((
+            data_tag,
+            parse_type_ident,
+            many0(parse_ident),
+            opt(preceded(
+                assign_tag,
+                separated_list1(pipe_tag, parse_con_decl),
+            )),
+            opt(preceded(deriving_tag, parse_class_list)),
+        )),
+        |(_, name, params, constructors, deriving)| {
+            Stmt::DataStmt(DataDecl {
+                name,
+                params,
+                constructors: constructors.unwrap_or_default(),
+                deriving: deriving.unwrap_or_default(),
+            })
+        },
+    )(input)
+}
+
+// `newtype N a = C t` is the data type with the one constructor, which the
+// interpreter doesn't tell apart
+fn parse_newtype_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    let (i1, (_, name, params, _)) =
+        tuple((newtype_tag, parse_type_ident, many0(parse_ident), assign_tag))(input)?;
+    let (i2, constructor) = parse_con_decl(i1)?;
+    if constructor.fields.len() != 1 {
+        return expected(i1, "a constructor with one field");
+    }
+    let (i3, deriving) = opt(preceded(deriving_tag, parse_class_list))(i2)?;
+    let decl = DataDecl {
+        name,
+        params,
+        constructors: vec![constructor],
+        deriving: deriving.unwrap_or_default(),
+    };
+    Ok((i3, Stmt::DataStmt(decl)))
+}
+
+fn parse_type_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((type_tag, parse_type_ident, many0(parse_ident), assign_tag, parse_type)),
+        |(_, name, params, _, ty)| Stmt::TypeStmt(name, params, ty),
+    )(input)
+}
+
+fn parse_con_decl(input: Tokens) -> IResult<Tokens, ConDecl> {
+    alt((
+        map(
+            pair(
+                parse_type_ident,
+                delimited(
+                    lbrace_tag,
+                    separated_list1(comma_tag, parse_field_decl),
+                    rbrace_tag,
+                ),
+            ),
+            |(name, groups)| {
+                let (mut labels, mut fields) = (vec![], vec![]);
+                for (names, ty) in groups {
+                    fields.extend(names.iter().map(|_| ty.clone()));
+                    labels.extend(names);
+                }
+                ConDecl {
+                    name,
+                    fields,
+                    labels,
+                }
+            },
+        ),
+        map(pair(parse_type_ident, many0(parse_atype)), |(name, fields)| {
+            ConDecl {
+                name,
+                fields,
+                labels: vec![],
+            }
+        }),
+    ))(input)
+}
+
+// `name, other :: Type` inside a record declaration
+fn parse_field_decl(input: Tokens) -> IResult<Tokens, (Vec<Ident>, Type)> {
+    separated_pair(
+        separated_list1(comma_tag, parse_ident),
+        double_colon_tag,
+        parse_type,
+    )(input)
+}
+
+fn parse_class_list(input: Tokens) -> IResult<Tokens, Vec<Ident>> {
+    alt((
+        delimited(
+            lparen_tag,
+            separated_list0(comma_tag, parse_type_ident),
+            rparen_tag,
+        ),
+        map(parse_type_ident, |class| vec![class]),
+    ))(input)
+}
+
+// Standalone deriving: `deriving instance Show a => Show (Tree a)`
+fn parse_deriving_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    let (i1, (_, _, context)) = tuple((deriving_tag, instance_tag, parse_context))(input)?;
+    let (rest, (class, ty)) = pair(parse_type_ident, parse_atype)(i1)?;
+    Ok((
+        rest,
+        Stmt::DerivingStmt(InstanceDecl {
+            context,
+            class,
+            ty,
+            body: vec![],
+            span: span_between(&i1, &rest),
+        }),
+    ))
+}
+
+// `default (Integer, Double)`; `default ()` turns defaulting off
+fn parse_default_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        preceded(
+            default_tag,
+            delimited(
+                lparen_tag,
+                separated_list0(comma_tag, parse_type),
+                rparen_tag,
+            ),
+        ),
+        Stmt::DefaultStmt,
+    )(input)
+}
+
+fn parse_class_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    map(
+        tuple((
+            class_tag,
+            parse_context,
+            parse_type_ident,
+            parse_ident,
+            parse_wheres,
+        )),
+        |(_, context, name, var, body)| {
+            Stmt::ClassStmt(ClassDecl {
+                context,
+                name,
+                var,
+                body,
+            })
+        },
+    )(input)
+}
+
+fn parse_instance_stmt(input: Tokens) -> IResult<Tokens, Stmt> {
+    let (i1, (_, context)) = pair(instance_tag, parse_context)(input)?;
+    let (i2, (class, ty)) = pair(parse_type_ident, parse_atype)(i1)?;
+    let (rest, body) = parse_wheres(i2)?;
+    Ok((
+        rest,
+        Stmt::InstanceStmt(InstanceDecl {
+            context,
+            class,
+            ty,
+            body,
+            span: span_between(&i1, &i2),
+        }),
+    ))
+}
+
+// Types
+
+fn parse_context(input: Tokens) -> IResult<Tokens, Context> {
+    map(
+        opt(terminated(
+            alt((
+                delimited(lparen_tag, separated_list0(comma_tag, parse_pred), rparen_tag),
+                map(parse_pred, |pred| vec![pred]),
+            )),
+            fat_arrow_tag,
+        )),
+        Option::unwrap_or_default,
+    )(input)
+}
+
+fn parse_pred(input: Tokens) -> IResult<Tokens, Pred> {
+    map(pair(parse_type_ident, parse_atype), |(class, ty)| Pred {
+        class,
+        ty,
+    })(input)
+}
+
+fn parse_qual_type(input: Tokens) -> IResult<Tokens, (Context, Type)> {
+    map(
+        tuple((opt(parse_forall), parse_context, parse_type)),
+        |(vars, context, ty)| match vars {
+            Some(vars) => (context, Type::ForallType(vars, Box::new(ty))),
+            None => (context, ty),
+        },
+    )(input)
+}
+
+// `forall a b.`, the type variables of a signature made explicit
+fn parse_forall(input: Tokens) -> IResult<Tokens, Vec<Ident>> {
+    let (rest, _) = verify(parse_ident, |Ident(name)| name == "forall")(input)?;
+    extension(input, Extension::ScopedTypeVariables)?;
+    terminated(
+        many1(parse_ident),
+        verify(parse_operator, |Ident(op)| op == "."),
+    )(rest)
+}
+
+fn parse_type(input: Tokens) -> IResult<Tokens, Type> {
+    map(
+        pair(parse_btype, opt(preceded(arrow_tag, parse_type))),
+        |(ty, result)| match result {
+            Some(result) => Type::FnType(Box::new(ty), Box::new(result)),
+            None => ty,
+        },
+    )(input)
+}
+
+fn parse_btype(input: Tokens) -> IResult<Tokens, Type> {
+    map(many1(parse_atype), |types| {
+        types
+            .into_iter()
+            .reduce(|f, arg| Type::AppType(Box::new(f), Box::new(arg)))
+            .unwrap()
+    })(input)
+}
+
+fn parse_atype(input: Tokens) -> IResult<Tokens, Type> {
+    alt((
+        map(parse_ident, Type::VarType),
+        map(parse_type_ident, Type::ConType),
+        value(Type::ConType(Ident("[]".to_owned())), pair(lbracket_tag, rbracket_tag)),
+        map(delimited(lbracket_tag, parse_type, rbracket_tag), |ty| {
+            Type::ListType(Box::new(ty))
+        }),
+        map(parse_tuple_con, Type::ConType),
+        map(
+            delimited(
+                lparen_tag,
+                separated_list0(comma_tag, parse_type),
+                rparen_tag,
+            ),
+            |mut types| match types.len() {
+                1 => types.remove(0),
+                _ => Type::TupleType(types),
+            },
+        ),
+    ))(input)
+}
+
+// Patterns
+
+fn parse_pattern(input: Tokens) -> IResult<Tokens, Pattern> {
+    map(
+        pair(parse_pattern_app, opt(preceded(colon_tag, parse_pattern))),
+        |(head, tail)| match tail {
+            Some(tail) => Pattern::ConPat(Ident(":".to_owned()), vec![head, tail]),
+            None => head,
+        },
+    )(input)
+}
+
+fn parse_pattern_app(input: Tokens) -> IResult<Tokens, Pattern> {
+    alt((
+        map(pair(parse_type_ident, many1(parse_apat)), |(con, args)| {
+            Pattern::ConPat(con, args)
+        }),
+        map(preceded(minus_tag, parse_literal), |literal| match literal {
+            Literal::IntLiteral(i) => Pattern::LitPat(Literal::IntLiteral(-i)),
+            Literal::FloatLiteral(f) => Pattern::LitPat(Literal::FloatLiteral(-f)),
+            literal => Pattern::LitPat(literal),
+        }),
+        parse_apat,
+    ))(input)
+}
+
+fn parse_apat(input: Tokens) -> IResult<Tokens, Pattern> {
+    alt((
+        map(
+            pair(parse_ident, opt(preceded(at_tag, parse_apat))),
+            |(Ident(name), pat)| match pat {
+                Some(pat) => Pattern::AsPat(Ident(name), Box::new(pat)),
+                None if name == "_" => Pattern::WildPat,
+                None => Pattern::VarPat(Ident(name)),
+            },
+        ),
+        map(parse_type_ident, |con| Pattern::ConPat(con, vec![])),
+        map(parse_literal, Pattern::LitPat),
+        map(
+            delimited(
+                lbracket_tag,
+                separated_list0(comma_tag, parse_pattern),
+                rbracket_tag,
+            ),
+            Pattern::ListPat,
+        ),
+        map(
+            delimited(
+                lparen_tag,
+                separated_list0(comma_tag, parse_pattern),
+                rparen_tag,
+            ),
+            |mut patterns| match patterns.len() {
+                1 => patterns.remove(0),
+                _ => Pattern::TuplePat(patterns),
+            },
+        ),
+        parse_sig_pattern,
+    ))(input)
+}
+
+fn parse_sig_pattern(input: Tokens) -> IResult<Tokens, Pattern> {
+    let (rest, (pat, ty)) = delimited(
+        lparen_tag,
+        separated_pair(parse_pattern, double_colon_tag, parse_type),
+        rparen_tag,
+    )(input)?;
+    // `(x :: Int) > 0` at the start of a statement is an expression, the
+    // pattern is only certain once a binding follows it
+    let binds = matches!(
+        rest.tok.first(),
+        Some(Token::Assign | Token::Arrow | Token::LeftArrow | Token::Pipe)
+    );
+    match extension(input, Extension::ScopedTypeVariables) {
+        Err(Err::Failure(err)) if !binds => return Err(Err::Error(err)),
+        result => result?,
+    };
+    Ok((rest, Pattern::SigPat(Box::new(pat), ty)))
+}
+
+// Expressions
+
 fn parse_atom_expr(input: Tokens) -> IResult<Tokens, Expr> {
     alt((
         parse_lit_expr,
+        parse_negative_literal,
         parse_ident_expr,
-        parse_prefix_expr,
         parse_paren_expr,
         parse_array_expr,
         parse_hash_expr,
         parse_if_expr,
         parse_fn_expr,
+        parse_lambda_expr,
+        parse_case_expr,
+        parse_let_expr,
+        parse_do_expr,
+    ))(input)
+}
+
+// Arguments that can follow a function without parentheses
+fn parse_arg_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    alt((
+        parse_lit_expr,
+        parse_negative_literal,
+        parse_ident_expr,
+        parse_array_expr,
     ))(input)
 }
 
 fn parse_paren_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    delimited(lparen_tag, parse_expr, rparen_tag)(input)
+    alt((
+        map(parse_tuple_con, Expr::IdentExpr),
+        map(delimited(lparen_tag, parse_infix_op, rparen_tag), |op| {
+            Expr::IdentExpr(Ident(op.name().to_owned()))
+        }),
+        parse_right_section,
+        map(
+            delimited(lparen_tag, alt((parse_exprs, empty_boxed_vec)), rparen_tag),
+            |mut exprs| match exprs.len() {
+                1 => exprs.remove(0),
+                _ => Expr::TupleExpr(exprs),
+            },
+        ),
+        parse_left_section,
+        parse_tuple_section,
+    ))(input)
+}
+
+// `(, x)`, `(x,)` or `(, x,)`: some but not all components are missing
+fn parse_tuple_section(input: Tokens) -> IResult<Tokens, Expr> {
+    let (rest, components) = delimited(
+        lparen_tag,
+        separated_list1(comma_tag, opt(parse_expr)),
+        rparen_tag,
+    )(input)?;
+    let missing = components.iter().filter(|c| c.is_none()).count();
+    if components.len() < 2 || missing == 0 || missing == components.len() {
+        return Err(Err::Error(ParseError::new(input)));
+    }
+    extension(input, Extension::TupleSections)?;
+    Ok((rest, Expr::TupleSectionExpr(components)))
+}
+
+// `(op e)`, except that `(- e)` is a negation
+fn parse_right_section(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        delimited(
+            lparen_tag,
+            pair(
+                verify(parse_infix_op, |op| *op != Infix::Minus),
+                parse_expr,
+            ),
+            rparen_tag,
+        ),
+        |(op, expr)| Expr::RightSectionExpr(op, Box::new(expr)),
+    )(input)
+}
+
+// `(e op)`; the operator is left unparsed by `go_parse_pratt_expr` as it is
+// followed by the closing parenthesis
+fn parse_left_section(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        delimited(lparen_tag, pair(parse_expr, parse_infix_op), rparen_tag),
+        |(expr, op)| Expr::LeftSectionExpr(Box::new(expr), op),
+    )(input)
+}
+
+// `(,)`, `(,,)` ...
+fn parse_tuple_con(input: Tokens) -> IResult<Tokens, Ident> {
+    map(
+        delimited(lparen_tag, many1(comma_tag), rparen_tag),
+        |commas| Ident::tuple_con(commas.len() + 1),
+    )(input)
 }
 
 fn parse_lit_expr(input: Tokens) -> IResult<Tokens, Expr> {
     map(parse_literal, Expr::LitExpr)(input)
 }
 fn parse_ident_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    map(parse_ident, Expr::IdentExpr)(input)
+    alt((
+        map(parse_ident, |name| match name.0.starts_with('_') {
+            true => Expr::HoleExpr(name),
+            false => Expr::IdentExpr(name),
+        }),
+        map(parse_type_ident, Expr::IdentExpr),
+    ))(input)
 }
 fn parse_comma_exprs(input: Tokens) -> IResult<Tokens, Expr> {
     preceded(comma_tag, parse_expr)(input)
@@ -159,13 +1164,81 @@ fn empty_boxed_vec(input: Tokens) -> IResult<Tokens, Vec<Expr>> {
     Ok((input, vec![]))
 }
 fn parse_array_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    alt((
+        parse_sequence_expr,
+        parse_comprehension_expr,
+        map(
+            delimited(
+                lbracket_tag,
+                alt((parse_exprs, empty_boxed_vec)),
+                rbracket_tag,
+            ),
+            Expr::ArrayExpr,
+        ),
+    ))(input)
+}
+
+fn parse_comprehension_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        delimited(
+            lbracket_tag,
+            separated_pair(
+                parse_expr,
+                pipe_tag,
+                separated_list1(comma_tag, parse_qualifier),
+            ),
+            rbracket_tag,
+        ),
+        |(expr, qualifiers)| Expr::ListCompExpr {
+            expr: Box::new(expr),
+            qualifiers,
+        },
+    )(input)
+}
+
+fn parse_qualifier(input: Tokens) -> IResult<Tokens, Qualifier> {
+    alt((
+        map(
+            separated_pair(parse_pattern, left_arrow_tag, parse_expr),
+            |(pat, expr)| Qualifier::Generator(pat, expr),
+        ),
+        // a `let` followed by `in` is a guard
+        map(
+            terminated(preceded(let_tag, |i| parse_block(i, parse_decl)), not(in_tag)),
+            |decls| Qualifier::LetQual(merge_clauses(decls)),
+        ),
+        map(parse_expr, Qualifier::Guard),
+    ))(input)
+}
+
+// `[a ..]`, `[a, b ..]`, `[a .. c]` and `[a, b .. c]`, as applications of the
+// `Enum` methods
+fn parse_sequence_expr(input: Tokens) -> IResult<Tokens, Expr> {
     map(
         delimited(
             lbracket_tag,
-            alt((parse_exprs, empty_boxed_vec)),
+            tuple((
+                parse_expr,
+                opt(preceded(comma_tag, parse_expr)),
+                preceded(range_tag, opt(parse_expr)),
+            )),
             rbracket_tag,
         ),
-        Expr::ArrayExpr,
+        |(from, then, to)| {
+            let method = match (&then, &to) {
+                (None, None) => "enumFrom",
+                (Some(_), None) => "enumFromThen",
+                (None, Some(_)) => "enumFromTo",
+                (Some(_), Some(_)) => "enumFromThenTo",
+            };
+            let args = [Some(from), then, to].into_iter().flatten();
+            args.fold(Expr::IdentExpr(Ident(method.to_owned())), |f, arg| {
+                Expr::CallExpr {
+                    function: Box::new(f),
+                    arguments: vec![arg],
+                }
+            })
+        },
     )(input)
 }
 fn parse_hash_pair(input: Tokens) -> IResult<Tokens, (Literal, Expr)> {
@@ -191,75 +1264,210 @@ fn parse_hash_expr(input: Tokens) -> IResult<Tokens, Expr> {
     )(input)
 }
 
-fn parse_prefix_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    let (i1, t1) = alt((plus_tag, minus_tag, not_tag))(input)?;
-    if t1.tok.is_empty() {
-        Err(Err::Error(error_position!(input, ErrorKind::Tag)))
-    } else {
-        let (i2, e) = parse_atom_expr(i1)?;
-        match t1.tok[0].clone() {
-            Token::Plus => Ok((i2, Expr::PrefixExpr(Prefix::PrefixPlus, Box::new(e)))),
-            Token::Minus => Ok((i2, Expr::PrefixExpr(Prefix::PrefixMinus, Box::new(e)))),
-            Token::Not => Ok((i2, Expr::PrefixExpr(Prefix::Not, Box::new(e)))),
-            _ => Err(Err::Error(error_position!(input, ErrorKind::Tag))),
-        }
+// `- e` at the start of an operand of a chain; `e` is an application, the
+// operators after it are associated by `fixity::Fixities`
+fn parse_negation(input: Tokens) -> IResult<Tokens, Expr> {
+    let (rest, e) = preceded(pair(not(parse_negative_literal), minus_tag), |i| {
+        parse_pratt_expr(i, Precedence::POperator)
+    })(input)?;
+    let negation = Expr::PrefixExpr(Prefix::PrefixMinus, Box::new(e));
+    Ok((rest, located(&input, &rest, negation)))
+}
+
+// `-1` with `NegativeLiterals`, the sign immediately followed by the number
+fn parse_negative_literal(input: Tokens) -> IResult<Tokens, Expr> {
+    let adjacent = match (input.span_at(0), input.span_at(1)) {
+        (Some(sign), Some(number)) => sign.end == number.start,
+        _ => false,
+    };
+    if !adjacent || !input.extensions.contains(Extension::NegativeLiterals) {
+        return Err(Err::Error(ParseError::new(input)));
+    }
+    let (rest, literal) = preceded(minus_tag, parse_literal)(input)?;
+    match literal {
+        Literal::IntLiteral(i) => Ok((rest, Expr::LitExpr(Literal::IntLiteral(-i)))),
+        Literal::FloatLiteral(f) => Ok((rest, Expr::LitExpr(Literal::FloatLiteral(-f)))),
+        _ => Err(Err::Error(ParseError::new(input))),
     }
 }
 
 fn parse_pratt_expr(input: Tokens, precedence: Precedence) -> IResult<Tokens, Expr> {
     let (i1, left) = parse_atom_expr(input)?;
-    go_parse_pratt_expr(i1, precedence, left)
+    let last_end = last_span_end(&input, &i1);
+    go_parse_pratt_expr(i1, precedence, located(&input, &i1, left), last_end)
 }
 
-fn go_parse_pratt_expr(input: Tokens, precedence: Precedence, left: Expr) -> IResult<Tokens, Expr> {
-    let (i1, t1) = take(1usize)(input)?;
+// The expression parsed between two positions of the input, with its span
+// when the tokens have spans
+fn located(before: &Tokens, after: &Tokens, expr: Expr) -> Expr {
+    match (&expr, span_between(before, after)) {
+        (Expr::Located(..), _) => expr,
+        (_, Some(span)) => Expr::Located(span, Box::new(expr)),
+        _ => expr,
+    }
+}
 
-    if t1.tok.is_empty() {
-        Ok((i1, left))
-    } else {
-        let preview = &t1.tok[0];
-        let p = infix_op(preview);
-        match p {
-            (Precedence::PCall, _) if precedence < Precedence::PCall => {
-                let (i2, left2) = parse_call_expr(input, left)?;
-                go_parse_pratt_expr(i2, precedence, left2)
-            }
-            (Precedence::PIndex, _) if precedence < Precedence::PIndex => {
-                let (i2, left2) = parse_index_expr(input, left)?;
-                go_parse_pratt_expr(i2, precedence, left2)
-            }
-            (ref peek_precedence, _) if precedence < *peek_precedence => {
-                let (i2, left2) = parse_infix_expr(input, left)?;
-                go_parse_pratt_expr(i2, precedence, left2)
-            }
-            _ => Ok((input, left)),
-        }
+// The span of the tokens consumed between two positions of the input
+fn span_between(before: &Tokens, after: &Tokens) -> Option<Span> {
+    let end = last_span_end(before, after)?;
+    before.span_at(0).map(|first| Span { end, ..first })
+}
+
+// End offset of the last token consumed between two positions of the input
+fn last_span_end(before: &Tokens, after: &Tokens) -> Option<usize> {
+    let consumed = before.input_len() - after.input_len();
+    consumed
+        .checked_sub(1)
+        .and_then(|last| before.span_at(last))
+        .map(|span| span.end)
+}
+
+// Expressions ending in a block or extending as far right as possible
+// cannot be applied to arguments by juxtaposition.
+fn is_applicable(expr: &Expr) -> bool {
+    !matches!(
+        expr.unlocated(),
+        Expr::PrefixExpr(..)
+            | Expr::IfExpr { .. }
+            | Expr::MultiIfExpr(_)
+            | Expr::LambdaCaseExpr(_)
+            | Expr::FnExpr { .. }
+            | Expr::HashExpr(_)
+            | Expr::LambdaExpr { .. }
+            | Expr::CaseExpr { .. }
+            | Expr::LetExpr { .. }
+            | Expr::DoExpr(_)
+    )
+}
+
+fn go_parse_pratt_expr(
+    input: Tokens,
+    precedence: Precedence,
+    left: Expr,
+    last_end: Option<usize>,
+) -> IResult<Tokens, Expr> {
+    if input.tok.is_empty() {
+        return Ok((input, left));
     }
+    let (_, t1) = take(1usize)(input)?;
+    let preview = &t1.tok[0];
+    // `xs[i]` indexes while `f [x]` applies `f` to a list; without spans
+    // every bracket following an expression is an index.
+    let spaced = match (last_end, input.span_at(0)) {
+        (Some(end), Some(span)) => span.start > end,
+        _ => false,
+    };
+    let start = left.span();
+    let p = match parse_infix_op(input) {
+        // the operator of a left section has no right operand
+        Ok((rest, _)) if rparen_tag(rest).is_ok() => return Ok((input, left)),
+        Ok((_, op)) => (Precedence::POperator, Some(op)),
+        Err(_) => infix_op(preview),
+    };
+    let (i2, left2) = match p {
+        (Precedence::PCall, _) if precedence < Precedence::PCall && *preview == Token::LParen => {
+            parse_call_expr(input, left)?
+        }
+        (Precedence::PCall, _) if precedence < Precedence::PCall && is_applicable(&left) => {
+            parse_apply_expr(input, left)?
+        }
+        // `f -1` applies `f` to a negative literal, `f - 1` and `f-1` subtract
+        (Precedence::POperator, Some(Infix::Minus))
+            if precedence < Precedence::PCall
+                && spaced
+                && is_applicable(&left)
+                && parse_negative_literal(input).is_ok() =>
+        {
+            parse_apply_expr(input, left)?
+        }
+        (Precedence::PIndex, _) if precedence < Precedence::PIndex && !spaced => {
+            parse_index_expr(input, left)?
+        }
+        (Precedence::PIndex, _) if precedence < Precedence::PCall && is_applicable(&left) => {
+            parse_apply_expr(input, left)?
+        }
+        (ref peek_precedence, Some(_)) if precedence < *peek_precedence => {
+            parse_infix_expr(input, left)?
+        }
+        (Precedence::PLowest, None)
+            if precedence < Precedence::PCall && is_applicable(&left) && is_block(preview) =>
+        {
+            parse_block_argument(input, left)?
+        }
+        _ => return Ok((input, left)),
+    };
+    let last_end = last_span_end(&input, &i2);
+    // an application, an index or a block argument spans from its function
+    let left2 = match (start, last_end, &left2) {
+        (_, _, Expr::InfixChain(..)) => left2,
+        (Some(start), Some(end), _) => Expr::Located(Span { end, ..start }, Box::new(left2)),
+        _ => left2,
+    };
+    go_parse_pratt_expr(i2, precedence, left2, last_end)
 }
 
+// Operators and operands are collected flat, `fixity::Fixities` associates
+// them once the fixity declarations in scope are known.
 fn parse_infix_expr(input: Tokens, left: Expr) -> IResult<Tokens, Expr> {
-    let (i1, t1) = take(1usize)(input)?;
-    if t1.tok.is_empty() {
-        Err(Err::Error(error_position!(input, ErrorKind::Tag)))
-    } else {
-        let next = &t1.tok[0];
-        let (precedence, maybe_op) = infix_op(next);
-        match maybe_op {
-            None => Err(Err::Error(error_position!(input, ErrorKind::Tag))),
-            Some(op) => {
-                let (i2, right) = parse_pratt_expr(i1, precedence)?;
-                Ok((i2, Expr::InfixExpr(op, Box::new(left), Box::new(right))))
+    let mut rest = input;
+    let mut operands = vec![];
+    loop {
+        match parse_infix_op(rest) {
+            Ok((after, _)) if rparen_tag(after).is_ok() => break,
+            Ok((after, op)) => {
+                let (after, right) = alt((parse_negation, |i| {
+                    parse_pratt_expr(i, Precedence::POperator)
+                }))(after)?;
+                operands.push((op, right));
+                rest = after;
             }
+            Err(_) => break,
         }
     }
+    Ok((rest, Expr::InfixChain(Box::new(left), operands)))
+}
+
+// `f(a, b)` passes the components as separate arguments, `f ()` none
+fn parse_call_expr(input: Tokens, fn_handle: Expr) -> IResult<Tokens, Expr> {
+    map(parse_paren_expr, |e| Expr::CallExpr {
+        function: Box::new(fn_handle.clone()),
+        arguments: match e {
+            Expr::TupleExpr(exprs) => exprs,
+            e => vec![e],
+        },
+    })(input)
+}
+
+fn parse_apply_expr(input: Tokens, fn_handle: Expr) -> IResult<Tokens, Expr> {
+    let (rest, arg) = parse_arg_expr(input)?;
+    let expr = Expr::CallExpr {
+        function: Box::new(fn_handle),
+        arguments: vec![located(&input, &rest, arg)],
+    };
+    Ok((rest, expr))
+}
+
+fn is_block(t: &Token) -> bool {
+    matches!(
+        t,
+        Token::Do | Token::Backslash | Token::Case | Token::If | Token::Let
+    )
 }
 
-fn parse_call_expr(input: Tokens, fn_handle: Expr) -> IResult<Tokens, Expr> {
+// `f do ...` or `f \x -> ...`, the block being the last argument
+fn parse_block_argument(input: Tokens, fn_handle: Expr) -> IResult<Tokens, Expr> {
+    extension(input, Extension::BlockArguments)?;
     map(
-        delimited(lparen_tag, alt((parse_exprs, empty_boxed_vec)), rparen_tag),
-        |e| Expr::CallExpr {
+        alt((
+            parse_do_expr,
+            parse_lambda_expr,
+            parse_case_expr,
+            parse_if_expr,
+            parse_let_expr,
+        )),
+        |arg| Expr::CallExpr {
             function: Box::new(fn_handle.clone()),
-            arguments: e,
+            arguments: vec![arg],
         },
     )(input)
 }
@@ -274,22 +1482,55 @@ fn parse_index_expr(input: Tokens, arr: Expr) -> IResult<Tokens, Expr> {
 }
 
 fn parse_if_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    alt((
+        parse_multi_way_if,
+        map(
+            tuple((
+                if_tag,
+                lparen_tag,
+                parse_expr,
+                rparen_tag,
+                parse_block_stmt,
+                parse_else_expr,
+            )),
+            |(_, _, expr, _, c, a)| Expr::IfExpr {
+                cond: Box::new(expr),
+                consequence: c,
+                alternative: a,
+            },
+        ),
+        map(
+            tuple((
+                if_tag,
+                parse_expr,
+                opt(semicolon_tag),
+                then_tag,
+                parse_expr,
+                opt(semicolon_tag),
+                else_tag,
+                parse_expr,
+            )),
+            |(_, expr, _, _, c, _, _, a)| Expr::IfExpr {
+                cond: Box::new(expr),
+                consequence: vec![Stmt::ExprStmt(c)],
+                alternative: Some(vec![Stmt::ExprStmt(a)]),
+            },
+        ),
+    ))(input)
+}
+// `if | c1 -> e1 | c2 -> e2`
+fn parse_multi_way_if(input: Tokens) -> IResult<Tokens, Expr> {
+    let (rest, _) = if_tag(input)?;
+    pipe_tag(rest)?;
+    extension(rest, Extension::MultiWayIf)?;
     map(
-        tuple((
-            if_tag,
-            lparen_tag,
-            parse_expr,
-            rparen_tag,
-            parse_block_stmt,
-            parse_else_expr,
-        )),
-        |(_, _, expr, _, c, a)| Expr::IfExpr {
-            cond: Box::new(expr),
-            consequence: c,
-            alternative: a,
+        many1(tuple((pipe_tag, parse_expr, arrow_tag, parse_expr))),
+        |guards| {
+            Expr::MultiIfExpr(guards.into_iter().map(|(_, guard, _, expr)| (guard, expr)).collect())
         },
-    )(input)
+    )(rest)
 }
+
 fn parse_else_expr(input: Tokens) -> IResult<Tokens, Option<Program>> {
     opt(preceded(else_tag, parse_block_stmt))(input)
 }
@@ -315,34 +1556,288 @@ fn parse_params(input: Tokens) -> IResult<Tokens, Vec<Ident>> {
     )(input)
 }
 
+fn parse_lambda_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    if let Ok((rest, _)) = backslash_tag(input) {
+        if case_tag(rest).is_ok() {
+            return parse_lambda_case(rest);
+        }
+    }
+    map(
+        tuple((backslash_tag, many1(parse_apat), arrow_tag, parse_expr)),
+        |(_, params, _, body)| Expr::LambdaExpr {
+            params,
+            body: Box::new(body),
+        },
+    )(input)
+}
+
+// `\case alts`, from the `case`
+fn parse_lambda_case(input: Tokens) -> IResult<Tokens, Expr> {
+    extension(input, Extension::LambdaCase)?;
+    map(
+        preceded(case_tag, |i| parse_block(i, parse_alt)),
+        Expr::LambdaCaseExpr,
+    )(input)
+}
+
+fn parse_case_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        tuple((case_tag, parse_expr, of_tag, |i| parse_block(i, parse_alt))),
+        |(_, scrutinee, _, alts)| Expr::CaseExpr {
+            scrutinee: Box::new(scrutinee),
+            alts,
+        },
+    )(input)
+}
+
+fn parse_alt(input: Tokens) -> IResult<Tokens, ast::Alt> {
+    map(
+        tuple((parse_pattern, parse_rhs(arrow_tag), parse_wheres)),
+        |(pat, rhs, wheres)| ast::Alt { pat, rhs, wheres },
+    )(input)
+}
+
+fn parse_let_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        tuple((let_tag, |i| parse_block(i, parse_decl), in_tag, parse_expr)),
+        |(_, bindings, _, body)| Expr::LetExpr {
+            bindings: merge_clauses(bindings),
+            body: Box::new(body),
+        },
+    )(input)
+}
+
+// The statements of a `do` block are those of a list comprehension, but the
+// last one has to be an expression
+fn parse_do_expr(input: Tokens) -> IResult<Tokens, Expr> {
+    map(
+        verify(
+            preceded(do_tag, |i| parse_block(i, parse_qualifier)),
+            |stmts: &Vec<Qualifier>| matches!(stmts.last(), Some(Qualifier::Guard(_))),
+        ),
+        Expr::DoExpr,
+    )(input)
+}
+
+// Error recovery
+
+/// Splits the input into its top-level items for error recovery: an item
+/// ends at a `;` outside brackets, or where a line starts at or left of the
+/// column of the first item. Unlike `layout_region`, a line ends an item even
+/// inside brackets, so that an unclosed one doesn't swallow the rest of the
+/// file, unless it starts with the closing bracket, as in `};`.
+fn top_items(input: Tokens) -> Vec<Tokens> {
+    let column = input.span_at(0).map(|span| span.column);
+    let mut items = vec![];
+    let mut rest = input;
+    while rest.tok.first().is_some_and(|t| *t != Token::EOF) {
+        let mut depth = 0;
+        let mut end = rest.tok.len();
+        for (i, token) in rest.tok.iter().enumerate() {
+            let offside = match (column, i.checked_sub(1).and_then(|j| rest.span_at(j))) {
+                (Some(column), Some(previous)) => {
+                    let span = rest.span[i];
+                    span.line > previous.line
+                        && span.column <= column
+                        && !matches!(token, Token::RParen | Token::RBracket | Token::RBrace)
+                }
+                _ => false,
+            };
+            if offside || *token == Token::EOF {
+                end = i;
+                break;
+            }
+            match token {
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => depth -= 1,
+                Token::SemiColon if depth <= 0 => {
+                    end = i + 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        items.push(rest.slice(..end));
+        rest = rest.slice(end..);
+    }
+    items
+}
+
+fn error_of(err: Err<ParseError>) -> Option<ParseError> {
+    match err {
+        Err::Error(error) | Err::Failure(error) => Some(error),
+        Err::Incomplete(_) => None,
+    }
+}
+
+/// Parses one top-level item of `input` as statements. When that fails,
+/// the error is the furthest that parsing it as any kind of statement got.
+fn recover_stmts(input: Tokens, item: Tokens) -> Result<Program, ParseError> {
+    let error = match terminated(parse_stmt_group, many0(semicolon_tag))(item) {
+        Ok((rest, stmts)) if rest.tok.is_empty() => return Ok(stmts),
+        // a statement ended before the item did
+        Ok((rest, _)) => ParseError::new(rest),
+        Err(e) => error_of(e).unwrap_or_else(|| ParseError::new(item)),
+    };
+    let errors = [
+        parse_decl_stmt(item).err(),
+        parse_let_stmt(item).err(),
+        parse_return_stmt(item).err(),
+    ];
+    let error = errors.into_iter().flatten().filter_map(error_of).fold(error, |a, b| a.or(b));
+    Err(error.within(input))
+}
+
+// The statements of `body`, a part of `input`, with error nodes for the
+// items that don't parse
+fn recover_body(input: Tokens, body: Tokens, errors: &mut Vec<ParseError>) -> Program {
+    let mut stmts = vec![];
+    for item in top_items(body) {
+        match recover_stmts(input, item) {
+            Ok(group) => stmts.extend(group),
+            Err(error) => {
+                errors.push(error.clone());
+                stmts.push(Stmt::ErrorStmt(error));
+            }
+        }
+    }
+    merge_clauses(stmts)
+}
+
+// The input after its pragmas. An unsupported extension is an error, but
+// parsing goes on without the pragma naming it.
+fn recover_pragmas<'a>(input: Tokens<'a>, errors: &mut Vec<ParseError>) -> Tokens<'a> {
+    match parse_pragmas(input) {
+        Ok((rest, _)) => rest,
+        Err(e) => {
+            errors.extend(error_of(e));
+            let pragmas = input.tok.iter().take_while(|t| matches!(t, Token::LanguagePragma(_)));
+            input.slice(pragmas.count()..)
+        }
+    }
+}
+
+fn recover_module(input: Tokens) -> (Module, Vec<ParseError>) {
+    let mut errors = vec![];
+    let mut rest = recover_pragmas(input, &mut errors);
+    let mut header = None;
+    if module_tag(rest).is_ok() {
+        match parse_module_header(rest) {
+            Ok((after, parsed)) => {
+                header = Some(parsed);
+                rest = after;
+            }
+            Err(e) => {
+                errors.extend(error_of(e).map(|error| error.within(input)));
+                rest = rest.slice(top_items(rest)[0].input_len()..);
+            }
+        }
+    }
+    let mut imports = vec![];
+    while import_tag(rest).is_ok() {
+        let item = top_items(rest)[0];
+        match terminated(parse_import, many0(semicolon_tag))(item) {
+            Ok((left, import)) if left.tok.is_empty() => imports.push(import),
+            Ok((left, _)) => errors.push(ParseError::new(left).within(input)),
+            Err(e) => errors.extend(error_of(e).map(|error| error.within(input))),
+        }
+        rest = rest.slice(item.input_len()..);
+    }
+    let body = recover_body(input, rest, &mut errors);
+    let (name, exports) = header.unwrap_or((Ident("Main".to_owned()), None));
+    let module = Module {
+        name,
+        exports,
+        imports,
+        body,
+    };
+    (module, errors)
+}
+
 pub struct Parser;
 
 impl Parser {
     pub fn parse_tokens(tokens: Tokens) -> IResult<Tokens, Program> {
         parse_program(tokens)
     }
+
+    pub fn parse_module(tokens: Tokens) -> IResult<Tokens, Module> {
+        parse_module(tokens)
+    }
+
+    /// The extensions enabled by the `LANGUAGE` pragmas at the start of the
+    /// tokens, together with those the tokens already have.
+    pub fn parse_pragmas(tokens: Tokens) -> IResult<Tokens, Extensions> {
+        parse_pragmas(tokens)
+    }
+
+    /// Parses a program, going on after syntax errors to report all of them.
+    /// A top-level item that doesn't parse becomes a `Stmt::ErrorStmt` in
+    /// the partial program, and parsing resumes at the next item.
+    pub fn parse_tokens_recovering(tokens: Tokens) -> (Program, Vec<ParseError>) {
+        match parse_program(tokens) {
+            Ok((_, program)) => (program, vec![]),
+            Err(e) => {
+                let mut errors = vec![];
+                let body = recover_pragmas(tokens, &mut errors);
+                let program = recover_body(tokens, body, &mut errors);
+                if errors.is_empty() {
+                    errors.extend(error_of(e));
+                }
+                (program, errors)
+            }
+        }
+    }
+
+    /// `parse_tokens_recovering` for a module: the header and each import
+    /// are recovered from like a top-level item.
+    pub fn parse_module_recovering(tokens: Tokens) -> (Module, Vec<ParseError>) {
+        match parse_module(tokens) {
+            Ok((_, module)) => (module, vec![]),
+            Err(e) => {
+                let (module, mut errors) = recover_module(tokens);
+                if errors.is_empty() {
+                    errors.extend(error_of(e));
+                }
+                (module, errors)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::*;
+    use crate::parser::fixity::*;
+    use crate::parser::fold::{Fold, Unlocate};
+    use crate::parser::pretty::Printer;
+    use crate::parser::visit::{visit_expr, Visitor};
+
+    // Parses with the operators associated by the Prelude fixities
+    fn parse(tokens: Tokens) -> Program {
+        let (_, program) = Parser::parse_tokens(tokens).unwrap();
+        Unlocate.fold_program(Fixities::new().resolve_program(program).unwrap())
+    }
 
     fn assert_input_with_program(input: &str, expected_results: Program) {
         let (_, r) = Lexer::lex_tokens(input).unwrap();
-        let tokens = Tokens::new(&r);
-        let (_, result) = Parser::parse_tokens(tokens).unwrap();
+        let result = parse(Tokens::new(&r));
+        assert_eq!(result, expected_results);
+    }
+
+    fn assert_layout_with_program(input: &str, expected_results: Program) {
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let result = parse(Tokens::with_spans(&r, &spans));
         assert_eq!(result, expected_results);
     }
 
     fn compare_inputs(input: &str, input2: &str) {
         let (_, r) = Lexer::lex_tokens(input).unwrap();
-        let tokens = Tokens::new(&r);
-        let (_, result) = Parser::parse_tokens(tokens).unwrap();
+        let result = parse(Tokens::new(&r));
 
         let (_, r) = Lexer::lex_tokens(input2).unwrap();
-        let tokens = Tokens::new(&r);
-        let (_, expected_results) = Parser::parse_tokens(tokens).unwrap();
+        let expected_results = parse(Tokens::new(&r));
 
         assert_eq!(result, expected_results);
     }
@@ -437,22 +1932,32 @@ mod tests {
     #[test]
     fn prefix_expr() {
         let input = "-foobar;\
-             +10\
-             !true\
+             - 2 ^ 2;\
+             -f x * y + z\
             ";
 
+        let int = |i| Box::new(Expr::LitExpr(Literal::IntLiteral(i)));
+        let var = |name: &str| Box::new(Expr::IdentExpr(Ident(name.to_owned())));
         let program: Program = vec![
+            Stmt::ExprStmt(Expr::PrefixExpr(Prefix::PrefixMinus, var("foobar"))),
             Stmt::ExprStmt(Expr::PrefixExpr(
                 Prefix::PrefixMinus,
-                Box::new(Expr::IdentExpr(Ident("foobar".to_owned()))),
-            )),
-            Stmt::ExprStmt(Expr::PrefixExpr(
-                Prefix::PrefixPlus,
-                Box::new(Expr::LitExpr(Literal::IntLiteral(10))),
+                Box::new(Expr::InfixExpr(Infix::from_name("^"), int(2), int(2))),
             )),
-            Stmt::ExprStmt(Expr::PrefixExpr(
-                Prefix::Not,
-                Box::new(Expr::LitExpr(Literal::BoolLiteral(true))),
+            Stmt::ExprStmt(Expr::InfixExpr(
+                Infix::Plus,
+                Box::new(Expr::PrefixExpr(
+                    Prefix::PrefixMinus,
+                    Box::new(Expr::InfixExpr(
+                        Infix::Multiply,
+                        Box::new(Expr::CallExpr {
+                            function: var("f"),
+                            arguments: vec![*var("x")],
+                        }),
+                        var("y"),
+                    )),
+                )),
+                var("z"),
             )),
         ];
 
@@ -463,7 +1968,8 @@ mod tests {
     fn prefix_expr2() {
         let input = "-(foobar);\
              (+(10));\
-             (((!true)));\
+             (((- true)));\
+             (-x) ^ 2;\
             ";
 
         let program: Program = vec![
@@ -471,14 +1977,23 @@ mod tests {
                 Prefix::PrefixMinus,
                 Box::new(Expr::IdentExpr(Ident("foobar".to_owned()))),
             )),
-            Stmt::ExprStmt(Expr::PrefixExpr(
-                Prefix::PrefixPlus,
+            // `(+ e)` is a section, only `-` is a prefix inside parentheses
+            Stmt::ExprStmt(Expr::RightSectionExpr(
+                Infix::Plus,
                 Box::new(Expr::LitExpr(Literal::IntLiteral(10))),
             )),
             Stmt::ExprStmt(Expr::PrefixExpr(
-                Prefix::Not,
+                Prefix::PrefixMinus,
                 Box::new(Expr::LitExpr(Literal::BoolLiteral(true))),
             )),
+            Stmt::ExprStmt(Expr::InfixExpr(
+                Infix::from_name("^"),
+                Box::new(Expr::PrefixExpr(
+                    Prefix::PrefixMinus,
+                    Box::new(Expr::IdentExpr(Ident("x".to_owned()))),
+                )),
+                Box::new(Expr::LitExpr(Literal::IntLiteral(2))),
+            )),
         ];
 
         assert_input_with_program(input, program);
@@ -506,13 +2021,13 @@ mod tests {
 
         assert_input_with_program(input, program);
 
-        let input = "10 + 5 / -20 - (x + x)";
+        let input = "10 + 5 / (-20) - (x + x)";
 
         let input2 = "10 + (5 / (-20)) - (x + x)";
 
         compare_inputs(input, input2);
 
-        let input = "10 + 5 / -20 - (x + x)";
+        let input = "10 + 5 / (-20) - (x + x)";
 
         let program: Program = vec![Stmt::ExprStmt(Expr::InfixExpr(
             Infix::Minus,
@@ -540,9 +2055,9 @@ mod tests {
 
     #[test]
     fn op_precedence() {
-        let input = "!-a";
+        let input = "- a * b - c";
 
-        let input2 = "(!(-a))";
+        let input2 = "((-(a * b)) - c)";
 
         compare_inputs(input, input2);
 
@@ -584,11 +2099,12 @@ mod tests {
 
         let input = "3 + 4; -5 * 5";
 
-        let input2 = "(3 + 4);((-5) * 5)";
+        let input2 = "(3 + 4);(-(5 * 5))";
 
         compare_inputs(input, input2);
 
-        let input = "5 > 4 == 3 < 4";
+        // comparisons are all `infix 4`, so they do not chain
+        let input = "(5 > 4) == (3 < 4)";
 
         let input2 = "((5 > 4) == (3 < 4))";
 
@@ -596,9 +2112,9 @@ mod tests {
 
         let input = "5 < 4 != 3 > 4";
 
-        let input2 = "((5 < 4) != (3 > 4))";
-
-        compare_inputs(input, input2);
+        let (_, r) = Lexer::lex_tokens(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::new(&r)).unwrap();
+        assert!(Fixities::new().resolve_program(program).is_err());
 
         let input = "3 + 4 * 5 == 3 * 1 + 4 * 5";
 
@@ -951,4 +2467,822 @@ mod tests {
 
         assert_input_with_program(input, program);
     }
+
+    fn ident(name: &str) -> Ident {
+        Ident(name.to_owned())
+    }
+
+    fn var_expr(name: &str) -> Expr {
+        Expr::IdentExpr(ident(name))
+    }
+
+    fn var_type(name: &str) -> Type {
+        Type::VarType(ident(name))
+    }
+
+    fn con_type(name: &str) -> Type {
+        Type::ConType(ident(name))
+    }
+
+    fn fn_type(arg: Type, result: Type) -> Type {
+        Type::FnType(Box::new(arg), Box::new(result))
+    }
+
+    fn pred(class: &str, ty: Type) -> Pred {
+        Pred {
+            class: ident(class),
+            ty,
+        }
+    }
+
+    #[test]
+    fn class_decl() {
+        let input = "class (Eq a) => Ord a where\n  \
+                     compare, cmp :: a -> a -> Ordering\n  \
+                     x < y = compare x y == LT\n";
+        let program = vec![Stmt::ClassStmt(ClassDecl {
+            context: vec![pred("Eq", var_type("a"))],
+            name: ident("Ord"),
+            var: ident("a"),
+            body: vec![
+                Stmt::SigStmt(
+                    vec![ident("compare"), ident("cmp")],
+                    vec![],
+                    fn_type(
+                        var_type("a"),
+                        fn_type(var_type("a"), con_type("Ordering")),
+                    ),
+                    None,
+                ),
+                Stmt::FunStmt(
+                    ident("<"),
+                    vec![Clause {
+                        params: vec![
+                            Pattern::VarPat(ident("x")),
+                            Pattern::VarPat(ident("y")),
+                        ],
+                        rhs: Rhs::Plain(Expr::InfixExpr(
+                            Infix::Equal,
+                            Box::new(Expr::CallExpr {
+                                function: Box::new(Expr::CallExpr {
+                                    function: Box::new(var_expr("compare")),
+                                    arguments: vec![var_expr("x")],
+                                }),
+                                arguments: vec![var_expr("y")],
+                            }),
+                            Box::new(var_expr("LT")),
+                        )),
+                        wheres: vec![],
+                    }],
+                ),
+            ],
+        })];
+        assert_layout_with_program(input, program);
+
+        // explicit braces and semicolons instead of layout
+        compare_inputs(
+            "class Eq a where { (==), (/=) :: a -> a -> Bool; x /= y = not (x == y) }",
+            "class Eq a where {(==), (/=) :: a -> a -> Bool ; x /= y = not (x == y)}",
+        );
+    }
+
+    #[test]
+    fn instance_decl() {
+        let input = "instance Show a => Show (Tree a) where\n  \
+                     show (Leaf x) = show x\n  \
+                     show (Node l r) = show l ++ show r\n";
+        let show_call = |arg: &str| Expr::CallExpr {
+            function: Box::new(var_expr("show")),
+            arguments: vec![var_expr(arg)],
+        };
+        let program = vec![Stmt::InstanceStmt(InstanceDecl {
+            context: vec![pred("Show", var_type("a"))],
+            class: ident("Show"),
+            ty: Type::AppType(Box::new(con_type("Tree")), Box::new(var_type("a"))),
+            body: vec![Stmt::FunStmt(
+                ident("show"),
+                vec![
+                    Clause {
+                        params: vec![Pattern::ConPat(
+                            ident("Leaf"),
+                            vec![Pattern::VarPat(ident("x"))],
+                        )],
+                        rhs: Rhs::Plain(show_call("x")),
+                        wheres: vec![],
+                    },
+                    Clause {
+                        params: vec![Pattern::ConPat(
+                            ident("Node"),
+                            vec![Pattern::VarPat(ident("l")), Pattern::VarPat(ident("r"))],
+                        )],
+                        rhs: Rhs::Plain(Expr::InfixExpr(
+                            Infix::Op(ident("++")),
+                            Box::new(show_call("l")),
+                            Box::new(show_call("r")),
+                        )),
+                        wheres: vec![],
+                    },
+                ],
+            )],
+            span: None,
+        })];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn data_decl() {
+        let input = "data Tree a = Leaf a | Node (Tree a) (Tree a)";
+        let tree_a = Type::AppType(Box::new(con_type("Tree")), Box::new(var_type("a")));
+        let program = vec![Stmt::DataStmt(DataDecl {
+            name: ident("Tree"),
+            params: vec![ident("a")],
+            constructors: vec![
+                ConDecl {
+                    name: ident("Leaf"),
+                    fields: vec![var_type("a")],
+                    labels: vec![],
+                },
+                ConDecl {
+                    name: ident("Node"),
+                    fields: vec![tree_a.clone(), tree_a],
+                    labels: vec![],
+                },
+            ],
+            deriving: vec![],
+        })];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn newtype_decl() {
+        let input = "newtype Name = Name { unName :: String } deriving Eq";
+        let program = vec![Stmt::DataStmt(DataDecl {
+            name: ident("Name"),
+            params: vec![],
+            constructors: vec![ConDecl {
+                name: ident("Name"),
+                fields: vec![con_type("String")],
+                labels: vec![ident("unName")],
+            }],
+            deriving: vec![ident("Eq")],
+        })];
+        assert_layout_with_program(input, program);
+        let messages = |input: &str| {
+            let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let (_, errors) = Parser::parse_tokens_recovering(Tokens::with_spans(&r, &spans));
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages("newtype P = P Int Int\ntype Name = String"),
+            ["1:13: unexpected `P`, expected a constructor with one field"]
+        );
+    }
+
+    #[test]
+    fn type_decl() {
+        let input = "type Name = String\ntype Table k v = [(k, v)]";
+        let program = vec![
+            Stmt::TypeStmt(ident("Name"), vec![], con_type("String")),
+            Stmt::TypeStmt(
+                ident("Table"),
+                vec![ident("k"), ident("v")],
+                Type::ListType(Box::new(Type::TupleType(vec![var_type("k"), var_type("v")]))),
+            ),
+        ];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn deriving_decl() {
+        let input = "data P = P { name, nick :: String, age :: Int }\n  deriving (Eq, Show)\n\
+                     deriving instance Ord P";
+        let string = con_type("String");
+        let program = vec![
+            Stmt::DataStmt(DataDecl {
+                name: ident("P"),
+                params: vec![],
+                constructors: vec![ConDecl {
+                    name: ident("P"),
+                    fields: vec![string.clone(), string, con_type("Int")],
+                    labels: vec![ident("name"), ident("nick"), ident("age")],
+                }],
+                deriving: vec![ident("Eq"), ident("Show")],
+            }),
+            Stmt::DerivingStmt(InstanceDecl {
+                context: vec![],
+                class: ident("Ord"),
+                ty: con_type("P"),
+                body: vec![],
+                span: None,
+            }),
+        ];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn default_decl() {
+        let input = "default (Integer, Double)\ndefault ()";
+        let program = vec![
+            Stmt::DefaultStmt(vec![con_type("Integer"), con_type("Double")]),
+            Stmt::DefaultStmt(vec![]),
+        ];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn tuples() {
+        let input = "swap :: (a, b) -> (b, a)\nswap (x, ()) = (y, x, ())\npair = (,)";
+        let program = vec![
+            Stmt::SigStmt(
+                vec![ident("swap")],
+                vec![],
+                fn_type(
+                    Type::TupleType(vec![var_type("a"), var_type("b")]),
+                    Type::TupleType(vec![var_type("b"), var_type("a")]),
+                ),
+                None,
+            ),
+            Stmt::FunStmt(
+                ident("swap"),
+                vec![Clause {
+                    params: vec![Pattern::TuplePat(vec![
+                        Pattern::VarPat(ident("x")),
+                        Pattern::TuplePat(vec![]),
+                    ])],
+                    rhs: Rhs::Plain(Expr::TupleExpr(vec![
+                        var_expr("y"),
+                        var_expr("x"),
+                        Expr::TupleExpr(vec![]),
+                    ])),
+                    wheres: vec![],
+                }],
+            ),
+            Stmt::LetStmt(ident("pair"), var_expr("(,)")),
+        ];
+        assert_layout_with_program(input, program);
+        compare_inputs("(1)", "1");
+    }
+
+    #[test]
+    fn arithmetic_sequences() {
+        compare_inputs("[1..]", "enumFrom 1");
+        compare_inputs("[1, 3..]", "enumFromThen 1 3");
+        compare_inputs("['a'..'z']", "enumFromTo 'a' 'z'");
+        compare_inputs("[x, x - 1..0]", "enumFromThenTo x (x - 1) 0");
+        compare_inputs("[1, 2]", "[1, 2]");
+    }
+
+    #[test]
+    fn list_comprehension() {
+        let input = "[(x, z) | Just x <- xs, x > 0, let z = 2]";
+        let program = vec![Stmt::ExprStmt(Expr::ListCompExpr {
+            expr: Box::new(Expr::TupleExpr(vec![var_expr("x"), var_expr("z")])),
+            qualifiers: vec![
+                Qualifier::Generator(
+                    Pattern::ConPat(ident("Just"), vec![Pattern::VarPat(ident("x"))]),
+                    var_expr("xs"),
+                ),
+                Qualifier::Guard(Expr::InfixExpr(
+                    Infix::GreaterThan,
+                    Box::new(var_expr("x")),
+                    Box::new(Expr::LitExpr(Literal::IntLiteral(0))),
+                )),
+                Qualifier::LetQual(vec![Stmt::LetStmt(
+                    ident("z"),
+                    Expr::LitExpr(Literal::IntLiteral(2)),
+                )]),
+            ],
+        })];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn semicolons_in_inner_blocks() {
+        let parse_layout = |input: &str| {
+            let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            parse(Tokens::with_spans(&r, &spans))
+        };
+        let compare_layout = |input: &str, explicit: &str| {
+            assert_eq!(parse_layout(input), parse_layout(explicit));
+        };
+        compare_layout("f = a + b where a = 1; b = 2", "f = a + b where { a = 1; b = 2 }");
+        compare_layout(
+            "x = case m of Just x -> x; Nothing -> 0\ny = 1",
+            "x = case m of { Just x -> x; Nothing -> 0 }; y = 1",
+        );
+        compare_layout("x = do a; b\ny = 1", "x = do { a; b }; y = 1");
+        compare_layout("x = let a = 1; b = 2 in a; y = b", "x = let { a = 1; b = 2 } in a; y = b");
+        compare_layout(
+            "x = (case m of Just y -> y); z = 1",
+            "x = (case m of { Just y -> y }); z = 1",
+        );
+        assert_eq!(parse_layout("f = a + b where a = 1; b = 2").len(), 1);
+    }
+
+    #[test]
+    fn pattern_bindings() {
+        let input = "(a, b) = p\nf = x where Just x = m\ng = let (y : _) = m in y";
+        let var_pat = |name: &str| Pattern::VarPat(ident(name));
+        let program = vec![
+            Stmt::PatStmt(
+                Pattern::TuplePat(vec![var_pat("a"), var_pat("b")]),
+                Rhs::Plain(var_expr("p")),
+                vec![],
+            ),
+            Stmt::FunStmt(
+                ident("f"),
+                vec![Clause {
+                    params: vec![],
+                    rhs: Rhs::Plain(var_expr("x")),
+                    wheres: vec![Stmt::PatStmt(
+                        Pattern::ConPat(ident("Just"), vec![var_pat("x")]),
+                        Rhs::Plain(var_expr("m")),
+                        vec![],
+                    )],
+                }],
+            ),
+            Stmt::LetStmt(
+                ident("g"),
+                Expr::LetExpr {
+                    bindings: vec![Stmt::PatStmt(
+                        Pattern::ConPat(ident(":"), vec![var_pat("y"), Pattern::WildPat]),
+                        Rhs::Plain(var_expr("m")),
+                        vec![],
+                    )],
+                    body: Box::new(var_expr("y")),
+                },
+            ),
+        ];
+        assert_layout_with_program(input, program);
+        assert_input_with_program(
+            "do { let { (a, b) = p }; a }",
+            vec![Stmt::ExprStmt(Expr::DoExpr(vec![
+                Qualifier::LetQual(vec![Stmt::PatStmt(
+                    Pattern::TuplePat(vec![var_pat("a"), var_pat("b")]),
+                    Rhs::Plain(var_expr("p")),
+                    vec![],
+                )]),
+                Qualifier::Guard(var_expr("a")),
+            ]))],
+        );
+    }
+
+    #[test]
+    fn do_blocks() {
+        let input = "main = do\n  Just x <- m\n  let y = x\n  print y\n  return y";
+        let call = |f: &str, x: &str| Expr::CallExpr {
+            function: Box::new(var_expr(f)),
+            arguments: vec![var_expr(x)],
+        };
+        let program = vec![Stmt::LetStmt(
+            ident("main"),
+            Expr::DoExpr(vec![
+                Qualifier::Generator(
+                    Pattern::ConPat(ident("Just"), vec![Pattern::VarPat(ident("x"))]),
+                    var_expr("m"),
+                ),
+                Qualifier::LetQual(vec![Stmt::LetStmt(ident("y"), var_expr("x"))]),
+                Qualifier::Guard(call("print", "y")),
+                Qualifier::Guard(call("return", "y")),
+            ]),
+        )];
+        assert_layout_with_program(input, program);
+        assert_input_with_program(
+            "do { x <- m; f x }",
+            vec![Stmt::ExprStmt(Expr::DoExpr(vec![
+                Qualifier::Generator(Pattern::VarPat(ident("x")), var_expr("m")),
+                Qualifier::Guard(call("f", "x")),
+            ]))],
+        );
+        // the last statement has to be an expression
+        let (_, r) = Lexer::lex_tokens("do { x <- m }").unwrap();
+        assert!(Parser::parse_tokens(Tokens::new(&r)).is_err());
+    }
+
+    #[test]
+    fn modules() {
+        let input = "module Data.Queue (Queue, Tree(..), Op((:+), Lit), empty, module M) where\n\
+                     import qualified Data.Map as Map\n\
+                     import Data.List (sortBy)\n\
+                     import Prelude hiding (lookup)\n\
+                     empty = Map.empty";
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, module) = Parser::parse_module(Tokens::with_spans(&r, &spans)).unwrap();
+        let module = Unlocate.fold_module(module);
+        let import = |module: &str| Import {
+            module: ident(module),
+            qualified: false,
+            alias: None,
+            hiding: false,
+            entities: None,
+        };
+        let expected = Module {
+            name: ident("Data.Queue"),
+            exports: Some(vec![
+                Entity::Type(ident("Queue"), Members::Abstract),
+                Entity::Type(ident("Tree"), Members::All),
+                Entity::Type(ident("Op"), Members::Listed(vec![ident(":+"), ident("Lit")])),
+                Entity::Var(ident("empty")),
+                Entity::Module(ident("M")),
+            ]),
+            imports: vec![
+                Import {
+                    qualified: true,
+                    alias: Some(ident("Map")),
+                    ..import("Data.Map")
+                },
+                Import {
+                    entities: Some(vec![Entity::Var(ident("sortBy"))]),
+                    ..import("Data.List")
+                },
+                Import {
+                    hiding: true,
+                    entities: Some(vec![Entity::Var(ident("lookup"))]),
+                    ..import("Prelude")
+                },
+            ],
+            body: vec![Stmt::LetStmt(ident("empty"), var_expr("Map.empty"))],
+        };
+        assert_eq!(module, expected);
+        // a file without a header is the module `Main`
+        let (_, r) = Lexer::lex_tokens("import A; x").unwrap();
+        let (_, module) = Parser::parse_module(Tokens::new(&r)).unwrap();
+        assert_eq!(module.name, ident("Main"));
+        assert_eq!(module.imports, vec![import("A")]);
+        assert_eq!(module.body, vec![Stmt::ExprStmt(var_expr("x"))]);
+    }
+
+    #[test]
+    fn error_recovery() {
+        let int = |i| Expr::LitExpr(Literal::IntLiteral(i));
+        let input = "f x = (1 + 2\ng = 3\nh = [1, 2\nk = 4";
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (program, errors) = Parser::parse_tokens_recovering(Tokens::with_spans(&r, &spans));
+        let program = Unlocate.fold_program(program);
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "2:1: unexpected `g`, expected one of `)`, a backquote, an operator",
+                "4:1: unexpected `k`, expected one of `..`, `]`",
+            ]
+        );
+        assert_eq!(errors[0].expected[0], Expected::Token(Token::RParen));
+        assert_eq!(
+            program,
+            vec![
+                Stmt::ErrorStmt(errors[0].clone()),
+                Stmt::LetStmt(ident("g"), int(3)),
+                Stmt::ErrorStmt(errors[1].clone()),
+                Stmt::LetStmt(ident("k"), int(4)),
+            ]
+        );
+        // an item that ends early, and the end of the input
+        let (_, r) = Lexer::lex_tokens("let x = 5 ); let y = (;").unwrap();
+        let (_, errors) = Parser::parse_tokens_recovering(Tokens::new(&r));
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages[0], "unexpected `)`");
+        assert!(messages[1].starts_with("unexpected `;`, expected one of `,`"));
+        assert!(messages[1].contains("an operator, `)`, a literal"));
+
+        let input = "module M (x where\nimport A\nimport\ny = 1";
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (module, errors) = Parser::parse_module_recovering(Tokens::with_spans(&r, &spans));
+        let module = Unlocate.fold_module(module);
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "1:13: unexpected `where`, expected `)`",
+                "4:1: unexpected `y`, expected a constructor",
+            ]
+        );
+        assert_eq!(module.name, ident("Main"));
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.body, vec![Stmt::LetStmt(ident("y"), int(1))]);
+        // without errors, recovering parses like `parse_module`
+        let (_, r) = Lexer::lex_tokens("import A; x").unwrap();
+        let (module, errors) = Parser::parse_module_recovering(Tokens::new(&r));
+        assert!(errors.is_empty());
+        assert_eq!(module.body, vec![Stmt::ExprStmt(var_expr("x"))]);
+    }
+
+    #[test]
+    fn syntax_extensions() {
+        let parse_with = |input: &str| {
+            let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let (program, errors) = Parser::parse_tokens_recovering(Tokens::with_spans(&r, &spans));
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            let program = Fixities::new().resolve_program(program).unwrap();
+            (Unlocate.fold_program(program), messages)
+        };
+        let int = |i| Expr::LitExpr(Literal::IntLiteral(i));
+        let apply = |function, argument| Expr::CallExpr {
+            function: Box::new(function),
+            arguments: vec![argument],
+        };
+        let input = "{-# LANGUAGE LambdaCase, MultiWayIf, TupleSections, BlockArguments #-}\n\
+                     f = \\case\n  0 -> 1\n  _ -> 2\n\
+                     g x = if | x -> 1\n         | otherwise -> 2\n\
+                     h = (, 1, )\n\
+                     k = when x do\n  y";
+        let alt = |pat, expr| ast::Alt {
+            pat,
+            rhs: Rhs::Plain(expr),
+            wheres: vec![],
+        };
+        let clause = |params, expr| Clause {
+            params,
+            rhs: Rhs::Plain(expr),
+            wheres: vec![],
+        };
+        let program = vec![
+            Stmt::LetStmt(
+                ident("f"),
+                Expr::LambdaCaseExpr(vec![
+                    alt(Pattern::LitPat(Literal::IntLiteral(0)), int(1)),
+                    alt(Pattern::WildPat, int(2)),
+                ]),
+            ),
+            Stmt::FunStmt(
+                ident("g"),
+                vec![clause(
+                    vec![Pattern::VarPat(ident("x"))],
+                    Expr::MultiIfExpr(vec![
+                        (var_expr("x"), int(1)),
+                        (var_expr("otherwise"), int(2)),
+                    ]),
+                )],
+            ),
+            Stmt::LetStmt(ident("h"), Expr::TupleSectionExpr(vec![None, Some(int(1)), None])),
+            Stmt::LetStmt(
+                ident("k"),
+                apply(
+                    apply(var_expr("when"), var_expr("x")),
+                    Expr::DoExpr(vec![Qualifier::Guard(var_expr("y"))]),
+                ),
+            ),
+        ];
+        assert_eq!(parse_with(input), (program, vec![]));
+        // without the pragma
+        let (_, messages) = parse_with("f = \\case { _ -> 1 }\ng = (1,)\nk = forM xs \\x -> x");
+        assert_eq!(
+            messages,
+            vec![
+                "1:6: unexpected `case`; enable extension LambdaCase with \
+                 {-# LANGUAGE LambdaCase #-}",
+                "2:5: unexpected `(`; enable extension TupleSections with \
+                 {-# LANGUAGE TupleSections #-}",
+                "3:13: unexpected `\\`; enable extension BlockArguments with \
+                 {-# LANGUAGE BlockArguments #-}",
+            ]
+        );
+        let (_, messages) = parse_with("{-# LANGUAGE Foo #-}\nx = if | True -> 1");
+        assert_eq!(
+            messages,
+            vec![
+                "1:1: unexpected `Foo`, expected one of LambdaCase, MultiWayIf, \
+                 TupleSections, BlockArguments, NegativeLiterals, ScopedTypeVariables",
+                "2:8: unexpected `|`; enable extension MultiWayIf with \
+                 {-# LANGUAGE MultiWayIf #-}",
+            ]
+        );
+    }
+
+    #[test]
+    fn expression_spans() {
+        // each located expression as written, with where it starts
+        struct Spans(Vec<String>);
+        impl<'ast> Visitor<'ast> for Spans {
+            fn visit_expr(&mut self, expr: &'ast Expr) {
+                if let Expr::Located(span, inner) = expr {
+                    let text = Printer::new().expr(inner);
+                    self.0.push(format!("{}:{} {}", span.line, span.column, text));
+                }
+                visit_expr(self, expr)
+            }
+        }
+        let input = "f = (x, g x)\n  where g y = -y + 1 :: Int";
+        let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&r, &spans)).unwrap();
+        let mut spans = Spans(vec![]);
+        spans.visit_program(&Fixities::new().resolve_program(program).unwrap());
+        assert_eq!(
+            spans.0,
+            vec![
+                "1:5 (x, g x)",
+                "1:6 x",
+                "1:9 g x",
+                "1:9 g",
+                "1:11 x",
+                "2:15 - y + 1 :: Int",
+                "2:15 - y + 1",
+                "2:15 - y",
+                "2:16 y",
+                "2:20 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn negation() {
+        let parse_with = |input: &str| {
+            let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let (_, program) = Parser::parse_tokens(Tokens::with_spans(&r, &spans)).unwrap();
+            Fixities::new().resolve_program(program).map(|program| Unlocate.fold_program(program))
+        };
+        let int = |i| Box::new(Expr::LitExpr(Literal::IntLiteral(i)));
+        let var = |name: &str| Box::new(var_expr(name));
+        let negate = |expr| Expr::PrefixExpr(Prefix::PrefixMinus, expr);
+        let infix = |op: &str, left, right| Expr::InfixExpr(Infix::from_name(op), left, right);
+        let stmts = |exprs: Vec<Expr>| Ok(exprs.into_iter().map(Stmt::ExprStmt).collect());
+        assert_eq!(
+            parse_with("f -1; x-1; - 2 ^ 2; a == - b"),
+            stmts(vec![
+                infix("-", var("f"), int(1)),
+                infix("-", var("x"), int(1)),
+                negate(Box::new(infix("^", int(2), int(2)))),
+                infix("==", var("a"), Box::new(negate(var("b")))),
+            ])
+        );
+        assert_eq!(
+            parse_with("{-# LANGUAGE NegativeLiterals #-}\nf -1; x-1; -2 ^ 2; - 2 ^ 2"),
+            stmts(vec![
+                Expr::CallExpr {
+                    function: var("f"),
+                    arguments: vec![*int(-1)],
+                },
+                infix("-", var("x"), int(1)),
+                infix("^", int(-2), int(2)),
+                negate(Box::new(infix("^", int(2), int(2)))),
+            ])
+        );
+        assert_eq!(
+            parse_with("1 + -3"),
+            Err("Precedence parsing error: cannot mix `+` [infixl 6] and prefix `-` \
+                 [infixl 6] in the same infix expression"
+                .to_owned())
+        );
+        assert!(parse_with("a * - b").is_err());
+    }
+
+    #[test]
+    fn type_annotations() {
+        let parse_with = |input: &str| {
+            let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let (program, errors) = Parser::parse_tokens_recovering(Tokens::with_spans(&r, &spans));
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            let program = Fixities::new().resolve_program(program).unwrap();
+            (Unlocate.fold_program(program), messages)
+        };
+        let con = |name: &str| Type::ConType(ident(name));
+        let var = |name: &str| Type::VarType(ident(name));
+        let list = |ty| Type::ListType(Box::new(ty));
+        let typed =
+            |expr, context, ty| Stmt::ExprStmt(Expr::TypedExpr(Box::new(expr), context, ty));
+        let read = Expr::CallExpr {
+            function: Box::new(var_expr("read")),
+            arguments: vec![var_expr("s")],
+        };
+        let eq = Pred {
+            class: ident("Eq"),
+            ty: var("a"),
+        };
+        assert_eq!(
+            parse_with("read s :: Int; (xs :: Eq a => [a]); x + y :: Int"),
+            (
+                vec![
+                    typed(read, vec![], con("Int")),
+                    typed(var_expr("xs"), vec![eq.clone()], list(var("a"))),
+                    typed(
+                        Expr::InfixExpr(
+                            Infix::Plus,
+                            Box::new(var_expr("x")),
+                            Box::new(var_expr("y"))
+                        ),
+                        vec![],
+                        con("Int")
+                    ),
+                ],
+                vec![]
+            )
+        );
+        let input = "{-# LANGUAGE ScopedTypeVariables #-}\n\
+                     f :: forall a. Eq a => a -> [a]\n\
+                     g = \\(x :: Int) -> x";
+        let program = vec![
+            Stmt::SigStmt(
+                vec![ident("f")],
+                vec![eq],
+                Type::ForallType(
+                    vec![ident("a")],
+                    Box::new(Type::FnType(Box::new(var("a")), Box::new(list(var("a"))))),
+                ),
+                None,
+            ),
+            Stmt::LetStmt(
+                ident("g"),
+                Expr::LambdaExpr {
+                    params: vec![Pattern::SigPat(
+                        Box::new(Pattern::VarPat(ident("x"))),
+                        con("Int"),
+                    )],
+                    body: Box::new(var_expr("x")),
+                },
+            ),
+        ];
+        assert_eq!(parse_with(input), (program, vec![]));
+        let (_, messages) = parse_with("f :: forall a. a\ng = \\(x :: Int) -> x");
+        assert_eq!(
+            messages,
+            vec![
+                "1:6: unexpected `forall`; enable extension ScopedTypeVariables with \
+                 {-# LANGUAGE ScopedTypeVariables #-}",
+                "2:6: unexpected `(`; enable extension ScopedTypeVariables with \
+                 {-# LANGUAGE ScopedTypeVariables #-}",
+            ]
+        );
+    }
+
+    #[test]
+    fn typed_holes() {
+        let arguments = [
+            Expr::HoleExpr(ident("_")),
+            Expr::HoleExpr(ident("_acc")),
+            var_expr("x_"),
+        ];
+        let call = arguments.into_iter().fold(var_expr("f"), |function, argument| {
+            Expr::CallExpr {
+                function: Box::new(function),
+                arguments: vec![argument],
+            }
+        });
+        let program = vec![Stmt::ExprStmt(call)];
+        assert_layout_with_program("f _ _acc x_", program);
+    }
+
+    #[test]
+    fn sections() {
+        let int = |i| Box::new(Expr::LitExpr(Literal::IntLiteral(i)));
+        let div = Infix::Op(ident("div"));
+        let input = "[(+ 1), (2 *), (`div` 2), (x `div`), (- 1), (+), (`elem`)]";
+        let program = vec![Stmt::ExprStmt(Expr::ArrayExpr(vec![
+            Expr::RightSectionExpr(Infix::Plus, int(1)),
+            Expr::LeftSectionExpr(int(2), Infix::Multiply),
+            Expr::RightSectionExpr(div.clone(), int(2)),
+            Expr::LeftSectionExpr(Box::new(var_expr("x")), div),
+            Expr::PrefixExpr(Prefix::PrefixMinus, int(1)),
+            var_expr("+"),
+            var_expr("elem"),
+        ]))];
+        assert_layout_with_program(input, program);
+        compare_inputs("(a + b *)", "((a + b) *)");
+        compare_inputs("map (+ 1) xs", "(map (+ 1)) xs");
+    }
+
+    #[test]
+    fn backtick_operators() {
+        compare_inputs("a `div` b + 1", "(a `div` b) + 1");
+        compare_inputs("a `div` b `mod` c", "(a `div` b) `mod` c");
+        compare_inputs("f x `elem` xs", "(f x) `elem` xs");
+        let input = "x `elem` (y:ys) = True";
+        let program = vec![Stmt::FunStmt(
+            ident("elem"),
+            vec![Clause {
+                params: vec![
+                    Pattern::VarPat(ident("x")),
+                    Pattern::ConPat(
+                        ident(":"),
+                        vec![Pattern::VarPat(ident("y")), Pattern::VarPat(ident("ys"))],
+                    ),
+                ],
+                rhs: Rhs::Plain(var_expr("True")),
+                wheres: vec![],
+            }],
+        )];
+        assert_layout_with_program(input, program);
+    }
+
+    #[test]
+    fn fixity_declarations() {
+        let fixity = |assoc, precedence| Fixity { assoc, precedence };
+        let input = "infixl 6 <+>, `plus`\ninfixr 5 +++\ninfix `elem`";
+        let program = vec![
+            Stmt::FixityStmt(fixity(Assoc::Left, 6), vec![ident("<+>"), ident("plus")]),
+            Stmt::FixityStmt(fixity(Assoc::Right, 5), vec![ident("+++")]),
+            Stmt::FixityStmt(fixity(Assoc::Non, 9), vec![ident("elem")]),
+        ];
+        assert_layout_with_program(input, program);
+        compare_inputs("infixr 6 <+>; a <+> b <+> c", "infixr 6 <+>; a <+> (b <+> c)");
+        compare_inputs("a <+> b <+> c", "(a <+> b) <+> c");
+        compare_inputs("infixr 0 `f`; a `f` b + c `f` d", "infixr 0 `f`; a `f` ((b + c) `f` d)");
+        compare_inputs(
+            "let { infixr 6 <+> } in a <+> b <+> c",
+            "let { infixr 6 <+> } in a <+> (b <+> c)",
+        );
+        compare_inputs(
+            "(let { infixr 6 <+> } in a) <+> b <+> c",
+            "((let { infixr 6 <+> } in a) <+> b) <+> c",
+        );
+    }
 }
@@ -0,0 +1,1152 @@
+use crate::parser::ast::*;
+use crate::parser::fixity::Fixities;
+use std::fmt;
+
+/// Prints ASTs as source text that parses back to the same AST once its
+/// operator chains are associated by `Fixities`. Operands are parenthesized
+/// only where the fixities in scope require it, and nested blocks are
+/// written with explicit braces, so that every top-level item or clause is a
+/// single line. ASTs the parser can't produce, such as a call whose single
+/// argument is a tuple or a right section of `-`, have no such source.
+pub struct Printer {
+    fixities: Fixities,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Printer {
+    /// A printer with the fixities of the Prelude operators.
+    pub fn new() -> Self {
+        Printer::with_fixities(Fixities::new())
+    }
+
+    pub fn with_fixities(fixities: Fixities) -> Self {
+        Printer { fixities }
+    }
+
+    /// A program, one top-level item or equation per line. Its fixity
+    /// declarations apply to the whole program, as in `resolve_program`.
+    pub fn program(&mut self, program: &Program) -> String {
+        self.fixities.declare(program);
+        self.lines(program).join("\n")
+    }
+
+    pub fn module(&mut self, module: &Module) -> String {
+        let mut lines = vec![match &module.exports {
+            Some(exports) => format!("module {} {} where", module.name.0, entities(exports)),
+            None => format!("module {} where", module.name.0),
+        }];
+        lines.extend(module.imports.iter().map(import));
+        if !module.body.is_empty() {
+            lines.push(self.program(&module.body));
+        }
+        lines.join("\n")
+    }
+
+    /// A statement or declaration. The equations of a function are on
+    /// separate lines.
+    pub fn stmt(&mut self, stmt: &Stmt) -> String {
+        self.decl(stmt).join("\n")
+    }
+
+    pub fn expr(&mut self, expr: &Expr) -> String {
+        match expr.unlocated() {
+            Expr::IdentExpr(name) | Expr::HoleExpr(name) => var(name),
+            Expr::LitExpr(literal) => literal.to_string(),
+            Expr::PrefixExpr(Prefix::PrefixMinus, operand) => {
+                format!("- {}", self.infix_operand(operand, Fixity::NEGATION, Assoc::Right))
+            }
+            Expr::InfixExpr(op, left, right) => {
+                let fixity = self.fixities.get(op.name());
+                format!(
+                    "{} {} {}",
+                    self.infix_operand(left, fixity, Assoc::Left),
+                    infix(op),
+                    self.infix_operand(right, fixity, Assoc::Right)
+                )
+            }
+            Expr::InfixChain(first, rest) => {
+                let mut text = self.chain_operand(first);
+                for (op, operand) in rest {
+                    let operand = self.chain_operand(operand);
+                    text.push_str(&format!(" {} {}", infix(op), operand));
+                }
+                text
+            }
+            Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            } => match (consequence.as_slice(), alternative.as_deref()) {
+                ([Stmt::ExprStmt(then)], Some([Stmt::ExprStmt(otherwise)])) => {
+                    // an `if` without `else` would take this one
+                    let then = match then.unlocated() {
+                        Expr::IfExpr {
+                            alternative: None, ..
+                        } => format!("({})", self.expr(then)),
+                        then => self.expr(then),
+                    };
+                    format!(
+                        "if {} then {} else {}",
+                        self.expr(cond),
+                        then,
+                        self.expr(otherwise)
+                    )
+                }
+                (_, alternative) => {
+                    let mut text =
+                        format!("if ({}) {}", self.expr(cond), self.statements(consequence));
+                    if let Some(alternative) = alternative {
+                        text.push_str(&format!(" else {}", self.statements(alternative)));
+                    }
+                    text
+                }
+            },
+            Expr::FnExpr { params, body } => {
+                let params: Vec<&str> = params.iter().map(|Ident(name)| name.as_str()).collect();
+                format!("fn({}) {}", params.join(", "), self.statements(body))
+            }
+            Expr::CallExpr {
+                function,
+                arguments,
+            } => {
+                // `parse_apply_expr` only applies expressions that can't
+                // extend to the right, even in parentheses
+                let applicable = !matches!(
+                    function.unlocated(),
+                    Expr::PrefixExpr(..)
+                        | Expr::IfExpr { .. }
+                        | Expr::MultiIfExpr(_)
+                        | Expr::LambdaCaseExpr(_)
+                        | Expr::FnExpr { .. }
+                        | Expr::HashExpr(_)
+                        | Expr::LambdaExpr { .. }
+                        | Expr::CaseExpr { .. }
+                        | Expr::LetExpr { .. }
+                        | Expr::DoExpr(_)
+                );
+                let function = self.operand(function);
+                match arguments.as_slice() {
+                    [argument] if applicable => {
+                        format!("{} {}", function, self.argument(argument))
+                    }
+                    arguments => format!("{} ({})", function, self.exprs(arguments)),
+                }
+            }
+            Expr::ArrayExpr(exprs) => format!("[{}]", self.exprs(exprs)),
+            Expr::HashExpr(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, self.expr(value)))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            Expr::IndexExpr { array, index } => {
+                format!("{}[{}]", self.operand(array), self.expr(index))
+            }
+            Expr::LambdaExpr { params, body } => {
+                let params: Vec<String> = params.iter().map(apat).collect();
+                format!("\\{} -> {}", params.join(" "), self.expr(body))
+            }
+            Expr::CaseExpr { scrutinee, alts } => {
+                let scrutinee = self.expr(scrutinee);
+                let alts: Vec<String> = alts.iter().map(|alt| self.alt(alt)).collect();
+                format!("case {} of {}", scrutinee, block(&alts))
+            }
+            Expr::LambdaCaseExpr(alts) => {
+                let alts: Vec<String> = alts.iter().map(|alt| self.alt(alt)).collect();
+                format!("\\case {}", block(&alts))
+            }
+            Expr::MultiIfExpr(guards) => {
+                let guards: Vec<String> = guards
+                    .iter()
+                    .map(|(guard, expr)| format!("| {} -> {}", self.expr(guard), self.expr(expr)))
+                    .collect();
+                format!("if {}", guards.join(" "))
+            }
+            Expr::LetExpr { bindings, body } => {
+                self.fixities.push_scope(bindings);
+                let text = format!("let {} in {}", self.block(bindings), self.expr(body));
+                self.fixities.pop_scope();
+                text
+            }
+            Expr::TupleExpr(exprs) => format!("({})", self.exprs(exprs)),
+            Expr::TupleSectionExpr(components) => {
+                let components: Vec<String> = components
+                    .iter()
+                    .map(|component| match component {
+                        Some(expr) => self.expr(expr),
+                        None => String::new(),
+                    })
+                    .collect();
+                format!("({})", components.join(", "))
+            }
+            Expr::LeftSectionExpr(operand, op) => {
+                let fixity = self.fixities.get(op.name());
+                let operand = self.infix_operand(operand, fixity, Assoc::Left);
+                format!("({} {})", operand, infix(op))
+            }
+            Expr::RightSectionExpr(op, operand) => {
+                let fixity = self.fixities.get(op.name());
+                let operand = self.infix_operand(operand, fixity, Assoc::Right);
+                format!("({} {})", infix(op), operand)
+            }
+            Expr::ListCompExpr { expr, qualifiers } => {
+                let expr = self.expr(expr);
+                let qualifiers: Vec<String> =
+                    qualifiers.iter().map(|q| self.qualifier(q)).collect();
+                format!("[{} | {}]", expr, qualifiers.join(", "))
+            }
+            Expr::DoExpr(stmts) => {
+                let stmts: Vec<String> = stmts.iter().map(|q| self.qualifier(q)).collect();
+                format!("do {}", block(&stmts))
+            }
+            Expr::TypedExpr(expr, context, ty) => {
+                let expr = match expr.unlocated() {
+                    Expr::InfixExpr(..) | Expr::PrefixExpr(..) | Expr::InfixChain(..) => {
+                        self.expr(expr)
+                    }
+                    expr => self.operand(expr),
+                };
+                format!("{} :: {}", expr, signature(context, ty))
+            }
+            Expr::DictExpr(dict) => dict.to_string(),
+            Expr::Located(_, expr) => self.expr(expr),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) -> String {
+        let exprs: Vec<String> = exprs.iter().map(|expr| self.expr(expr)).collect();
+        exprs.join(", ")
+    }
+
+    // An expression followed by an argument, an index or nothing, as the
+    // operand of a prefix operator: it can't extend to the right
+    fn operand(&mut self, expr: &Expr) -> String {
+        match expr.unlocated() {
+            Expr::LitExpr(literal) if !is_negative(literal) => self.expr(expr),
+            Expr::IdentExpr(_)
+            | Expr::HoleExpr(_)
+            | Expr::DictExpr(_)
+            | Expr::CallExpr { .. }
+            | Expr::IndexExpr { .. }
+            | Expr::ArrayExpr(_)
+            | Expr::TupleExpr(_)
+            | Expr::TupleSectionExpr(_)
+            | Expr::LeftSectionExpr(..)
+            | Expr::RightSectionExpr(..)
+            | Expr::ListCompExpr { .. } => self.expr(expr),
+            expr => format!("({})", self.expr(expr)),
+        }
+    }
+
+    // An argument applied by juxtaposition, which parenthesized is a call
+    // with the components of a tuple as arguments
+    fn argument(&mut self, expr: &Expr) -> String {
+        match expr.unlocated() {
+            Expr::LitExpr(literal) if !is_negative(literal) => self.expr(expr),
+            Expr::IdentExpr(_)
+            | Expr::HoleExpr(_)
+            | Expr::DictExpr(_)
+            | Expr::TupleExpr(_)
+            | Expr::TupleSectionExpr(_)
+            | Expr::LeftSectionExpr(..)
+            | Expr::RightSectionExpr(..) => self.expr(expr),
+            expr => format!("({})", self.expr(expr)),
+        }
+    }
+
+    // An operand of an operator with the given fixity, on the given side
+    fn infix_operand(&mut self, expr: &Expr, outer: Fixity, side: Assoc) -> String {
+        match expr.unlocated() {
+            Expr::InfixExpr(op, ..) => {
+                let inner = self.fixities.get(op.name());
+                let associates = inner.precedence == outer.precedence
+                    && inner.assoc == side
+                    && outer.assoc == side;
+                match inner.precedence > outer.precedence || associates {
+                    true => self.expr(expr),
+                    false => format!("({})", self.expr(expr)),
+                }
+            }
+            // a negation takes the operators binding tighter than `-` to
+            // its right, and can't follow one binding as tightly
+            Expr::PrefixExpr(..) => {
+                let negation = Fixity::NEGATION;
+                let bare = outer.precedence < negation.precedence
+                    || (side == Assoc::Left && outer == negation);
+                match bare {
+                    true => self.expr(expr),
+                    false => format!("({})", self.expr(expr)),
+                }
+            }
+            expr => self.chain_operand(expr),
+        }
+    }
+
+    fn chain_operand(&mut self, expr: &Expr) -> String {
+        match expr.unlocated() {
+            Expr::PrefixExpr(..) => self.expr(expr),
+            expr => self.operand(expr),
+        }
+    }
+
+    // The lines of the declarations of a program
+    fn lines(&mut self, program: &[Stmt]) -> Vec<String> {
+        program.iter().flat_map(|stmt| self.decl(stmt)).collect()
+    }
+
+    // Declarations between braces, separated by semicolons
+    fn block(&mut self, decls: &Program) -> String {
+        block(&self.lines(decls))
+    }
+
+    // The statements of a `fn` or `if` body, each ended by a semicolon
+    fn statements(&mut self, stmts: &[Stmt]) -> String {
+        match self.lines(stmts).as_slice() {
+            [] => "{}".to_owned(),
+            lines => format!("{{ {}; }}", lines.join("; ")),
+        }
+    }
+
+    fn decl(&mut self, stmt: &Stmt) -> Vec<String> {
+        let line = match stmt {
+            Stmt::LetStmt(name, expr) => format!("{} = {}", var(name), self.expr(expr)),
+            Stmt::ReturnStmt(expr) => format!("return {}", self.expr(expr)),
+            // `x :: t` alone would be a signature
+            Stmt::ExprStmt(expr @ Expr::TypedExpr(..)) => format!("({})", self.expr(expr)),
+            Stmt::ExprStmt(expr) => self.expr(expr),
+            Stmt::SigStmt(names, context, ty, _) => {
+                let names: Vec<String> = names.iter().map(var).collect();
+                format!("{} :: {}", names.join(", "), signature(context, ty))
+            }
+            Stmt::FunStmt(name, clauses) => {
+                return clauses
+                    .iter()
+                    .map(|clause| self.clause(name, clause))
+                    .collect()
+            }
+            Stmt::PatStmt(pat, rhs, wheres) => {
+                self.fixities.push_scope(wheres);
+                let rhs = self.rhs(rhs, "=");
+                let wheres = self.wheres(wheres);
+                self.fixities.pop_scope();
+                format!("{} {}{}", pattern(pat), rhs, wheres)
+            }
+            Stmt::DataStmt(data) => {
+                let mut line = format!("data {}", data.name.0);
+                for Ident(param) in &data.params {
+                    line.push_str(&format!(" {}", param));
+                }
+                let constructors: Vec<String> = data.constructors.iter().map(con_decl).collect();
+                if !constructors.is_empty() {
+                    line.push_str(&format!(" = {}", constructors.join(" | ")));
+                }
+                if !data.deriving.is_empty() {
+                    let classes: Vec<&str> = data.deriving.iter().map(|c| c.0.as_str()).collect();
+                    line.push_str(&format!(" deriving ({})", classes.join(", ")));
+                }
+                line
+            }
+            Stmt::TypeStmt(Ident(name), params, ty) => {
+                let mut line = format!("type {}", name);
+                for Ident(param) in params {
+                    line.push_str(&format!(" {}", param));
+                }
+                format!("{} = {}", line, ty)
+            }
+            Stmt::ClassStmt(class) => format!(
+                "class {}{} {}{}",
+                qualified(&class.context),
+                class.name.0,
+                class.var.0,
+                self.wheres(&class.body)
+            ),
+            Stmt::InstanceStmt(instance) => format!(
+                "instance {}{} {}{}",
+                qualified(&instance.context),
+                instance.class.0,
+                atype(&instance.ty),
+                self.wheres(&instance.body)
+            ),
+            Stmt::DerivingStmt(instance) => format!(
+                "deriving instance {}{} {}",
+                qualified(&instance.context),
+                instance.class.0,
+                atype(&instance.ty)
+            ),
+            Stmt::FixityStmt(fixity, names) => {
+                let ops: Vec<String> = names
+                    .iter()
+                    .map(|n| infix(&Infix::from_name(&n.0)))
+                    .collect();
+                format!("{} {}", fixity, ops.join(", "))
+            }
+            Stmt::DefaultStmt(types) => {
+                let types: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+                format!("default ({})", types.join(", "))
+            }
+            Stmt::ErrorStmt(error) => format!("{{- {} -}}", error),
+        };
+        vec![line]
+    }
+
+    fn clause(&mut self, name: &Ident, clause: &Clause) -> String {
+        let mut line = var(name);
+        for param in &clause.params {
+            line.push_str(&format!(" {}", apat(param)));
+        }
+        self.fixities.push_scope(&clause.wheres);
+        let rhs = self.rhs(&clause.rhs, "=");
+        let wheres = self.wheres(&clause.wheres);
+        self.fixities.pop_scope();
+        format!("{} {}{}", line, rhs, wheres)
+    }
+
+    fn alt(&mut self, alt: &Alt) -> String {
+        self.fixities.push_scope(&alt.wheres);
+        let rhs = self.rhs(&alt.rhs, "->");
+        let wheres = self.wheres(&alt.wheres);
+        self.fixities.pop_scope();
+        format!("{} {}{}", pattern(&alt.pat), rhs, wheres)
+    }
+
+    fn rhs(&mut self, rhs: &Rhs, separator: &str) -> String {
+        match rhs {
+            Rhs::Plain(expr) => format!("{} {}", separator, self.expr(expr)),
+            Rhs::Guarded(guards) => {
+                let guards: Vec<String> = guards
+                    .iter()
+                    .map(|(guard, expr)| {
+                        // `| e :: a -> b` would read the alternative's arrow as part of the type
+                        let guard = match guard {
+                            Expr::TypedExpr(..) => format!("({})", self.expr(guard)),
+                            _ => self.expr(guard),
+                        };
+                        format!("| {} {} {}", guard, separator, self.expr(expr))
+                    })
+                    .collect();
+                guards.join(" ")
+            }
+        }
+    }
+
+    fn wheres(&mut self, decls: &Program) -> String {
+        match decls.is_empty() {
+            true => String::new(),
+            false => format!(" where {}", self.block(decls)),
+        }
+    }
+
+    fn qualifier(&mut self, qualifier: &Qualifier) -> String {
+        match qualifier {
+            Qualifier::Generator(pat, expr) => format!("{} <- {}", pattern(pat), self.expr(expr)),
+            Qualifier::Guard(expr) => self.expr(expr),
+            Qualifier::LetQual(decls) => {
+                self.fixities.push_scope(decls);
+                let text = format!("let {}", self.block(decls));
+                self.fixities.pop_scope();
+                text
+            }
+        }
+    }
+}
+
+fn block(items: &[String]) -> String {
+    match items {
+        [] => "{}".to_owned(),
+        items => format!("{{ {} }}", items.join("; ")),
+    }
+}
+
+// Operators are made of the symbol characters of the lexer
+fn is_symbolic(name: &str) -> bool {
+    name.starts_with(|c: char| "!#$%&*+./<=>?@\\^|-~:".contains(c))
+}
+
+// A name where an expression or pattern is expected: `x` or `(+)`
+fn var(name: &Ident) -> String {
+    match is_symbolic(&name.0) {
+        true => format!("({})", name.0),
+        false => name.0.clone(),
+    }
+}
+
+// An operator between its operands: `+` or `` `div` ``
+fn infix(op: &Infix) -> String {
+    match op.name() {
+        name if is_symbolic(name) => name.to_owned(),
+        name => format!("`{}`", name),
+    }
+}
+
+fn entities(entities: &[Entity]) -> String {
+    let entities: Vec<String> = entities
+        .iter()
+        .map(|entity| match entity {
+            Entity::Var(name) => var(name),
+            Entity::Type(Ident(name), Members::Abstract) => name.clone(),
+            Entity::Type(Ident(name), Members::All) => format!("{}(..)", name),
+            Entity::Type(Ident(name), Members::Listed(members)) => {
+                let members: Vec<String> = members.iter().map(var).collect();
+                format!("{}({})", name, members.join(", "))
+            }
+            Entity::Module(Ident(name)) => format!("module {}", name),
+        })
+        .collect();
+    format!("({})", entities.join(", "))
+}
+
+fn import(import: &Import) -> String {
+    let mut line = "import".to_owned();
+    if import.qualified {
+        line.push_str(" qualified");
+    }
+    line.push_str(&format!(" {}", import.module.0));
+    if let Some(Ident(alias)) = &import.alias {
+        line.push_str(&format!(" as {}", alias));
+    }
+    if import.hiding {
+        line.push_str(" hiding");
+    }
+    if let Some(listed) = &import.entities {
+        line.push_str(&format!(" {}", entities(listed)));
+    }
+    line
+}
+
+// Patterns, at the levels of `parse_pattern`, `parse_pattern_app` and
+// `parse_apat`
+
+fn pattern(pat: &Pattern) -> String {
+    match pat {
+        Pattern::ConPat(_, args) if is_cons(pat) => {
+            format!("{} : {}", pattern_app(&args[0]), pattern(&args[1]))
+        }
+        pat => pattern_app(pat),
+    }
+}
+
+fn pattern_app(pat: &Pattern) -> String {
+    match pat {
+        Pattern::ConPat(con, args) if !args.is_empty() && !is_cons(pat) => {
+            let args: Vec<String> = args.iter().map(apat).collect();
+            format!("{} {}", var(con), args.join(" "))
+        }
+        Pattern::LitPat(literal) => literal.to_string(),
+        pat => apat(pat),
+    }
+}
+
+fn is_cons(pat: &Pattern) -> bool {
+    matches!(pat, Pattern::ConPat(Ident(con), args) if con == ":" && args.len() == 2)
+}
+
+fn apat(pat: &Pattern) -> String {
+    match pat {
+        Pattern::WildPat => "_".to_owned(),
+        Pattern::VarPat(name) => var(name),
+        Pattern::LitPat(literal) if !is_negative(literal) => literal.to_string(),
+        Pattern::ConPat(Ident(con), args) if args.is_empty() => con.clone(),
+        Pattern::ListPat(pats) => format!("[{}]", patterns(pats)),
+        Pattern::TuplePat(pats) => format!("({})", patterns(pats)),
+        Pattern::AsPat(Ident(name), pat) => format!("{}@{}", name, apat(pat)),
+        Pattern::SigPat(pat, ty) => format!("({} :: {})", pattern(pat), ty),
+        pat => format!("({})", pattern(pat)),
+    }
+}
+
+fn patterns(pats: &[Pattern]) -> String {
+    let pats: Vec<String> = pats.iter().map(pattern).collect();
+    pats.join(", ")
+}
+
+fn is_negative(literal: &Literal) -> bool {
+    match literal {
+        Literal::IntLiteral(i) => *i < 0,
+        Literal::FloatLiteral(f) => f.is_sign_negative(),
+        _ => false,
+    }
+}
+
+// Types, at the levels of `parse_type`, `parse_btype` and `parse_atype`
+
+fn btype(ty: &Type) -> String {
+    match ty {
+        Type::AppType(function, arg) => format!("{} {}", btype(function), atype(arg)),
+        ty => atype(ty),
+    }
+}
+
+fn atype(ty: &Type) -> String {
+    match ty {
+        Type::VarType(Ident(name)) | Type::ConType(Ident(name)) => name.clone(),
+        Type::ListType(ty) => format!("[{}]", ty),
+        Type::TupleType(types) => {
+            let types: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+            format!("({})", types.join(", "))
+        }
+        ty => format!("({})", ty),
+    }
+}
+
+// The type of a signature, after its `forall` if any
+fn signature(context: &[Pred], ty: &Type) -> String {
+    match ty {
+        Type::ForallType(vars, ty) => {
+            let vars: Vec<&str> = vars.iter().map(|Ident(var)| var.as_str()).collect();
+            format!("forall {}. {}{}", vars.join(" "), qualified(context), ty)
+        }
+        ty => format!("{}{}", qualified(context), ty),
+    }
+}
+
+// A context and its `=>`, if any
+fn qualified(context: &[Pred]) -> String {
+    let preds: Vec<String> = context
+        .iter()
+        .map(|pred| format!("{} {}", pred.class.0, atype(&pred.ty)))
+        .collect();
+    match preds.as_slice() {
+        [] => String::new(),
+        [pred] => format!("{} => ", pred),
+        preds => format!("({}) => ", preds.join(", ")),
+    }
+}
+
+fn con_decl(con: &ConDecl) -> String {
+    if con.labels.is_empty() {
+        let fields: Vec<String> = con
+            .fields
+            .iter()
+            .map(|ty| format!(" {}", atype(ty)))
+            .collect();
+        return format!("{}{}", con.name.0, fields.concat());
+    }
+    let fields: Vec<String> = con
+        .labels
+        .iter()
+        .zip(&con.fields)
+        .map(|(label, ty)| format!("{} :: {}", var(label), ty))
+        .collect();
+    format!("{} {{ {} }}", con.name.0, fields.join(", "))
+}
+
+// Literals with the escapes of the lexer
+fn escape(c: char, quote: char) -> String {
+    match c {
+        '\n' => "\\n".to_owned(),
+        '\t' => "\\t".to_owned(),
+        '\r' => "\\r".to_owned(),
+        '\0' => "\\0".to_owned(),
+        '\\' => "\\\\".to_owned(),
+        c if c == quote => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::IntLiteral(i) => write!(f, "{}", i),
+            Literal::FloatLiteral(x) => write!(f, "{:?}", x),
+            Literal::BoolLiteral(b) => write!(f, "{}", b),
+            Literal::StringLiteral(s) => {
+                let chars: String = s.chars().map(|c| escape(c, '"')).collect();
+                write!(f, "\"{}\"", chars)
+            }
+            Literal::CharLiteral(c) => write!(f, "'{}'", escape(*c, '\'')),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::FnType(arg, result) => write!(f, "{} -> {}", btype(arg), result),
+            Type::ForallType(..) => write!(f, "{}", signature(&[], self)),
+            ty => write!(f, "{}", btype(ty)),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", pattern(self))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Printer::new().expr(self))
+    }
+}
+
+/// Dictionaries are named after GHC's: `$fShow[]` is the instance
+/// `Show [a]` and `$pEq d` the `Eq` dictionary within the dictionary `d`.
+impl fmt::Display for Dict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dict::Instance {
+                class,
+                tycon,
+                context,
+                ..
+            } if context.is_empty() => write!(f, "$f{}{}", class.0, tycon.0),
+            Dict::Instance {
+                class,
+                tycon,
+                context,
+                ..
+            } => {
+                let context: Vec<String> = context.iter().map(|d| d.to_string()).collect();
+                write!(f, "($f{}{} {})", class.0, tycon.0, context.join(" "))
+            }
+            Dict::Param(name) => write!(f, "{}", name.0),
+            Dict::Super(class, _, dict) => write!(f, "($p{} {})", class.0, dict),
+            Dict::Dynamic(class) => write!(f, "$dyn{}", class.0),
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Printer::new().stmt(self))
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Printer::new().module(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::{Extension, Extensions, Tokens};
+    use crate::lexer::Lexer;
+    use crate::parser::fold::{Fold, Unlocate};
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> Program {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans))
+            .unwrap_or_else(|e| panic!("{:?} does not parse: {:?}", input, e));
+        let program = Fixities::new()
+            .resolve_program(program)
+            .unwrap_or_else(|e| panic!("{:?} does not resolve: {}", input, e));
+        Unlocate.fold_program(program)
+    }
+
+    fn assert_prints(input: &str, expected: &str) {
+        let printed = Printer::new().program(&parse(input));
+        assert_eq!(printed, expected);
+        assert_eq!(parse(&printed), parse(input));
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_prints("(a + (b * c)) - d", "a + b * c - d");
+        assert_prints("a - (b - c)", "a - (b - c)");
+        assert_prints("(a ++ b) ++ c", "(a ++ b) ++ c");
+        assert_prints("a : (b : [])", "a : b : []");
+        assert_prints("(a == b) == c", "(a == b) == c");
+        assert_prints("f (g x) (h y)", "f (g x) (h y)");
+        assert_prints("(\\x -> x)(1) + (f $ x)", "(\\x -> x) (1) + (f $ x)");
+        assert_prints("- (f x) * 2", "- f x * 2");
+        assert_prints("(- a) * b + (- c)", "(- a) * b + (- c)");
+        assert_prints("(- a) - b == (- c)", "- a - b == - c");
+        assert_prints("(`div` 2) . (x `elem`)", "(`div` 2) . (x `elem`)");
+        assert_prints("f (read s :: Int) + (x :: Int)", "f (read s :: Int) + (x :: Int)");
+        assert_prints("(a + b :: Num a => a)", "(a + b :: Num a => a)");
+        assert_prints(
+            "infixr 6 <+>\na <+> (b <+> c)",
+            "infixr 6 <+>\na <+> b <+> c",
+        );
+    }
+
+    #[test]
+    fn test_declarations() {
+        let input = "data T a = L | N (T a) a [T a] deriving (Eq, Show)\n\
+                     class Eq a => Container f where { empty :: f a }\n\
+                     f :: (Eq a, Show a) => a -> (a -> b) -> [b]\n\
+                     f 0 g = []\n\
+                     f n g | n > 0 = [g n] where { m = n }\n\
+                     main = do { x <- getLine; let { y = x }; print [c | c <- y, c /= ' '] }";
+        let expected = "data T a = L | N (T a) a [T a] deriving (Eq, Show)\n\
+                        class Eq a => Container f where { empty :: f a }\n\
+                        f :: (Eq a, Show a) => a -> (a -> b) -> [b]\n\
+                        f 0 g = []\n\
+                        f n g | n > 0 = [g n] where { m = n }\n\
+                        main = do { x <- getLine; let { y = x }; \
+                        print ([c | c <- y, c /= ' ']) }";
+        assert_prints(input, expected);
+        assert_prints(
+            "(a, b) = p\nJust x | c = m where { m = n }\nh : t = l",
+            "(a, b) = p\nJust x | c = m where { m = n }\nh : t = l",
+        );
+        assert_prints(
+            "type Table k v = [(k, v)]\ntype M = Maybe",
+            "type Table k v = [(k, v)]\ntype M = Maybe",
+        );
+        assert_prints(
+            "let f = fn(x) { if (x) { return \"a\\n\"; } else { x } }",
+            "f = fn(x) { if (x) { return \"a\\n\"; } else { x; }; }",
+        );
+    }
+
+    #[test]
+    fn test_syntax_extensions() {
+        let mut extensions = Extensions::default();
+        for extension in Extension::ALL {
+            extensions.insert(extension);
+        }
+        let parse = |input: &str| {
+            let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+            let tokens = Tokens::with_spans(&tokens, &spans).with_extensions(extensions);
+            let (_, program) = Parser::parse_tokens(tokens).unwrap();
+            Unlocate.fold_program(Fixities::new().resolve_program(program).unwrap())
+        };
+        let input = "f = \\case\n\
+                     \x20 Just x -> if | x > 0 -> (x,)\n\
+                     \x20              | otherwise -> (, x)\n\
+                     g = forM_ xs \\x -> when x do\n  print x\n\
+                     h :: forall a. Show a => a -> String\n\
+                     h = \\(x :: a) -> show x";
+        let expected = "f = \\case { Just x -> if | x > 0 -> (x, ) | otherwise -> (, x) }\n\
+                        g = forM_ xs (\\x -> when x (do { print x }))\n\
+                        h :: forall a. Show a => a -> String\n\
+                        h = \\(x :: a) -> show x";
+        let printed = Printer::new().program(&parse(input));
+        assert_eq!(printed, expected);
+        assert_eq!(parse(&printed), parse(input));
+    }
+
+    #[test]
+    fn test_module() {
+        let input = "module Data.Queue (Queue, Tree(..), Op((:+), Lit), (<+>), module M) where\n\
+                     import qualified Data.Map as Map\n\
+                     import Prelude hiding (lookup)\n\
+                     empty = Map.empty";
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, module) = Parser::parse_module(Tokens::with_spans(&tokens, &spans)).unwrap();
+        assert_eq!(module.to_string(), input);
+    }
+
+    // A xorshift generator of the ASTs the parser produces
+    struct Gen(u64);
+
+    impl Gen {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.below(items.len())]
+        }
+
+        fn ident(&mut self) -> Ident {
+            Ident(
+                self.pick(&["x", "y", "xs", "go", "f'", "M.lookup"])
+                    .to_owned(),
+            )
+        }
+
+        fn con(&mut self) -> Ident {
+            Ident(self.pick(&["Just", "Nothing", "Node", "M.Leaf"]).to_owned())
+        }
+
+        fn op(&mut self) -> Infix {
+            let ops = [
+                "+", "-", "*", "/", "==", "<", "++", ":", "$", ".", "&&", "<+>", "div", "elem",
+            ];
+            Infix::from_name(self.pick(&ops))
+        }
+
+        fn literal(&mut self) -> Literal {
+            match self.below(5) {
+                0 => Literal::IntLiteral(self.below(100) as i64),
+                1 => Literal::FloatLiteral(self.below(40) as f64 / 8.0),
+                2 => Literal::BoolLiteral(self.below(2) == 0),
+                3 => Literal::StringLiteral(self.pick(&["", "a b", "q\"\\\n"]).to_owned()),
+                _ => Literal::CharLiteral(['c', '\'', '\\', '\t'][self.below(4)]),
+            }
+        }
+
+        fn many<T>(
+            &mut self,
+            min: usize,
+            max: usize,
+            mut item: impl FnMut(&mut Self) -> T,
+        ) -> Vec<T> {
+            let n = min + self.below(max - min + 1);
+            (0..n).map(|_| item(self)).collect()
+        }
+
+        // The components of a tuple, which has none or at least two
+        fn tuple<T>(&mut self, item: impl FnMut(&mut Self) -> T) -> Vec<T> {
+            match self.below(3) {
+                0 => vec![],
+                _ => self.many(2, 3, item),
+            }
+        }
+
+        fn expr(&mut self, depth: usize) -> Expr {
+            if depth == 0 {
+                return match self.below(3) {
+                    0 => Expr::LitExpr(self.literal()),
+                    1 => Expr::IdentExpr(self.con()),
+                    _ => Expr::IdentExpr(self.ident()),
+                };
+            }
+            let d = depth - 1;
+            let boxed = |g: &mut Self| Box::new(g.expr(d));
+            match self.below(23) {
+                0 => Expr::IdentExpr(Ident(self.op().name().to_owned())),
+                1 => Expr::PrefixExpr(Prefix::PrefixMinus, boxed(self)),
+                2..=4 => Expr::InfixExpr(self.op(), boxed(self), boxed(self)),
+                5 => Expr::IfExpr {
+                    cond: boxed(self),
+                    consequence: vec![Stmt::ExprStmt(self.expr(d))],
+                    alternative: Some(vec![Stmt::ExprStmt(self.expr(d))]),
+                },
+                6 => Expr::IfExpr {
+                    cond: boxed(self),
+                    consequence: self.statements(d),
+                    alternative: None,
+                },
+                7 => Expr::FnExpr {
+                    params: self.many(0, 2, Self::ident),
+                    body: self.statements(d),
+                },
+                8 | 9 => {
+                    let argument = match self.expr(d) {
+                        Expr::TupleExpr(_) => Expr::IdentExpr(self.ident()),
+                        argument => argument,
+                    };
+                    Expr::CallExpr {
+                        function: boxed(self),
+                        arguments: vec![argument],
+                    }
+                }
+                10 => Expr::CallExpr {
+                    function: boxed(self),
+                    arguments: self.tuple(|g| g.expr(d)),
+                },
+                11 => Expr::ArrayExpr(self.many(0, 3, |g| g.expr(d))),
+                12 => Expr::HashExpr(self.many(0, 2, |g| (g.literal(), g.expr(d)))),
+                13 => Expr::IndexExpr {
+                    array: boxed(self),
+                    index: boxed(self),
+                },
+                14 => Expr::LambdaExpr {
+                    params: self.many(1, 2, |g| g.pattern(d)),
+                    body: boxed(self),
+                },
+                15 => Expr::CaseExpr {
+                    scrutinee: boxed(self),
+                    alts: self.many(0, 2, |g| Alt {
+                        pat: g.pattern(d),
+                        rhs: g.rhs(d),
+                        wheres: g.decls(d),
+                    }),
+                },
+                16 => Expr::LetExpr {
+                    bindings: self.decls(d),
+                    body: boxed(self),
+                },
+                17 => Expr::TupleExpr(self.tuple(|g| g.expr(d))),
+                18 => Expr::LeftSectionExpr(boxed(self), self.op()),
+                19 => match self.op() {
+                    Infix::Minus => Expr::RightSectionExpr(Infix::Plus, boxed(self)),
+                    op => Expr::RightSectionExpr(op, boxed(self)),
+                },
+                20 => Expr::ListCompExpr {
+                    expr: boxed(self),
+                    qualifiers: self.many(1, 3, |g| g.qualifier(d)),
+                },
+                21 => Expr::TypedExpr(boxed(self), self.context(1), self.ty(2)),
+                _ => {
+                    let mut stmts = self.many(0, 2, |g| g.qualifier(d));
+                    stmts.push(Qualifier::Guard(self.expr(d)));
+                    Expr::DoExpr(stmts)
+                }
+            }
+        }
+
+        fn qualifier(&mut self, depth: usize) -> Qualifier {
+            match self.below(3) {
+                0 => Qualifier::Generator(self.pattern(depth), self.expr(depth)),
+                1 => Qualifier::LetQual(self.decls(depth)),
+                _ => Qualifier::Guard(self.expr(depth)),
+            }
+        }
+
+        fn pattern(&mut self, depth: usize) -> Pattern {
+            let d = depth.saturating_sub(1);
+            match self.below(if depth == 0 { 4 } else { 10 }) {
+                0 => Pattern::WildPat,
+                1 => Pattern::VarPat(Ident(self.pick(&["x", "y", "rest"]).to_owned())),
+                2 => match self.literal() {
+                    Literal::IntLiteral(i) if self.below(2) == 0 => {
+                        Pattern::LitPat(Literal::IntLiteral(-i))
+                    }
+                    literal => Pattern::LitPat(literal),
+                },
+                3 => Pattern::ConPat(self.con(), vec![]),
+                4 | 5 => Pattern::ConPat(self.con(), self.many(1, 2, |g| g.pattern(d))),
+                6 => Pattern::ConPat(
+                    Ident(":".to_owned()),
+                    vec![self.pattern(d), self.pattern(d)],
+                ),
+                7 => Pattern::ListPat(self.many(0, 2, |g| g.pattern(d))),
+                8 => Pattern::TuplePat(self.tuple(|g| g.pattern(d))),
+                _ => Pattern::AsPat(Ident("all".to_owned()), Box::new(self.pattern(d))),
+            }
+        }
+
+        fn ty(&mut self, depth: usize) -> Type {
+            let d = depth.saturating_sub(1);
+            match self.below(if depth == 0 { 3 } else { 7 }) {
+                0 => Type::VarType(Ident(self.pick(&["a", "b"]).to_owned())),
+                1 => Type::ConType(Ident(self.pick(&["Int", "Maybe", "[]", "(,)"]).to_owned())),
+                2 => Type::TupleType(vec![]),
+                3 => Type::AppType(Box::new(self.ty(d)), Box::new(self.ty(d))),
+                4 => Type::FnType(Box::new(self.ty(d)), Box::new(self.ty(d))),
+                5 => Type::ListType(Box::new(self.ty(d))),
+                _ => Type::TupleType(self.many(2, 3, |g| g.ty(d))),
+            }
+        }
+
+        fn context(&mut self, depth: usize) -> Context {
+            self.many(0, 2, |g| Pred {
+                class: g.con(),
+                ty: g.ty(depth),
+            })
+        }
+
+        fn rhs(&mut self, depth: usize) -> Rhs {
+            match self.below(2) {
+                0 => Rhs::Plain(self.expr(depth)),
+                _ => Rhs::Guarded(self.many(1, 2, |g| (g.expr(depth), g.expr(depth)))),
+            }
+        }
+
+        // Function names are distinct so that no equations merge
+        fn decl(&mut self, depth: usize, index: usize) -> Stmt {
+            let name = Ident(format!("f{}", index));
+            match self.below(5) {
+                0 => Stmt::LetStmt(name, self.expr(depth)),
+                1 => Stmt::SigStmt(
+                    vec![name, Ident("<+>".to_owned())],
+                    self.context(1),
+                    self.ty(2),
+                    None,
+                ),
+                2 => Stmt::FixityStmt(
+                    Fixity {
+                        assoc: [Assoc::Left, Assoc::Right, Assoc::Non][self.below(3)],
+                        precedence: self.below(10) as u8,
+                    },
+                    vec![Ident(self.op().name().to_owned())],
+                ),
+                _ => {
+                    let clauses = self.many(1, 2, |g| {
+                        let params = g.many(1, 2, |g| g.pattern(depth));
+                        Clause {
+                            params,
+                            rhs: g.rhs(depth),
+                            wheres: g.decls(depth.saturating_sub(1)),
+                        }
+                    });
+                    Stmt::FunStmt(name, clauses)
+                }
+            }
+        }
+
+        fn decls(&mut self, depth: usize) -> Program {
+            if depth == 0 {
+                return vec![];
+            }
+            let n = self.below(3);
+            (0..n).map(|i| self.decl(depth - 1, i)).collect()
+        }
+
+        // Equations in a `fn` body are separate statements, so none are made
+        fn statements(&mut self, depth: usize) -> Program {
+            self.many(0, 2, |g| match g.below(3) {
+                0 => Stmt::LetStmt(g.ident(), g.expr(depth)),
+                1 => Stmt::ReturnStmt(g.expr(depth)),
+                _ => Stmt::ExprStmt(g.expr(depth)),
+            })
+        }
+
+        fn program(&mut self, depth: usize) -> Program {
+            let mut program = self.decls(depth + 1);
+            let n = program.len();
+            program.push(match self.below(7) {
+                0 => Stmt::DataStmt(DataDecl {
+                    name: Ident("T".to_owned()),
+                    params: vec![Ident("a".to_owned())],
+                    constructors: self.many(0, 2, |g| {
+                        let fields = g.many(0, 2, |g| g.ty(2));
+                        let labels = match g.below(2) {
+                            0 => (0..fields.len())
+                                .map(|i| Ident(format!("l{}", i)))
+                                .collect(),
+                            _ => vec![],
+                        };
+                        ConDecl {
+                            name: g.con(),
+                            fields,
+                            labels,
+                        }
+                    }),
+                    deriving: self.many(0, 2, |g| g.con()),
+                }),
+                1 => Stmt::ClassStmt(ClassDecl {
+                    context: self.context(0),
+                    name: self.con(),
+                    var: Ident("a".to_owned()),
+                    body: self.decls(depth),
+                }),
+                2 | 3 => Stmt::InstanceStmt(InstanceDecl {
+                    context: self.context(0),
+                    class: self.con(),
+                    ty: self.ty(2),
+                    body: self.decls(depth),
+                    span: None,
+                }),
+                4 => Stmt::DerivingStmt(InstanceDecl {
+                    context: self.context(0),
+                    class: self.con(),
+                    ty: self.ty(2),
+                    body: vec![],
+                    span: None,
+                }),
+                5 => Stmt::DefaultStmt(self.many(0, 2, |g| g.ty(2))),
+                _ => Stmt::ExprStmt(self.expr(depth)),
+            });
+            program.push(self.decl(depth, n + 1));
+            program
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut gen = Gen(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let program = gen.program(3);
+            let printed = Printer::new().program(&program);
+            assert_eq!(parse(&printed), program, "printed as\n{}", printed);
+        }
+    }
+}
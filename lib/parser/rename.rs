@@ -0,0 +1,796 @@
+use crate::lexer::token::{Span, Token, Tokens};
+use crate::parser::ast::*;
+use crate::parser::fold::{self, Fold};
+use crate::parser::visit::Visitor;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// What a name refers to once resolved.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Binder {
+    /// Bound by a pattern, a `let` or a `where`, and renamed apart
+    Local,
+    /// Defined at the top level of the module
+    TopLevel,
+    /// Imported from the module defining it
+    Imported(String),
+    /// Built into the evaluator, such as `+` or `primEq`
+    Builtin,
+}
+
+/// What is wrong with a name.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Problem {
+    NotInScope,
+    /// Defined twice in the same scope
+    Duplicate,
+    /// Imported from each of these modules
+    Ambiguous(Vec<String>),
+    /// Named by a type or fixity signature with no definition next to it
+    NoBinding,
+}
+
+/// A name that doesn't resolve, and where it is.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RenameError {
+    pub name: Ident,
+    pub problem: Problem,
+    /// `None` when the renamer doesn't have the tokens of the module.
+    pub span: Option<Span>,
+}
+
+/// `3:7: identifier not found: x` with the line and column when they are
+/// known.
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}:{}: ", span.line, span.column)?;
+        }
+        let Ident(ref name) = self.name;
+        match self.problem {
+            Problem::NotInScope => write!(f, "identifier not found: {}", name),
+            Problem::Duplicate => write!(f, "Multiple declarations of `{}`", name),
+            Problem::Ambiguous(ref modules) => {
+                let candidates: Vec<String> = modules
+                    .iter()
+                    .map(|module| format!("`{}.{}`", module, self.name.unqualified()))
+                    .collect();
+                write!(
+                    f,
+                    "Ambiguous occurrence `{}`: it could refer to {}",
+                    name,
+                    candidates.join(" or ")
+                )
+            }
+            Problem::NoBinding => write!(
+                f,
+                "The signature for `{}` lacks an accompanying binding",
+                name
+            ),
+        }
+    }
+}
+
+/// A module whose names all resolve. Local binders are renamed apart, `x` to
+/// `x#1`, so that every name of the module stands for the one binder
+/// `binders` gives it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Resolved {
+    pub module: Module,
+    pub binders: HashMap<String, Binder>,
+}
+
+/// The name a local binder had in the source, before it was renamed apart.
+pub fn source_name(name: &str) -> &str {
+    match name.rsplit_once('#') {
+        Some((source, n)) if !source.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+            source
+        }
+        _ => name,
+    }
+}
+
+// `:`, `()`, the tuple constructors and the operators the parser knows are
+// in scope whatever the imports
+fn is_syntax(name: &str) -> bool {
+    name == ":"
+        || name == "[]"
+        || Ident(name.to_owned()).tuple_arity().is_some()
+        || !matches!(Infix::from_name(name), Infix::Op(_))
+}
+
+// The names a group of declarations binds: its variables and functions and,
+// at the top level, the constructors, field labels and class methods.
+fn bound_names(decls: &Program) -> Vec<String> {
+    let mut names = vec![];
+    for decl in decls {
+        match decl {
+            Stmt::LetStmt(Ident(name), _) | Stmt::FunStmt(Ident(name), _) => names.push(name),
+            Stmt::PatStmt(pat, ..) => names.extend(pat.vars().into_iter().map(|Ident(var)| var)),
+            Stmt::DataStmt(data) => {
+                for con in &data.constructors {
+                    names.push(&con.name.0);
+                    names.extend(con.labels.iter().map(|Ident(label)| label));
+                }
+            }
+            Stmt::ClassStmt(class) => {
+                for stmt in &class.body {
+                    if let Stmt::SigStmt(methods, ..) = stmt {
+                        names.extend(methods.iter().map(|Ident(method)| method));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    names.into_iter().cloned().collect()
+}
+
+// The names of an expression in the order the renamer meets them
+struct Names<'ast>(Vec<&'ast str>);
+
+impl<'ast> Visitor<'ast> for Names<'ast> {
+    fn visit_ident(&mut self, Ident(name): &'ast Ident) {
+        self.0.push(name);
+    }
+}
+
+// The names bound in one scope, with what they are renamed to
+#[derive(Default)]
+struct Scope {
+    names: HashMap<String, String>,
+    // those whose binding the renamer has gone past, to find a second one
+    defined: HashSet<String>,
+}
+
+/// Resolves every name of a module before it is evaluated, to a local,
+/// top-level, imported or builtin binder, and reports all the names that
+/// don't resolve at once. A top-level definition hides an imported name, as
+/// in the evaluator.
+///
+/// The AST has no positions: given the tokens the module was parsed from,
+/// the renamer takes the span of the n-th occurrence of a name in the
+/// tokens for the n-th occurrence it meets.
+#[derive(Default)]
+pub struct Renamer {
+    imports: HashMap<String, Vec<(String, Binder)>>,
+    scopes: Vec<Scope>,
+    types: HashSet<String>,
+    binders: HashMap<String, Binder>,
+    // the positions of the occurrences of each name still to be met
+    spans: HashMap<String, VecDeque<Span>>,
+    errors: Vec<RenameError>,
+    fresh: usize,
+}
+
+impl Renamer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locates the names of the module at the tokens it was parsed from.
+    pub fn with_spans(mut self, tokens: Tokens) -> Self {
+        for (index, token) in tokens.tok.iter().enumerate() {
+            let name = match token {
+                Token::Ident(name) | Token::TypeIdent(name) | Token::Operator(name) => name,
+                _ => continue,
+            };
+            if let Some(span) = tokens.span_at(index) {
+                self.spans.entry(name.clone()).or_default().push_back(span);
+            }
+        }
+        self
+    }
+
+    /// Brings a name imported from a module into scope. A name imported from
+    /// several modules is an error where it is used.
+    pub fn import(&mut self, name: &str, module: &str, binder: Binder) {
+        let entities = self.imports.entry(name.to_owned()).or_default();
+        if !entities.iter().any(|(origin, _)| origin == module) {
+            entities.push((module.to_owned(), binder));
+        }
+    }
+
+    /// Renames the module, or reports every name in it that doesn't resolve.
+    pub fn rename_module(mut self, module: Module) -> Result<Resolved, Vec<RenameError>> {
+        let module = self.fold_module(module);
+        // report in source order rather than in the order of the walk
+        self.errors
+            .sort_by_key(|e| e.span.map_or((usize::MAX, 0), |s| (s.line, s.column)));
+        match self.errors.is_empty() {
+            true => Ok(Resolved {
+                module,
+                binders: self.binders,
+            }),
+            false => Err(self.errors),
+        }
+    }
+
+    fn locate(&mut self, name: &str) -> Option<Span> {
+        self.spans.get_mut(name).and_then(VecDeque::pop_front)
+    }
+
+    fn error(&mut self, name: &str, problem: Problem, span: Option<Span>) {
+        self.errors.push(RenameError {
+            name: Ident(name.to_owned()),
+            problem,
+            span,
+        });
+    }
+
+    fn fresh(&mut self, name: &str) -> String {
+        self.fresh += 1;
+        let renamed = format!("{}#{}", name, self.fresh);
+        self.binders.insert(renamed.clone(), Binder::Local);
+        renamed
+    }
+
+    fn lookup_local(&self, name: &str) -> Option<String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name))
+            .cloned()
+    }
+
+    // The scope of a `let` or `where`, whose bindings are all in scope in
+    // each other's definitions
+    fn group_scope(&mut self, decls: &Program) -> Scope {
+        let mut scope = Scope::default();
+        for name in bound_names(decls) {
+            let renamed = self.fresh(&name);
+            scope.names.insert(name, renamed);
+        }
+        scope
+    }
+
+    fn group(&mut self, decls: Program) -> Program {
+        decls.into_iter().map(|decl| self.fold_stmt(decl)).collect()
+    }
+
+    // The names of the top level are not renamed, the exports and the
+    // evaluator's environment know them by their names
+    fn top_level(&mut self, body: Program) -> Program {
+        let mut scope = Scope::default();
+        for name in bound_names(&body) {
+            self.binders.insert(name.clone(), Binder::TopLevel);
+            scope.names.insert(name.clone(), name);
+        }
+        self.scopes.push(scope);
+        let body = self.group(body);
+        self.scopes.pop();
+        body
+    }
+
+    // A binding occurrence of a name in the innermost scope, which may
+    // already list it when it binds a group of declarations
+    fn bind(&mut self, Ident(name): Ident) -> Ident {
+        let span = self.locate(&name);
+        let known = self.scopes.last().expect("a scope").names.contains_key(&name);
+        if !known {
+            let renamed = self.fresh(&name);
+            let scope = self.scopes.last_mut().expect("a scope");
+            scope.names.insert(name.clone(), renamed);
+        }
+        let scope = self.scopes.last_mut().expect("a scope");
+        let renamed = Ident(scope.names[&name].clone());
+        if !scope.defined.insert(name.clone()) {
+            self.error(&name, Problem::Duplicate, span);
+        }
+        renamed
+    }
+
+    fn resolve(&mut self, Ident(name): Ident) -> Ident {
+        let span = self.locate(&name);
+        if let Some(renamed) = self.lookup_local(&name) {
+            return Ident(renamed);
+        }
+        match self.imports.get(&name).map(Vec::as_slice) {
+            Some([(_, binder)]) => {
+                self.binders.insert(name.clone(), binder.clone());
+            }
+            Some(entities) => {
+                let modules = entities.iter().map(|(module, _)| module.clone()).collect();
+                self.error(&name, Problem::Ambiguous(modules), span);
+            }
+            None if is_syntax(&name) => {
+                self.binders.insert(name.clone(), Binder::Builtin);
+            }
+            None => self.error(&name, Problem::NotInScope, span),
+        }
+        Ident(name)
+    }
+
+    // A name in a type or fixity signature, defined in the same group
+    fn signed(&mut self, Ident(name): Ident) -> Ident {
+        let span = self.locate(&name);
+        let renamed = self.scopes.last().and_then(|scope| scope.names.get(&name));
+        match renamed {
+            Some(renamed) => Ident(renamed.clone()),
+            None => {
+                self.error(&name, Problem::NoBinding, span);
+                Ident(name)
+            }
+        }
+    }
+
+    fn define_type(&mut self, Ident(name): Ident) -> Ident {
+        let span = self.locate(&name);
+        if !self.types.insert(name.clone()) {
+            self.error(&name, Problem::Duplicate, span);
+        }
+        Ident(name)
+    }
+
+    // Takes the positions of the names of an expression that the renamer
+    // meets after names that follow it in the source
+    fn hold(&mut self, expr: &Expr) -> Vec<(String, Option<Span>)> {
+        let mut names = Names(vec![]);
+        names.visit_expr(expr);
+        names
+            .0
+            .into_iter()
+            .map(|name| (name.to_owned(), self.locate(name)))
+            .collect()
+    }
+
+    fn release(&mut self, held: Vec<(String, Option<Span>)>) {
+        for (name, span) in held.into_iter().rev() {
+            if let Some(span) = span {
+                self.spans.entry(name).or_default().push_front(span);
+            }
+        }
+    }
+
+    // A definition in a class or instance body, of one of the class methods
+    fn method(&mut self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::LetStmt(name, expr) => {
+                let name = self.resolve(name);
+                Stmt::LetStmt(name, self.fold_expr(expr))
+            }
+            Stmt::FunStmt(name, clauses) => {
+                let name = self.resolve(name);
+                let clauses = clauses.into_iter().map(|c| self.fold_clause(c)).collect();
+                Stmt::FunStmt(name, clauses)
+            }
+            stmt => self.fold_stmt(stmt),
+        }
+    }
+
+    // Each qualifier is in scope in those after it, until the comprehension
+    // or the `do` block closes the scopes
+    fn qualifiers(&mut self, qualifiers: Vec<Qualifier>) -> Vec<Qualifier> {
+        qualifiers
+            .into_iter()
+            .map(|qualifier| self.fold_qualifier(qualifier))
+            .collect()
+    }
+}
+
+impl Fold for Renamer {
+    fn fold_module(&mut self, module: Module) -> Module {
+        let name = self.fold_ident(module.name);
+        let exports = module
+            .exports
+            .map(|entities| entities.into_iter().map(|e| self.fold_entity(e)).collect());
+        let imports = module
+            .imports
+            .into_iter()
+            .map(|import| self.fold_import(import))
+            .collect();
+        let body = self.top_level(module.body);
+        Module {
+            name,
+            exports,
+            imports,
+            body,
+        }
+    }
+
+    /// The statements of a block, where each binding is in scope in itself
+    /// and the statements after it.
+    fn fold_program(&mut self, program: Program) -> Program {
+        let depth = self.scopes.len();
+        let block = program
+            .into_iter()
+            .map(|stmt| match stmt {
+                Stmt::LetStmt(..) | Stmt::FunStmt(..) | Stmt::PatStmt(..) => {
+                    self.scopes.push(Scope::default());
+                    self.fold_stmt(stmt)
+                }
+                stmt => self.fold_stmt(stmt),
+            })
+            .collect();
+        self.scopes.truncate(depth);
+        block
+    }
+
+    /// A declaration of the top level or of a `let` or `where` group.
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::LetStmt(name, expr) => {
+                let name = self.bind(name);
+                Stmt::LetStmt(name, self.fold_expr(expr))
+            }
+            Stmt::FunStmt(name, clauses) => {
+                let name = self.bind(name);
+                let clauses = clauses.into_iter().map(|c| self.fold_clause(c)).collect();
+                Stmt::FunStmt(name, clauses)
+            }
+            // the variables of the pattern are bound in the group, and the
+            // `where` only in the right-hand side
+            Stmt::PatStmt(pat, rhs, wheres) => {
+                let pat = self.fold_pattern(pat);
+                let depth = self.scopes.len();
+                let scope = self.group_scope(&wheres);
+                self.scopes.push(scope);
+                let rhs = self.fold_rhs(rhs);
+                let wheres = self.group(wheres);
+                self.scopes.truncate(depth);
+                Stmt::PatStmt(pat, rhs, wheres)
+            }
+            Stmt::SigStmt(names, context, ty, span) => {
+                let names = names.into_iter().map(|name| self.signed(name)).collect();
+                let context = context.into_iter().map(|p| self.fold_pred(p)).collect();
+                Stmt::SigStmt(names, context, self.fold_type(ty), span)
+            }
+            Stmt::FixityStmt(fixity, names) => {
+                let names = names.into_iter().map(|name| self.signed(name)).collect();
+                Stmt::FixityStmt(fixity, names)
+            }
+            stmt => fold::fold_stmt(self, stmt),
+        }
+    }
+
+    fn fold_clause(&mut self, clause: Clause) -> Clause {
+        let depth = self.scopes.len();
+        self.scopes.push(Scope::default());
+        let params = clause
+            .params
+            .into_iter()
+            .map(|pat| self.fold_pattern(pat))
+            .collect();
+        let wheres = self.group_scope(&clause.wheres);
+        self.scopes.push(wheres);
+        let rhs = self.fold_rhs(clause.rhs);
+        let wheres = self.group(clause.wheres);
+        self.scopes.truncate(depth);
+        Clause {
+            params,
+            rhs,
+            wheres,
+        }
+    }
+
+    fn fold_data_decl(&mut self, data: DataDecl) -> DataDecl {
+        let name = self.define_type(data.name);
+        let params = data.params.into_iter().map(|p| self.fold_ident(p)).collect();
+        // constructors of a type may share a field label
+        let mut labels = HashSet::new();
+        let constructors = data
+            .constructors
+            .into_iter()
+            .map(|con| {
+                let name = self.bind(con.name);
+                let fields = con.fields.into_iter().map(|ty| self.fold_type(ty)).collect();
+                let labels = con
+                    .labels
+                    .into_iter()
+                    .map(|label| match labels.insert(label.0.clone()) {
+                        true => self.bind(label),
+                        false => self.fold_ident(label),
+                    })
+                    .collect();
+                ConDecl {
+                    name,
+                    fields,
+                    labels,
+                }
+            })
+            .collect();
+        let deriving = data.deriving.into_iter().map(|c| self.fold_ident(c)).collect();
+        DataDecl {
+            name,
+            params,
+            constructors,
+            deriving,
+        }
+    }
+
+    /// The signatures of a class define its methods, its other definitions
+    /// are defaults for them.
+    fn fold_class_decl(&mut self, class: ClassDecl) -> ClassDecl {
+        let context = class.context.into_iter().map(|p| self.fold_pred(p)).collect();
+        let name = self.define_type(class.name);
+        let var = self.fold_ident(class.var);
+        let body = class
+            .body
+            .into_iter()
+            .map(|stmt| match stmt {
+                Stmt::SigStmt(names, context, ty, span) => {
+                    let names = names.into_iter().map(|name| self.bind(name)).collect();
+                    let context = context.into_iter().map(|p| self.fold_pred(p)).collect();
+                    Stmt::SigStmt(names, context, self.fold_type(ty), span)
+                }
+                stmt => self.method(stmt),
+            })
+            .collect();
+        ClassDecl {
+            context,
+            name,
+            var,
+            body,
+        }
+    }
+
+    fn fold_instance_decl(&mut self, instance: InstanceDecl) -> InstanceDecl {
+        let context = instance
+            .context
+            .into_iter()
+            .map(|p| self.fold_pred(p))
+            .collect();
+        let class = self.fold_ident(instance.class);
+        let ty = self.fold_type(instance.ty);
+        let body = instance
+            .body
+            .into_iter()
+            .map(|stmt| match stmt {
+                Stmt::SigStmt(names, context, ty, span) => {
+                    let names = names.into_iter().map(|name| self.resolve(name)).collect();
+                    let context = context.into_iter().map(|p| self.fold_pred(p)).collect();
+                    Stmt::SigStmt(names, context, self.fold_type(ty), span)
+                }
+                stmt => self.method(stmt),
+            })
+            .collect();
+        InstanceDecl {
+            context,
+            class,
+            ty,
+            body,
+            span: instance.span,
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::IdentExpr(name) => Expr::IdentExpr(self.resolve(name)),
+            // a bound `_x` is a variable
+            Expr::HoleExpr(Ident(name)) => {
+                match self.lookup_local(&name).is_some() || self.imports.contains_key(&name) {
+                    true => Expr::IdentExpr(self.resolve(Ident(name))),
+                    false => {
+                        self.locate(&name);
+                        Expr::HoleExpr(Ident(name))
+                    }
+                }
+            }
+            Expr::FnExpr { params, body } => {
+                self.scopes.push(Scope::default());
+                let params = params.into_iter().map(|param| self.bind(param)).collect();
+                let body = self.fold_program(body);
+                self.scopes.pop();
+                Expr::FnExpr { params, body }
+            }
+            Expr::LambdaExpr { params, body } => {
+                self.scopes.push(Scope::default());
+                let params = params.into_iter().map(|pat| self.fold_pattern(pat)).collect();
+                let body = Box::new(self.fold_expr(*body));
+                self.scopes.pop();
+                Expr::LambdaExpr { params, body }
+            }
+            Expr::LetExpr { bindings, body } => {
+                let scope = self.group_scope(&bindings);
+                self.scopes.push(scope);
+                let bindings = self.group(bindings);
+                let body = Box::new(self.fold_expr(*body));
+                self.scopes.pop();
+                Expr::LetExpr { bindings, body }
+            }
+            // the head comes first in the source but is in the scope of the
+            // qualifiers
+            Expr::ListCompExpr { expr, qualifiers } => {
+                let depth = self.scopes.len();
+                let held = self.hold(&expr);
+                let qualifiers = self.qualifiers(qualifiers);
+                self.release(held);
+                let expr = Box::new(self.fold_expr(*expr));
+                self.scopes.truncate(depth);
+                Expr::ListCompExpr { expr, qualifiers }
+            }
+            Expr::DoExpr(stmts) => {
+                let depth = self.scopes.len();
+                let stmts = self.qualifiers(stmts);
+                self.scopes.truncate(depth);
+                Expr::DoExpr(stmts)
+            }
+            expr => fold::fold_expr(self, expr),
+        }
+    }
+
+    fn fold_alt(&mut self, alt: Alt) -> Alt {
+        let depth = self.scopes.len();
+        self.scopes.push(Scope::default());
+        let pat = self.fold_pattern(alt.pat);
+        let wheres = self.group_scope(&alt.wheres);
+        self.scopes.push(wheres);
+        let rhs = self.fold_rhs(alt.rhs);
+        let wheres = self.group(alt.wheres);
+        self.scopes.truncate(depth);
+        Alt { pat, rhs, wheres }
+    }
+
+    /// Opens the scope of the names the qualifier binds, see `qualifiers`.
+    fn fold_qualifier(&mut self, qualifier: Qualifier) -> Qualifier {
+        match qualifier {
+            Qualifier::Generator(pat, expr) => {
+                self.scopes.push(Scope::default());
+                let pat = self.fold_pattern(pat);
+                // the generator itself is outside the scope of its pattern
+                let scope = self.scopes.pop().expect("a scope");
+                let expr = self.fold_expr(expr);
+                self.scopes.push(scope);
+                Qualifier::Generator(pat, expr)
+            }
+            Qualifier::LetQual(decls) => {
+                let scope = self.group_scope(&decls);
+                self.scopes.push(scope);
+                Qualifier::LetQual(self.group(decls))
+            }
+            Qualifier::Guard(expr) => Qualifier::Guard(self.fold_expr(expr)),
+        }
+    }
+
+    fn fold_pattern(&mut self, pat: Pattern) -> Pattern {
+        match pat {
+            Pattern::VarPat(name) => Pattern::VarPat(self.bind(name)),
+            Pattern::AsPat(name, pat) => {
+                let name = self.bind(name);
+                Pattern::AsPat(name, Box::new(self.fold_pattern(*pat)))
+            }
+            Pattern::ConPat(con, args) => {
+                let con = self.resolve(con);
+                let args = args.into_iter().map(|pat| self.fold_pattern(pat)).collect();
+                Pattern::ConPat(con, args)
+            }
+            pat => fold::fold_pattern(self, pat),
+        }
+    }
+
+    /// `+` and the other operators the parser knows stay built in, unless a
+    /// local definition hides them.
+    fn fold_infix(&mut self, op: Infix) -> Infix {
+        match op {
+            Infix::Op(name) => Infix::Op(self.resolve(name)),
+            op => match self.lookup_local(op.name()) {
+                Some(renamed) if renamed != op.name() => Infix::Op(Ident(renamed)),
+                _ => op,
+            },
+        }
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        self.locate(&ident.0);
+        ident
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::pretty::Printer;
+    use crate::parser::Parser;
+
+    fn rename(input: &str) -> Result<Resolved, Vec<String>> {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let tokens = Tokens::with_spans(&tokens, &spans);
+        let (_, module) = Parser::parse_module(tokens).unwrap();
+        let mut renamer = Renamer::new().with_spans(tokens);
+        for name in ["map", "show", "print", "return", ">>=", "Just"] {
+            renamer.import(name, "Prelude", Binder::Imported("Prelude".to_owned()));
+        }
+        renamer.import("len", "Prelude", Binder::Builtin);
+        renamer.import("size", "Shapes", Binder::Imported("Shapes".to_owned()));
+        renamer.import("size", "Sizes", Binder::Imported("Sizes".to_owned()));
+        renamer
+            .rename_module(module)
+            .map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    fn renamed(input: &str) -> String {
+        let resolved = rename(input).unwrap_or_else(|errors| panic!("{:?}", errors));
+        Printer::new().program(&resolved.module.body)
+    }
+
+    #[test]
+    fn test_rename_apart() {
+        assert_eq!(
+            renamed("f x = g x where g y = x + y\nx = map f [len x]"),
+            "f x#1 = g#2 x#1 where { g#2 y#3 = x#1 + y#3 }\nx = map f ([len x])"
+        );
+        assert_eq!(
+            renamed("f x = \\x -> case x of { Just x -> x; x -> x }"),
+            "f x#1 = \\x#2 -> case x#2 of { Just x#3 -> x#3; x#4 -> x#4 }"
+        );
+        // a generator is outside the scope of its own pattern
+        assert_eq!(
+            renamed("xs = [x | x <- xs, let y = x, x <- [y]]"),
+            "xs = [x#3 | x#1 <- xs, let { y#2 = x#1 }, x#3 <- [y#2]]"
+        );
+        assert_eq!(
+            renamed("main = do { x <- return 1; let { x = 2 }; print x }"),
+            "main = do { x#1 <- return 1; let { x#2 = 2 }; print x#2 }"
+        );
+        // a local definition hides a built in operator
+        assert_eq!(
+            renamed("f a = a + 1 where x + y = x"),
+            "f a#1 = a#1 +#2 1 where { (+#2) x#3 y#4 = x#3 }"
+        );
+        let resolved = rename(
+            "data T = A | B\nclass C a where { m :: a }\ninstance C T where { m = A }\n\
+             f _x = _x + _y",
+        )
+        .unwrap();
+        assert_eq!(
+            Printer::new().program(&resolved.module.body),
+            "data T = A | B\nclass C a where { m :: a }\ninstance C T where { m = A }\n\
+             f _x#1 = _x#1 + _y"
+        );
+        assert_eq!(resolved.binders["_x#1"], Binder::Local);
+        assert_eq!(resolved.binders["A"], Binder::TopLevel);
+        assert_eq!(resolved.binders["m"], Binder::TopLevel);
+        assert_eq!(source_name("_x#1"), "_x");
+        assert_eq!(source_name("<#>"), "<#>");
+    }
+
+    #[test]
+    fn test_binders() {
+        let resolved = rename("import Shapes\nf = map show [len []]").unwrap();
+        let binder = |name: &str| resolved.binders.get(name).cloned();
+        assert_eq!(binder("f"), Some(Binder::TopLevel));
+        assert_eq!(binder("map"), Some(Binder::Imported("Prelude".to_owned())));
+        assert_eq!(binder("len"), Some(Binder::Builtin));
+        assert_eq!(binder("size"), None);
+    }
+
+    #[test]
+    fn test_scope_errors() {
+        assert_eq!(
+            rename("f x = y\ng = f z\nh = [w | v <- [1], odd v]").map(|_| ()),
+            Err(vec![
+                "1:7: identifier not found: y".to_owned(),
+                "2:7: identifier not found: z".to_owned(),
+                "3:6: identifier not found: w".to_owned(),
+                "3:20: identifier not found: odd".to_owned(),
+            ])
+        );
+        assert_eq!(
+            rename("f x x = 1\ndata T = A | B\ndata T = A\nf = 2").map(|_| ()),
+            Err(vec![
+                "1:5: Multiple declarations of `x`".to_owned(),
+                "3:6: Multiple declarations of `T`".to_owned(),
+                "3:10: Multiple declarations of `A`".to_owned(),
+                "4:1: Multiple declarations of `f`".to_owned(),
+            ])
+        );
+        assert_eq!(
+            rename("f = size\ng :: Int\ninfixl 6 <+>").map(|_| ()),
+            Err(vec![
+                "1:5: Ambiguous occurrence `size`: it could refer to `Shapes.size` or \
+                 `Sizes.size`"
+                    .to_owned(),
+                "2:1: The signature for `g` lacks an accompanying binding".to_owned(),
+                "3:10: The signature for `<+>` lacks an accompanying binding".to_owned(),
+            ])
+        );
+        assert_eq!(
+            rename("module M where\nx = Just (Q.empty, Nothing)").map(|_| ()),
+            Err(vec![
+                "2:11: identifier not found: Q.empty".to_owned(),
+                "2:20: identifier not found: Nothing".to_owned(),
+            ])
+        );
+    }
+}
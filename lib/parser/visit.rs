@@ -0,0 +1,974 @@
+use crate::parser::ast::*;
+
+/// A traversal of a borrowed AST. Every method walks the children of its
+/// node by default, by calling the function of the same name, so a pass only
+/// overrides the nodes it is interested in and calls that function where it
+/// still wants to descend.
+pub trait Visitor<'ast> {
+    fn visit_module(&mut self, module: &'ast Module) {
+        visit_module(self, module)
+    }
+
+    fn visit_import(&mut self, import: &'ast Import) {
+        visit_import(self, import)
+    }
+
+    fn visit_entity(&mut self, entity: &'ast Entity) {
+        visit_entity(self, entity)
+    }
+
+    /// A sequence of statements or declarations: a program or the body of a
+    /// block, class or instance, the bindings of a `let` or a `where`.
+    fn visit_program(&mut self, program: &'ast [Stmt]) {
+        visit_program(self, program)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        visit_stmt(self, stmt)
+    }
+
+    fn visit_clause(&mut self, clause: &'ast Clause) {
+        visit_clause(self, clause)
+    }
+
+    fn visit_rhs(&mut self, rhs: &'ast Rhs) {
+        visit_rhs(self, rhs)
+    }
+
+    fn visit_data_decl(&mut self, data: &'ast DataDecl) {
+        visit_data_decl(self, data)
+    }
+
+    fn visit_con_decl(&mut self, con: &'ast ConDecl) {
+        visit_con_decl(self, con)
+    }
+
+    fn visit_class_decl(&mut self, class: &'ast ClassDecl) {
+        visit_class_decl(self, class)
+    }
+
+    fn visit_instance_decl(&mut self, instance: &'ast InstanceDecl) {
+        visit_instance_decl(self, instance)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        visit_expr(self, expr)
+    }
+
+    fn visit_alt(&mut self, alt: &'ast Alt) {
+        visit_alt(self, alt)
+    }
+
+    fn visit_qualifier(&mut self, qualifier: &'ast Qualifier) {
+        visit_qualifier(self, qualifier)
+    }
+
+    fn visit_pattern(&mut self, pat: &'ast Pattern) {
+        visit_pattern(self, pat)
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        visit_type(self, ty)
+    }
+
+    fn visit_pred(&mut self, pred: &'ast Pred) {
+        visit_pred(self, pred)
+    }
+
+    fn visit_infix(&mut self, op: &'ast Infix) {
+        visit_infix(self, op)
+    }
+
+    fn visit_literal(&mut self, _literal: &'ast Literal) {}
+
+    /// Every name: of variables, constructors, types, classes and modules,
+    /// where they are bound as well as where they are used.
+    fn visit_ident(&mut self, _ident: &'ast Ident) {}
+}
+
+pub fn visit_module<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, module: &'ast Module) {
+    v.visit_ident(&module.name);
+    for entity in module.exports.iter().flatten() {
+        v.visit_entity(entity);
+    }
+    for import in &module.imports {
+        v.visit_import(import);
+    }
+    v.visit_program(&module.body);
+}
+
+pub fn visit_import<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, import: &'ast Import) {
+    v.visit_ident(&import.module);
+    if let Some(alias) = &import.alias {
+        v.visit_ident(alias);
+    }
+    for entity in import.entities.iter().flatten() {
+        v.visit_entity(entity);
+    }
+}
+
+pub fn visit_entity<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, entity: &'ast Entity) {
+    match entity {
+        Entity::Var(name) | Entity::Module(name) => v.visit_ident(name),
+        Entity::Type(name, members) => {
+            v.visit_ident(name);
+            if let Members::Listed(members) = members {
+                for member in members {
+                    v.visit_ident(member);
+                }
+            }
+        }
+    }
+}
+
+pub fn visit_program<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, program: &'ast [Stmt]) {
+    for stmt in program {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn visit_stmt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, stmt: &'ast Stmt) {
+    match stmt {
+        Stmt::LetStmt(name, expr) => {
+            v.visit_ident(name);
+            v.visit_expr(expr);
+        }
+        Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) => v.visit_expr(expr),
+        Stmt::SigStmt(names, context, ty, _) => {
+            for name in names {
+                v.visit_ident(name);
+            }
+            for pred in context {
+                v.visit_pred(pred);
+            }
+            v.visit_type(ty);
+        }
+        Stmt::FunStmt(name, clauses) => {
+            v.visit_ident(name);
+            for clause in clauses {
+                v.visit_clause(clause);
+            }
+        }
+        Stmt::PatStmt(pat, rhs, wheres) => {
+            v.visit_pattern(pat);
+            v.visit_rhs(rhs);
+            v.visit_program(wheres);
+        }
+        Stmt::DataStmt(data) => v.visit_data_decl(data),
+        Stmt::TypeStmt(name, params, ty) => {
+            v.visit_ident(name);
+            for param in params {
+                v.visit_ident(param);
+            }
+            v.visit_type(ty);
+        }
+        Stmt::ClassStmt(class) => v.visit_class_decl(class),
+        Stmt::InstanceStmt(instance) | Stmt::DerivingStmt(instance) => {
+            v.visit_instance_decl(instance)
+        }
+        Stmt::FixityStmt(_, names) => {
+            for name in names {
+                v.visit_ident(name);
+            }
+        }
+        Stmt::DefaultStmt(types) => {
+            for ty in types {
+                v.visit_type(ty);
+            }
+        }
+        Stmt::ErrorStmt(_) => {}
+    }
+}
+
+pub fn visit_clause<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, clause: &'ast Clause) {
+    for param in &clause.params {
+        v.visit_pattern(param);
+    }
+    v.visit_rhs(&clause.rhs);
+    v.visit_program(&clause.wheres);
+}
+
+pub fn visit_rhs<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, rhs: &'ast Rhs) {
+    match rhs {
+        Rhs::Plain(expr) => v.visit_expr(expr),
+        Rhs::Guarded(guards) => {
+            for (guard, expr) in guards {
+                v.visit_expr(guard);
+                v.visit_expr(expr);
+            }
+        }
+    }
+}
+
+pub fn visit_data_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, data: &'ast DataDecl) {
+    v.visit_ident(&data.name);
+    for param in &data.params {
+        v.visit_ident(param);
+    }
+    for con in &data.constructors {
+        v.visit_con_decl(con);
+    }
+    for class in &data.deriving {
+        v.visit_ident(class);
+    }
+}
+
+pub fn visit_con_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, con: &'ast ConDecl) {
+    v.visit_ident(&con.name);
+    for field in &con.fields {
+        v.visit_type(field);
+    }
+    for label in &con.labels {
+        v.visit_ident(label);
+    }
+}
+
+pub fn visit_class_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, class: &'ast ClassDecl) {
+    for pred in &class.context {
+        v.visit_pred(pred);
+    }
+    v.visit_ident(&class.name);
+    v.visit_ident(&class.var);
+    v.visit_program(&class.body);
+}
+
+pub fn visit_instance_decl<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    instance: &'ast InstanceDecl,
+) {
+    for pred in &instance.context {
+        v.visit_pred(pred);
+    }
+    v.visit_ident(&instance.class);
+    v.visit_type(&instance.ty);
+    v.visit_program(&instance.body);
+}
+
+pub fn visit_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast Expr) {
+    match expr {
+        Expr::IdentExpr(name) | Expr::HoleExpr(name) => v.visit_ident(name),
+        Expr::LitExpr(literal) => v.visit_literal(literal),
+        Expr::PrefixExpr(_, operand) => v.visit_expr(operand),
+        Expr::InfixExpr(op, left, right) => {
+            v.visit_expr(left);
+            v.visit_infix(op);
+            v.visit_expr(right);
+        }
+        Expr::InfixChain(first, rest) => {
+            v.visit_expr(first);
+            for (op, operand) in rest {
+                v.visit_infix(op);
+                v.visit_expr(operand);
+            }
+        }
+        Expr::IfExpr {
+            cond,
+            consequence,
+            alternative,
+        } => {
+            v.visit_expr(cond);
+            v.visit_program(consequence);
+            if let Some(alternative) = alternative {
+                v.visit_program(alternative);
+            }
+        }
+        Expr::FnExpr { params, body } => {
+            for param in params {
+                v.visit_ident(param);
+            }
+            v.visit_program(body);
+        }
+        Expr::CallExpr {
+            function,
+            arguments,
+        } => {
+            v.visit_expr(function);
+            for argument in arguments {
+                v.visit_expr(argument);
+            }
+        }
+        Expr::ArrayExpr(exprs) | Expr::TupleExpr(exprs) => {
+            for expr in exprs {
+                v.visit_expr(expr);
+            }
+        }
+        Expr::HashExpr(pairs) => {
+            for (key, value) in pairs {
+                v.visit_literal(key);
+                v.visit_expr(value);
+            }
+        }
+        Expr::IndexExpr { array, index } => {
+            v.visit_expr(array);
+            v.visit_expr(index);
+        }
+        Expr::LambdaExpr { params, body } => {
+            for param in params {
+                v.visit_pattern(param);
+            }
+            v.visit_expr(body);
+        }
+        Expr::CaseExpr { scrutinee, alts } => {
+            v.visit_expr(scrutinee);
+            for alt in alts {
+                v.visit_alt(alt);
+            }
+        }
+        Expr::LambdaCaseExpr(alts) => {
+            for alt in alts {
+                v.visit_alt(alt);
+            }
+        }
+        Expr::MultiIfExpr(guards) => {
+            for (guard, expr) in guards {
+                v.visit_expr(guard);
+                v.visit_expr(expr);
+            }
+        }
+        Expr::TupleSectionExpr(components) => {
+            for expr in components.iter().flatten() {
+                v.visit_expr(expr);
+            }
+        }
+        Expr::LetExpr { bindings, body } => {
+            v.visit_program(bindings);
+            v.visit_expr(body);
+        }
+        Expr::LeftSectionExpr(operand, op) => {
+            v.visit_expr(operand);
+            v.visit_infix(op);
+        }
+        Expr::RightSectionExpr(op, operand) => {
+            v.visit_infix(op);
+            v.visit_expr(operand);
+        }
+        Expr::ListCompExpr { expr, qualifiers } => {
+            v.visit_expr(expr);
+            for qualifier in qualifiers {
+                v.visit_qualifier(qualifier);
+            }
+        }
+        Expr::DoExpr(stmts) => {
+            for stmt in stmts {
+                v.visit_qualifier(stmt);
+            }
+        }
+        Expr::TypedExpr(expr, context, ty) => {
+            v.visit_expr(expr);
+            for pred in context {
+                v.visit_pred(pred);
+            }
+            v.visit_type(ty);
+        }
+        Expr::DictExpr(_) => {}
+        Expr::Located(_, expr) => v.visit_expr(expr),
+    }
+}
+
+pub fn visit_alt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, alt: &'ast Alt) {
+    v.visit_pattern(&alt.pat);
+    v.visit_rhs(&alt.rhs);
+    v.visit_program(&alt.wheres);
+}
+
+pub fn visit_qualifier<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, qualifier: &'ast Qualifier) {
+    match qualifier {
+        Qualifier::Generator(pat, expr) => {
+            v.visit_pattern(pat);
+            v.visit_expr(expr);
+        }
+        Qualifier::Guard(expr) => v.visit_expr(expr),
+        Qualifier::LetQual(decls) => v.visit_program(decls),
+    }
+}
+
+pub fn visit_pattern<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, pat: &'ast Pattern) {
+    match pat {
+        Pattern::WildPat => {}
+        Pattern::VarPat(name) => v.visit_ident(name),
+        Pattern::LitPat(literal) => v.visit_literal(literal),
+        Pattern::ConPat(con, args) => {
+            v.visit_ident(con);
+            for arg in args {
+                v.visit_pattern(arg);
+            }
+        }
+        Pattern::ListPat(pats) | Pattern::TuplePat(pats) => {
+            for pat in pats {
+                v.visit_pattern(pat);
+            }
+        }
+        Pattern::AsPat(name, pat) => {
+            v.visit_ident(name);
+            v.visit_pattern(pat);
+        }
+        Pattern::SigPat(pat, ty) => {
+            v.visit_pattern(pat);
+            v.visit_type(ty);
+        }
+    }
+}
+
+pub fn visit_type<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, ty: &'ast Type) {
+    match ty {
+        Type::VarType(name) | Type::ConType(name) => v.visit_ident(name),
+        Type::AppType(left, right) | Type::FnType(left, right) => {
+            v.visit_type(left);
+            v.visit_type(right);
+        }
+        Type::ListType(ty) => v.visit_type(ty),
+        Type::TupleType(types) => {
+            for ty in types {
+                v.visit_type(ty);
+            }
+        }
+        Type::ForallType(vars, ty) => {
+            for var in vars {
+                v.visit_ident(var);
+            }
+            v.visit_type(ty);
+        }
+    }
+}
+
+pub fn visit_pred<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, pred: &'ast Pred) {
+    v.visit_ident(&pred.class);
+    v.visit_type(&pred.ty);
+}
+
+pub fn visit_infix<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, op: &'ast Infix) {
+    if let Infix::Op(name) = op {
+        v.visit_ident(name);
+    }
+}
+
+/// A traversal that can change the AST in place. It walks the same nodes as
+/// `Visitor`, in the same order.
+pub trait VisitorMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        visit_module_mut(self, module)
+    }
+
+    fn visit_import_mut(&mut self, import: &mut Import) {
+        visit_import_mut(self, import)
+    }
+
+    fn visit_entity_mut(&mut self, entity: &mut Entity) {
+        visit_entity_mut(self, entity)
+    }
+
+    /// Statements can be added or removed here.
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        visit_program_mut(self, program)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        visit_stmt_mut(self, stmt)
+    }
+
+    fn visit_clause_mut(&mut self, clause: &mut Clause) {
+        visit_clause_mut(self, clause)
+    }
+
+    fn visit_rhs_mut(&mut self, rhs: &mut Rhs) {
+        visit_rhs_mut(self, rhs)
+    }
+
+    fn visit_data_decl_mut(&mut self, data: &mut DataDecl) {
+        visit_data_decl_mut(self, data)
+    }
+
+    fn visit_con_decl_mut(&mut self, con: &mut ConDecl) {
+        visit_con_decl_mut(self, con)
+    }
+
+    fn visit_class_decl_mut(&mut self, class: &mut ClassDecl) {
+        visit_class_decl_mut(self, class)
+    }
+
+    fn visit_instance_decl_mut(&mut self, instance: &mut InstanceDecl) {
+        visit_instance_decl_mut(self, instance)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_expr_mut(self, expr)
+    }
+
+    fn visit_alt_mut(&mut self, alt: &mut Alt) {
+        visit_alt_mut(self, alt)
+    }
+
+    fn visit_qualifier_mut(&mut self, qualifier: &mut Qualifier) {
+        visit_qualifier_mut(self, qualifier)
+    }
+
+    fn visit_pattern_mut(&mut self, pat: &mut Pattern) {
+        visit_pattern_mut(self, pat)
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        visit_type_mut(self, ty)
+    }
+
+    fn visit_pred_mut(&mut self, pred: &mut Pred) {
+        visit_pred_mut(self, pred)
+    }
+
+    fn visit_infix_mut(&mut self, op: &mut Infix) {
+        visit_infix_mut(self, op)
+    }
+
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+}
+
+pub fn visit_module_mut<V: VisitorMut + ?Sized>(v: &mut V, module: &mut Module) {
+    v.visit_ident_mut(&mut module.name);
+    for entity in module.exports.iter_mut().flatten() {
+        v.visit_entity_mut(entity);
+    }
+    for import in &mut module.imports {
+        v.visit_import_mut(import);
+    }
+    v.visit_program_mut(&mut module.body);
+}
+
+pub fn visit_import_mut<V: VisitorMut + ?Sized>(v: &mut V, import: &mut Import) {
+    v.visit_ident_mut(&mut import.module);
+    if let Some(alias) = &mut import.alias {
+        v.visit_ident_mut(alias);
+    }
+    for entity in import.entities.iter_mut().flatten() {
+        v.visit_entity_mut(entity);
+    }
+}
+
+pub fn visit_entity_mut<V: VisitorMut + ?Sized>(v: &mut V, entity: &mut Entity) {
+    match entity {
+        Entity::Var(name) | Entity::Module(name) => v.visit_ident_mut(name),
+        Entity::Type(name, members) => {
+            v.visit_ident_mut(name);
+            if let Members::Listed(members) = members {
+                for member in members {
+                    v.visit_ident_mut(member);
+                }
+            }
+        }
+    }
+}
+
+pub fn visit_program_mut<V: VisitorMut + ?Sized>(v: &mut V, program: &mut Program) {
+    for stmt in program {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn visit_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::LetStmt(name, expr) => {
+            v.visit_ident_mut(name);
+            v.visit_expr_mut(expr);
+        }
+        Stmt::ReturnStmt(expr) | Stmt::ExprStmt(expr) => v.visit_expr_mut(expr),
+        Stmt::SigStmt(names, context, ty, _) => {
+            for name in names {
+                v.visit_ident_mut(name);
+            }
+            for pred in context {
+                v.visit_pred_mut(pred);
+            }
+            v.visit_type_mut(ty);
+        }
+        Stmt::FunStmt(name, clauses) => {
+            v.visit_ident_mut(name);
+            for clause in clauses {
+                v.visit_clause_mut(clause);
+            }
+        }
+        Stmt::PatStmt(pat, rhs, wheres) => {
+            v.visit_pattern_mut(pat);
+            v.visit_rhs_mut(rhs);
+            v.visit_program_mut(wheres);
+        }
+        Stmt::DataStmt(data) => v.visit_data_decl_mut(data),
+        Stmt::TypeStmt(name, params, ty) => {
+            v.visit_ident_mut(name);
+            for param in params {
+                v.visit_ident_mut(param);
+            }
+            v.visit_type_mut(ty);
+        }
+        Stmt::ClassStmt(class) => v.visit_class_decl_mut(class),
+        Stmt::InstanceStmt(instance) | Stmt::DerivingStmt(instance) => {
+            v.visit_instance_decl_mut(instance)
+        }
+        Stmt::FixityStmt(_, names) => {
+            for name in names {
+                v.visit_ident_mut(name);
+            }
+        }
+        Stmt::DefaultStmt(types) => {
+            for ty in types {
+                v.visit_type_mut(ty);
+            }
+        }
+        Stmt::ErrorStmt(_) => {}
+    }
+}
+
+pub fn visit_clause_mut<V: VisitorMut + ?Sized>(v: &mut V, clause: &mut Clause) {
+    for param in &mut clause.params {
+        v.visit_pattern_mut(param);
+    }
+    v.visit_rhs_mut(&mut clause.rhs);
+    v.visit_program_mut(&mut clause.wheres);
+}
+
+pub fn visit_rhs_mut<V: VisitorMut + ?Sized>(v: &mut V, rhs: &mut Rhs) {
+    match rhs {
+        Rhs::Plain(expr) => v.visit_expr_mut(expr),
+        Rhs::Guarded(guards) => {
+            for (guard, expr) in guards {
+                v.visit_expr_mut(guard);
+                v.visit_expr_mut(expr);
+            }
+        }
+    }
+}
+
+pub fn visit_data_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, data: &mut DataDecl) {
+    v.visit_ident_mut(&mut data.name);
+    for param in &mut data.params {
+        v.visit_ident_mut(param);
+    }
+    for con in &mut data.constructors {
+        v.visit_con_decl_mut(con);
+    }
+    for class in &mut data.deriving {
+        v.visit_ident_mut(class);
+    }
+}
+
+pub fn visit_con_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, con: &mut ConDecl) {
+    v.visit_ident_mut(&mut con.name);
+    for field in &mut con.fields {
+        v.visit_type_mut(field);
+    }
+    for label in &mut con.labels {
+        v.visit_ident_mut(label);
+    }
+}
+
+pub fn visit_class_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, class: &mut ClassDecl) {
+    for pred in &mut class.context {
+        v.visit_pred_mut(pred);
+    }
+    v.visit_ident_mut(&mut class.name);
+    v.visit_ident_mut(&mut class.var);
+    v.visit_program_mut(&mut class.body);
+}
+
+pub fn visit_instance_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, instance: &mut InstanceDecl) {
+    for pred in &mut instance.context {
+        v.visit_pred_mut(pred);
+    }
+    v.visit_ident_mut(&mut instance.class);
+    v.visit_type_mut(&mut instance.ty);
+    v.visit_program_mut(&mut instance.body);
+}
+
+pub fn visit_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::IdentExpr(name) | Expr::HoleExpr(name) => v.visit_ident_mut(name),
+        Expr::LitExpr(literal) => v.visit_literal_mut(literal),
+        Expr::PrefixExpr(_, operand) => v.visit_expr_mut(operand),
+        Expr::InfixExpr(op, left, right) => {
+            v.visit_expr_mut(left);
+            v.visit_infix_mut(op);
+            v.visit_expr_mut(right);
+        }
+        Expr::InfixChain(first, rest) => {
+            v.visit_expr_mut(first);
+            for (op, operand) in rest {
+                v.visit_infix_mut(op);
+                v.visit_expr_mut(operand);
+            }
+        }
+        Expr::IfExpr {
+            cond,
+            consequence,
+            alternative,
+        } => {
+            v.visit_expr_mut(cond);
+            v.visit_program_mut(consequence);
+            if let Some(alternative) = alternative {
+                v.visit_program_mut(alternative);
+            }
+        }
+        Expr::FnExpr { params, body } => {
+            for param in params {
+                v.visit_ident_mut(param);
+            }
+            v.visit_program_mut(body);
+        }
+        Expr::CallExpr {
+            function,
+            arguments,
+        } => {
+            v.visit_expr_mut(function);
+            for argument in arguments {
+                v.visit_expr_mut(argument);
+            }
+        }
+        Expr::ArrayExpr(exprs) | Expr::TupleExpr(exprs) => {
+            for expr in exprs {
+                v.visit_expr_mut(expr);
+            }
+        }
+        Expr::HashExpr(pairs) => {
+            for (key, value) in pairs {
+                v.visit_literal_mut(key);
+                v.visit_expr_mut(value);
+            }
+        }
+        Expr::IndexExpr { array, index } => {
+            v.visit_expr_mut(array);
+            v.visit_expr_mut(index);
+        }
+        Expr::LambdaExpr { params, body } => {
+            for param in params {
+                v.visit_pattern_mut(param);
+            }
+            v.visit_expr_mut(body);
+        }
+        Expr::CaseExpr { scrutinee, alts } => {
+            v.visit_expr_mut(scrutinee);
+            for alt in alts {
+                v.visit_alt_mut(alt);
+            }
+        }
+        Expr::LambdaCaseExpr(alts) => {
+            for alt in alts {
+                v.visit_alt_mut(alt);
+            }
+        }
+        Expr::MultiIfExpr(guards) => {
+            for (guard, expr) in guards {
+                v.visit_expr_mut(guard);
+                v.visit_expr_mut(expr);
+            }
+        }
+        Expr::TupleSectionExpr(components) => {
+            for expr in components.iter_mut().flatten() {
+                v.visit_expr_mut(expr);
+            }
+        }
+        Expr::LetExpr { bindings, body } => {
+            v.visit_program_mut(bindings);
+            v.visit_expr_mut(body);
+        }
+        Expr::LeftSectionExpr(operand, op) => {
+            v.visit_expr_mut(operand);
+            v.visit_infix_mut(op);
+        }
+        Expr::RightSectionExpr(op, operand) => {
+            v.visit_infix_mut(op);
+            v.visit_expr_mut(operand);
+        }
+        Expr::ListCompExpr { expr, qualifiers } => {
+            v.visit_expr_mut(expr);
+            for qualifier in qualifiers {
+                v.visit_qualifier_mut(qualifier);
+            }
+        }
+        Expr::DoExpr(stmts) => {
+            for stmt in stmts {
+                v.visit_qualifier_mut(stmt);
+            }
+        }
+        Expr::TypedExpr(expr, context, ty) => {
+            v.visit_expr_mut(expr);
+            for pred in context {
+                v.visit_pred_mut(pred);
+            }
+            v.visit_type_mut(ty);
+        }
+        Expr::DictExpr(_) => {}
+        Expr::Located(_, expr) => v.visit_expr_mut(expr),
+    }
+}
+
+pub fn visit_alt_mut<V: VisitorMut + ?Sized>(v: &mut V, alt: &mut Alt) {
+    v.visit_pattern_mut(&mut alt.pat);
+    v.visit_rhs_mut(&mut alt.rhs);
+    v.visit_program_mut(&mut alt.wheres);
+}
+
+pub fn visit_qualifier_mut<V: VisitorMut + ?Sized>(v: &mut V, qualifier: &mut Qualifier) {
+    match qualifier {
+        Qualifier::Generator(pat, expr) => {
+            v.visit_pattern_mut(pat);
+            v.visit_expr_mut(expr);
+        }
+        Qualifier::Guard(expr) => v.visit_expr_mut(expr),
+        Qualifier::LetQual(decls) => v.visit_program_mut(decls),
+    }
+}
+
+pub fn visit_pattern_mut<V: VisitorMut + ?Sized>(v: &mut V, pat: &mut Pattern) {
+    match pat {
+        Pattern::WildPat => {}
+        Pattern::VarPat(name) => v.visit_ident_mut(name),
+        Pattern::LitPat(literal) => v.visit_literal_mut(literal),
+        Pattern::ConPat(con, args) => {
+            v.visit_ident_mut(con);
+            for arg in args {
+                v.visit_pattern_mut(arg);
+            }
+        }
+        Pattern::ListPat(pats) | Pattern::TuplePat(pats) => {
+            for pat in pats {
+                v.visit_pattern_mut(pat);
+            }
+        }
+        Pattern::AsPat(name, pat) => {
+            v.visit_ident_mut(name);
+            v.visit_pattern_mut(pat);
+        }
+        Pattern::SigPat(pat, ty) => {
+            v.visit_pattern_mut(pat);
+            v.visit_type_mut(ty);
+        }
+    }
+}
+
+pub fn visit_type_mut<V: VisitorMut + ?Sized>(v: &mut V, ty: &mut Type) {
+    match ty {
+        Type::VarType(name) | Type::ConType(name) => v.visit_ident_mut(name),
+        Type::AppType(left, right) | Type::FnType(left, right) => {
+            v.visit_type_mut(left);
+            v.visit_type_mut(right);
+        }
+        Type::ListType(ty) => v.visit_type_mut(ty),
+        Type::TupleType(types) => {
+            for ty in types {
+                v.visit_type_mut(ty);
+            }
+        }
+        Type::ForallType(vars, ty) => {
+            for var in vars {
+                v.visit_ident_mut(var);
+            }
+            v.visit_type_mut(ty);
+        }
+    }
+}
+
+pub fn visit_pred_mut<V: VisitorMut + ?Sized>(v: &mut V, pred: &mut Pred) {
+    v.visit_ident_mut(&mut pred.class);
+    v.visit_type_mut(&mut pred.ty);
+}
+
+pub fn visit_infix_mut<V: VisitorMut + ?Sized>(v: &mut V, op: &mut Infix) {
+    if let Infix::Op(name) = op {
+        v.visit_ident_mut(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::Tokens;
+    use crate::lexer::Lexer;
+    use crate::parser::fixity::Fixities;
+    use crate::parser::pretty::Printer;
+    use crate::parser::Parser;
+    use std::collections::HashSet;
+
+    fn parse(input: &str) -> Program {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).unwrap();
+        Fixities::new().resolve_program(program).unwrap()
+    }
+
+    // The variables a program uses, ignoring scopes.
+    #[derive(Default)]
+    struct Uses<'ast>(HashSet<&'ast str>);
+
+    impl<'ast> Visitor<'ast> for Uses<'ast> {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let Expr::IdentExpr(Ident(name)) = expr {
+                self.0.insert(name);
+            }
+            visit_expr(self, expr)
+        }
+
+        fn visit_infix(&mut self, op: &'ast Infix) {
+            self.0.insert(op.name());
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let program = parse(
+            "f (x:xs) | x > 0 = g x where g y = [z * y | z <- xs, odd z]\n\
+             h = \\(a, b) -> case a of { Just c -> c `div` b; _ -> (+ 1) b }",
+        );
+        let mut uses = Uses::default();
+        uses.visit_program(&program);
+        let mut uses: Vec<_> = uses.0.into_iter().collect();
+        uses.sort();
+        assert_eq!(
+            uses,
+            ["*", "+", ">", "a", "b", "c", "div", "g", "odd", "x", "xs", "y", "z"]
+        );
+    }
+
+    // Counts every name, to check that the walk reaches all kinds of nodes.
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl<'ast> Visitor<'ast> for Names {
+        fn visit_ident(&mut self, Ident(name): &'ast Ident) {
+            self.0.push(name.clone());
+        }
+    }
+
+    #[test]
+    fn test_declarations() {
+        let program = parse(
+            "data T a = L | N (T a) a deriving Show\n\
+             class Eq a => C a where { m :: a -> [a] }\n\
+             instance C b => C (T b) where { m t = [t] }\n\
+             infixr 5 +++\n\
+             s :: (Int, Bool)",
+        );
+        let mut names = Names::default();
+        names.visit_program(&program);
+        assert_eq!(
+            names.0.join(" "),
+            "T a L N T a a Show Eq a C a m a a C b C T b m t t +++ s Int Bool"
+        );
+    }
+
+    struct Rename<'a>(&'a str, &'a str);
+
+    impl VisitorMut for Rename<'_> {
+        fn visit_ident_mut(&mut self, ident: &mut Ident) {
+            if ident.0 == self.0 {
+                ident.0 = self.1.to_owned();
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut program = parse("f x = let y = x in \\x -> x + y\nlet z = f 1;");
+        Rename("x", "v").visit_program_mut(&mut program);
+        assert_eq!(
+            Printer::new().program(&program),
+            "f v = let { y = v } in \\v -> v + y\nz = f 1"
+        );
+    }
+}
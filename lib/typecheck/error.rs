@@ -0,0 +1,378 @@
+use crate::lexer::token::{Span, Token, Tokens};
+use crate::lexer::Lexer;
+use crate::parser::ast::Expr;
+use crate::parser::rename::source_name;
+use crate::typecheck::source_text;
+use std::fmt;
+
+/// How errors are reported: as text for people, or one JSON object a line
+/// for editors and other tools.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Why a program is ill-typed, and where.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TypeError {
+    pub message: String,
+    /// The types that don't match, when that is the error
+    pub mismatch: Option<Box<Mismatch>>,
+    /// The argument of a call the error is in
+    pub argument: Option<Box<Argument>>,
+    /// The innermost expression being checked, as written
+    pub expr: Option<String>,
+    /// The definitions the error is in, the innermost first
+    pub bindings: Vec<String>,
+    /// Where the argument or expression is in the source, or else the
+    /// definition. `None` when the module was parsed without spans. Boxed,
+    /// as errors are passed around a lot more than they are located.
+    pub span: Option<Box<Span>>,
+}
+
+/// The type a context expects and the one it is given, as printed in the
+/// message.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Mismatch {
+    pub expected: String,
+    pub actual: String,
+}
+
+/// The `n`-th argument of a call, counting from 1, with the function it is
+/// passed to, both as written.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Argument {
+    pub position: usize,
+    pub function: String,
+    pub argument: String,
+}
+
+impl TypeError {
+    pub fn new(message: String) -> Self {
+        TypeError {
+            message,
+            mismatch: None,
+            argument: None,
+            expr: None,
+            bindings: vec![],
+            span: None,
+        }
+    }
+
+    /// Types that should be equal and aren't.
+    pub fn mismatch(message: String, expected: String, actual: String) -> Self {
+        TypeError {
+            mismatch: Some(Box::new(Mismatch { expected, actual })),
+            ..TypeError::new(message)
+        }
+    }
+
+    /// Records the expression the error arose in, unless it is already
+    /// known to be in a part of it, and where the parser found it.
+    pub fn in_expr(mut self, expr: &Expr) -> Self {
+        if self.expr.is_none() {
+            self.expr = Some(source_text(expr));
+        }
+        self.at(expr)
+    }
+
+    /// Records that the error is the argument's not fitting the function,
+    /// rather than in a part of the argument.
+    pub fn in_argument(mut self, position: usize, function: &Expr, argument: &Expr) -> Self {
+        if self.expr.is_none() && self.argument.is_none() {
+            self.argument = Some(Box::new(Argument {
+                position,
+                function: source_text(function),
+                argument: source_text(argument),
+            }));
+            return self.at(argument);
+        }
+        self
+    }
+
+    /// Records a definition the error is in, after those inside it.
+    pub fn in_binding(mut self, name: &str) -> Self {
+        // the definitions a desugaring introduces are not in the source
+        let name = source_name(name);
+        if !name.starts_with('$') && self.bindings.last().map(String::as_str) != Some(name) {
+            self.bindings.push(name.to_owned());
+        }
+        self
+    }
+
+    /// Records where the source the error is in is, such as a signature,
+    /// unless a span inside it is known.
+    pub fn at_span(mut self, span: Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span.map(Box::new);
+        }
+        self
+    }
+
+    // The span of the expression, unless one inside it is known
+    fn at(self, expr: &Expr) -> Self {
+        self.at_span(expr.span())
+    }
+
+    /// Finds the error in the tokens its module was parsed from when the
+    /// expressions it is in have no spans: the outermost definition it is
+    /// in.
+    pub fn locate(mut self, tokens: &Tokens) -> Self {
+        if self.span.is_none() {
+            self.span = locate(tokens, &self.bindings, None, None);
+        }
+        self
+    }
+
+    /// The error as a JSON object on one line.
+    pub fn to_json(&self) -> String {
+        let string = |s: Option<&String>| s.map_or("null".to_owned(), |s| json_string(s));
+        let argument = match self.argument {
+            Some(ref argument) => format!(
+                "{{\"position\":{},\"function\":{},\"argument\":{}}}",
+                argument.position,
+                json_string(&argument.function),
+                json_string(&argument.argument)
+            ),
+            None => "null".to_owned(),
+        };
+        let bindings: Vec<String> = self.bindings.iter().map(|b| json_string(b)).collect();
+        format!(
+            "{{\"message\":{},\"span\":{},\"expected\":{},\"actual\":{},\"argument\":{},\
+             \"expression\":{},\"bindings\":[{}]}}",
+            json_string(&self.message),
+            json_span(&self.span),
+            string(self.mismatch.as_ref().map(|m| &m.expected)),
+            string(self.mismatch.as_ref().map(|m| &m.actual)),
+            argument,
+            string(self.expr.as_ref()),
+            bindings.join(",")
+        )
+    }
+
+    /// The error in the format asked for.
+    pub fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Human => self.to_string(),
+            ErrorFormat::Json => self.to_json(),
+        }
+    }
+}
+
+/// `3:7: Couldn't match ...` followed by an indented line for the argument,
+/// one for the expression and one for each definition it is in, as in GHC.
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref span) = self.span {
+            write!(f, "{}:{}: ", span.line, span.column)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(ref argument) = self.argument {
+            write!(
+                f,
+                "\n  In the {} argument of `{}`, namely `{}`",
+                ordinal(argument.position),
+                argument.function,
+                argument.argument
+            )?;
+        }
+        if let Some(ref expr) = self.expr {
+            write!(f, "\n  In the expression: {}", expr)?;
+        }
+        for binding in &self.bindings {
+            write!(f, "\n  In the definition of `{}`", binding)?;
+        }
+        Ok(())
+    }
+}
+
+/// A program that type checks but likely doesn't do what its author
+/// meant, such as a `case` missing a constructor.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Warning {
+    pub message: String,
+    /// The source text to locate the warning at
+    pub text: Option<String>,
+    /// The equation of the outermost definition the warning is about
+    pub equation: Option<usize>,
+    /// The definitions the warning is in, the innermost first
+    pub bindings: Vec<String>,
+    pub span: Option<Box<Span>>,
+}
+
+impl Warning {
+    pub fn new(message: String, bindings: &[String]) -> Self {
+        Warning {
+            message,
+            text: None,
+            equation: None,
+            bindings: bindings.iter().rev().map(|b| source_name(b).to_owned()).collect(),
+            span: None,
+        }
+    }
+
+    /// Finds the warning in the tokens its module was parsed from, as
+    /// `TypeError::locate` does an error.
+    pub fn locate(mut self, tokens: &Tokens) -> Self {
+        if self.span.is_none() {
+            let text = self.text.as_deref();
+            self.span = locate(tokens, &self.bindings, text, self.equation);
+        }
+        self
+    }
+
+    /// The warning as a JSON object on one line.
+    pub fn to_json(&self) -> String {
+        let bindings: Vec<String> = self.bindings.iter().map(|b| json_string(b)).collect();
+        format!(
+            "{{\"warning\":{},\"span\":{},\"bindings\":[{}]}}",
+            json_string(&self.message),
+            json_span(&self.span),
+            bindings.join(",")
+        )
+    }
+
+    pub fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Human => self.to_string(),
+            ErrorFormat::Json => self.to_json(),
+        }
+    }
+}
+
+/// `3:1: warning: Pattern match(es) are non-exhaustive ...` with a line for
+/// each definition it is in.
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref span) = self.span {
+            write!(f, "{}:{}: ", span.line, span.column)?;
+        }
+        write!(f, "warning: {}", self.message)?;
+        for binding in &self.bindings {
+            write!(f, "\n  In the definition of `{}`", binding)?;
+        }
+        Ok(())
+    }
+}
+
+fn ordinal(n: usize) -> String {
+    const ORDINALS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    match ORDINALS.get(n.wrapping_sub(1)) {
+        Some(ordinal) => ordinal.to_string(),
+        None => format!("{}th", n),
+    }
+}
+
+fn json_span(span: &Option<Box<Span>>) -> String {
+    match span {
+        Some(span) => format!(
+            "{{\"line\":{},\"column\":{},\"start\":{},\"end\":{}}}",
+            span.line, span.column, span.start, span.end
+        ),
+        None => "null".to_owned(),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// The span of the first occurrence of the tokens of the text in the
+// equations of the outermost of the definitions, or in the `n`-th, or else
+// of the definition
+fn locate(
+    tokens: &Tokens,
+    bindings: &[String],
+    text: Option<&str>,
+    equation: Option<usize>,
+) -> Option<Box<Span>> {
+    if tokens.span.is_empty() {
+        return None;
+    }
+    let mut ranges = match bindings.last() {
+        Some(name) => equations(tokens, name),
+        None => vec![(0, tokens.tok.len())],
+    };
+    if let Some(range) = equation.and_then(|n| ranges.get(n).copied()) {
+        ranges = vec![range];
+    }
+    let found = text.and_then(|text| {
+        let (_, needle) = Lexer::lex_tokens(text).ok()?;
+        let needle: Vec<&Token> = needle.iter().filter(|t| significant(t)).collect();
+        ranges.iter().find_map(|&range| find(tokens, range, &needle))
+    });
+    let span = match bindings.is_empty() {
+        true => found,
+        false => found.or_else(|| ranges.first().and_then(|&(start, _)| tokens.span_at(start))),
+    };
+    span.map(Box::new)
+}
+
+// The printed form of an expression has the parentheses it needs, the
+// source those its author wrote
+fn significant(token: &Token) -> bool {
+    !matches!(token, Token::LParen | Token::RParen | Token::EOF)
+}
+
+// The token ranges of the equations defining a name: from a line starting
+// with the name, signatures aside, to the next line starting no further
+// right
+fn equations(tokens: &Tokens, name: &str) -> Vec<(usize, usize)> {
+    let starts_line = |i: usize| i == 0 || tokens.span[i - 1].line < tokens.span[i].line;
+    let mut ranges = vec![];
+    for (i, token) in tokens.tok.iter().enumerate() {
+        let defines = matches!(token, Token::Ident(n) | Token::Operator(n) if n == name)
+            && starts_line(i)
+            && !matches!(
+                tokens.tok.get(i + 1),
+                Some(Token::DoubleColon | Token::Comma)
+            );
+        if !defines {
+            continue;
+        }
+        let column = tokens.span[i].column;
+        let end = (i + 1..tokens.tok.len())
+            .find(|&j| starts_line(j) && tokens.span[j].column <= column)
+            .unwrap_or(tokens.tok.len());
+        ranges.push((i, end));
+    }
+    ranges
+}
+
+// The span of the first occurrence of the tokens in the range
+fn find(tokens: &Tokens, (start, end): (usize, usize), needle: &[&Token]) -> Option<Span> {
+    let haystack: Vec<usize> = (start..end)
+        .filter(|&i| significant(&tokens.tok[i]))
+        .collect();
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).find_map(|window| {
+        let matches = window
+            .iter()
+            .zip(needle)
+            .all(|(&i, token)| tokens.tok[i] == **token);
+        let (first, last) = (tokens.span[window[0]], tokens.span[*window.last()?]);
+        matches.then_some(Span {
+            end: last.end,
+            ..first
+        })
+    })
+}
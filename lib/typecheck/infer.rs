@@ -0,0 +1,2121 @@
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::depend::*;
+use crate::parser::desugar::*;
+use crate::parser::fold::{self, Fold};
+use crate::parser::rename::source_name;
+use crate::typecheck::kinds::KindInfer;
+use crate::typecheck::types::{Pred, Scheme, Tidy, Ty};
+use crate::typecheck::{source_text, TypeEnv, TypeError};
+use std::collections::{HashMap, HashSet};
+
+type Result<T> = std::result::Result<T, TypeError>;
+
+// A unification variable, unbound with the level of the innermost binding
+// group it may be generalized at, or bound to a type
+#[derive(Debug, Clone)]
+enum VarState {
+    Unbound(usize),
+    Bound(Ty),
+}
+
+// Why two types don't unify
+enum Mismatch {
+    Types,
+    Occurs(Ty, Ty),
+    Escape(String),
+}
+
+// A class constraint to solve, and the name of the dictionary that is its
+// evidence in the elaborated program
+#[derive(Debug, Clone)]
+struct Wanted {
+    pred: Pred,
+    dict: String,
+    // what gives rise to the constraint, such as "a use of `show`"
+    origin: String,
+    // the innermost expression it arises in, as written, and where
+    expr: Option<String>,
+    span: Option<Span>,
+    // the definitions it arises in, the innermost first
+    bindings: Vec<String>,
+}
+
+impl Wanted {
+    // A constraint the solution of this one needs
+    fn implied(&self, pred: Pred) -> Wanted {
+        Wanted {
+            pred,
+            dict: String::new(),
+            ..self.clone()
+        }
+    }
+
+    fn error(&self, message: String) -> TypeError {
+        let mut err = TypeError::new(message);
+        err.expr = self.expr.clone();
+        err.span = self.span.map(Box::new);
+        self.bindings.iter().fold(err, |err, binding| err.in_binding(binding))
+    }
+}
+
+// A hole met during inference, reported once the types are known
+struct Hole {
+    name: String,
+    ty: Ty,
+    locals: Vec<(String, Scheme)>,
+    // the constraints the enclosing signatures give
+    givens: Vec<(Pred, Dict)>,
+    bindings: Vec<String>,
+    span: Option<Span>,
+    // the level of its scope, deep enough for the skolems it may be used at
+    level: usize,
+}
+
+/// Hindley–Milner inference of the types of a module, Algorithm J with
+/// levels: the type variables of a binding group are generalized when they
+/// don't occur in the types of the enclosing scopes, which is when their
+/// level is deeper than the group's.
+///
+/// Inference also elaborates the module into explicit dictionary passing.
+/// Each use of an overloaded name is applied to a placeholder dictionary per
+/// constraint of its type; the constraints are solved by the instances and
+/// the signatures' contexts when their binding group is done, and those left
+/// on the group's type variables become its dictionary parameters. The
+/// solutions replace the placeholders once the whole module is checked.
+pub struct Infer<'env> {
+    env: &'env TypeEnv,
+    vars: Vec<VarState>,
+    // the level of each skolem, which no variable of a shallower level may
+    // be bound to
+    skolems: Vec<usize>,
+    // the names of the type variables of the signatures being checked
+    rigid: Vec<String>,
+    level: usize,
+    wanteds: Vec<Wanted>,
+    // the dictionary of each placeholder solved so far
+    evidence: HashMap<String, Dict>,
+    dicts: usize,
+    globals: HashMap<String, Scheme>,
+    builtins: HashSet<String>,
+    // the imports from the prelude, which the evaluator runs unelaborated
+    prelude: HashSet<String>,
+    // the types an ambiguous type variable is defaulted to, the first that
+    // satisfies its constraints, and whether GHCi's extended rules apply
+    defaults: Vec<Ty>,
+    extended: bool,
+    // the types of the names in scope, the top level first
+    scopes: Vec<HashMap<String, Scheme>>,
+    // the type variables in scope, bound by `forall` or a pattern signature
+    tyvars: Vec<HashMap<String, Ty>>,
+    holes: Vec<Hole>,
+    // the constraints of the signatures being checked, innermost last
+    givens: Vec<(Pred, Dict)>,
+    // the definitions being checked, innermost last
+    bindings: Vec<String>,
+    pub errors: Vec<TypeError>,
+}
+
+impl<'env> Infer<'env> {
+    /// Inference at the top level of a module, whose constructors, labels
+    /// and methods are in `top`.
+    pub fn new(
+        env: &'env TypeEnv,
+        top: HashMap<String, Scheme>,
+        globals: HashMap<String, Scheme>,
+        builtins: HashSet<String>,
+        prelude: HashSet<String>,
+    ) -> Self {
+        Infer {
+            env,
+            vars: vec![],
+            skolems: vec![],
+            rigid: vec![],
+            level: 0,
+            wanteds: vec![],
+            evidence: HashMap::new(),
+            dicts: 0,
+            globals,
+            builtins,
+            prelude,
+            defaults: vec![Ty::con("Integer"), Ty::con("Double")],
+            extended: false,
+            scopes: vec![top],
+            tyvars: vec![HashMap::new()],
+            holes: vec![],
+            givens: vec![],
+            bindings: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Defaults to the given types, `default (Integer, Double)` unless the
+    /// module declares others. The extended rules of an interactive session
+    /// also default the variables of `Show`, `Eq`, `Ord` and `Monad`
+    /// constraints.
+    pub fn with_defaults(mut self, defaults: Vec<Ty>, extended: bool) -> Self {
+        self.defaults = defaults;
+        self.extended = extended;
+        self
+    }
+
+    fn fresh(&mut self) -> Ty {
+        self.vars.push(VarState::Unbound(self.level));
+        Ty::Var(self.vars.len() - 1)
+    }
+
+    // A skolem named after the type variable, with a number when a type
+    // variable of the same name is already in scope
+    fn skolem(&mut self, name: &str) -> Ty {
+        let shadowed = self.rigid.iter().filter(|rigid| *rigid == name).count();
+        self.rigid.push(name.to_owned());
+        self.skolems.push(self.level);
+        let name = match shadowed {
+            0 => name.to_owned(),
+            n => format!("{}{}", name, n),
+        };
+        Ty::Skolem(self.skolems.len() - 1, name)
+    }
+
+    // The type with its outermost bound variables replaced
+    fn shallow(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(v) => match self.vars[*v] {
+                VarState::Bound(ref ty) => self.shallow(ty),
+                VarState::Unbound(_) => ty.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// The type with all its bound variables replaced.
+    pub fn zonk(&self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::App(f, arg) => Ty::app(self.zonk(&f), self.zonk(&arg)),
+            ty => ty,
+        }
+    }
+
+    /// The types of the top-level names, with their variables fixed by the
+    /// uses of the names since.
+    pub fn top_level(&mut self) -> HashMap<String, Scheme> {
+        let top = std::mem::take(&mut self.scopes[0]);
+        top.into_iter()
+            .map(|(name, scheme)| (name, self.zonk_scheme(&scheme)))
+            .collect()
+    }
+
+    fn zonk_scheme(&self, scheme: &Scheme) -> Scheme {
+        Scheme {
+            names: scheme.names.clone(),
+            preds: scheme
+                .preds
+                .iter()
+                .map(|p| Pred {
+                    class: p.class.clone(),
+                    ty: self.zonk(&p.ty),
+                })
+                .collect(),
+            ty: self.zonk(&scheme.ty),
+        }
+    }
+
+    /// Makes the types equal, or explains why they can't be.
+    pub fn unify(&mut self, expected: &Ty, actual: &Ty) -> Result<()> {
+        self.unify_types(expected, actual).map_err(|mismatch| {
+            let (expected, actual) = (self.zonk(expected), self.zonk(actual));
+            let mut tidy = Tidy::new(&[&expected, &actual]);
+            let (expected, actual) = (tidy.ty(&expected), tidy.ty(&actual));
+            let message = match mismatch {
+                Mismatch::Types => format!(
+                    "Couldn't match expected type `{}` with actual type `{}`",
+                    expected, actual
+                ),
+                Mismatch::Occurs(var, ty) => format!(
+                    "Occurs check: cannot construct the infinite type: {} ~ {}",
+                    tidy.ty(&var),
+                    tidy.ty(&self.zonk(&ty))
+                ),
+                Mismatch::Escape(name) => format!(
+                    "Couldn't match expected type `{}` with actual type `{}`: \
+                     the type variable `{}` would escape its scope",
+                    expected, actual, name
+                ),
+            };
+            TypeError::mismatch(message, expected.to_string(), actual.to_string())
+        })
+    }
+
+    fn unify_types(&mut self, a: &Ty, b: &Ty) -> std::result::Result<(), Mismatch> {
+        match (self.shallow(a), self.shallow(b)) {
+            // a variable unified with a Monkey value is a Monkey value, its
+            // constraints solved as the program runs
+            (Ty::Var(v), Ty::Dynamic) | (Ty::Dynamic, Ty::Var(v)) => self.bind(v, Ty::Dynamic),
+            (Ty::Dynamic, _) | (_, Ty::Dynamic) => Ok(()),
+            (Ty::Var(x), Ty::Var(y)) if x == y => Ok(()),
+            (Ty::Var(v), ty) | (ty, Ty::Var(v)) => self.bind(v, ty),
+            (Ty::Skolem(x, _), Ty::Skolem(y, _)) if x == y => Ok(()),
+            (Ty::Con(x), Ty::Con(y)) if x == y => Ok(()),
+            (Ty::App(f, x), Ty::App(g, y)) => {
+                self.unify_types(&f, &g)?;
+                self.unify_types(&x, &y)
+            }
+            _ => Err(Mismatch::Types),
+        }
+    }
+
+    fn bind(&mut self, var: usize, ty: Ty) -> std::result::Result<(), Mismatch> {
+        let level = match self.vars[var] {
+            VarState::Unbound(level) => level,
+            VarState::Bound(_) => unreachable!("binding a bound variable"),
+        };
+        self.adjust(var, level, &ty)
+            .map_err(|mismatch| match mismatch {
+                Mismatch::Occurs(..) => Mismatch::Occurs(Ty::Var(var), ty.clone()),
+                mismatch => mismatch,
+            })?;
+        self.vars[var] = VarState::Bound(ty);
+        Ok(())
+    }
+
+    // The occurs check, which also brings the variables of the type to the
+    // level of the variable bound to it: they can now only be generalized
+    // where it can.
+    fn adjust(&mut self, var: usize, level: usize, ty: &Ty) -> std::result::Result<(), Mismatch> {
+        match self.shallow(ty) {
+            Ty::Var(v) if v == var => Err(Mismatch::Occurs(Ty::Var(v), ty.clone())),
+            Ty::Var(v) => {
+                if let VarState::Unbound(l) = self.vars[v] {
+                    self.vars[v] = VarState::Unbound(l.min(level));
+                }
+                Ok(())
+            }
+            Ty::Skolem(s, name) if self.skolems[s] > level => Err(Mismatch::Escape(name)),
+            Ty::App(f, arg) => {
+                self.adjust(var, level, &f)?;
+                self.adjust(var, level, &arg)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Whether a type only mentions variables and skolems of the enclosing
+    // scopes
+    fn is_outer(&self, ty: &Ty) -> bool {
+        match self.shallow(ty) {
+            Ty::Var(v) => matches!(self.vars[v], VarState::Unbound(l) if l <= self.level),
+            Ty::Skolem(s, _) => self.skolems[s] <= self.level,
+            Ty::App(f, arg) => self.is_outer(&f) && self.is_outer(&arg),
+            _ => true,
+        }
+    }
+
+    // The unbound variables of a type deeper than the current level
+    fn generalizable(&self, ty: &Ty, vars: &mut Vec<usize>) {
+        match self.shallow(ty) {
+            Ty::Var(v) => {
+                let deeper = matches!(self.vars[v], VarState::Unbound(l) if l > self.level);
+                if deeper && !vars.contains(&v) {
+                    vars.push(v);
+                }
+            }
+            Ty::App(f, arg) => {
+                self.generalizable(&f, vars);
+                self.generalizable(&arg, vars);
+            }
+            _ => {}
+        }
+    }
+
+    // The type with the given variables quantified
+    fn quantify(&self, ty: &Ty, vars: &[usize]) -> Ty {
+        match self.shallow(ty) {
+            Ty::Var(v) => match vars.iter().position(|&w| w == v) {
+                Some(i) => Ty::Gen(i),
+                None => Ty::Var(v),
+            },
+            Ty::App(f, arg) => Ty::app(self.quantify(&f, vars), self.quantify(&arg, vars)),
+            ty => ty,
+        }
+    }
+
+    // The type of a use of a name, and the placeholders for the dictionaries
+    // of the constraints of its scheme
+    fn instantiate(&mut self, scheme: &Scheme, origin: &str) -> (Ty, Vec<String>) {
+        let vars: Vec<Ty> = scheme.names.iter().map(|_| self.fresh()).collect();
+        let dicts = scheme
+            .preds
+            .iter()
+            .map(|p| self.want(p.instantiate(&vars), origin))
+            .collect();
+        (scheme.ty.instantiate(&vars), dicts)
+    }
+
+    fn want(&mut self, pred: Pred, origin: &str) -> String {
+        let dict = self.fresh_dict();
+        self.wanteds.push(Wanted {
+            pred,
+            dict: dict.clone(),
+            origin: origin.to_owned(),
+            expr: None,
+            span: None,
+            bindings: self.bindings.iter().rev().cloned().collect(),
+        });
+        dict
+    }
+
+    fn fresh_dict(&mut self) -> String {
+        self.dicts += 1;
+        format!("$d{}", self.dicts)
+    }
+
+    // The argument and result of a function type
+    fn split_fun(&mut self, ty: &Ty) -> Result<(Ty, Ty)> {
+        let ty = self.shallow(ty);
+        if let Ty::Dynamic = ty {
+            return Ok((Ty::Dynamic, Ty::Dynamic));
+        }
+        if let Some((arg, result)) = ty.as_fun() {
+            return Ok((arg.clone(), result.clone()));
+        }
+        let (arg, result) = (self.fresh(), self.fresh());
+        self.unify(&Ty::fun(arg.clone(), result.clone()), &ty)?;
+        Ok((arg, result))
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.tyvars.push(HashMap::new());
+    }
+
+    fn leave_scope(&mut self) {
+        self.scopes.pop();
+        self.tyvars.pop();
+    }
+
+    fn bind_name(&mut self, name: &str, scheme: Scheme) {
+        let scope = self.scopes.last_mut().expect("a scope");
+        scope.insert(name.to_owned(), scheme);
+    }
+
+    fn scoped_tyvars(&self) -> HashMap<String, Ty> {
+        self.tyvars.iter().flatten().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    // The type of a name in scope. Names the renamer allows but that no
+    // import binds are those of the prelude and the builtin syntax.
+    fn lookup(&self, name: &str) -> Option<Scheme> {
+        if let Some(scheme) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return Some(scheme.clone());
+        }
+        if let Some(scheme) = self.globals.get(name) {
+            return Some(scheme.clone());
+        }
+        if let Some(scheme) = self.env.scheme("Prelude", name) {
+            return Some(scheme.clone());
+        }
+        let a = || vec!["a".to_owned()];
+        match name {
+            ":" => Some(Scheme {
+                names: a(),
+                preds: vec![],
+                ty: Ty::fun(Ty::Gen(0), Ty::fun(Ty::list(Ty::Gen(0)), Ty::list(Ty::Gen(0)))),
+            }),
+            "[]" => Some(Scheme {
+                names: a(),
+                preds: vec![],
+                ty: Ty::list(Ty::Gen(0)),
+            }),
+            name => {
+                let arity = Ident(name.to_owned()).tuple_arity()?;
+                let components: Vec<Ty> = (0..arity).map(Ty::Gen).collect();
+                let ty = components
+                    .iter()
+                    .rev()
+                    .fold(Ty::tuple(components.clone()), |ty, c| Ty::fun(c.clone(), ty));
+                Some(Scheme {
+                    names: (0..arity).map(crate::typecheck::types::var_name).collect(),
+                    preds: vec![],
+                    ty,
+                })
+            }
+        }
+    }
+
+    // The type of a use of a name, and the use applied to its dictionaries
+    fn lookup_var(&mut self, name: &str) -> Result<(Ty, Expr)> {
+        let scheme = self.lookup(name).ok_or_else(|| {
+            TypeError::new(format!("Variable not in scope: {}", source_name(name)))
+        })?;
+        let (ty, dicts) = self.instantiate(&scheme, &use_of(name));
+        let var = Expr::IdentExpr(Ident(name.to_owned()));
+        match self.takes_dictionaries(name) {
+            true => Ok((ty, with_dictionaries(var, &dicts))),
+            false => Ok((ty, var)),
+        }
+    }
+
+    // The evaluator runs the prelude without dictionaries, so of its names
+    // only the methods take them.
+    fn takes_dictionaries(&self, name: &str) -> bool {
+        if self.scopes.iter().any(|scope| scope.contains_key(name)) {
+            return true;
+        }
+        if self.globals.contains_key(name) && !self.prelude.contains(name) {
+            return true;
+        }
+        let name = Ident(name.to_owned());
+        let method = name.unqualified();
+        self.env.classes.values().any(|class| class.methods.contains_key(method))
+    }
+
+    // A builtin of the evaluator takes all the arguments of `f(x, y)`
+    fn is_builtin(&self, expr: &Expr) -> bool {
+        match expr.unlocated() {
+            Expr::IdentExpr(Ident(name)) => {
+                self.builtins.contains(name) && !self.scopes.iter().any(|s| s.contains_key(name))
+            }
+            _ => false,
+        }
+    }
+
+    /// Infers the types of a module's definitions and expressions, recording
+    /// the errors of each binding group and going on with the next. The
+    /// module comes back elaborated, its binding groups in dependency order.
+    pub fn check_program(&mut self, body: &Program) -> Program {
+        let mut errors = vec![];
+        let sigs = self.signatures(body, &mut errors);
+        self.errors.extend(errors);
+        for (name, (scheme, _)) in &sigs {
+            self.bind_name(name, scheme.clone());
+        }
+        let mut program = vec![];
+        for group in dependency_order(body.clone()) {
+            let start = self.wanteds.len();
+            let result = match group {
+                Group::Stmt(Stmt::ExprStmt(ref expr)) => {
+                    self.infer_expr(expr).map(|(_, expr)| vec![Stmt::ExprStmt(expr)])
+                }
+                Group::Stmt(Stmt::ReturnStmt(ref expr)) => {
+                    self.infer_expr(expr).map(|(_, expr)| vec![Stmt::ReturnStmt(expr)])
+                }
+                Group::Stmt(ref stmt) => Ok(vec![stmt.clone()]),
+                Group::Bindings { ref bindings, .. } => {
+                    let result = self.infer_group(bindings, &sigs);
+                    if result.is_err() {
+                        // a definition in error can be used at any type
+                        for binding in bindings {
+                            let name = binding_name(binding);
+                            if !sigs.contains_key(name) {
+                                self.bind_name(name, any());
+                            }
+                        }
+                    }
+                    result
+                }
+            };
+            match result {
+                Ok(stmts) => program.extend(stmts),
+                Err(err) => {
+                    self.wanteds.truncate(start);
+                    self.recover(err);
+                    match group {
+                        Group::Stmt(stmt) => program.push(stmt),
+                        Group::Bindings { bindings, .. } => program.extend(bindings),
+                    }
+                }
+            }
+        }
+        program
+    }
+
+    // Records the error of a top-level declaration, and leaves its scopes
+    fn recover(&mut self, err: TypeError) {
+        self.errors.push(err);
+        self.level = 0;
+        self.scopes.truncate(1);
+        self.tyvars.truncate(1);
+        self.rigid.clear();
+        self.bindings.clear();
+    }
+
+    // The schemes of the signatures of a group of declarations, with whether
+    // they bring their type variables into scope
+    fn signatures(
+        &self,
+        decls: &Program,
+        errors: &mut Vec<TypeError>,
+    ) -> HashMap<String, (Scheme, bool)> {
+        let mut sigs = HashMap::new();
+        let scope = self.scoped_tyvars();
+        for decl in decls {
+            if let Stmt::SigStmt(names, context, ty, span) = decl {
+                let scoped = matches!(ty, Type::ForallType(..));
+                match self.env.generalize_sig(context, ty, &mut vec![], &scope) {
+                    Ok(scheme) => {
+                        for Ident(name) in names {
+                            sigs.insert(name.clone(), (scheme.clone(), scoped));
+                        }
+                    }
+                    Err(err) => errors.push(err.in_binding(&names[0].0).at_span(*span)),
+                }
+            }
+        }
+        sigs
+    }
+
+    /// Checks the default methods of the classes of a module and the methods
+    /// of its instances against the types of the class methods, elaborating
+    /// them in place.
+    pub fn check_classes(&mut self, program: &mut Program) {
+        for stmt in program.iter_mut() {
+            let start = self.wanteds.len();
+            let result = match stmt {
+                Stmt::ClassStmt(decl) => self.check_class(decl).map(Stmt::ClassStmt),
+                // the kind errors of an instance were reported when it was
+                // declared, and its methods would only repeat them
+                Stmt::InstanceStmt(decl) if KindInfer::new(self.env).instance(decl).is_err() => {
+                    continue
+                }
+                Stmt::InstanceStmt(decl) => self.check_instance(decl).map(Stmt::InstanceStmt),
+                _ => continue,
+            };
+            match result {
+                Ok(elaborated) => *stmt = elaborated,
+                Err(err) => {
+                    self.wanteds.truncate(start);
+                    self.recover(err);
+                }
+            }
+        }
+    }
+
+    // A default method finds the dictionary of the instance it is used for
+    // bound rather than as a parameter.
+    fn check_class(&mut self, decl: &ClassDecl) -> Result<ClassDecl> {
+        let env = self.env;
+        let class = match env.classes.get(&decl.name.0) {
+            Some(class) => class,
+            None => return Ok(decl.clone()),
+        };
+        let mut body = vec![];
+        for stmt in &decl.body {
+            body.push(match class.methods.get(binding_name(stmt)) {
+                Some(scheme) if is_binding(stmt) => self.check_explicit(stmt, scheme, false, true)?,
+                _ => stmt.clone(),
+            });
+        }
+        Ok(ClassDecl {
+            body,
+            ..decl.clone()
+        })
+    }
+
+    // The class variable of each method is the instance type, whose type
+    // variables are skolems: the methods must work whatever they stand for,
+    // given the instance's context. The dictionaries of the context are
+    // bound for the methods, those of the methods' own constraints are their
+    // parameters.
+    fn check_instance(&mut self, decl: &InstanceDecl) -> Result<InstanceDecl> {
+        let Ident(ref name) = decl.class;
+        let env = self.env;
+        let class = match env.classes.get(name) {
+            Some(class) => class,
+            None => return Err(TypeError::new(format!("Not in scope: type class `{}`", name))),
+        };
+        self.level += 1;
+        let rigid = self.rigid.len();
+        let mut vars = HashMap::new();
+        let mut var = |var: &str| {
+            if let Some(ty) = vars.get(var) {
+                return Ok(Ty::clone(ty));
+            }
+            let skolem = self.skolem(var);
+            vars.insert(var.to_owned(), skolem.clone());
+            Ok(skolem)
+        };
+        let head = env.convert(&decl.ty, &mut var).and_then(|ty| {
+            let context = decl
+                .context
+                .iter()
+                .map(|pred| env.convert_pred(pred, &mut var))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((ty, context))
+        });
+        let result = head.and_then(|(ty, context)| {
+            let context: Vec<(Pred, Dict)> = context
+                .into_iter()
+                .enumerate()
+                .map(|(i, pred)| (pred, Dict::Param(Dict::context(i))))
+                .collect();
+            let mut body = vec![];
+            for stmt in &decl.body {
+                if !is_binding(stmt) {
+                    body.push(stmt.clone());
+                    continue;
+                }
+                let method = binding_name(stmt);
+                let scheme = class.methods.get(method).ok_or_else(|| {
+                    TypeError::new(format!(
+                        "`{}` is not a (visible) method of class `{}`",
+                        source_name(method),
+                        name
+                    ))
+                })?;
+                let mut types = vec![ty.clone()];
+                for var in &scheme.names[1..] {
+                    types.push(self.skolem(var));
+                }
+                let mut givens = context.clone();
+                let params = self.parameters(&scheme.preds[1..], &types, &mut givens);
+                let (start, outer) = (self.wanteds.len(), self.givens.len());
+                self.givens.extend(givens.iter().cloned());
+                let def = self.check_binding(stmt, &scheme.ty.instantiate(&types));
+                self.givens.truncate(outer);
+                let def = def?;
+                self.entail(start, &givens)?;
+                body.push(with_parameters(def, &params));
+            }
+            Ok(InstanceDecl {
+                body,
+                ..decl.clone()
+            })
+        });
+        self.level -= 1;
+        self.rigid.truncate(rigid);
+        result
+    }
+
+    // Fresh dictionary parameters for constraints of a signature, given to
+    // the definition it is the type of
+    fn parameters(
+        &mut self,
+        preds: &[Pred],
+        types: &[Ty],
+        givens: &mut Vec<(Pred, Dict)>,
+    ) -> Vec<String> {
+        let mut params = vec![];
+        for pred in preds {
+            let dict = self.fresh_dict();
+            givens.push((pred.instantiate(types), Dict::Param(Ident(dict.clone()))));
+            params.push(dict);
+        }
+        params
+    }
+
+    // Bindings with a signature are checked against it once those without
+    // are inferred, which can then use them at any type. The elaborated
+    // bindings are in the order of the group.
+    fn infer_group(
+        &mut self,
+        bindings: &Program,
+        sigs: &HashMap<String, (Scheme, bool)>,
+    ) -> Result<Vec<Stmt>> {
+        let (explicit, implicit): (Vec<&Stmt>, Vec<&Stmt>) = bindings
+            .iter()
+            .filter(|stmt| is_binding(stmt))
+            .partition(|stmt| sigs.contains_key(binding_name(stmt)));
+        for def in &explicit {
+            let name = binding_name(def);
+            self.bind_name(name, sigs[name].0.clone());
+        }
+        let mut elaborated = HashMap::new();
+        if !implicit.is_empty() {
+            for def in self.infer_implicit(&implicit)? {
+                elaborated.insert(binding_name(&def).to_owned(), def);
+            }
+        }
+        for def in explicit {
+            let (scheme, scoped) = &sigs[binding_name(def)];
+            let def = self.check_explicit(def, scheme, *scoped, false)?;
+            elaborated.insert(binding_name(&def).to_owned(), def);
+        }
+        Ok(bindings
+            .iter()
+            .map(|stmt| match elaborated.remove(binding_name(stmt)) {
+                Some(def) if is_binding(stmt) => def,
+                _ => stmt.clone(),
+            })
+            .collect())
+    }
+
+    // The constraints of the group on its generalized type variables are
+    // the same for all its bindings, which take their dictionaries as
+    // parameters.
+    fn infer_implicit(&mut self, implicit: &[&Stmt]) -> Result<Vec<Stmt>> {
+        let start = self.wanteds.len();
+        self.level += 1;
+        let types: Vec<Ty> = implicit.iter().map(|_| self.fresh()).collect();
+        for (def, ty) in implicit.iter().zip(&types) {
+            self.bind_name(binding_name(def), Scheme::mono(ty.clone()));
+        }
+        let mut defs = vec![];
+        for (def, ty) in implicit.iter().zip(&types) {
+            defs.push(self.check_binding(def, ty)?);
+        }
+        self.level -= 1;
+        let wanteds = self.wanteds.split_off(start);
+        // the monomorphism restriction: a group with a binding that is not a
+        // function can't be overloaded, its constrained type variables are
+        // fixed by its uses
+        let restricted = implicit.iter().any(|def| match def {
+            Stmt::LetStmt(..) => true,
+            Stmt::FunStmt(_, clauses) => clauses.iter().all(|c| c.params.is_empty()),
+            _ => false,
+        });
+        if restricted {
+            for wanted in &wanteds {
+                let mut vars = vec![];
+                self.generalizable(&wanted.pred.ty, &mut vars);
+                for var in vars {
+                    self.vars[var] = VarState::Unbound(self.level);
+                }
+            }
+        }
+        let mut residual = vec![];
+        for wanted in &wanteds {
+            let dict = self.solve(wanted, &[], &mut residual)?;
+            self.evidence.insert(wanted.dict.clone(), dict);
+        }
+        let mut vars = vec![];
+        for ty in &types {
+            self.generalizable(ty, &mut vars);
+        }
+        let residual = self.default(residual, |infer, var| {
+            let mut mentioned = vec![];
+            infer.generalizable(&Ty::Var(var), &mut mentioned);
+            !mentioned.is_empty() && !vars.contains(&var)
+        })?;
+        let mut params = vec![];
+        for wanted in residual {
+            let mut mentioned = vec![];
+            self.generalizable(&wanted.pred.ty, &mut mentioned);
+            if mentioned.is_empty() {
+                self.wanteds.push(wanted);
+            } else if let Some(&var) = mentioned.iter().find(|v| !vars.contains(v)) {
+                return Err(ambiguous(&wanted, var));
+            } else {
+                params.push(wanted);
+            }
+        }
+        let params = self.simplify(params);
+        for (def, ty) in implicit.iter().zip(&types) {
+            let mut vars = vec![];
+            self.generalizable(ty, &mut vars);
+            for param in &params {
+                self.generalizable(&param.pred.ty, &mut vars);
+            }
+            let scheme = Scheme {
+                names: (0..vars.len()).map(crate::typecheck::types::var_name).collect(),
+                preds: params
+                    .iter()
+                    .map(|param| Pred {
+                        class: param.pred.class.clone(),
+                        ty: self.quantify(&param.pred.ty, &vars),
+                    })
+                    .collect(),
+                ty: self.quantify(ty, &vars),
+            };
+            self.bind_name(binding_name(def), scheme);
+        }
+        let dicts: Vec<String> = params.into_iter().map(|param| param.dict).collect();
+        // the bindings were monomorphic in their own bodies
+        let mut recursive = Recursive {
+            members: implicit.iter().map(|def| binding_name(def).to_owned()).collect(),
+            dicts: &dicts,
+        };
+        Ok(defs
+            .into_iter()
+            .map(|def| with_parameters(recursive.fold_stmt(def), &dicts))
+            .collect())
+    }
+
+    // Drops the constraints a superclass of another one implies, `Eq a`
+    // with `Ord a`, solving them from it
+    fn simplify(&mut self, params: Vec<Wanted>) -> Vec<Wanted> {
+        let mut kept = vec![];
+        for (i, wanted) in params.iter().enumerate() {
+            let others: Vec<(Pred, Dict)> = params
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, other)| (other.pred.clone(), Dict::Param(Ident(other.dict.clone()))))
+                .collect();
+            let implied = self.closure(&others).into_iter().find(|(pred, _)| *pred == wanted.pred);
+            match implied {
+                Some((_, dict)) => {
+                    self.evidence.insert(wanted.dict.clone(), dict);
+                }
+                None => kept.push(wanted.clone()),
+            }
+        }
+        kept
+    }
+
+    // Checks a binding against its signature, with the signature's type
+    // variables as skolems. The signature's context provides the
+    // constraints on them, its dictionaries the parameters of the binding,
+    // except for the class constraint of a default method with `this`.
+    fn check_explicit(
+        &mut self,
+        def: &Stmt,
+        scheme: &Scheme,
+        scoped: bool,
+        this: bool,
+    ) -> Result<Stmt> {
+        let (start, rigid) = (self.wanteds.len(), self.rigid.len());
+        self.level += 1;
+        let skolems: Vec<Ty> = scheme.names.iter().map(|name| self.skolem(name)).collect();
+        let ty = scheme.ty.instantiate(&skolems);
+        let mut givens = vec![];
+        let mut preds = scheme.preds.as_slice();
+        if this {
+            givens.push((preds[0].instantiate(&skolems), Dict::Param(Dict::this())));
+            preds = &preds[1..];
+        }
+        let params = self.parameters(preds, &skolems, &mut givens);
+        if scoped {
+            let vars = scheme.names.iter().cloned().zip(skolems).collect();
+            self.tyvars.push(vars);
+        }
+        let outer = self.givens.len();
+        self.givens.extend(givens.iter().cloned());
+        let result = self.check_binding(def, &ty);
+        self.givens.truncate(outer);
+        if scoped {
+            self.tyvars.pop();
+        }
+        self.rigid.truncate(rigid);
+        self.level -= 1;
+        let def = result?;
+        self.entail(start, &givens)?;
+        Ok(with_parameters(def, &params))
+    }
+
+    // Solves the constraints wanted since `start`, once the inner scope
+    // they arose in is left, by the given ones and the instances. Those on
+    // the type variables of the enclosing scopes are left for them.
+    fn entail(&mut self, start: usize, givens: &[(Pred, Dict)]) -> Result<()> {
+        let givens = self.closure(givens);
+        let wanteds = self.wanteds.split_off(start);
+        let mut residual = vec![];
+        for wanted in &wanteds {
+            let dict = self.solve(wanted, &givens, &mut residual)?;
+            self.evidence.insert(wanted.dict.clone(), dict);
+        }
+        let residual = self.default(residual, |infer, var| !infer.is_outer(&Ty::Var(var)))?;
+        for wanted in residual {
+            if self.is_outer(&wanted.pred.ty) {
+                self.wanteds.push(wanted);
+                continue;
+            }
+            return Err(match wanted.pred.ty.split() {
+                (Ty::Var(var), _) => ambiguous(&wanted, *var),
+                _ => no_instance(&wanted, &wanted.pred),
+            });
+        }
+        Ok(())
+    }
+
+    /// Solves the constraints left at the top level of the module, once
+    /// all its definitions are checked.
+    pub fn solve_top(&mut self) {
+        let mut residual = vec![];
+        // a constraint with no instance is reported once, for its first use
+        let mut unsolved = vec![];
+        for wanted in std::mem::take(&mut self.wanteds) {
+            match self.solve(&wanted, &[], &mut residual) {
+                Ok(dict) => {
+                    self.evidence.insert(wanted.dict.clone(), dict);
+                }
+                Err(err) => {
+                    let pred = Pred {
+                        class: wanted.pred.class.clone(),
+                        ty: self.zonk(&wanted.pred.ty),
+                    };
+                    if !unsolved.contains(&pred) {
+                        self.errors.push(err);
+                        unsolved.push(pred);
+                    }
+                }
+            }
+        }
+        let residual = match self.default(residual, |_, _| true) {
+            Ok(residual) => residual,
+            Err(err) => {
+                self.errors.push(err);
+                return;
+            }
+        };
+        // and an ambiguous type variable once, for its first constraint
+        let mut ambiguities = vec![];
+        for wanted in residual {
+            let err = match self.zonk(&wanted.pred.ty).split() {
+                (Ty::Var(var), _) if ambiguities.contains(var) => continue,
+                (Ty::Var(var), _) => {
+                    ambiguities.push(*var);
+                    ambiguous(&wanted, *var)
+                }
+                _ => no_instance(&wanted, &wanted.pred),
+            };
+            self.errors.push(err);
+        }
+    }
+
+    // Defaults the type variables of the constraints left that no type in
+    // scope determines, those `undetermined` says, when the rules allow:
+    // each becomes the first default type its constraints hold at. The
+    // constraints that are still not solved are given back.
+    fn default(
+        &mut self,
+        residual: Vec<Wanted>,
+        undetermined: impl Fn(&Self, usize) -> bool,
+    ) -> Result<Vec<Wanted>> {
+        let mut groups: Vec<(usize, Vec<Wanted>)> = vec![];
+        let mut kept = vec![];
+        for wanted in residual {
+            match self.zonk(&wanted.pred.ty) {
+                Ty::Var(var) if undetermined(self, var) => {
+                    match groups.iter_mut().find(|(v, _)| *v == var) {
+                        Some((_, group)) => group.push(wanted),
+                        None => groups.push((var, vec![wanted])),
+                    }
+                }
+                _ => kept.push(wanted),
+            }
+        }
+        let mut defaulted = false;
+        for (var, group) in groups {
+            if let Some(ty) = self.default_type(&group) {
+                self.unify(&ty, &Ty::Var(var))?;
+                defaulted = true;
+            }
+            kept.extend(group);
+        }
+        if !defaulted {
+            return Ok(kept);
+        }
+        let mut residual = vec![];
+        for wanted in &kept {
+            let dict = self.solve(wanted, &[], &mut residual)?;
+            self.evidence.insert(wanted.dict.clone(), dict);
+        }
+        Ok(residual)
+    }
+
+    // The first default type all the constraints on a variable hold at, if
+    // the rules apply to them: one of their classes is numeric, and all
+    // are the prelude's. The extended rules ask for a numeric class or
+    // one GHCi needs to show a result, and take any others.
+    fn default_type(&mut self, group: &[Wanted]) -> Option<Ty> {
+        let numeric = group.iter().any(|w| matches!(w.pred.class.as_str(), "Num" | "Fractional"));
+        let applies = match self.extended {
+            true => {
+                numeric
+                    || group.iter().any(|w| {
+                        matches!(w.pred.class.as_str(), "Show" | "Eq" | "Ord" | "Monad")
+                    })
+            }
+            false => {
+                numeric
+                    && group.iter().all(|w| {
+                        let class = self.env.classes.get(&w.pred.class);
+                        class.is_some_and(|class| class.module == "Prelude")
+                    })
+            }
+        };
+        if !applies {
+            return None;
+        }
+        self.defaults.clone().into_iter().find(|ty| {
+            group.iter().all(|wanted| {
+                let wanted = wanted.implied(Pred {
+                    class: wanted.pred.class.clone(),
+                    ty: ty.clone(),
+                });
+                let mut residual = vec![];
+                self.solve(&wanted, &[], &mut residual).is_ok() && residual.is_empty()
+            })
+        })
+    }
+
+    /// The program with the dictionaries of the solved constraints in
+    /// place of their placeholders.
+    pub fn elaborate(&self, program: Program) -> Program {
+        Evidence(&self.evidence).fold_program(program)
+    }
+
+    // The given constraints and those their superclasses imply, each with
+    // its dictionary, the given ones first
+    fn closure(&self, givens: &[(Pred, Dict)]) -> Vec<(Pred, Dict)> {
+        let mut closure: Vec<(Pred, Dict)> = vec![];
+        for (pred, dict) in givens {
+            let pred = Pred {
+                class: pred.class.clone(),
+                ty: self.zonk(&pred.ty),
+            };
+            closure.push((pred, dict.clone()));
+        }
+        let mut i = 0;
+        while i < closure.len() {
+            let (pred, dict) = closure[i].clone();
+            if let Some(class) = self.env.classes.get(&pred.class) {
+                for superclass in &class.superclasses {
+                    let implied = Pred {
+                        class: superclass.clone(),
+                        ty: pred.ty.clone(),
+                    };
+                    if !closure.iter().any(|(p, _)| *p == implied) {
+                        let dict = Dict::Super(
+                            Ident(superclass.clone()),
+                            Ident(pred.class.clone()),
+                            Box::new(dict.clone()),
+                        );
+                        closure.push((implied, dict));
+                    }
+                }
+            }
+            i += 1;
+        }
+        closure
+    }
+
+    // The dictionary for a constraint: a given one, one of an instance for
+    // the constraint's type constructor, or for a constraint on a type
+    // variable, a parameter it will be the caller's job to pass. The
+    // dictionaries of the instance's context and of the class's
+    // superclasses are solved along.
+    fn solve(
+        &mut self,
+        wanted: &Wanted,
+        givens: &[(Pred, Dict)],
+        residual: &mut Vec<Wanted>,
+    ) -> Result<Dict> {
+        let pred = Pred {
+            class: wanted.pred.class.clone(),
+            ty: self.zonk(&wanted.pred.ty),
+        };
+        if let Some((_, dict)) = givens.iter().find(|(given, _)| *given == pred) {
+            return Ok(dict.clone());
+        }
+        let env = self.env;
+        let class = Ident(pred.class.clone());
+        match pred.ty.split() {
+            (Ty::Dynamic, _) => Ok(Dict::Dynamic(class)),
+            (Ty::Con(tycon), _) => {
+                let instance = env
+                    .instance(&pred.class, tycon)
+                    .and_then(|instance| {
+                        let types = instance.match_head(&pred.ty)?;
+                        Some((instance, types))
+                    })
+                    .ok_or_else(|| no_instance(wanted, &pred))?;
+                let (instance, types) = instance;
+                let mut context = vec![];
+                for constraint in &instance.context {
+                    let implied = wanted.implied(constraint.instantiate(&types));
+                    context.push(self.solve(&implied, givens, residual)?);
+                }
+                let mut superclasses = vec![];
+                let supers = env.classes.get(&pred.class).map(|c| c.superclasses.clone());
+                for superclass in supers.unwrap_or_default() {
+                    let implied = wanted.implied(Pred {
+                        class: superclass,
+                        ty: pred.ty.clone(),
+                    });
+                    superclasses.push(self.solve(&implied, givens, residual)?);
+                }
+                Ok(Dict::Instance {
+                    class,
+                    tycon: Ident(tycon.clone()),
+                    context,
+                    superclasses,
+                })
+            }
+            _ => {
+                if let Some(solved) = residual.iter().find(|r| r.pred == pred) {
+                    return Ok(Dict::Param(Ident(solved.dict.clone())));
+                }
+                let dict = self.fresh_dict();
+                residual.push(Wanted {
+                    pred,
+                    dict: dict.clone(),
+                    ..wanted.clone()
+                });
+                Ok(Dict::Param(Ident(dict)))
+            }
+        }
+    }
+
+    fn check_binding(&mut self, def: &Stmt, ty: &Ty) -> Result<Stmt> {
+        let name = binding_name(def);
+        self.bindings.push(name.to_owned());
+        let result = match def {
+            Stmt::LetStmt(name, expr) => {
+                self.check_expr(expr, ty).map(|expr| Stmt::LetStmt(name.clone(), expr))
+            }
+            Stmt::FunStmt(Ident(name), clauses) => {
+                let arity = clauses.first().map_or(0, |c| c.params.len());
+                match clauses.iter().all(|c| c.params.len() == arity) {
+                    true => clauses
+                        .iter()
+                        .map(|c| self.check_clause(c, ty))
+                        .collect::<Result<_>>()
+                        .map(|clauses| Stmt::FunStmt(Ident(name.clone()), clauses)),
+                    false => Err(TypeError::new(format!(
+                        "Equations for `{}` have different numbers of arguments",
+                        source_name(name)
+                    ))),
+                }
+            }
+            def => Ok(def.clone()),
+        };
+        self.bindings.pop();
+        result.map_err(|err| err.in_binding(name))
+    }
+
+    fn check_clause(&mut self, clause: &Clause, ty: &Ty) -> Result<Clause> {
+        self.enter_scope();
+        let mut ty = ty.clone();
+        for param in &clause.params {
+            let (arg, result) = self.split_fun(&ty)?;
+            self.check_pat(param, &arg)?;
+            ty = result;
+        }
+        let (rhs, wheres) = self.check_rhs(&clause.rhs, &clause.wheres, &ty)?;
+        self.leave_scope();
+        Ok(Clause {
+            params: clause.params.clone(),
+            rhs,
+            wheres,
+        })
+    }
+
+    fn check_rhs(&mut self, rhs: &Rhs, wheres: &Program, ty: &Ty) -> Result<(Rhs, Program)> {
+        self.enter_scope();
+        let wheres = self.infer_bindings(wheres)?;
+        let rhs = match rhs {
+            Rhs::Plain(expr) => Rhs::Plain(self.check_expr(expr, ty)?),
+            Rhs::Guarded(guards) => {
+                let mut checked = vec![];
+                for (guard, expr) in guards {
+                    let guard = self.check_expr(guard, &Ty::con("Bool"))?;
+                    checked.push((guard, self.check_expr(expr, ty)?));
+                }
+                Rhs::Guarded(checked)
+            }
+        };
+        self.leave_scope();
+        Ok((rhs, wheres))
+    }
+
+    // The bindings of a `let` or a `where`, into the innermost scope
+    fn infer_bindings(&mut self, decls: &Program) -> Result<Program> {
+        let mut errors = vec![];
+        let sigs = self.signatures(decls, &mut errors);
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        let mut elaborated = vec![];
+        for group in dependency_order(decls.clone()) {
+            match group {
+                Group::Bindings { ref bindings, .. } => {
+                    elaborated.extend(self.infer_group(bindings, &sigs)?)
+                }
+                Group::Stmt(stmt) => elaborated.push(stmt),
+            }
+        }
+        Ok(elaborated)
+    }
+
+    // The statements of a Monkey block or of an `if` branch, of the type of
+    // the last one
+    fn infer_block(&mut self, block: &Program) -> Result<(Ty, Program)> {
+        let depth = self.scopes.len();
+        let mut ty = Ty::Dynamic;
+        let mut elaborated = vec![];
+        for stmt in block {
+            ty = match stmt {
+                Stmt::ExprStmt(expr) => {
+                    let (ty, expr) = self.infer_expr(expr)?;
+                    elaborated.push(Stmt::ExprStmt(expr));
+                    ty
+                }
+                Stmt::ReturnStmt(expr) => {
+                    let (ty, expr) = self.infer_expr(expr)?;
+                    elaborated.push(Stmt::ReturnStmt(expr));
+                    ty
+                }
+                // each binding is in scope in the statements after it
+                Stmt::LetStmt(..) | Stmt::FunStmt(..) => {
+                    self.enter_scope();
+                    elaborated.extend(self.infer_group(&vec![stmt.clone()], &HashMap::new())?);
+                    Ty::Dynamic
+                }
+                stmt => {
+                    elaborated.push(stmt.clone());
+                    Ty::Dynamic
+                }
+            };
+        }
+        self.scopes.truncate(depth);
+        self.tyvars.truncate(depth);
+        Ok((ty, elaborated))
+    }
+
+    fn check_expr(&mut self, expr: &Expr, ty: &Ty) -> Result<Expr> {
+        let (actual, elaborated) = self.infer_expr(expr)?;
+        self.unify(ty, &actual).map_err(|err| err.in_expr(expr))?;
+        Ok(elaborated)
+    }
+
+    /// The type of an expression and the expression elaborated, or the
+    /// error in its innermost subexpression that has one. The constraints
+    /// arising in the expression and in none of its subexpressions but
+    /// names are recorded to arise in it.
+    pub fn infer_expr(&mut self, expr: &Expr) -> Result<(Ty, Expr)> {
+        let start = self.wanteds.len();
+        let result = self.infer(expr).map_err(|err| err.in_expr(expr))?;
+        if !matches!(expr.unlocated(), Expr::IdentExpr(_) | Expr::HoleExpr(_)) {
+            let mut text = None;
+            for wanted in self.wanteds.iter_mut().skip(start) {
+                if wanted.expr.is_none() {
+                    let text = text.get_or_insert_with(|| source_text(expr));
+                    wanted.expr = Some(text.clone());
+                    wanted.span = expr.span();
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Result<(Ty, Expr)> {
+        match expr {
+            // the elaborated module has no use for where expressions were,
+            // but a hole is reported where it is
+            Expr::Located(span, expr) => {
+                let holes = self.holes.len();
+                let result = self.infer(expr);
+                for hole in &mut self.holes[holes..] {
+                    hole.span.get_or_insert(*span);
+                }
+                result
+            }
+            Expr::IdentExpr(Ident(name)) => self.lookup_var(name),
+            Expr::HoleExpr(Ident(name)) => match self.lookup(name) {
+                Some(scheme) => {
+                    let (ty, dicts) = self.instantiate(&scheme, &use_of(name));
+                    Ok((ty, with_dictionaries(expr.clone(), &dicts)))
+                }
+                None => Ok((self.hole(name), expr.clone())),
+            },
+            Expr::LitExpr(literal) => Ok(self.infer_literal(literal)),
+            Expr::PrefixExpr(Prefix::PrefixMinus, operand) => {
+                // the evaluator negates numbers of any type
+                let (ty, operand) = self.infer_expr(operand)?;
+                let num = Pred {
+                    class: "Num".to_owned(),
+                    ty: ty.clone(),
+                };
+                self.want(num, &use_of("negate"));
+                Ok((ty, Expr::PrefixExpr(Prefix::PrefixMinus, Box::new(operand))))
+            }
+            Expr::InfixExpr(op, left, right) => {
+                let (ty, f) = self.lookup_var(op.name())?;
+                let function = Expr::IdentExpr(Ident(op.name().to_owned()));
+                let (ty, elaborated) = self.apply(ty, &function, 1, left)?;
+                if *op == Infix::Minus {
+                    self.check_subtrahend(&ty, left, right)?;
+                }
+                let (ty, right) = self.apply(ty, &function, 2, right)?;
+                let left = elaborated;
+                let expr = match f {
+                    Expr::IdentExpr(_) => {
+                        Expr::InfixExpr(op.clone(), Box::new(left), Box::new(right))
+                    }
+                    f => call(call(f, left), right),
+                };
+                Ok((ty, expr))
+            }
+            Expr::InfixChain(..) => Ok((Ty::Dynamic, expr.clone())),
+            Expr::IfExpr {
+                cond,
+                consequence,
+                alternative,
+            } => {
+                let cond = self.check_expr(cond, &Ty::con("Bool"))?;
+                let (ty, consequence) = self.infer_block(consequence)?;
+                let (ty, alternative) = match alternative {
+                    Some(alternative) => {
+                        let (other, alternative) = self.infer_block(alternative)?;
+                        self.unify(&ty, &other)?;
+                        (ty, Some(alternative))
+                    }
+                    None => (Ty::Dynamic, None),
+                };
+                let expr = Expr::IfExpr {
+                    cond: Box::new(cond),
+                    consequence,
+                    alternative,
+                };
+                Ok((ty, expr))
+            }
+            Expr::FnExpr { params, body } => {
+                self.enter_scope();
+                for Ident(param) in params {
+                    self.bind_name(param, Scheme::mono(Ty::Dynamic));
+                }
+                let (_, body) = self.infer_block(body)?;
+                self.leave_scope();
+                let params = params.clone();
+                Ok((Ty::Dynamic, Expr::FnExpr { params, body }))
+            }
+            Expr::CallExpr {
+                function,
+                arguments,
+            } => {
+                let (ty, f) = self.infer_expr(function)?;
+                let (head, before) = head_of(function);
+                let (ty, arguments) = match arguments.as_slice() {
+                    [argument] => {
+                        let (ty, argument) = self.apply(ty, head, before + 1, argument)?;
+                        (ty, vec![argument])
+                    }
+                    arguments if self.is_builtin(function) => {
+                        let mut ty = ty;
+                        let mut elaborated = vec![];
+                        for (i, argument) in arguments.iter().enumerate() {
+                            let position = before + i + 1;
+                            let (result, argument) = self.apply(ty, head, position, argument)?;
+                            elaborated.push(argument);
+                            ty = result;
+                        }
+                        (ty, elaborated)
+                    }
+                    // `f(x, y)` passes a tuple to a curried function
+                    arguments => {
+                        let tuple = Expr::TupleExpr(arguments.to_vec());
+                        match self.apply(ty, head, before + 1, &tuple)? {
+                            (ty, Expr::TupleExpr(arguments)) => (ty, arguments),
+                            (ty, argument) => (ty, vec![argument]),
+                        }
+                    }
+                };
+                let expr = Expr::CallExpr {
+                    function: Box::new(f),
+                    arguments,
+                };
+                Ok((ty, expr))
+            }
+            Expr::ArrayExpr(elements) => {
+                let elem = self.fresh();
+                let elements = elements
+                    .iter()
+                    .map(|element| self.check_expr(element, &elem))
+                    .collect::<Result<_>>()?;
+                Ok((Ty::list(elem), Expr::ArrayExpr(elements)))
+            }
+            Expr::HashExpr(pairs) => {
+                let mut elaborated = vec![];
+                for (key, value) in pairs {
+                    let (_, value) = self.infer_expr(value)?;
+                    elaborated.push((key.clone(), value));
+                }
+                Ok((Ty::Dynamic, Expr::HashExpr(elaborated)))
+            }
+            Expr::IndexExpr { array, index } => {
+                let (_, array) = self.infer_expr(array)?;
+                let (_, index) = self.infer_expr(index)?;
+                let expr = Expr::IndexExpr {
+                    array: Box::new(array),
+                    index: Box::new(index),
+                };
+                Ok((Ty::Dynamic, expr))
+            }
+            Expr::LambdaExpr { params, body } => {
+                self.enter_scope();
+                let mut args = vec![];
+                for param in params {
+                    args.push(self.infer_pat(param)?);
+                }
+                let (result, body) = self.infer_expr(body)?;
+                self.leave_scope();
+                let ty = args.into_iter().rev().fold(result, |ty, arg| Ty::fun(arg, ty));
+                let expr = Expr::LambdaExpr {
+                    params: params.clone(),
+                    body: Box::new(body),
+                };
+                Ok((ty, expr))
+            }
+            Expr::CaseExpr { scrutinee, alts } => {
+                let (ty, scrutinee) = self.infer_expr(scrutinee)?;
+                let result = self.fresh();
+                let mut elaborated = vec![];
+                for alt in alts {
+                    self.enter_scope();
+                    self.check_pat(&alt.pat, &ty)?;
+                    let (rhs, wheres) = self.check_rhs(&alt.rhs, &alt.wheres, &result)?;
+                    self.leave_scope();
+                    elaborated.push(Alt {
+                        pat: alt.pat.clone(),
+                        rhs,
+                        wheres,
+                    });
+                }
+                let expr = Expr::CaseExpr {
+                    scrutinee: Box::new(scrutinee),
+                    alts: elaborated,
+                };
+                Ok((result, expr))
+            }
+            Expr::LetExpr { bindings, body } => {
+                self.enter_scope();
+                let bindings = self.infer_bindings(bindings)?;
+                let (ty, body) = self.infer_expr(body)?;
+                self.leave_scope();
+                let body = Box::new(body);
+                Ok((ty, Expr::LetExpr { bindings, body }))
+            }
+            Expr::TupleExpr(components) => {
+                let mut types = vec![];
+                let mut elaborated = vec![];
+                for component in components {
+                    let (ty, component) = self.infer_expr(component)?;
+                    types.push(ty);
+                    elaborated.push(component);
+                }
+                Ok((Ty::tuple(types), Expr::TupleExpr(elaborated)))
+            }
+            Expr::LeftSectionExpr(left, op) => {
+                let (ty, f) = self.lookup_var(op.name())?;
+                let function = Expr::IdentExpr(Ident(op.name().to_owned()));
+                let (ty, left) = self.apply(ty, &function, 1, left)?;
+                let expr = match f {
+                    Expr::IdentExpr(_) => Expr::LeftSectionExpr(Box::new(left), op.clone()),
+                    f => call(f, left),
+                };
+                Ok((ty, expr))
+            }
+            Expr::RightSectionExpr(op, right) => {
+                let (ty, f) = self.lookup_var(op.name())?;
+                let (arg, result) = self.split_fun(&ty)?;
+                let (second, result) = self.split_fun(&result)?;
+                let right = self.check_expr(right, &second)?;
+                let expr = match f {
+                    Expr::IdentExpr(_) => Expr::RightSectionExpr(op.clone(), Box::new(right)),
+                    // `\x -> op x right`
+                    f => {
+                        let x = Ident("$section".to_owned());
+                        Expr::LambdaExpr {
+                            params: vec![Pattern::VarPat(x.clone())],
+                            body: Box::new(call(call(f, Expr::IdentExpr(x)), right)),
+                        }
+                    }
+                };
+                Ok((Ty::fun(arg, result), expr))
+            }
+            Expr::TypedExpr(expr, context, ty) => self.infer_typed(expr, context, ty),
+            Expr::LambdaCaseExpr(alts) => self.infer_expr(&desugar_lambda_case(alts.clone())),
+            Expr::MultiIfExpr(guards) => self.infer_expr(&desugar_multi_way_if(guards.clone())),
+            Expr::TupleSectionExpr(components) => {
+                self.infer_expr(&desugar_tuple_section(components.clone()))
+            }
+            Expr::ListCompExpr { expr, qualifiers } => {
+                let expr = desugar_comprehension(*expr.clone(), qualifiers.clone());
+                self.infer_expr(&expr)
+            }
+            Expr::DoExpr(stmts) => self.infer_expr(&desugar_do(stmts.clone())),
+            Expr::DictExpr(_) => Ok((Ty::Dynamic, expr.clone())),
+        }
+    }
+
+    // The result of applying a function of the type to the argument. A
+    // mismatch is in the application rather than in the argument.
+    // A numeric literal is a number of any type of its class: `3` is
+    // `fromInteger 3` and `2.5` is `fromRational 2.5`, by the prelude's
+    // methods whatever the module hides
+    fn infer_literal(&mut self, literal: &Literal) -> (Ty, Expr) {
+        let (class, method) = match literal {
+            Literal::IntLiteral(_) => ("Num", "$fromInteger"),
+            Literal::FloatLiteral(_) => ("Fractional", "$fromRational"),
+            literal => return (literal_type(literal), Expr::LitExpr(literal.clone())),
+        };
+        let ty = self.fresh();
+        let pred = Pred {
+            class: class.to_owned(),
+            ty: ty.clone(),
+        };
+        let dict = self.want(pred, &format!("the literal `{}`", literal));
+        let method = with_dictionaries(Expr::IdentExpr(Ident(method.to_owned())), &[dict]);
+        (ty, call(method, Expr::LitExpr(literal.clone())))
+    }
+
+    // `f -1` is `f - 1`, which subtracts from the function its author meant
+    // to apply to `-1`. `rest` is the type of `(-)` given `f`.
+    fn check_subtrahend(&self, rest: &Ty, left: &Expr, right: &Expr) -> Result<()> {
+        let function = match self.shallow(rest).as_fun() {
+            Some((operand, _)) => self.shallow(operand).as_fun().is_some(),
+            None => false,
+        };
+        if !function || !matches!(right.unlocated(), Expr::LitExpr(_) | Expr::IdentExpr(_)) {
+            return Ok(());
+        }
+        let (f, x) = (source_text(left), source_text(right));
+        Err(TypeError::new(format!(
+            "Cannot subtract from a function: `{f} -{x}` is `{f} - {x}`, \
+             write `{f} (-{x})` to apply `{f}` to a negative number"
+        )))
+    }
+
+    // Applies a function of type `ty` to the `position`-th argument of a
+    // call of `function`
+    fn apply(
+        &mut self,
+        ty: Ty,
+        function: &Expr,
+        position: usize,
+        argument: &Expr,
+    ) -> Result<(Ty, Expr)> {
+        let (arg, result) = self.split_fun(&ty)?;
+        let (actual, elaborated) = self.infer_expr(argument)?;
+        self.unify(&arg, &actual)
+            .map_err(|err| err.in_argument(position, function, argument))?;
+        Ok((result, elaborated))
+    }
+
+    // `e :: t` checks `e` against `t`, whose type variables not in scope
+    // stand for any type. The dictionaries of the context are bound around
+    // `e`.
+    fn infer_typed(&mut self, expr: &Expr, context: &Context, ty: &Type) -> Result<(Ty, Expr)> {
+        let scope = self.scoped_tyvars();
+        let scheme = self.env.generalize_sig(context, ty, &mut vec![], &scope)?;
+        let (start, rigid) = (self.wanteds.len(), self.rigid.len());
+        self.level += 1;
+        let skolems: Vec<Ty> = scheme.names.iter().map(|name| self.skolem(name)).collect();
+        let mut givens = vec![];
+        let params = self.parameters(&scheme.preds, &skolems, &mut givens);
+        let outer = self.givens.len();
+        self.givens.extend(givens.iter().cloned());
+        let result = self.check_expr(expr, &scheme.ty.instantiate(&skolems));
+        self.givens.truncate(outer);
+        self.rigid.truncate(rigid);
+        self.level -= 1;
+        let mut elaborated = result?;
+        self.entail(start, &givens)?;
+        let (actual, dicts) = self.instantiate(&scheme, "an expression type signature");
+        if !params.is_empty() {
+            let bindings = params
+                .into_iter()
+                .zip(dicts)
+                .map(|(param, dict)| {
+                    Stmt::LetStmt(Ident(param), Expr::DictExpr(Dict::Param(Ident(dict))))
+                })
+                .collect();
+            let body = Box::new(elaborated);
+            elaborated = Expr::LetExpr { bindings, body };
+        }
+        let expr = Expr::TypedExpr(Box::new(elaborated), context.clone(), ty.clone());
+        Ok((actual, expr))
+    }
+
+    fn check_pat(&mut self, pat: &Pattern, ty: &Ty) -> Result<()> {
+        let actual = self.infer_pat(pat)?;
+        self.unify(ty, &actual)
+    }
+
+    // The type of the values a pattern matches, binding its variables in the
+    // innermost scope
+    fn infer_pat(&mut self, pat: &Pattern) -> Result<Ty> {
+        match pat {
+            Pattern::WildPat => Ok(self.fresh()),
+            Pattern::VarPat(Ident(name)) => {
+                let ty = self.fresh();
+                self.bind_name(name, Scheme::mono(ty.clone()));
+                Ok(ty)
+            }
+            // the value is compared with the literal at its own type
+            Pattern::LitPat(literal @ (Literal::IntLiteral(_) | Literal::FloatLiteral(_))) => {
+                let ty = self.fresh();
+                let eq = Pred {
+                    class: "Eq".to_owned(),
+                    ty: ty.clone(),
+                };
+                self.want(eq, &format!("the literal `{}`", literal));
+                let (actual, _) = self.infer_literal(literal);
+                self.unify(&ty, &actual)?;
+                Ok(ty)
+            }
+            Pattern::LitPat(literal) => Ok(literal_type(literal)),
+            Pattern::ConPat(Ident(con), args) => {
+                let scheme = self.lookup(con).ok_or_else(|| {
+                    TypeError::new(format!("Data constructor not in scope: {}", con))
+                })?;
+                let mut arity = 0;
+                let mut ty = &scheme.ty;
+                while let Some((_, result)) = ty.as_fun() {
+                    arity += 1;
+                    ty = result;
+                }
+                if arity != args.len() {
+                    return Err(TypeError::new(format!(
+                        "The constructor `{}` should have {} argument{}, but has been given {}",
+                        con,
+                        arity,
+                        if arity == 1 { "" } else { "s" },
+                        args.len()
+                    )));
+                }
+                let (mut ty, _) = self.instantiate(&scheme, &use_of(con));
+                for arg in args {
+                    let (field, result) = self.split_fun(&ty)?;
+                    self.check_pat(arg, &field)?;
+                    ty = result;
+                }
+                Ok(ty)
+            }
+            Pattern::ListPat(elements) => {
+                let elem = self.fresh();
+                for element in elements {
+                    self.check_pat(element, &elem)?;
+                }
+                Ok(Ty::list(elem))
+            }
+            Pattern::TuplePat(components) => {
+                let types = components
+                    .iter()
+                    .map(|c| self.infer_pat(c))
+                    .collect::<Result<_>>()?;
+                Ok(Ty::tuple(types))
+            }
+            Pattern::AsPat(Ident(name), pat) => {
+                let ty = self.infer_pat(pat)?;
+                self.bind_name(name, Scheme::mono(ty.clone()));
+                Ok(ty)
+            }
+            // the type variables of the signature that are not in scope are
+            // bound by the pattern
+            Pattern::SigPat(pat, sig) => {
+                let env = self.env;
+                let mut scope = self.scoped_tyvars();
+                KindInfer::new(env).signature(&vec![], sig)?;
+                let ty = env.convert(sig, &mut |var| {
+                    if let Some(ty) = scope.get(var) {
+                        return Ok(Ty::clone(ty));
+                    }
+                    self.vars.push(VarState::Unbound(self.level));
+                    let ty = Ty::Var(self.vars.len() - 1);
+                    self.tyvars.last_mut().expect("a scope").insert(var.to_owned(), ty.clone());
+                    scope.insert(var.to_owned(), ty.clone());
+                    Ok(ty)
+                })?;
+                self.check_pat(pat, &ty)?;
+                Ok(ty)
+            }
+        }
+    }
+
+    // The type of a hole is whatever its context asks for
+    fn hole(&mut self, name: &str) -> Ty {
+        let ty = self.fresh();
+        let mut locals = vec![];
+        for scope in self.scopes[1..].iter().rev() {
+            let mut names: Vec<&String> = scope.keys().collect();
+            names.sort();
+            for name in names {
+                if !name.starts_with('$') {
+                    locals.push((name.clone(), scope[name].clone()));
+                }
+            }
+        }
+        self.holes.push(Hole {
+            name: name.to_owned(),
+            ty: ty.clone(),
+            locals,
+            givens: self.givens.clone(),
+            bindings: self.bindings.iter().rev().cloned().collect(),
+            span: None,
+            level: self.level,
+        });
+        ty
+    }
+
+    /// Reports each hole with its type, the types of the local bindings in
+    /// scope and the names whose types fit it, as GHC does.
+    pub fn report_holes(&mut self) {
+        for hole in std::mem::take(&mut self.holes) {
+            let ty = self.zonk(&hole.ty);
+            let locals: Vec<Scheme> =
+                hole.locals.iter().map(|(_, scheme)| self.zonk_scheme(scheme)).collect();
+            // the variables of the locals' types are those of the hole's
+            let types: Vec<&Ty> = locals.iter().map(|scheme| &scheme.ty).collect();
+            let mut tidy = Tidy::new(&[&[&ty], types.as_slice()].concat());
+            let mut message = format!("Found hole: {} :: {}", hole.name, tidy.ty(&ty));
+            if !hole.locals.is_empty() {
+                message.push_str("\n  Relevant bindings include");
+                for ((name, _), mut scheme) in hole.locals.iter().zip(locals) {
+                    if scheme.names.is_empty() {
+                        scheme.ty = tidy.ty(&scheme.ty);
+                    }
+                    message.push_str(&format!("\n    {} :: {}", source_name(name), scheme));
+                }
+            }
+            let fits = self.fits(&ty, &hole);
+            if !fits.is_empty() {
+                message.push_str("\n  Valid hole fits include");
+                for (name, mut scheme) in fits {
+                    if scheme.names.is_empty() {
+                        scheme.ty = tidy.ty(&scheme.ty);
+                    }
+                    message.push_str(&format!("\n    {} :: {}", name, scheme));
+                }
+            }
+            let err = TypeError::new(message).at_span(hole.span);
+            let err = hole.bindings.iter().fold(err, |err, binding| err.in_binding(binding));
+            self.errors.push(err);
+        }
+    }
+
+    // The first few names in scope that can be used at the type of a hole,
+    // the locals first. The type variables of the hole and of the locals
+    // stand for any type, and the constraints of a fit must hold.
+    fn fits(&mut self, ty: &Ty, hole: &Hole) -> Vec<(String, Scheme)> {
+        const MAX_FITS: usize = 6;
+        let mut candidates: Vec<(String, Scheme)> = hole.locals.clone();
+        let mut globals: Vec<(String, Scheme)> = self.scopes[0]
+            .iter()
+            .chain(&self.globals)
+            .map(|(name, scheme)| (name.clone(), scheme.clone()))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals.dedup_by(|(a, _), (b, _)| a == b);
+        candidates.extend(globals);
+        let mut vars = vec![];
+        self.unbound_vars(ty, &mut vars);
+        for (_, scheme) in &hole.locals {
+            self.unbound_vars(&scheme.ty, &mut vars);
+        }
+        let givens = self.closure(&hole.givens);
+        let level = std::mem::replace(&mut self.level, hole.level);
+        let mut fits = vec![];
+        for (name, scheme) in candidates {
+            let name = source_name(&name).to_owned();
+            let visible = name.starts_with(|c: char| c.is_lowercase())
+                && Ident(name.clone()).qualifier().is_none()
+                && !name.starts_with("prim")
+                && !fits.iter().any(|(fit, _)| *fit == name);
+            if !visible {
+                continue;
+            }
+            let saved = self.vars.clone();
+            let (skolems, rigid) = (self.skolems.len(), self.rigid.len());
+            let (wanteds, dicts) = (self.wanteds.len(), self.dicts);
+            let fixed: Vec<Ty> = vars.iter().map(|_| self.skolem("t")).collect();
+            let target = substitute(ty, &vars, &fixed);
+            let (candidate, _) = self.instantiate(&scheme, &name);
+            let candidate = substitute(&self.zonk(&candidate), &vars, &fixed);
+            let fit = self.unify_types(&target, &candidate).is_ok()
+                && self.holds(wanteds, &givens);
+            self.vars = saved;
+            self.skolems.truncate(skolems);
+            self.rigid.truncate(rigid);
+            self.wanteds.truncate(wanteds);
+            self.dicts = dicts;
+            if fit {
+                fits.push((name, self.zonk_scheme(&scheme)));
+                if fits.len() == MAX_FITS {
+                    break;
+                }
+            }
+        }
+        self.level = level;
+        fits
+    }
+
+    // Whether the constraints wanted since `start` have instances or are
+    // given, but for those on type variables yet to be determined
+    fn holds(&mut self, start: usize, givens: &[(Pred, Dict)]) -> bool {
+        let mut residual = vec![];
+        let wanteds = self.wanteds[start..].to_vec();
+        wanteds.iter().all(|wanted| self.solve(wanted, givens, &mut residual).is_ok())
+            && residual.iter().all(|wanted| matches!(wanted.pred.ty.split().0, Ty::Var(_)))
+    }
+
+    fn unbound_vars(&self, ty: &Ty, vars: &mut Vec<usize>) {
+        match self.shallow(ty) {
+            Ty::Var(v) if !vars.contains(&v) => vars.push(v),
+            Ty::App(f, arg) => {
+                self.unbound_vars(&f, vars);
+                self.unbound_vars(&arg, vars);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn substitute(ty: &Ty, vars: &[usize], types: &[Ty]) -> Ty {
+    match ty {
+        Ty::Var(v) => match vars.iter().position(|w| w == v) {
+            Some(i) => types[i].clone(),
+            None => ty.clone(),
+        },
+        Ty::App(f, arg) => Ty::app(substitute(f, vars, types), substitute(arg, vars, types)),
+        ty => ty.clone(),
+    }
+}
+
+fn use_of(name: &str) -> String {
+    format!("a use of `{}`", source_name(name))
+}
+
+fn no_instance(wanted: &Wanted, pred: &Pred) -> TypeError {
+    let pred = Tidy::new(&[&pred.ty]).pred(pred);
+    wanted.error(format!("No instance for ({}) arising from {}", pred, wanted.origin))
+}
+
+fn ambiguous(wanted: &Wanted, var: usize) -> TypeError {
+    let mut tidy = Tidy::new(&[&wanted.pred.ty]);
+    wanted.error(format!(
+        "Ambiguous type variable `{}` arising from {} prevents the constraint `({})` \
+         from being solved.",
+        tidy.ty(&Ty::Var(var)),
+        wanted.origin,
+        tidy.pred(&wanted.pred)
+    ))
+}
+
+// The function a call applies, and the number of arguments it is given
+// before the call's own: `map f` gives `map` its first argument
+fn head_of(function: &Expr) -> (&Expr, usize) {
+    match function.unlocated() {
+        Expr::CallExpr {
+            function,
+            arguments,
+        } => {
+            let (head, before) = head_of(function);
+            (head, before + arguments.len())
+        }
+        function => (function, 0),
+    }
+}
+
+fn call(function: Expr, argument: Expr) -> Expr {
+    Expr::CallExpr {
+        function: Box::new(function),
+        arguments: vec![argument],
+    }
+}
+
+// A use of an overloaded name applied to the dictionaries of its
+// constraints, one at a time
+fn with_dictionaries(expr: Expr, dicts: &[String]) -> Expr {
+    dicts.iter().fold(expr, |expr, dict| {
+        call(expr, Expr::DictExpr(Dict::Param(Ident(dict.clone()))))
+    })
+}
+
+// A binding taking the dictionaries of its constraints before its own
+// parameters
+fn with_parameters(def: Stmt, dicts: &[String]) -> Stmt {
+    if dicts.is_empty() {
+        return def;
+    }
+    let params: Vec<Pattern> = dicts.iter().map(|d| Pattern::VarPat(Ident(d.clone()))).collect();
+    match def {
+        Stmt::FunStmt(name, clauses) => {
+            let clauses = clauses
+                .into_iter()
+                .map(|clause| Clause {
+                    params: params.iter().cloned().chain(clause.params).collect(),
+                    ..clause
+                })
+                .collect();
+            Stmt::FunStmt(name, clauses)
+        }
+        Stmt::LetStmt(name, expr) => {
+            let clause = Clause {
+                params,
+                rhs: Rhs::Plain(expr),
+                wheres: vec![],
+            };
+            Stmt::FunStmt(name, vec![clause])
+        }
+        def => def,
+    }
+}
+
+// Applies the uses of the bindings of a group within it, which were
+// monomorphic there, to the group's dictionary parameters. A `let` binding
+// the same name, such as the `$ok` of an inner generator, hides them.
+struct Recursive<'a> {
+    members: HashSet<String>,
+    dicts: &'a [String],
+}
+
+impl Recursive<'_> {
+    fn member(&self, op: &Infix) -> Option<Expr> {
+        let Ident(name) = match op {
+            Infix::Op(name) => name,
+            _ => return None,
+        };
+        match self.members.contains(name) {
+            true => Some(with_dictionaries(Expr::IdentExpr(Ident(name.clone())), self.dicts)),
+            false => None,
+        }
+    }
+}
+
+impl Fold for Recursive<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if self.dicts.is_empty() {
+            return expr;
+        }
+        match expr {
+            Expr::IdentExpr(Ident(ref name)) if self.members.contains(name) => {
+                with_dictionaries(expr, self.dicts)
+            }
+            Expr::LetExpr { bindings, body } => {
+                let hidden: Vec<String> = bindings
+                    .iter()
+                    .map(|stmt| binding_name(stmt).to_owned())
+                    .filter(|name| self.members.remove(name))
+                    .collect();
+                let expr = fold::fold_expr(self, Expr::LetExpr { bindings, body });
+                self.members.extend(hidden);
+                expr
+            }
+            Expr::InfixExpr(op, left, right) => match self.member(&op) {
+                Some(f) => call(call(f, self.fold_expr(*left)), self.fold_expr(*right)),
+                None => fold::fold_expr(self, Expr::InfixExpr(op, left, right)),
+            },
+            Expr::LeftSectionExpr(left, op) => match self.member(&op) {
+                Some(f) => call(f, self.fold_expr(*left)),
+                None => fold::fold_expr(self, Expr::LeftSectionExpr(left, op)),
+            },
+            Expr::RightSectionExpr(op, right) => match self.member(&op) {
+                Some(f) => {
+                    let x = Ident("$section".to_owned());
+                    Expr::LambdaExpr {
+                        params: vec![Pattern::VarPat(x.clone())],
+                        body: Box::new(call(call(f, Expr::IdentExpr(x)), self.fold_expr(*right))),
+                    }
+                }
+                None => fold::fold_expr(self, Expr::RightSectionExpr(op, right)),
+            },
+            expr => fold::fold_expr(self, expr),
+        }
+    }
+}
+
+// Replaces the placeholders of the solved constraints by their dictionaries
+struct Evidence<'a>(&'a HashMap<String, Dict>);
+
+impl Evidence<'_> {
+    fn resolve(&self, dict: Dict) -> Dict {
+        match dict {
+            Dict::Param(Ident(name)) => match self.0.get(&name) {
+                Some(solved) => self.resolve(solved.clone()),
+                None => Dict::Param(Ident(name)),
+            },
+            Dict::Instance {
+                class,
+                tycon,
+                context,
+                superclasses,
+            } => Dict::Instance {
+                class,
+                tycon,
+                context: context.into_iter().map(|d| self.resolve(d)).collect(),
+                superclasses: superclasses.into_iter().map(|d| self.resolve(d)).collect(),
+            },
+            Dict::Super(class, subclass, dict) => {
+                Dict::Super(class, subclass, Box::new(self.resolve(*dict)))
+            }
+            dict => dict,
+        }
+    }
+}
+
+impl Fold for Evidence<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if let Some(literal) = self.literal(&expr) {
+            return Expr::LitExpr(literal);
+        }
+        match expr {
+            Expr::DictExpr(dict) => Expr::DictExpr(self.resolve(dict)),
+            expr => fold::fold_expr(self, expr),
+        }
+    }
+}
+
+impl Evidence<'_> {
+    // A literal `$fromInteger d 3` at a type the evaluator has numbers of,
+    // or at that of a Monkey value, is the literal itself
+    fn literal(&self, expr: &Expr) -> Option<Literal> {
+        let (method, literal) = match expr {
+            Expr::CallExpr {
+                function,
+                arguments,
+            } => match arguments.as_slice() {
+                [Expr::LitExpr(literal)] => (&**function, literal),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let dict = match method {
+            Expr::CallExpr {
+                function,
+                arguments,
+            } => match (&**function, arguments.as_slice()) {
+                (Expr::IdentExpr(Ident(name)), [Expr::DictExpr(dict)])
+                    if name == "$fromInteger" || name == "$fromRational" =>
+                {
+                    self.resolve(dict.clone())
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match (dict, literal) {
+            (Dict::Dynamic(_), literal) => Some(literal.clone()),
+            (Dict::Instance { tycon, .. }, Literal::IntLiteral(i)) if tycon.0 == "Double" => {
+                Some(Literal::FloatLiteral(*i as f64))
+            }
+            (Dict::Instance { tycon, .. }, literal)
+                if tycon.0 == "Integer" || tycon.0 == "Double" =>
+            {
+                Some(literal.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn literal_type(literal: &Literal) -> Ty {
+    match literal {
+        Literal::IntLiteral(_) => Ty::con("Integer"),
+        Literal::FloatLiteral(_) => Ty::con("Double"),
+        Literal::BoolLiteral(_) => Ty::con("Bool"),
+        Literal::StringLiteral(_) => Ty::string(),
+        Literal::CharLiteral(_) => Ty::con("Char"),
+    }
+}
+
+fn is_binding(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::LetStmt(..) | Stmt::FunStmt(..))
+}
+
+fn binding_name(stmt: &Stmt) -> &str {
+    match stmt {
+        Stmt::LetStmt(Ident(name), _) | Stmt::FunStmt(Ident(name), _) => name,
+        _ => "",
+    }
+}
+
+// The type of a definition in error, so that its uses raise no more errors
+fn any() -> Scheme {
+    Scheme {
+        names: vec!["a".to_owned()],
+        preds: vec![],
+        ty: Ty::Gen(0),
+    }
+}
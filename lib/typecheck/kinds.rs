@@ -0,0 +1,354 @@
+use crate::parser::ast::*;
+use crate::typecheck::types::Kind;
+use crate::typecheck::{TypeEnv, TypeError};
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, TypeError>;
+
+/// Kind inference, which checks that types are applied to types of the
+/// kinds their constructors take: `Maybe Int Int` gives `Maybe` one type
+/// too many, and `instance Functor Int` a type where `Functor` is over
+/// constructors of kind `* -> *`.
+///
+/// The data types, type synonyms and classes a module declares have their
+/// kinds inferred together from their declarations, those of the modules
+/// before it being known. A kind nothing determines, such as that of the
+/// `a` of `data Proxy a = Proxy`, is `*`.
+pub struct KindInfer<'env> {
+    env: &'env TypeEnv,
+    vars: Vec<Option<Kind>>,
+    // the kinds of the type constructors and classes being declared
+    tycons: HashMap<String, Kind>,
+    classes: HashMap<String, Kind>,
+    // the kinds of the type variables in scope
+    tyvars: HashMap<String, Kind>,
+}
+
+impl<'env> KindInfer<'env> {
+    pub fn new(env: &'env TypeEnv) -> Self {
+        KindInfer {
+            env,
+            vars: vec![],
+            tycons: HashMap::new(),
+            classes: HashMap::new(),
+            tyvars: HashMap::new(),
+        }
+    }
+
+    /// Infers the kinds of the data types, type synonyms and classes a
+    /// program declares, with the errors of each declaration.
+    pub fn declarations(
+        mut self,
+        body: &Program,
+    ) -> (HashMap<String, Kind>, HashMap<String, Kind>, Vec<TypeError>) {
+        for stmt in body {
+            match stmt {
+                Stmt::DataStmt(DataDecl { name, .. }) | Stmt::TypeStmt(name, ..) => {
+                    let kind = self.fresh();
+                    self.tycons.insert(name.0.clone(), kind);
+                }
+                Stmt::ClassStmt(decl) => {
+                    let kind = self.fresh();
+                    self.classes.insert(decl.name.0.clone(), kind);
+                }
+                _ => {}
+            }
+        }
+        let mut errors = vec![];
+        for stmt in body {
+            let result = match stmt {
+                Stmt::DataStmt(decl) => self.data(decl),
+                Stmt::TypeStmt(name, params, ty) => self.synonym(name, params, ty),
+                Stmt::ClassStmt(decl) => self.class(decl),
+                _ => Ok(()),
+            };
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+        let tycons = self
+            .tycons
+            .iter()
+            .map(|(name, k)| (name.clone(), self.default(k)))
+            .collect();
+        let classes = self
+            .classes
+            .iter()
+            .map(|(name, k)| (name.clone(), self.default(k)))
+            .collect();
+        (tycons, classes, errors)
+    }
+
+    // `data T a b = C t1 t2` has kind `ka -> kb -> *`, where the fields
+    // are types
+    fn data(&mut self, decl: &DataDecl) -> Result<()> {
+        let params: Vec<Kind> = decl.params.iter().map(|_| self.fresh()).collect();
+        self.tyvars = decl
+            .params
+            .iter()
+            .map(|Ident(p)| p.clone())
+            .zip(params.clone())
+            .collect();
+        let kind = params
+            .into_iter()
+            .rev()
+            .fold(Kind::Star, |kind, param| Kind::arrow(param, kind));
+        let declared = self.tycons[&decl.name.0].clone();
+        self.unify(&declared, &kind);
+        let context = || format!("In the data declaration for `{}`", decl.name.0);
+        for con in &decl.constructors {
+            for field in &con.fields {
+                self.check(field, &Kind::Star)
+                    .map_err(|err| within(err, &context()))?;
+            }
+        }
+        Ok(())
+    }
+
+    // `type T a b = t` has kind `ka -> kb -> k` for the kind `k` of `t`,
+    // which need not be a type: `type M = Maybe` is `* -> *`
+    fn synonym(&mut self, Ident(name): &Ident, params: &[Ident], ty: &Type) -> Result<()> {
+        let kinds: Vec<Kind> = params.iter().map(|_| self.fresh()).collect();
+        self.tyvars = params
+            .iter()
+            .map(|Ident(p)| p.clone())
+            .zip(kinds.clone())
+            .collect();
+        let context = || format!("In the type synonym declaration for `{}`", name);
+        let result = self.infer(ty).map_err(|err| within(err, &context()))?;
+        let kind = kinds
+            .into_iter()
+            .rev()
+            .fold(result, |kind, param| Kind::arrow(param, kind));
+        let declared = self.tycons[name].clone();
+        self.unify(&declared, &kind);
+        Ok(())
+    }
+
+    // The kind of a class is that of its variable in the signatures of its
+    // methods, and that of its superclasses
+    fn class(&mut self, decl: &ClassDecl) -> Result<()> {
+        let kind = self.classes[&decl.name.0].clone();
+        let context = || format!("In the class declaration for `{}`", decl.name.0);
+        for pred in &decl.context {
+            let superclass = self.class_kind(&pred.class.0);
+            if let Some(superclass) = superclass {
+                if !self.unify(&kind, &superclass) {
+                    let message = format!(
+                        "The superclass `{}` is over types of kind `{}`, but `{}` has kind `{}`",
+                        pred.class.0,
+                        self.zonk(&superclass),
+                        decl.var.0,
+                        self.zonk(&kind)
+                    );
+                    return Err(within(TypeError::new(message), &context()));
+                }
+            }
+        }
+        for stmt in &decl.body {
+            if let Stmt::SigStmt(_, preds, ty, span) = stmt {
+                self.tyvars = HashMap::from([(decl.var.0.clone(), kind.clone())]);
+                self.signature(preds, ty)
+                    .map_err(|err| within(err.at_span(*span), &context()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the kinds of a signature: its type is a type, and each
+    /// constraint is on a type of the kind of its class.
+    pub fn signature(&mut self, context: &Context, ty: &Type) -> Result<()> {
+        for pred in context {
+            self.pred(pred)?;
+        }
+        self.check(ty, &Kind::Star)
+    }
+
+    /// Checks the head of an instance against its class, and its context.
+    /// The errors are located at the head.
+    pub fn instance(&mut self, decl: &InstanceDecl) -> Result<()> {
+        let context = || {
+            format!(
+                "In the instance declaration for `{} {}`",
+                decl.class.0, decl.ty
+            )
+        };
+        let within = |err: TypeError| within(err.at_span(decl.span), &context());
+        let pred = Pred {
+            class: decl.class.clone(),
+            ty: decl.ty.clone(),
+        };
+        self.pred(&pred).map_err(within)?;
+        for pred in &decl.context {
+            self.pred(pred).map_err(within)?;
+        }
+        Ok(())
+    }
+
+    fn pred(&mut self, pred: &Pred) -> Result<()> {
+        match self.class_kind(&pred.class.0) {
+            Some(kind) => self.check(&pred.ty, &kind),
+            // the conversion of the constraint reports an unknown class
+            None => Ok(()),
+        }
+    }
+
+    fn check(&mut self, ty: &Type, expected: &Kind) -> Result<()> {
+        let kind = self.infer(ty)?;
+        match self.unify(expected, &kind) {
+            true => Ok(()),
+            false => Err(self.mismatch(expected, ty, &kind)),
+        }
+    }
+
+    fn infer(&mut self, ty: &Type) -> Result<Kind> {
+        match ty {
+            Type::VarType(Ident(name)) => match self.tyvars.get(name) {
+                Some(kind) => Ok(kind.clone()),
+                None => {
+                    let kind = self.fresh();
+                    self.tyvars.insert(name.clone(), kind.clone());
+                    Ok(kind)
+                }
+            },
+            Type::ConType(Ident(name)) => Ok(self.tycon_kind(name)),
+            Type::AppType(f, arg) => {
+                let kind = self.infer(f)?;
+                match self.shallow(&kind) {
+                    Kind::Arrow(param, result) => {
+                        self.check(arg, &param)?;
+                        Ok(*result)
+                    }
+                    Kind::Var(_) => {
+                        let (param, result) = (self.infer(arg)?, self.fresh());
+                        self.unify(&kind, &Kind::arrow(param, result.clone()));
+                        Ok(result)
+                    }
+                    Kind::Star => {
+                        let expected = Kind::arrow(self.infer(arg)?, self.fresh());
+                        Err(self.mismatch(&expected, f, &kind))
+                    }
+                }
+            }
+            Type::FnType(arg, result) => {
+                self.check(arg, &Kind::Star)?;
+                self.check(result, &Kind::Star)?;
+                Ok(Kind::Star)
+            }
+            Type::ListType(elem) => {
+                self.check(elem, &Kind::Star)?;
+                Ok(Kind::Star)
+            }
+            Type::TupleType(components) => {
+                for component in components {
+                    self.check(component, &Kind::Star)?;
+                }
+                Ok(Kind::Star)
+            }
+            Type::ForallType(_, ty) => self.infer(ty),
+        }
+    }
+
+    // The kinds nothing determines yet are given as `*`, as they would be
+    // at the end of the declarations
+    fn mismatch(&self, expected: &Kind, ty: &Type, actual: &Kind) -> TypeError {
+        let (expected, actual) = (self.default(expected), self.default(actual));
+        TypeError::new(match expected {
+            Kind::Star => format!("Expected a type, but `{}` has kind `{}`", ty, actual),
+            expected => format!(
+                "Expected kind `{}`, but `{}` has kind `{}`",
+                expected, ty, actual
+            ),
+        })
+    }
+
+    // `String`, `Int` and `Float` are synonyms of types. A constructor not
+    // in scope can have any kind, its conversion reports it.
+    fn tycon_kind(&mut self, name: &str) -> Kind {
+        if let Some(kind) = self.tycons.get(name) {
+            return kind.clone();
+        }
+        match name {
+            "String" | "Int" | "Float" => Kind::Star,
+            name => match self.env.datatype(name) {
+                Some(datatype) => datatype.kind,
+                None => match self.env.synonyms.get(name) {
+                    Some(synonym) => synonym.kind.clone(),
+                    None => self.fresh(),
+                },
+            },
+        }
+    }
+
+    fn class_kind(&self, name: &str) -> Option<Kind> {
+        if let Some(kind) = self.classes.get(name) {
+            return Some(kind.clone());
+        }
+        self.env.classes.get(name).map(|class| class.kind.clone())
+    }
+
+    fn fresh(&mut self) -> Kind {
+        self.vars.push(None);
+        Kind::Var(self.vars.len() - 1)
+    }
+
+    fn shallow(&self, kind: &Kind) -> Kind {
+        match kind {
+            Kind::Var(v) => match self.vars[*v] {
+                Some(ref bound) => self.shallow(bound),
+                None => kind.clone(),
+            },
+            kind => kind.clone(),
+        }
+    }
+
+    fn zonk(&self, kind: &Kind) -> Kind {
+        match self.shallow(kind) {
+            Kind::Arrow(arg, result) => Kind::arrow(self.zonk(&arg), self.zonk(&result)),
+            kind => kind,
+        }
+    }
+
+    // The kind with the variables nothing determined taken as `*`
+    fn default(&self, kind: &Kind) -> Kind {
+        match self.zonk(kind) {
+            Kind::Arrow(arg, result) => Kind::arrow(self.default(&arg), self.default(&result)),
+            Kind::Var(_) => Kind::Star,
+            Kind::Star => Kind::Star,
+        }
+    }
+
+    fn unify(&mut self, a: &Kind, b: &Kind) -> bool {
+        match (self.shallow(a), self.shallow(b)) {
+            (Kind::Var(v), Kind::Var(w)) if v == w => true,
+            (Kind::Var(v), kind) | (kind, Kind::Var(v)) => {
+                if self.occurs(v, &kind) {
+                    return false;
+                }
+                self.vars[v] = Some(kind);
+                true
+            }
+            (Kind::Star, Kind::Star) => true,
+            (Kind::Arrow(a1, r1), Kind::Arrow(a2, r2)) => {
+                self.unify(&a1, &a2) && self.unify(&r1, &r2)
+            }
+            _ => false,
+        }
+    }
+
+    fn occurs(&self, var: usize, kind: &Kind) -> bool {
+        match self.shallow(kind) {
+            Kind::Var(v) => v == var,
+            Kind::Arrow(arg, result) => self.occurs(var, &arg) || self.occurs(var, &result),
+            Kind::Star => false,
+        }
+    }
+}
+
+/// An error with the declaration it is in.
+pub fn within(err: TypeError, context: &str) -> TypeError {
+    TypeError {
+        message: format!("{}\n  {}", err.message, context),
+        ..err
+    }
+}
@@ -0,0 +1,1422 @@
+pub mod error;
+pub mod infer;
+pub mod kinds;
+pub mod patterns;
+pub mod types;
+
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::fold::{self, Fold};
+use crate::parser::rename::source_name;
+pub use crate::typecheck::error::{Argument, ErrorFormat, Mismatch, TypeError, Warning};
+use crate::typecheck::infer::*;
+use crate::typecheck::kinds::{within, KindInfer};
+use crate::typecheck::patterns::PatternCheck;
+use crate::typecheck::types::{var_name, Kind, Pred, Scheme, Ty};
+use std::collections::{HashMap, HashSet};
+
+/// A renamed expression as it was written.
+pub fn source_text(expr: &Expr) -> String {
+    SourceNames.fold_expr(expr.clone()).to_string()
+}
+
+/// A renamed pattern as it was written.
+pub fn source_pattern(pat: &Pattern) -> String {
+    SourceNames.fold_pattern(pat.clone()).to_string()
+}
+
+// Restores the names of a renamed expression
+struct SourceNames;
+
+impl Fold for SourceNames {
+    fn fold_ident(&mut self, Ident(name): Ident) -> Ident {
+        Ident(source_name(&name).to_owned())
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold::fold_expr(self, expr)
+    }
+}
+
+/// The module the definitions of an interactive session belong to.
+pub const INTERACTIVE: &str = "Interactive";
+
+/// A data type: its parameters, its constructors with their numbers of
+/// fields, and its kind.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DataType {
+    pub params: Vec<String>,
+    pub constructors: Vec<(String, usize)>,
+    pub kind: Kind,
+}
+
+/// A type synonym, `type Pair a = (a, a)`: the type it stands for over its
+/// parameters `Gen(0)` to `Gen(n - 1)`, and its kind.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Synonym {
+    pub params: usize,
+    pub ty: Ty,
+    pub kind: Kind,
+}
+
+/// A class: the module declaring it, the variable of its declaration and
+/// its kind, its superclasses and the type of each method, in which `Gen(0)`
+/// is the class variable.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ClassInfo {
+    pub module: String,
+    pub var: String,
+    pub kind: Kind,
+    pub superclasses: Vec<String>,
+    pub methods: HashMap<String, Scheme>,
+}
+
+/// An instance, `instance (Show a, Show b) => Show (Either a b)`: the module
+/// declaring it, and its head and context over the variables `Gen(0)` to
+/// `Gen(n - 1)`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct InstanceInfo {
+    pub module: String,
+    pub vars: usize,
+    pub head: Ty,
+    pub context: Vec<Pred>,
+}
+
+impl InstanceInfo {
+    /// The types the instance's variables stand for in a type its head
+    /// matches.
+    pub fn match_head(&self, ty: &Ty) -> Option<Vec<Ty>> {
+        fn matches(head: &Ty, ty: &Ty, types: &mut [Option<Ty>]) -> bool {
+            match (head, ty) {
+                (Ty::Gen(i), ty) => match types[*i] {
+                    Some(ref bound) => bound == ty,
+                    None => {
+                        types[*i] = Some(ty.clone());
+                        true
+                    }
+                },
+                (Ty::App(f, x), Ty::App(g, y)) => matches(f, g, types) && matches(x, y, types),
+                (head, ty) => head == ty,
+            }
+        }
+        let mut types = vec![None; self.vars];
+        match matches(&self.head, ty, &mut types) {
+            true => Some(types.into_iter().map(|ty| ty.unwrap_or(Ty::Dynamic)).collect()),
+            false => None,
+        }
+    }
+}
+
+/// A name a module imports, from the module defining it. A builtin takes all
+/// the arguments of a call `f(x, y)` rather than a tuple.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Imported {
+    pub name: String,
+    pub origin: String,
+    pub builtin: bool,
+}
+
+/// The types of the modules checked so far, and the data types, classes and
+/// instances they declare, which are known by name everywhere as in the
+/// evaluator.
+#[derive(Debug, Default)]
+pub struct TypeEnv {
+    modules: HashMap<String, HashMap<String, Scheme>>,
+    pub datatypes: HashMap<String, DataType>,
+    pub synonyms: HashMap<String, Synonym>,
+    pub classes: HashMap<String, ClassInfo>,
+    /// The instances by class and type constructor
+    pub instances: HashMap<(String, String), InstanceInfo>,
+    /// The warnings of the modules and lines checked since they were last
+    /// taken
+    warnings: Vec<Warning>,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        let mut env = TypeEnv::default();
+        for (name, params, constructors) in [
+            ("Integer", vec![], vec![]),
+            ("Double", vec![], vec![]),
+            ("Char", vec![], vec![]),
+            ("[]", vec!["a"], vec![("[]", 0), (":", 2)]),
+            ("->", vec!["a", "b"], vec![]),
+        ] {
+            let datatype = DataType {
+                kind: Kind::of_arity(params.len()),
+                params: params.into_iter().map(str::to_owned).collect(),
+                constructors: constructors.into_iter().map(|(c, n)| (c.to_owned(), n)).collect(),
+            };
+            env.datatypes.insert(name.to_owned(), datatype);
+        }
+        env
+    }
+
+    /// The warnings of the modules checked since the last call.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn is_checked(&self, module: &str) -> bool {
+        self.modules.contains_key(module)
+    }
+
+    /// The type of a top-level name of a module checked before.
+    pub fn scheme(&self, module: &str, name: &str) -> Option<&Scheme> {
+        self.modules.get(module)?.get(name)
+    }
+
+    /// The data type of this name, the tuples included.
+    pub fn datatype(&self, name: &str) -> Option<DataType> {
+        if let Some(arity) = Ident(name.to_owned()).tuple_arity() {
+            return Some(DataType {
+                params: (0..arity).map(var_name).collect(),
+                constructors: vec![(name.to_owned(), arity)],
+                kind: Kind::of_arity(arity),
+            });
+        }
+        self.datatypes.get(name).cloned()
+    }
+
+    /// The data type a constructor belongs to.
+    pub fn constructor_type(&self, con: &str) -> Option<DataType> {
+        if Ident(con.to_owned()).tuple_arity().is_some() {
+            return self.datatype(con);
+        }
+        self.datatypes
+            .values()
+            .find(|datatype| datatype.constructors.iter().any(|(c, _)| c == con))
+            .cloned()
+    }
+
+    /// The instance of a class for a type constructor. Those of the tuples
+    /// are built in, as the evaluator derives them, and belong to no module.
+    pub fn instance(&self, class: &str, tycon: &str) -> Option<InstanceInfo> {
+        if let Some(instance) = self.instances.get(&(class.to_owned(), tycon.to_owned())) {
+            return Some(instance.clone());
+        }
+        let arity = match tycon {
+            "()" => 0,
+            tycon => Ident(tycon.to_owned()).tuple_arity()?,
+        };
+        let derivable = match class {
+            "Eq" | "Ord" | "Show" | "Read" | "Bounded" => true,
+            "Ix" => arity <= 5,
+            _ => false,
+        };
+        match derivable {
+            true => Some(derived("", class, Ty::con(tycon), arity)),
+            false => None,
+        }
+    }
+
+    /// Infers the types of the top-level definitions of a module, and checks
+    /// them against their signatures, before anything runs. The module's
+    /// types are kept for the modules importing it, and the module comes
+    /// back elaborated into explicit dictionary passing.
+    pub fn check_module(
+        &mut self,
+        name: &str,
+        body: &Program,
+        imports: &[Imported],
+    ) -> Result<Program, Vec<TypeError>> {
+        let (program, top) = self.check(name, body, imports, false)?;
+        self.modules.insert(name.to_owned(), top);
+        Ok(program)
+    }
+
+    /// Checks a line of an interactive session as `check_module` does a
+    /// module, with the names of the earlier lines in scope. GHCi's
+    /// extended defaulting rules give a type to `show []` and `return 1`.
+    /// The line's definitions are kept for the lines after it.
+    pub fn check_interactive(
+        &mut self,
+        body: &Program,
+        imports: &[Imported],
+    ) -> Result<Program, Vec<TypeError>> {
+        let earlier = self.modules.get(INTERACTIVE).cloned().unwrap_or_default();
+        let mut imports: Vec<Imported> = imports
+            .iter()
+            .filter(|import| !earlier.contains_key(&import.name))
+            .cloned()
+            .collect();
+        imports.extend(earlier.keys().map(|name| Imported {
+            name: name.clone(),
+            origin: INTERACTIVE.to_owned(),
+            builtin: false,
+        }));
+        let (program, top) = self.check(INTERACTIVE, body, &imports, true)?;
+        self.modules.entry(INTERACTIVE.to_owned()).or_default().extend(top);
+        Ok(program)
+    }
+
+    fn check(
+        &mut self,
+        name: &str,
+        body: &Program,
+        imports: &[Imported],
+        interactive: bool,
+    ) -> Result<(Program, HashMap<String, Scheme>), Vec<TypeError>> {
+        let mut errors = vec![];
+        let top = self.declare(name, body, &mut errors);
+        let mut globals = HashMap::new();
+        let mut builtins = HashSet::new();
+        let mut prelude = HashSet::new();
+        for import in imports {
+            if import.origin == "Prelude" {
+                prelude.insert(import.name.clone());
+            }
+            let unqualified = Ident(import.name.clone()).unqualified().to_owned();
+            if let Some(scheme) = self.scheme(&import.origin, &unqualified) {
+                globals.insert(import.name.clone(), scheme.clone());
+            }
+            if import.builtin {
+                builtins.insert(import.name.clone());
+            }
+        }
+        let defaults = self.defaults(body, interactive).unwrap_or_else(|err| {
+            errors.push(err);
+            self.defaults(&vec![], interactive).unwrap_or_default()
+        });
+        let mut infer =
+            Infer::new(self, top, globals, builtins, prelude).with_defaults(defaults, interactive);
+        let mut program = infer.check_program(body);
+        infer.check_classes(&mut program);
+        infer.solve_top();
+        infer.report_holes();
+        errors.append(&mut infer.errors);
+        let top = infer.top_level();
+        let program = infer.elaborate(program);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        // whether the matches cover their values is only worth asking of
+        // a program that type checks
+        let mut warnings = PatternCheck::new(self).check_program(body);
+        self.warnings.append(&mut warnings);
+        Ok((program, top))
+    }
+
+    // The types of the module's `default` declaration, in order, or
+    // `(Integer, Double)` without one, and `((), Integer, Double, IO)` in an
+    // interactive session
+    fn defaults(&self, body: &Program, interactive: bool) -> Result<Vec<Ty>, TypeError> {
+        let mut declared = body.iter().filter_map(|stmt| match stmt {
+            Stmt::DefaultStmt(types) => Some(types),
+            _ => None,
+        });
+        let types = match (declared.next(), declared.next()) {
+            (None, _) if interactive => {
+                return Ok(vec![
+                    Ty::tuple(vec![]),
+                    Ty::con("Integer"),
+                    Ty::con("Double"),
+                    Ty::con("IO"),
+                ])
+            }
+            (None, _) => return Ok(vec![Ty::con("Integer"), Ty::con("Double")]),
+            (Some(types), None) => types,
+            (Some(_), Some(_)) => {
+                return Err(TypeError::new("Multiple default declarations".to_owned()))
+            }
+        };
+        let mut var = |name: &str| {
+            Err(TypeError::new(format!("Not in scope: type variable `{}`", name)))
+        };
+        types
+            .iter()
+            .map(|ty| {
+                KindInfer::new(self).signature(&vec![], ty)?;
+                self.convert(ty, &mut var)
+            })
+            .collect()
+    }
+
+    // Registers the data types and classes of a program, and gives the types
+    // of their constructors, field labels and methods.
+    fn declare(
+        &mut self,
+        module: &str,
+        body: &Program,
+        errors: &mut Vec<TypeError>,
+    ) -> HashMap<String, Scheme> {
+        let mut top = HashMap::new();
+        for stmt in body {
+            match stmt {
+                Stmt::DataStmt(decl) => {
+                    let datatype = DataType {
+                        params: decl.params.iter().map(|Ident(p)| p.clone()).collect(),
+                        constructors: decl
+                            .constructors
+                            .iter()
+                            .map(|c| (c.name.0.clone(), c.fields.len()))
+                            .collect(),
+                        kind: Kind::of_arity(decl.params.len()),
+                    };
+                    self.datatypes.insert(decl.name.0.clone(), datatype);
+                }
+                Stmt::ClassStmt(decl) => {
+                    let class = ClassInfo {
+                        module: module.to_owned(),
+                        var: decl.var.0.clone(),
+                        kind: Kind::Star,
+                        superclasses: decl.context.iter().map(|p| p.class.0.clone()).collect(),
+                        methods: HashMap::new(),
+                    };
+                    self.classes.insert(decl.name.0.clone(), class);
+                }
+                _ => {}
+            }
+        }
+        let (tycons, classes, kind_errors) = KindInfer::new(self).declarations(body);
+        for (name, kind) in &tycons {
+            if let Some(datatype) = self.datatypes.get_mut(name) {
+                datatype.kind = kind.clone();
+            }
+        }
+        for (name, kind) in classes {
+            if let Some(class) = self.classes.get_mut(&name) {
+                class.kind = kind;
+            }
+        }
+        errors.extend(kind_errors);
+        self.declare_synonyms(body, &tycons, errors);
+        // the instances of the module so far, as they are reported
+        let mut declared = HashMap::new();
+        for stmt in body {
+            let result = match stmt {
+                Stmt::DataStmt(decl) => self.declare_data(module, decl, &mut top, &mut declared),
+                Stmt::ClassStmt(decl) => self.declare_class(decl, &mut top),
+                Stmt::InstanceStmt(decl) | Stmt::DerivingStmt(decl) => {
+                    self.declare_instance(module, decl, &mut declared)
+                }
+                _ => Ok(()),
+            };
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+        top
+    }
+
+    // The synonyms of a program, each after those its type is written with,
+    // so that a type expands to one without them. One in error stands for
+    // any type, so that its uses are not reported too.
+    fn declare_synonyms(
+        &mut self,
+        body: &Program,
+        kinds: &HashMap<String, Kind>,
+        errors: &mut Vec<TypeError>,
+    ) {
+        let mut pending: Vec<(&Ident, &Vec<Ident>, &Type)> = body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::TypeStmt(name, params, ty) => Some((name, params, ty)),
+                _ => None,
+            })
+            .collect();
+        while !pending.is_empty() {
+            let names: Vec<&str> = pending.iter().map(|(Ident(name), ..)| name.as_str()).collect();
+            let ready = pending.iter().position(|(_, _, ty)| {
+                let mut tycons = vec![];
+                type_constructors(ty, &mut tycons);
+                !tycons.iter().any(|tycon| names.contains(tycon))
+            });
+            let (Ident(name), params, ty) = match ready {
+                Some(i) => pending.remove(i),
+                None => {
+                    let names: Vec<String> = names.iter().map(|n| format!("`{}`", n)).collect();
+                    errors.push(TypeError::new(format!(
+                        "Cycle in type synonym declarations: {}",
+                        names.join(", ")
+                    )));
+                    for (Ident(name), params, _) in pending.drain(..) {
+                        self.declare_synonym(name, params.len(), Ty::Dynamic, kinds);
+                    }
+                    return;
+                }
+            };
+            let vars: Vec<&str> = params.iter().map(|Ident(p)| p.as_str()).collect();
+            let ty = self.convert(ty, &mut |var| match vars.iter().position(|v| *v == var) {
+                Some(i) => Ok(Ty::Gen(i)),
+                None => Err(TypeError::new(format!("Not in scope: type variable `{}`", var))),
+            });
+            let ty = ty.unwrap_or_else(|err| {
+                let context = format!("In the type synonym declaration for `{}`", name);
+                errors.push(within(err, &context));
+                Ty::Dynamic
+            });
+            self.declare_synonym(name, params.len(), ty, kinds);
+        }
+    }
+
+    fn declare_synonym(
+        &mut self,
+        name: &str,
+        params: usize,
+        ty: Ty,
+        kinds: &HashMap<String, Kind>,
+    ) {
+        let kind = kinds[name].clone();
+        self.synonyms.insert(name.to_owned(), Synonym { params, ty, kind });
+    }
+
+    // `Just :: a -> Maybe a`, and a label `x :: T a -> a` for each field
+    fn declare_data(
+        &mut self,
+        module: &str,
+        decl: &DataDecl,
+        top: &mut HashMap<String, Scheme>,
+        declared: &mut HashMap<(String, String), String>,
+    ) -> Result<(), TypeError> {
+        let names: Vec<String> = decl.params.iter().map(|Ident(p)| p.clone()).collect();
+        let result = (0..names.len()).fold(Ty::Con(decl.name.0.clone()), |ty, i| {
+            Ty::app(ty, Ty::Gen(i))
+        });
+        let scheme = |ty| Scheme {
+            names: names.clone(),
+            preds: vec![],
+            ty,
+        };
+        for con in &decl.constructors {
+            let mut fields = vec![];
+            for field in &con.fields {
+                let field = self.convert(field, &mut |var| {
+                    match names.iter().position(|name| name == var) {
+                        Some(i) => Ok(Ty::Gen(i)),
+                        None => Err(TypeError::new(format!(
+                            "Not in scope: type variable `{}`",
+                            var
+                        ))),
+                    }
+                });
+                fields.push(field.map_err(|err| err.in_binding(&con.name.0))?);
+            }
+            for (label, field) in con.labels.iter().zip(&fields) {
+                let selector = Ty::fun(result.clone(), field.clone());
+                top.entry(label.0.clone()).or_insert_with(|| scheme(selector));
+            }
+            let ty = fields.into_iter().rev().fold(result.clone(), |ty, field| Ty::fun(field, ty));
+            top.insert(con.name.0.clone(), scheme(ty));
+        }
+        for Ident(class) in &decl.deriving {
+            let instance = derived(module, class, Ty::Con(decl.name.0.clone()), names.len());
+            let origin = format!("Derived in the declaration of `{}`", decl.name.0);
+            let key = (class.clone(), decl.name.0.clone());
+            self.add_instance(key, instance, origin, None, declared)?;
+        }
+        Ok(())
+    }
+
+    // An instance's head is a type constructor applied to type variables,
+    // or a type the evaluator takes its constructor from such as `String`.
+    fn declare_instance(
+        &mut self,
+        module: &str,
+        decl: &InstanceDecl,
+        declared: &mut HashMap<(String, String), String>,
+    ) -> Result<(), TypeError> {
+        KindInfer::new(self).instance(decl)?;
+        let mut vars: Vec<String> = vec![];
+        let mut var = |name: &str| {
+            let i = vars.iter().position(|v| v == name).unwrap_or_else(|| {
+                vars.push(name.to_owned());
+                vars.len() - 1
+            });
+            Ok(Ty::Gen(i))
+        };
+        let head = self.convert(&decl.ty, &mut var)?;
+        let context = decl
+            .context
+            .iter()
+            .map(|pred| self.convert_pred(pred, &mut var))
+            .collect::<Result<_, _>>()?;
+        let tycon = match head.split() {
+            (Ty::Con(tycon), _) => tycon.clone(),
+            _ => {
+                return Err(TypeError::new(format!(
+                    "Illegal instance declaration for `{} {}`",
+                    decl.class.0, head
+                )))
+            }
+        };
+        let instance = InstanceInfo {
+            module: module.to_owned(),
+            vars: vars.len(),
+            head,
+            context,
+        };
+        let origin = match decl.span {
+            Some(span) => format!("Defined at {}:{}", span.line, span.column),
+            None => "Defined in this module".to_owned(),
+        };
+        let key = (decl.class.0.clone(), tycon);
+        self.add_instance(key, instance, origin, decl.span, declared)
+    }
+
+    // An instance of the module, unless it has one of the class for the type
+    // constructor already. `origin` says where it is declared, for the error.
+    fn add_instance(
+        &mut self,
+        key: (String, String),
+        instance: InstanceInfo,
+        origin: String,
+        span: Option<Span>,
+        declared: &mut HashMap<(String, String), String>,
+    ) -> Result<(), TypeError> {
+        let head = Pred {
+            class: key.0.clone(),
+            ty: instance.head.clone(),
+        };
+        let line = format!("instance {} -- {}", head, origin);
+        if let Some(first) = declared.get(&key) {
+            let mut err = TypeError::new(format!(
+                "Duplicate instance declarations:\n  {}\n  {}",
+                first, line
+            ));
+            err.span = span.map(Box::new);
+            return Err(err);
+        }
+        declared.insert(key.clone(), line);
+        self.instances.insert(key, instance);
+        Ok(())
+    }
+
+    // The methods of `class C a` are `C a => t` for their signature `t`
+    fn declare_class(
+        &mut self,
+        decl: &ClassDecl,
+        top: &mut HashMap<String, Scheme>,
+    ) -> Result<(), TypeError> {
+        let Ident(ref class) = decl.name;
+        let mut methods = HashMap::new();
+        for stmt in &decl.body {
+            if let Stmt::SigStmt(names, context, ty, _) = stmt {
+                let mut vars = vec![decl.var.0.clone()];
+                // the kinds of the signature were checked with the class's
+                let scheme = self
+                    .generalize(context, ty, &mut vars, &HashMap::new())
+                    .map_err(|err| err.in_binding(&names[0].0))?;
+                let mut preds = vec![Pred {
+                    class: class.clone(),
+                    ty: Ty::Gen(0),
+                }];
+                preds.extend(scheme.preds);
+                let scheme = Scheme { preds, ..scheme };
+                for Ident(method) in names {
+                    methods.insert(method.clone(), scheme.clone());
+                    top.insert(method.clone(), scheme.clone());
+                }
+            }
+        }
+        if let Some(info) = self.classes.get_mut(class) {
+            info.methods = methods;
+        }
+        Ok(())
+    }
+
+    /// The type of a signature, quantified over its variables other than
+    /// those in scope. `vars` starts with the variables to quantify first,
+    /// such as the variable of a class.
+    pub fn generalize_sig(
+        &self,
+        context: &Context,
+        ty: &Type,
+        vars: &mut Vec<String>,
+        scope: &HashMap<String, Ty>,
+    ) -> Result<Scheme, TypeError> {
+        KindInfer::new(self).signature(context, ty)?;
+        self.generalize(context, ty, vars, scope)
+    }
+
+    fn generalize(
+        &self,
+        context: &Context,
+        ty: &Type,
+        vars: &mut Vec<String>,
+        scope: &HashMap<String, Ty>,
+    ) -> Result<Scheme, TypeError> {
+        let ty = match ty {
+            Type::ForallType(_, ty) => ty,
+            ty => ty,
+        };
+        let mut var = |name: &str| -> Result<Ty, TypeError> {
+            if let Some(ty) = scope.get(name) {
+                return Ok(ty.clone());
+            }
+            match vars.iter().position(|v| v == name) {
+                Some(i) => Ok(Ty::Gen(i)),
+                None => {
+                    vars.push(name.to_owned());
+                    Ok(Ty::Gen(vars.len() - 1))
+                }
+            }
+        };
+        let ty = self.convert(ty, &mut var)?;
+        let preds = context
+            .iter()
+            .map(|pred| self.convert_pred(pred, &mut var))
+            .collect::<Result<_, _>>()?;
+        Ok(Scheme {
+            names: vars.clone(),
+            preds,
+            ty,
+        })
+    }
+
+    pub fn convert_pred(
+        &self,
+        pred: &crate::parser::ast::Pred,
+        var: &mut dyn FnMut(&str) -> Result<Ty, TypeError>,
+    ) -> Result<Pred, TypeError> {
+        let Ident(ref class) = pred.class;
+        if !self.classes.contains_key(class) {
+            return Err(TypeError::new(format!("Not in scope: type class `{}`", class)));
+        }
+        Ok(Pred {
+            class: class.clone(),
+            ty: self.convert(&pred.ty, var)?,
+        })
+    }
+
+    /// The checker's form of a type as written, with `var` giving the type
+    /// of each type variable, and the type synonyms expanded. The evaluator
+    /// has a single representation of integers and one of floating point
+    /// numbers, so `Int` is `Integer` and `Float` is `Double`.
+    pub fn convert(
+        &self,
+        ty: &Type,
+        var: &mut dyn FnMut(&str) -> Result<Ty, TypeError>,
+    ) -> Result<Ty, TypeError> {
+        let (head, args) = ty.split();
+        if let Type::ConType(Ident(name)) = head {
+            if let Some(synonym) = self.synonyms.get(name) {
+                return self.expand(name, synonym, &args, var);
+            }
+        }
+        Ok(match ty {
+            Type::VarType(Ident(name)) => var(name)?,
+            Type::ConType(Ident(name)) => match name.as_str() {
+                "String" => Ty::string(),
+                "Int" => Ty::con("Integer"),
+                "Float" => Ty::con("Double"),
+                name if self.datatype(name).is_some() => Ty::con(name),
+                name => {
+                    return Err(TypeError::new(format!(
+                        "Not in scope: type constructor `{}`",
+                        name
+                    )))
+                }
+            },
+            Type::AppType(f, arg) => Ty::app(self.convert(f, var)?, self.convert(arg, var)?),
+            Type::FnType(arg, result) => {
+                Ty::fun(self.convert(arg, var)?, self.convert(result, var)?)
+            }
+            Type::ListType(elem) => Ty::list(self.convert(elem, var)?),
+            Type::TupleType(components) => Ty::tuple(
+                components
+                    .iter()
+                    .map(|c| self.convert(c, var))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Type::ForallType(_, ty) => self.convert(ty, var)?,
+        })
+    }
+
+    // `Pair Int` is `(Integer, Integer)`. A synonym is given all its
+    // arguments, and the type it stands for any more.
+    fn expand(
+        &self,
+        name: &str,
+        synonym: &Synonym,
+        args: &[&Type],
+        var: &mut dyn FnMut(&str) -> Result<Ty, TypeError>,
+    ) -> Result<Ty, TypeError> {
+        if args.len() < synonym.params {
+            return Err(TypeError::new(format!(
+                "The type synonym `{}` should have {}, but has been given {}",
+                name,
+                arguments(synonym.params),
+                match args.len() {
+                    0 => "none".to_owned(),
+                    n => n.to_string(),
+                }
+            )));
+        }
+        let args = args
+            .iter()
+            .map(|arg| self.convert(arg, var))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (params, rest) = args.split_at(synonym.params);
+        let ty = synonym.ty.instantiate(params);
+        Ok(rest.iter().fold(ty, |ty, arg| Ty::app(ty, arg.clone())))
+    }
+}
+
+fn arguments(n: usize) -> String {
+    match n {
+        1 => "1 argument".to_owned(),
+        n => format!("{} arguments", n),
+    }
+}
+
+// The type constructors a type is written with
+fn type_constructors<'ast>(ty: &'ast Type, tycons: &mut Vec<&'ast str>) {
+    match ty {
+        Type::VarType(_) => {}
+        Type::ConType(Ident(name)) => tycons.push(name),
+        Type::AppType(f, arg) | Type::FnType(f, arg) => {
+            type_constructors(f, tycons);
+            type_constructors(arg, tycons);
+        }
+        Type::ListType(ty) | Type::ForallType(_, ty) => type_constructors(ty, tycons),
+        Type::TupleType(components) => {
+            for component in components {
+                type_constructors(component, tycons);
+            }
+        }
+    }
+}
+
+// The instance the evaluator derives for a type constructor, which needs
+// the class for each of its parameters
+fn derived(module: &str, class: &str, tycon: Ty, arity: usize) -> InstanceInfo {
+    InstanceInfo {
+        module: module.to_owned(),
+        vars: arity,
+        head: (0..arity).fold(tycon, |ty, i| Ty::app(ty, Ty::Gen(i))),
+        context: (0..arity)
+            .map(|i| Pred {
+                class: class.to_owned(),
+                ty: Ty::Gen(i),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token::Tokens;
+    use crate::lexer::Lexer;
+    use crate::parser::fixity::Fixities;
+    use crate::parser::fold::{Fold, Unlocate};
+    use crate::parser::Parser;
+
+    const PRELUDE: &str = include_str!("../evaluator/prelude.hs");
+
+    fn parse_located(input: &str) -> Program {
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (_, program) = Parser::parse_tokens(Tokens::with_spans(&tokens, &spans)).unwrap();
+        Fixities::new().resolve_program(program).unwrap()
+    }
+
+    // without spans, the errors of which are tested where they are located
+    fn parse(input: &str) -> Program {
+        Unlocate.fold_program(parse_located(input))
+    }
+
+    // The types of the definitions of a program checked against the prelude,
+    // or its errors
+    fn check(input: &str, names: &[&str]) -> Result<Vec<String>, String> {
+        let (env, result) = check_env(input);
+        match result {
+            Ok(()) => Ok(names
+                .iter()
+                .map(|name| env.scheme("Main", name).unwrap().to_string())
+                .collect()),
+            Err(errors) => Err(errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    }
+
+    fn check_env(input: &str) -> (TypeEnv, Result<(), Vec<TypeError>>) {
+        check_parsed(parse(input))
+    }
+
+    fn check_parsed(program: Program) -> (TypeEnv, Result<(), Vec<TypeError>>) {
+        let mut env = TypeEnv::new();
+        env.check_module("Prelude", &parse(PRELUDE), &[])
+            .unwrap_or_else(|errors| panic!("{}", errors[0]));
+        env.take_warnings();
+        let imports: Vec<Imported> = env.modules["Prelude"]
+            .keys()
+            .map(|name| Imported {
+                name: name.clone(),
+                origin: "Prelude".to_owned(),
+                builtin: false,
+            })
+            .collect();
+        let result = env.check_module("Main", &program, &imports).map(|_| ());
+        (env, result)
+    }
+
+    #[test]
+    fn test_inference() {
+        assert_eq!(
+            check(
+                "compose f g x = f (g x)\n\
+                 pair x = (x, x)\n\
+                 twice f = f . f\n\
+                 count [] = 0\n\
+                 count (_:xs) = 1 + count xs\n\
+                 safeHead [] = Nothing\n\
+                 safeHead (x:_) = Just x",
+                &["compose", "pair", "twice", "count", "safeHead"]
+            ),
+            Ok(vec![
+                "(a -> b) -> (c -> a) -> c -> b".to_owned(),
+                "a -> (a, a)".to_owned(),
+                "(a -> a) -> a -> a".to_owned(),
+                "Num b => [a] -> b".to_owned(),
+                "[a] -> Maybe a".to_owned(),
+            ])
+        );
+        // let bound names are polymorphic, lambda bound ones are not
+        assert_eq!(
+            check(
+                "pairs = let { i x = x } in (i 1, i 'c')\n\
+                 isEven 0 = True\n\
+                 isEven n = isOdd (n - 1)\n\
+                 isOdd 0 = False\n\
+                 isOdd n = isEven (n - 1)",
+                &["pairs", "isOdd"]
+            ),
+            Ok(vec!["(Integer, Char)".to_owned(), "(Eq a, Num a) => a -> Bool".to_owned()])
+        );
+        assert_eq!(
+            check("f g = (g 1, g 'c')", &[]),
+            Err("No instance for (Num Char) arising from the literal `1`\n  \
+                 In the expression: 1\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("x = 1 + \"a\"", &[]),
+            Err("No instance for (Num [Char]) arising from a use of `+`\n  \
+                 In the expression: 1 + \"a\"\n  \
+                 In the definition of `x`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f x = x x", &[]),
+            Err("Occurs check: cannot construct the infinite type: a ~ a -> b\n  \
+                 In the first argument of `x`, namely `x`\n  \
+                 In the expression: x x\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_signatures() {
+        assert_eq!(
+            check(
+                "ident :: a -> a\n\
+                 ident x = x\n\
+                 len' :: [b] -> Int\n\
+                 len' [] = 0\n\
+                 len' (_:xs) = 1 + len' xs\n\
+                 swap :: (a, b) -> (b, a)\n\
+                 swap (x, y) = (y, x)",
+                &["ident", "len'", "swap"]
+            ),
+            Ok(vec![
+                "a -> a".to_owned(),
+                "[b] -> Integer".to_owned(),
+                "(a, b) -> (b, a)".to_owned()
+            ])
+        );
+        assert_eq!(
+            check("f :: a -> a\nf x = 1", &[]),
+            Err("No instance for (Num a) arising from the literal `1`\n  \
+                 In the expression: 1\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f :: Foo -> Int\nf _ = 1", &[]),
+            Err("Not in scope: type constructor `Foo`\n  In the definition of `f`".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_monomorphism_restriction() {
+        assert_eq!(
+            check("plus x y = x + y\nsq = \\x -> x * x\nn = sq 3", &["plus", "sq"]),
+            Ok(vec!["Num a => a -> a -> a".to_owned(), "Integer -> Integer".to_owned()])
+        );
+        // a binding that is not a function has a single type, which its
+        // uses fix
+        assert_eq!(
+            check("plus = (+)\ny = plus (1 :: Int) 2\nz = plus 1.5 2.5", &[]),
+            Err("No instance for (Fractional Integer) arising from the literal `1.5`\n  \
+                 In the expression: 1.5\n  \
+                 In the definition of `z`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("plus = (+)\ny = plus 1 2\nz = plus 1.5 2.5", &["plus"]),
+            Ok(vec!["Double -> Double -> Double".to_owned()])
+        );
+        assert_eq!(check("empty = []", &["empty"]), Ok(vec!["[a]".to_owned()]));
+    }
+
+    #[test]
+    fn test_scoped_type_variables() {
+        let program = |sig| {
+            format!(
+                "{{-# LANGUAGE ScopedTypeVariables #-}}\n\
+                 f :: {}a -> [a]\n\
+                 f x = ys\n\
+                 \x20 where\n\
+                 \x20   ys :: [a]\n\
+                 \x20   ys = [x]",
+                sig
+            )
+        };
+        assert_eq!(check(&program("forall a. "), &["f"]), Ok(vec!["a -> [a]".to_owned()]));
+        assert_eq!(
+            check(&program(""), &[]),
+            Err("Couldn't match expected type `[a1]` with actual type `[a]`\n  \
+                 In the expression: [x]\n  \
+                 In the definition of `ys`\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("g = (\\x -> x) :: a -> a\nh = 'c' :: a", &["g"]).map(|_| ()),
+            Err("Couldn't match expected type `a` with actual type `Char`\n  \
+                 In the expression: 'c'\n  \
+                 In the definition of `h`"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_constraints() {
+        assert_eq!(
+            check(
+                "member _ [] = False\n\
+                 member x (y:ys) = x == y || member x ys\n\
+                 between x y z = x <= y && y <= z && x /= z\n\
+                 shows' xs = map show [xs]\n\
+                 pairs = (member 'c' \"abc\", between 1 2 3)",
+                &["member", "between", "shows'", "pairs"]
+            ),
+            Ok(vec![
+                "Eq a => a -> [a] -> Bool".to_owned(),
+                "Ord a => a -> a -> a -> Bool".to_owned(),
+                "Show a => a -> [[Char]]".to_owned(),
+                "(Bool, Bool)".to_owned(),
+            ])
+        );
+        assert_eq!(
+            check("f = show not", &[]),
+            Err("No instance for (Show (Bool -> Bool)) arising from a use of `show`\n  \
+                 In the expression: show not\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        // the instance for a list needs one for its elements
+        assert_eq!(
+            check("data T = T\nf = [T] == []", &[]),
+            Err("No instance for (Eq T) arising from a use of `==`\n  \
+                 In the expression: [T] == []\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        // a signature's context must provide the constraints of the body
+        assert_eq!(
+            check("f :: Eq a => a -> a -> Bool\nf x y = x < y", &[]),
+            Err("No instance for (Ord a) arising from a use of `<`\n  \
+                 In the expression: x < y\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f :: Ord a => a -> a -> Bool\nf x y = x == y", &["f"]),
+            Ok(vec!["Ord a => a -> a -> Bool".to_owned()])
+        );
+        assert_eq!(
+            check("f s = show (read s)", &[]),
+            Err("Ambiguous type variable `a` arising from a use of `show` prevents the \
+                 constraint `(Show a)` from being solved.\n  \
+                 In the expression: show (read s)\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_data_and_classes() {
+        assert_eq!(
+            check(
+                "data Tree a = Leaf | Node (Tree a) a (Tree a)\n\
+                 data P = P { name :: String, age :: Int }\n\
+                 insert x Leaf = Node Leaf x Leaf\n\
+                 insert x t@(Node l y r) = if x < y then Node (insert x l) y r else t\n\
+                 trees = (Node Leaf 1 Leaf, Node Leaf 'c' Leaf)\n\
+                 class Container f where\n\
+                 \x20 empty :: f a\n\
+                 \x20 insert' :: a -> f a -> f a\n\
+                 instance Container [] where\n\
+                 \x20 empty = []\n\
+                 \x20 insert' = (:)",
+                &["Node", "age", "insert", "trees", "insert'"]
+            ),
+            Ok(vec![
+                "Tree a -> a -> Tree a -> Tree a".to_owned(),
+                "P -> Integer".to_owned(),
+                "Ord a => a -> Tree a -> Tree a".to_owned(),
+                "(Tree Integer, Tree Char)".to_owned(),
+                "Container f => a -> f a -> f a".to_owned(),
+            ])
+        );
+        assert_eq!(
+            check("f (Just x y) = x", &[]),
+            Err("The constructor `Just` should have 1 argument, but has been given 2\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check(
+                "class Container f where\n\
+                 \x20 empty :: f a\n\
+                 instance Container Maybe where\n\
+                 \x20 empty = Just 'c'",
+                &[]
+            ),
+            Err("Couldn't match expected type `Maybe a` with actual type `Maybe Char`\n  \
+                 In the expression: Just 'c'\n  \
+                 In the definition of `empty`"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_defaulting() {
+        // a literal is a number of any type of its class, and an ambiguous
+        // one with a numeric class is the first default type that fits
+        assert_eq!(
+            check(
+                "half x = x / 2\n\
+                 r = 1 / 2\n\
+                 n = 3\n\
+                 count xs = show 1 ++ xs",
+                &["half", "r", "n", "count"]
+            ),
+            Ok(vec![
+                "Fractional a => a -> a".to_owned(),
+                "Double".to_owned(),
+                "Integer".to_owned(),
+                "[Char] -> [Char]".to_owned(),
+            ])
+        );
+        assert_eq!(
+            check("default (Double)\nn = 3", &["n"]),
+            Ok(vec!["Double".to_owned()])
+        );
+        assert_eq!(
+            check("default ()\nf = show 3", &[]),
+            Err("Ambiguous type variable `a` arising from a use of `show` prevents the \
+                 constraint `(Show a)` from being solved.\n  \
+                 In the expression: show 3\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("default (Integer, Foo)\nn = 3", &[]),
+            Err("Not in scope: type constructor `Foo`".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_kinds() {
+        let (env, result) = check_env(
+            "data App f a = App (f a)\n\
+             data Proxy a = Proxy\n\
+             data Fix f = Fix (f (Fix f))\n\
+             newtype Compose f g a = Compose (f (g a))\n\
+             class Wrap t where\n\
+             \x20 wrap :: a -> t Maybe a",
+        );
+        assert_eq!(result, Ok(()));
+        let kinds: Vec<String> = ["App", "Proxy", "Fix", "Compose"]
+            .iter()
+            .map(|name| env.datatypes[*name].kind.to_string())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "(* -> *) -> * -> *",
+                "* -> *",
+                "(* -> *) -> *",
+                "(* -> *) -> (* -> *) -> * -> *"
+            ]
+        );
+        assert_eq!(env.classes["Wrap"].kind.to_string(), "(* -> *) -> * -> *");
+        assert_eq!(env.classes["Monad"].kind.to_string(), "* -> *");
+
+        assert_eq!(
+            check("f :: Maybe Int Int -> Int\nf _ = 1", &[]),
+            Err("Expected kind `* -> *`, but `Maybe Int` has kind `*`\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f :: Maybe -> Int\nf _ = 1", &[]),
+            Err("Expected a type, but `Maybe` has kind `* -> *`\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("data T = T [Maybe]", &[]),
+            Err("Expected a type, but `Maybe` has kind `* -> *`\n  \
+                 In the data declaration for `T`"
+                .to_owned())
+        );
+        assert_eq!(
+            check(
+                "class Functor' f where\n\
+                 \x20 fmap' :: (a -> b) -> f a -> f b\n\
+                 instance Functor' Int where\n\
+                 \x20 fmap' f x = x",
+                &[]
+            ),
+            Err("Expected kind `* -> *`, but `Int` has kind `*`\n  \
+                 In the instance declaration for `Functor' Int`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f :: Monad Int => Int\nf = 1", &[]),
+            Err("Expected kind `* -> *`, but `Int` has kind `*`\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_type_synonyms() {
+        let (env, result) = check_env(
+            "type Pair a = (a, a)\n\
+             type M = Maybe\n\
+             type Apply f a = f a",
+        );
+        assert_eq!(result, Ok(()));
+        let kinds: Vec<String> = ["Pair", "M", "Apply"]
+            .iter()
+            .map(|name| env.synonyms[*name].kind.to_string())
+            .collect();
+        assert_eq!(kinds, ["* -> *", "* -> *", "(* -> *) -> * -> *"]);
+        // expanded wherever they are written, given before or after
+        assert_eq!(
+            check(
+                "type Pair a = (a, a)\n\
+                 swap :: Pair a -> Pair a\n\
+                 swap (x, y) = (y, x)\n\
+                 m :: M Int\n\
+                 m = Just 1\n\
+                 type M = Maybe\n\
+                 data Named = Named Name\n\
+                 type Name = Label\n\
+                 type Label = String\n\
+                 name :: Named -> Name\n\
+                 name (Named n) = n",
+                &["swap", "m", "Named", "name"]
+            ),
+            Ok(vec![
+                "(a, a) -> (a, a)".to_owned(),
+                "Maybe Integer".to_owned(),
+                "[Char] -> Named".to_owned(),
+                "Named -> [Char]".to_owned()
+            ])
+        );
+        assert_eq!(
+            check("type Name = String\nf :: Name -> Bool\nf n = n", &[]),
+            Err("Couldn't match expected type `Bool` with actual type `[Char]`\n  \
+                 In the expression: n\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("type P a = Maybe a\ndata App f a = App (f a)\nx :: App P Int\nx = x", &[]),
+            Err("The type synonym `P` should have 1 argument, but has been given none\n  \
+                 In the definition of `x`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("type P a = (a, b)", &[]),
+            Err("Not in scope: type variable `b`\n  \
+                 In the type synonym declaration for `P`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("type P = Maybe Maybe", &[]),
+            Err("Expected a type, but `Maybe` has kind `* -> *`\n  \
+                 In the type synonym declaration for `P`"
+                .to_owned())
+        );
+        // a synonym in error is not reported again where it is used
+        assert_eq!(
+            check("type A = [B]\ntype B = Maybe A\nf :: A -> B\nf x = x", &[]),
+            Err("Cycle in type synonym declarations: `A`, `B`".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_error_messages() {
+        // the type variables of the types that don't match are named in
+        // order, and the argument that doesn't fit is pointed at
+        assert_eq!(
+            check("f = map fst \"ab\"", &[]),
+            Err("Couldn't match expected type `[(a, b)]` with actual type `[Char]`\n  \
+                 In the second argument of `map`, namely `\"ab\"`\n  \
+                 In the expression: map fst \"ab\"\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        assert_eq!(
+            check("f x = _ x", &[]),
+            Err("Found hole: _ :: a -> b\n  \
+                 Relevant bindings include\n    \
+                 x :: a\n  \
+                 Valid hole fits include\n    \
+                 f :: a -> b\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        // the type of a local is no more general than the hole's
+        assert_eq!(
+            check("g y = \\x -> _foo x", &[]),
+            Err("Found hole: _foo :: a -> b\n  \
+                 Relevant bindings include\n    \
+                 x :: a\n    \
+                 y :: c\n  \
+                 In the definition of `g`"
+                .to_owned())
+        );
+        let input = "f :: Int -> Bool\n\
+                     f n = go n\n\
+                     \x20 where go m = m && 'c'";
+        assert_eq!(
+            check(input, &[]),
+            Err("Couldn't match expected type `Bool` with actual type `Char`\n  \
+                 In the second argument of `(&&)`, namely `'c'`\n  \
+                 In the expression: m && 'c'\n  \
+                 In the definition of `go`\n  \
+                 In the definition of `f`"
+                .to_owned())
+        );
+        // parsed with spans, the error is located at the argument, and can
+        // be had as JSON
+        let (_, result) = check_parsed(parse_located(input));
+        let err = result.unwrap_err()[0].clone();
+        assert_eq!(
+            err.to_string().lines().next(),
+            Some("3:21: Couldn't match expected type `Bool` with actual type `Char`")
+        );
+        assert_eq!(
+            err.render(ErrorFormat::Json),
+            "{\"message\":\"Couldn't match expected type `Bool` with actual type `Char`\",\
+             \"span\":{\"line\":3,\"column\":21,\"start\":48,\"end\":51},\
+             \"expected\":\"Bool\",\"actual\":\"Char\",\
+             \"argument\":{\"position\":2,\"function\":\"(&&)\",\"argument\":\"'c'\"},\
+             \"expression\":\"m && 'c'\",\"bindings\":[\"go\",\"f\"]}"
+        );
+    }
+
+    // The warnings of a module that type checks, the first line of each
+    fn warnings(input: &str) -> Vec<String> {
+        let (mut env, result) = check_env(input);
+        result.unwrap_or_else(|errors| panic!("{}", errors[0]));
+        env.take_warnings().iter().map(|w| w.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_pattern_warnings() {
+        let input = "data Either a b = Left a | Right b\n\
+                     f :: Maybe (Either Int Bool) -> Int\n\
+                     f Nothing = 0\n\
+                     f (Just (Right _)) = 1";
+        assert_eq!(
+            warnings(input),
+            ["Pattern match(es) are non-exhaustive in the equations of `f`\n  \
+              Patterns not matched:\n    \
+              Just (Left _)"]
+        );
+        // the arguments of an equation are listed as they would be written
+        assert_eq!(
+            warnings("f :: Bool -> Maybe a -> Int\nf True (Just _) = 0"),
+            ["Pattern match(es) are non-exhaustive in the equations of `f`\n  \
+              Patterns not matched:\n    \
+              False _\n    \
+              True Nothing"]
+        );
+        assert_eq!(
+            warnings("f :: Bool -> Int\nf True = 1\nf False = 2\nf _ = 3"),
+            ["Pattern match is redundant: `f _`"]
+        );
+        assert_eq!(
+            warnings("f xs = case xs of\n  [] -> 0\n  (x:_) -> x\n  [a, b] -> a"),
+            ["Pattern match is redundant: `[a, b]` in a case alternative"]
+        );
+        assert_eq!(
+            warnings("f p = case (p, p) of\n  (True, False) -> 1\n  (False, _) -> 2"),
+            ["Pattern match(es) are non-exhaustive in a case expression\n  \
+              Patterns not matched:\n    \
+              (True, True)"]
+        );
+        assert_eq!(
+            warnings("f = \\(Just x) -> x"),
+            ["Pattern match(es) are non-exhaustive in a lambda\n  \
+              Patterns not matched:\n    \
+              Nothing"]
+        );
+        assert_eq!(
+            warnings("f xs = a + b where [a, b] = xs"),
+            ["Pattern match(es) are non-exhaustive in a pattern binding\n  \
+              Patterns not matched:\n    \
+              []\n    \
+              [_]\n    \
+              (_:_:_:_)"]
+        );
+        assert_eq!(
+            warnings("f p = let (a, Just b) = p in a + b"),
+            ["Pattern match(es) are non-exhaustive in a pattern binding\n  \
+              Patterns not matched:\n    \
+              (_, Nothing)"]
+        );
+        // a guard other than `otherwise` may fail, and literals of numbers
+        // leave other numbers
+        assert_eq!(
+            warnings("f x | x > 0 = 1\n    | x < 0 = 2\ng 0 = 1\ng n | otherwise = n"),
+            ["Pattern match(es) are non-exhaustive in the equations of `f`\n  \
+              Patterns not matched:\n    \
+              _"]
+        );
+        assert_eq!(
+            warnings("f :: String -> Int\nf \"a\" = 1\nf ('a':_) = 2\nf _ = 3"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            warnings("f :: String -> Int\nf \"a\" = 1\nf \"a\" = 2\nf _ = 3"),
+            ["Pattern match is redundant: `f \"a\"`"]
+        );
+        // where they are, in the tokens of the module
+        let input = "f x = case x of\n  Just y -> y";
+        let (_, (tokens, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+        let (mut env, _) = check_env(input);
+        let warning = env.take_warnings().remove(0);
+        assert_eq!(
+            warning.locate(&Tokens::with_spans(&tokens, &spans)).to_string(),
+            "1:7: warning: Pattern match(es) are non-exhaustive in a case expression\n  \
+             Patterns not matched:\n    \
+             Nothing\n  \
+             In the definition of `f`"
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use crate::parser::ast::*;
+use crate::parser::rename::source_name;
+use crate::parser::visit::{self, Visitor};
+use crate::typecheck::{source_pattern, source_text, TypeEnv, Warning};
+
+/// The most values a warning lists as not matched.
+const MAX_MISSING: usize = 4;
+
+/// Warns of the pattern matches of a module that can fail, with the values
+/// they miss, and of the equations and alternatives no value reaches, by
+/// Maranget's usefulness of a row of patterns below those before it.
+///
+/// It errs on the side of warning: a guard may fail unless it is
+/// `otherwise` or `True`, and literals of types with many values, such as
+/// numbers and characters, leave the others unmatched.
+pub struct PatternCheck<'env> {
+    env: &'env TypeEnv,
+    // the definitions being checked, the innermost last
+    bindings: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+// A pattern as far as matching goes: variables are wildcards, and lists,
+// tuples, strings and booleans the constructors they stand for
+#[derive(PartialEq, Debug, Clone)]
+enum Pat {
+    Wild,
+    Con(String, Vec<Pat>),
+    Lit(Literal),
+}
+
+// The patterns of an equation or alternative, and whether a guard may fail
+// after they match
+struct Row {
+    pats: Vec<Pat>,
+    guarded: bool,
+}
+
+impl<'env> PatternCheck<'env> {
+    pub fn new(env: &'env TypeEnv) -> Self {
+        PatternCheck {
+            env,
+            bindings: vec![],
+            warnings: vec![],
+        }
+    }
+
+    /// The warnings of a program that type checks.
+    pub fn check_program(mut self, program: &Program) -> Vec<Warning> {
+        self.visit_program(program);
+        self.warnings
+    }
+
+    // The equations of a function: a redundant one is written out, and the
+    // values not matched are the arguments of the calls that fail
+    fn equations(&mut self, name: &str, clauses: &[Clause]) {
+        let rows: Vec<Row> = clauses
+            .iter()
+            .map(|clause| Row {
+                pats: clause.params.iter().map(simplify).collect(),
+                guarded: may_fail(&clause.rhs),
+            })
+            .collect();
+        let width = rows.first().map_or(0, |row| row.pats.len());
+        if width == 0 {
+            return;
+        }
+        let (redundant, missing) = self.matches(&rows, width);
+        let source = source_name(name);
+        for i in redundant {
+            let pats: Vec<String> = clauses[i].params.iter().map(atomic_source).collect();
+            let text = format!("{} {}", source, pats.join(" "));
+            let mut warning = Warning::new(
+                format!("Pattern match is redundant: `{}`", text),
+                &self.bindings,
+            );
+            // the warning is in the equation, if it is of the outermost
+            // definition
+            warning.equation = Some(i).filter(|_| self.bindings.len() == 1);
+            warning.text = Some(text);
+            self.warnings.push(warning);
+        }
+        if !missing.is_empty() {
+            let what = format!("in the equations of `{}`", source);
+            self.non_exhaustive(&what, &missing, None);
+        }
+    }
+
+    // The alternatives of a `case` or `\case`, located at its scrutinee
+    fn alternatives(&mut self, alts: &[Alt], text: Option<String>) {
+        let rows: Vec<Row> = alts
+            .iter()
+            .map(|alt| Row {
+                pats: vec![simplify(&alt.pat)],
+                guarded: may_fail(&alt.rhs),
+            })
+            .collect();
+        let (redundant, missing) = self.matches(&rows, 1);
+        for i in redundant {
+            let message = format!(
+                "Pattern match is redundant: `{}` in a case alternative",
+                source_pattern(&alts[i].pat)
+            );
+            let mut warning = Warning::new(message, &self.bindings);
+            warning.text = text.clone();
+            self.warnings.push(warning);
+        }
+        if !missing.is_empty() {
+            self.non_exhaustive("in a case expression", &missing, text);
+        }
+    }
+
+    fn lambda(&mut self, params: &[Pattern]) {
+        let row = Row {
+            pats: params.iter().map(simplify).collect(),
+            guarded: false,
+        };
+        let (_, missing) = self.matches(&[row], params.len());
+        if !missing.is_empty() {
+            let pats: Vec<String> = params.iter().map(atomic_source).collect();
+            let text = format!("\\{}", pats.join(" "));
+            self.non_exhaustive("in a lambda", &missing, Some(text));
+        }
+    }
+
+    // The pattern of `(a, b) = e`, which fails when the variables it binds
+    // are used
+    fn pattern_binding(&mut self, pat: &Pattern) {
+        let row = Row {
+            pats: vec![simplify(pat)],
+            guarded: false,
+        };
+        let (_, missing) = self.matches(&[row], 1);
+        if !missing.is_empty() {
+            let text = source_pattern(pat);
+            self.non_exhaustive("in a pattern binding", &missing, Some(text));
+        }
+    }
+
+    fn non_exhaustive(&mut self, what: &str, missing: &[Vec<Pat>], text: Option<String>) {
+        let mut message = format!(
+            "Pattern match(es) are non-exhaustive {}\n  Patterns not matched:",
+            what
+        );
+        for row in missing.iter().take(MAX_MISSING) {
+            // the arguments of an equation are atomic, as they are written
+            let atomic = row.len() > 1;
+            let pats: Vec<String> = row.iter().map(|pat| pat.to_string_prec(atomic)).collect();
+            message.push_str(&format!("\n    {}", pats.join(" ")));
+        }
+        if missing.len() > MAX_MISSING {
+            message.push_str("\n    ...");
+        }
+        let mut warning = Warning::new(message, &self.bindings);
+        warning.text = text;
+        self.warnings.push(warning);
+    }
+
+    // The rows no value reaches past those before them, and the values
+    // no row matches
+    fn matches(&self, rows: &[Row], width: usize) -> (Vec<usize>, Vec<Vec<Pat>>) {
+        let mut matrix: Vec<Vec<Pat>> = vec![];
+        let mut redundant = vec![];
+        for (i, row) in rows.iter().enumerate() {
+            if !self.useful(&matrix, &row.pats) {
+                redundant.push(i);
+            }
+            // a row whose guard may fail matches nothing for certain
+            if !row.guarded {
+                matrix.push(row.pats.clone());
+            }
+        }
+        (redundant, self.missing(&matrix, width))
+    }
+
+    // Whether some value the row matches matches none of the rows above it
+    fn useful(&self, rows: &[Vec<Pat>], row: &[Pat]) -> bool {
+        let (first, rest) = match row.split_first() {
+            Some(split) => split,
+            None => return rows.is_empty(),
+        };
+        match first {
+            Pat::Con(con, args) => {
+                let row = [args.as_slice(), rest].concat();
+                self.useful(&specialize(rows, con, args.len()), &row)
+            }
+            Pat::Lit(literal) => self.useful(&specialize_literal(rows, literal), rest),
+            Pat::Wild => match self.complete(rows) {
+                Some(constructors) => constructors.iter().any(|(con, arity)| {
+                    let row = [vec![Pat::Wild; *arity].as_slice(), rest].concat();
+                    self.useful(&specialize(rows, con, *arity), &row)
+                }),
+                None => self.useful(&defaults(rows), rest),
+            },
+        }
+    }
+
+    // The values of `width` patterns none of the rows matches, as patterns
+    fn missing(&self, rows: &[Vec<Pat>], width: usize) -> Vec<Vec<Pat>> {
+        if width == 0 {
+            return match rows.is_empty() {
+                true => vec![vec![]],
+                false => vec![],
+            };
+        }
+        // the values of each constructor of the type the rows don't match,
+        // those no row starts with among them
+        let heads = heads(rows);
+        if let Some(constructors) = heads.first().and_then(|(con, n)| self.signature(con, *n)) {
+            let mut missing = vec![];
+            for (con, arity) in constructors {
+                for row in self.missing(&specialize(rows, &con, arity), arity + width - 1) {
+                    let (args, rest) = row.split_at(arity);
+                    let first = Pat::Con(con.clone(), args.to_vec());
+                    missing.push([&[first], rest].concat());
+                }
+            }
+            return missing;
+        }
+        // or those of each literal, and any value with those no row starts
+        // with
+        let mut missing = vec![];
+        for literal in literals(rows) {
+            for row in self.missing(&specialize_literal(rows, &literal), width - 1) {
+                missing.push([&[Pat::Lit(literal.clone())], row.as_slice()].concat());
+            }
+        }
+        for row in self.missing(&defaults(rows), width - 1) {
+            missing.push([&[Pat::Wild], row.as_slice()].concat());
+        }
+        missing
+    }
+
+    // The constructors of the type of the first column when the rows start
+    // with all of them
+    fn complete(&self, rows: &[Vec<Pat>]) -> Option<Vec<(String, usize)>> {
+        let heads = heads(rows);
+        let (con, arity) = heads.first()?;
+        let constructors = self.signature(con, *arity)?;
+        constructors
+            .iter()
+            .all(|(con, _)| heads.iter().any(|(head, _)| head == con))
+            .then_some(constructors)
+    }
+
+    // All the constructors of the type of one, with their numbers of fields
+    fn signature(&self, con: &str, arity: usize) -> Option<Vec<(String, usize)>> {
+        if Ident(con.to_owned()).tuple_arity().is_some() {
+            return Some(vec![(con.to_owned(), arity)]);
+        }
+        let datatype = self.env.constructor_type(con)?;
+        Some(datatype.constructors)
+    }
+}
+
+impl<'ast> Visitor<'ast> for PatternCheck<'_> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match stmt {
+            Stmt::FunStmt(Ident(name), clauses) => {
+                self.bindings.push(name.clone());
+                self.equations(name, clauses);
+                visit::visit_stmt(self, stmt);
+                self.bindings.pop();
+            }
+            Stmt::LetStmt(Ident(name), _) => {
+                self.bindings.push(name.clone());
+                visit::visit_stmt(self, stmt);
+                self.bindings.pop();
+            }
+            Stmt::PatStmt(pat, _, _) => {
+                self.pattern_binding(pat);
+                visit::visit_stmt(self, stmt);
+            }
+            stmt => visit::visit_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::CaseExpr { scrutinee, alts } => {
+                let text = format!("case {} of", source_text(scrutinee));
+                self.alternatives(alts, Some(text));
+            }
+            Expr::LambdaCaseExpr(alts) => self.alternatives(alts, None),
+            Expr::LambdaExpr { params, .. } => self.lambda(params),
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+}
+
+fn simplify(pat: &Pattern) -> Pat {
+    match pat {
+        Pattern::WildPat | Pattern::VarPat(_) => Pat::Wild,
+        Pattern::LitPat(Literal::BoolLiteral(b)) => {
+            Pat::Con(if *b { "True" } else { "False" }.to_owned(), vec![])
+        }
+        Pattern::LitPat(Literal::StringLiteral(s)) => s.chars().rev().fold(nil(), |list, c| {
+            cons(Pat::Lit(Literal::CharLiteral(c)), list)
+        }),
+        Pattern::LitPat(literal) => Pat::Lit(literal.clone()),
+        Pattern::ConPat(con, args) => Pat::Con(
+            con.unqualified().to_owned(),
+            args.iter().map(simplify).collect(),
+        ),
+        Pattern::ListPat(elements) => elements
+            .iter()
+            .rev()
+            .fold(nil(), |list, element| cons(simplify(element), list)),
+        Pattern::TuplePat(components) => Pat::Con(
+            Ident::tuple_con(components.len()).0,
+            components.iter().map(simplify).collect(),
+        ),
+        Pattern::AsPat(_, pat) | Pattern::SigPat(pat, _) => simplify(pat),
+    }
+}
+
+fn nil() -> Pat {
+    Pat::Con("[]".to_owned(), vec![])
+}
+
+fn cons(head: Pat, tail: Pat) -> Pat {
+    Pat::Con(":".to_owned(), vec![head, tail])
+}
+
+// Whether a right-hand side may have no guard that holds
+fn may_fail(rhs: &Rhs) -> bool {
+    match rhs {
+        Rhs::Plain(_) => false,
+        Rhs::Guarded(guards) => !guards.iter().any(|(guard, _)| match guard.unlocated() {
+            Expr::IdentExpr(Ident(name)) => name == "otherwise" || name == "True",
+            Expr::LitExpr(Literal::BoolLiteral(b)) => *b,
+            _ => false,
+        }),
+    }
+}
+
+// A pattern as an argument of an equation or a lambda
+fn atomic_source(pat: &Pattern) -> String {
+    let text = source_pattern(pat);
+    match pat {
+        Pattern::ConPat(_, args) if !args.is_empty() => format!("({})", text),
+        Pattern::SigPat(..) => format!("({})", text),
+        _ => text,
+    }
+}
+
+// The constructors the rows start with, in order, with their numbers of
+// fields
+fn heads(rows: &[Vec<Pat>]) -> Vec<(String, usize)> {
+    let mut heads: Vec<(String, usize)> = vec![];
+    for row in rows {
+        if let Some(Pat::Con(con, args)) = row.first() {
+            if !heads.iter().any(|(head, _)| head == con) {
+                heads.push((con.clone(), args.len()));
+            }
+        }
+    }
+    heads
+}
+
+// The literals the rows start with, in order
+fn literals(rows: &[Vec<Pat>]) -> Vec<Literal> {
+    let mut literals: Vec<Literal> = vec![];
+    for row in rows {
+        if let Some(Pat::Lit(literal)) = row.first() {
+            if !literals.contains(literal) {
+                literals.push(literal.clone());
+            }
+        }
+    }
+    literals
+}
+
+// The rows for the values built by the constructor, its fields in place of
+// the first column
+fn specialize(rows: &[Vec<Pat>], con: &str, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| match row.split_first()? {
+            (Pat::Con(head, args), rest) if head == con => Some([args.as_slice(), rest].concat()),
+            (Pat::Wild, rest) => Some([vec![Pat::Wild; arity].as_slice(), rest].concat()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn specialize_literal(rows: &[Vec<Pat>], literal: &Literal) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| match row.split_first()? {
+            (Pat::Lit(head), rest) if head == literal => Some(rest.to_vec()),
+            (Pat::Wild, rest) => Some(rest.to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+// The rows for the values the first column's constructors and literals
+// don't match
+fn defaults(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| match row.split_first()? {
+            (Pat::Wild, rest) => Some(rest.to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Pat {
+    // `Just (Left _)`, with parentheses around a constructor applied to
+    // fields when it is a field itself
+    fn to_string_prec(&self, atomic: bool) -> String {
+        match self {
+            Pat::Wild => "_".to_owned(),
+            Pat::Lit(literal) => literal.to_string(),
+            Pat::Con(con, args) if con == ":" => {
+                // `(x:y:_)`, the tails of a cons unparenthesised as `:` is
+                // right associative, or `[x, y]` when they end in `[]`
+                let mut elements = vec![&args[0]];
+                let mut tail = &args[1];
+                while let Pat::Con(con, args) = tail {
+                    if con != ":" {
+                        break;
+                    }
+                    elements.push(&args[0]);
+                    tail = &args[1];
+                }
+                if *tail == nil() {
+                    let elements: Vec<String> =
+                        elements.iter().map(|pat| pat.to_string_prec(false)).collect();
+                    return format!("[{}]", elements.join(", "));
+                }
+                let mut elements: Vec<String> =
+                    elements.iter().map(|pat| pat.to_string_prec(true)).collect();
+                elements.push(tail.to_string_prec(true));
+                format!("({})", elements.join(":"))
+            }
+            Pat::Con(con, args) if Ident(con.clone()).tuple_arity().is_some() => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string_prec(false)).collect();
+                format!("({})", args.join(", "))
+            }
+            Pat::Con(con, args) if args.is_empty() => con.clone(),
+            Pat::Con(con, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string_prec(true)).collect();
+                match atomic {
+                    true => format!("({} {})", con, args.join(" ")),
+                    false => format!("{} {}", con, args.join(" ")),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt;
+
+/// A type as the checker sees it. Functions, lists and tuples are
+/// applications of the constructors `->`, `[]` and `(,)`, so that a class
+/// variable such as the `m` of `Monad m` can stand for any of them.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Ty {
+    /// A unification variable, bound as inference goes
+    Var(usize),
+    /// A variable of a type signature, which only matches itself: the
+    /// definition must work whatever type it stands for.
+    Skolem(usize, String),
+    /// The variable quantified at this position by a `Scheme`
+    Gen(usize),
+    Con(String),
+    App(Box<Ty>, Box<Ty>),
+    /// The type of a Monkey value, known only as the program runs, which
+    /// matches any type
+    Dynamic,
+}
+
+impl Ty {
+    pub fn con(name: &str) -> Ty {
+        Ty::Con(name.to_owned())
+    }
+
+    pub fn app(f: Ty, arg: Ty) -> Ty {
+        Ty::App(Box::new(f), Box::new(arg))
+    }
+
+    pub fn fun(arg: Ty, result: Ty) -> Ty {
+        Ty::app(Ty::app(Ty::con("->"), arg), result)
+    }
+
+    pub fn list(elem: Ty) -> Ty {
+        Ty::app(Ty::con("[]"), elem)
+    }
+
+    /// The unit `()` when there are no components.
+    pub fn tuple(components: Vec<Ty>) -> Ty {
+        let con = Ty::Con(format!("({})", ",".repeat(components.len().saturating_sub(1))));
+        components.into_iter().fold(con, Ty::app)
+    }
+
+    pub fn string() -> Ty {
+        Ty::list(Ty::con("Char"))
+    }
+
+    /// The constructor and the arguments of `T a b`.
+    pub fn split(&self) -> (&Ty, Vec<&Ty>) {
+        let mut args = vec![];
+        let mut ty = self;
+        while let Ty::App(f, arg) = ty {
+            args.push(&**arg);
+            ty = f;
+        }
+        args.reverse();
+        (ty, args)
+    }
+
+    /// The argument and result of a function type.
+    pub fn as_fun(&self) -> Option<(&Ty, &Ty)> {
+        match self.split() {
+            (Ty::Con(con), args) if con == "->" && args.len() == 2 => Some((args[0], args[1])),
+            _ => None,
+        }
+    }
+
+    /// Replaces the quantified variables by the given types.
+    pub fn instantiate(&self, types: &[Ty]) -> Ty {
+        match self {
+            Ty::Gen(i) => types[*i].clone(),
+            Ty::App(f, arg) => Ty::app(f.instantiate(types), arg.instantiate(types)),
+            ty => ty.clone(),
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter, prec: u8) -> fmt::Result {
+        if let Some((arg, result)) = self.as_fun() {
+            if prec > 0 {
+                write!(f, "(")?;
+            }
+            arg.fmt_prec(f, 1)?;
+            write!(f, " -> ")?;
+            result.fmt_prec(f, 0)?;
+            if prec > 0 {
+                write!(f, ")")?;
+            }
+            return Ok(());
+        }
+        match self.split() {
+            (Ty::Con(con), args) if con == "[]" && args.len() == 1 => {
+                write!(f, "[")?;
+                args[0].fmt_prec(f, 0)?;
+                write!(f, "]")
+            }
+            (Ty::Con(con), args) if con.starts_with("(,") && con.len() == args.len() + 1 => {
+                write!(f, "(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.fmt_prec(f, 0)?;
+                }
+                write!(f, ")")
+            }
+            (_, args) if args.is_empty() => match self {
+                Ty::Var(n) => write!(f, "t{}", n),
+                Ty::Skolem(_, name) => write!(f, "{}", name),
+                Ty::Gen(i) => write!(f, "{}", var_name(*i)),
+                Ty::Con(con) => write!(f, "{}", con),
+                Ty::Dynamic => write!(f, "?"),
+                Ty::App(..) => unreachable!(),
+            },
+            (con, args) => {
+                if prec > 1 {
+                    write!(f, "(")?;
+                }
+                con.fmt_prec(f, 2)?;
+                for arg in args {
+                    write!(f, " ")?;
+                    arg.fmt_prec(f, 2)?;
+                }
+                if prec > 1 {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `a`, `b`, ..., `z`, `a1`, `b1`, ...
+pub fn var_name(i: usize) -> String {
+    let letter = (b'a' + (i % 26) as u8) as char;
+    match i / 26 {
+        0 => letter.to_string(),
+        n => format!("{}{}", letter, n),
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+/// Names the unification variables of the types of a message `a`, `b`, ...
+/// in the order they are met, skipping the names of the signature variables
+/// the types mention, so that `t12 -> t7` reads `a -> b`.
+#[derive(Default)]
+pub struct Tidy {
+    vars: Vec<usize>,
+    names: Vec<String>,
+    taken: Vec<String>,
+}
+
+impl Tidy {
+    pub fn new(types: &[&Ty]) -> Self {
+        fn skolems(ty: &Ty, taken: &mut Vec<String>) {
+            match ty {
+                Ty::Skolem(_, name) if !taken.contains(name) => taken.push(name.clone()),
+                Ty::App(f, arg) => {
+                    skolems(f, taken);
+                    skolems(arg, taken);
+                }
+                _ => {}
+            }
+        }
+        let mut tidy = Tidy::default();
+        for ty in types {
+            skolems(ty, &mut tidy.taken);
+        }
+        tidy
+    }
+
+    pub fn ty(&mut self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(v) => {
+                let i = match self.vars.iter().position(|w| w == v) {
+                    Some(i) => i,
+                    None => {
+                        let name = (0..)
+                            .map(var_name)
+                            .find(|name| !self.taken.contains(name) && !self.names.contains(name))
+                            .expect("a free name");
+                        self.vars.push(*v);
+                        self.names.push(name);
+                        self.vars.len() - 1
+                    }
+                };
+                Ty::Skolem(i, self.names[i].clone())
+            }
+            Ty::App(f, arg) => Ty::app(self.ty(f), self.ty(arg)),
+            ty => ty.clone(),
+        }
+    }
+
+    pub fn pred(&mut self, pred: &Pred) -> Pred {
+        Pred {
+            class: pred.class.clone(),
+            ty: self.ty(&pred.ty),
+        }
+    }
+}
+
+/// The kind of a type constructor: `*` for the types of values, `* -> *` for
+/// `Maybe`, `(* -> *) -> *` for a type taking a constructor like `Maybe`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Kind {
+    Star,
+    Arrow(Box<Kind>, Box<Kind>),
+    /// A kind not known yet, bound as kind inference goes
+    Var(usize),
+}
+
+impl Kind {
+    pub fn arrow(arg: Kind, result: Kind) -> Kind {
+        Kind::Arrow(Box::new(arg), Box::new(result))
+    }
+
+    /// The kind of a constructor taking `arity` types: `* -> ... -> *`.
+    pub fn of_arity(arity: usize) -> Kind {
+        (0..arity).fold(Kind::Star, |kind, _| Kind::arrow(Kind::Star, kind))
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Star => write!(f, "*"),
+            Kind::Arrow(arg, result) => match **arg {
+                Kind::Arrow(..) => write!(f, "({}) -> {}", arg, result),
+                _ => write!(f, "{} -> {}", arg, result),
+            },
+            Kind::Var(n) => write!(f, "k{}", n),
+        }
+    }
+}
+
+/// A class constraint such as `Eq a`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pred {
+    pub class: String,
+    pub ty: Ty,
+}
+
+impl Pred {
+    pub fn instantiate(&self, types: &[Ty]) -> Pred {
+        Pred {
+            class: self.class.clone(),
+            ty: self.ty.instantiate(types),
+        }
+    }
+}
+
+impl fmt::Display for Pred {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ", self.class)?;
+        self.ty.fmt_prec(f, 2)
+    }
+}
+
+/// The type of a name that can be used at many types, such as
+/// `Show a => a -> String`: the variables `Gen(0)` to `Gen(n - 1)` can be
+/// any types satisfying the constraints.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Scheme {
+    /// The names of the quantified variables, as written in a signature or
+    /// `a`, `b`, ... for inferred types
+    pub names: Vec<String>,
+    pub preds: Vec<Pred>,
+    pub ty: Ty,
+}
+
+impl Scheme {
+    /// The type of a name bound by a pattern or a lambda, which has a single
+    /// type.
+    pub fn mono(ty: Ty) -> Scheme {
+        Scheme {
+            names: vec![],
+            preds: vec![],
+            ty,
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vars: Vec<Ty> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Ty::Skolem(i, name.clone()))
+            .collect();
+        match self.preds.as_slice() {
+            [] => {}
+            [pred] => write!(f, "{} => ", pred.instantiate(&vars))?,
+            preds => {
+                let preds: Vec<String> =
+                    preds.iter().map(|p| p.instantiate(&vars).to_string()).collect();
+                write!(f, "({}) => ", preds.join(", "))?
+            }
+        }
+        write!(f, "{}", self.ty.instantiate(&vars))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let a = || Ty::Gen(0);
+        let maybe = |ty| Ty::app(Ty::con("Maybe"), ty);
+        let scheme = |names: &[&str], preds: Vec<Pred>, ty| Scheme {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            preds,
+            ty,
+        };
+        let show = Pred {
+            class: "Show".to_owned(),
+            ty: a(),
+        };
+        assert_eq!(
+            scheme(&["a"], vec![show.clone()], Ty::fun(a(), Ty::string())).to_string(),
+            "Show a => a -> [Char]"
+        );
+        assert_eq!(
+            Ty::fun(Ty::fun(Ty::Var(3), Ty::Var(4)), Ty::list(maybe(Ty::Var(3)))).to_string(),
+            "(t3 -> t4) -> [Maybe t3]"
+        );
+        assert_eq!(
+            Ty::app(maybe(maybe(Ty::con("Integer"))), Ty::tuple(vec![])).to_string(),
+            "Maybe (Maybe Integer) ()"
+        );
+        let m = |ty| Ty::app(Ty::Gen(0), ty);
+        assert_eq!(
+            scheme(
+                &["m", "a"],
+                vec![
+                    Pred {
+                        class: "Monad".to_owned(),
+                        ty: Ty::Gen(0),
+                    },
+                    show.instantiate(&[Ty::Gen(1)]),
+                ],
+                Ty::fun(m(Ty::Gen(1)), m(Ty::tuple(vec![Ty::Gen(1), Ty::con("Char")])))
+            )
+            .to_string(),
+            "(Monad m, Show a) => m a -> m (a, Char)"
+        );
+        assert_eq!(var_name(27), "b1");
+        let b = || Ty::Skolem(0, "b".to_owned());
+        let ty = Ty::fun(Ty::Var(12), Ty::fun(b(), Ty::fun(Ty::Var(7), Ty::Var(12))));
+        let mut tidy = Tidy::new(&[&ty]);
+        assert_eq!(tidy.ty(&ty).to_string(), "a -> b -> c -> a");
+        assert_eq!(tidy.ty(&Ty::list(Ty::Var(7))).to_string(), "[c]");
+        let star = || Kind::Star;
+        assert_eq!(Kind::of_arity(2).to_string(), "* -> * -> *");
+        assert_eq!(
+            Kind::arrow(Kind::of_arity(1), Kind::arrow(star(), Kind::Var(2))).to_string(),
+            "(* -> *) -> * -> k2"
+        );
+    }
+}
@@ -115,6 +115,7 @@ fn main() -> rustyline::Result<()> {
     println!();
 
     let mut evaluator = Evaluator::new();
+    let mut extensions = Extensions::default();
     let mut count = 1;
 
     loop {
@@ -124,19 +125,24 @@ fn main() -> rustyline::Result<()> {
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                let lex_tokens = Lexer::lex_tokens(&line);
+                let lex_tokens = Lexer::lex_tokens_with_spans(&line);
                 match lex_tokens {
-                    Ok((_, r)) => {
-                        let tokens = Tokens::new(&r);
-                        let parsed = Parser::parse_tokens(tokens);
-                        match parsed {
-                            Ok((_, program)) => {
-                                let eval = evaluator.eval_program(program);
-                                println!("{}", eval);
+                    Ok((_, (r, spans))) => {
+                        let tokens = Tokens::with_spans(&r, &spans).with_extensions(extensions);
+                        // a pragma stays in effect for the rest of the session
+                        if let Ok((_, enabled)) = Parser::parse_pragmas(tokens) {
+                            extensions = enabled;
+                        }
+                        let (program, errors) = Parser::parse_tokens_recovering(tokens);
+                        if errors.is_empty() {
+                            let eval = evaluator.eval_interactive(program);
+                            for warning in evaluator.take_warnings() {
+                                eprintln!("{}", warning);
                             }
-                            Err(Err::Error(_)) => println!("Parser error"),
-                            Err(Err::Failure(_)) => println!("Parser failure"),
-                            Err(Err::Incomplete(_)) => println!("Incomplete parsing"),
+                            println!("{}", eval);
+                        }
+                        for error in errors {
+                            println!("Parser error: {}", error);
                         }
                     }
                     Err(Err::Error(_)) => println!("Lexer error"),
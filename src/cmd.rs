@@ -5,7 +5,13 @@ pub enum Command {
     Noop,
 }
 
-pub fn read_command() -> Command {
+use haskell_lib::typecheck::ErrorFormat;
+use std::path::PathBuf;
+
+/// The command, the directories searched for imported modules, the format
+/// of type errors, and whether to print the Core of the module rather than
+/// run it.
+pub fn read_command() -> (Command, Vec<PathBuf>, ErrorFormat, bool) {
     let matches = clap_app!(haskell =>
         (version: "0.1.0")
         (author: "Brian Teague <brian.teague@gmail.com>")
@@ -13,14 +19,33 @@ pub fn read_command() -> Command {
         (@setting ArgRequiredElseHelp)
         (@arg src: -s --src +takes_value "Path of the source file")
         (@arg run: -r --run +takes_value "Code you want to run inline")
+        (@arg include: -i --include +takes_value +multiple
+            "Directory searched for imported modules")
+        (@arg error_format: --("error-format") +takes_value possible_value[human json]
+            "Format of type errors: text, or one JSON object a line")
+        (@arg dump_core: --("dump-core") "Print the Core the module desugars into")
     )
     .get_matches();
 
     let src_path = matches.value_of("src").map(|s| s.to_string());
     let run_string = matches.value_of("run").map(|s| s.to_string());
-    match (src_path, run_string) {
+    let includes = matches
+        .values_of("include")
+        .map_or(vec![], |dirs| dirs.map(PathBuf::from).collect());
+    // modules are looked for next to the source file first
+    let base = match src_path {
+        Some(ref s) => PathBuf::from(s).parent().map_or(PathBuf::from("."), PathBuf::from),
+        None => PathBuf::from("."),
+    };
+    let search_path = std::iter::once(base).chain(includes).collect();
+    let error_format = match matches.value_of("error_format") {
+        Some("json") => ErrorFormat::Json,
+        _ => ErrorFormat::Human,
+    };
+    let command = match (src_path, run_string) {
         (Some(s), _) => Command::FileRead(s),
         (_, Some(s)) => Command::RunInlineCode(s),
         _ => Command::Noop,
-    }
+    };
+    (command, search_path, error_format, matches.is_present("dump_core"))
 }
@@ -3,10 +3,12 @@ extern crate haskell_lib;
 extern crate clap;
 extern crate nom;
 
+use haskell_lib::evaluator::object::Object;
 use haskell_lib::evaluator::*;
 use haskell_lib::lexer::token::*;
 use haskell_lib::lexer::*;
 use haskell_lib::parser::*;
+use haskell_lib::typecheck::ErrorFormat;
 use nom::Err;
 use std::fs::File;
 use std::io::prelude::*;
@@ -22,7 +24,8 @@ fn read_file(file_path: String) -> Result<String, ::std::io::Error> {
 }
 
 fn main() {
-    let code_string = match cmd::read_command() {
+    let (command, search_path, error_format, dump_core) = cmd::read_command();
+    let code_string = match command {
         Command::FileRead(file_path) => read_file(file_path).ok(),
         Command::RunInlineCode(code) => Some(code),
         Command::Noop => None,
@@ -30,19 +33,32 @@ fn main() {
 
     if let Some(code_string) = code_string {
         let mut evaluator = Evaluator::new();
-        let lex_tokens = Lexer::lex_tokens(&code_string);
+        evaluator.set_search_path(search_path);
+        evaluator.set_error_format(error_format);
+        let lex_tokens = Lexer::lex_tokens_with_spans(&code_string);
         match lex_tokens {
-            Ok((_, r)) => {
-                let tokens = Tokens::new(&r);
-                let parsed = Parser::parse_tokens(tokens);
-                match parsed {
-                    Ok((_, program)) => {
-                        let eval = evaluator.eval_program(program);
-                        println!("{}", eval);
+            Ok((_, (r, spans))) => {
+                let tokens = Tokens::with_spans(&r, &spans);
+                let (module, errors) = Parser::parse_module_recovering(tokens);
+                if errors.is_empty() && dump_core {
+                    match evaluator.core_module(module, tokens) {
+                        Ok(program) => println!("{}", program),
+                        Err(err) => println!("{}", err),
                     }
-                    Err(Err::Error(_)) => println!("Parser error"),
-                    Err(Err::Failure(_)) => println!("Parser failure"),
-                    Err(Err::Incomplete(_)) => println!("Incomplete parsing"),
+                } else if errors.is_empty() {
+                    match evaluator.eval_module_with_spans(module, tokens) {
+                        // the JSON lines alone, for the tool reading them
+                        Object::Error(err) if error_format == ErrorFormat::Json => {
+                            println!("{}", err)
+                        }
+                        eval => println!("{}", eval),
+                    }
+                    for warning in evaluator.take_warnings() {
+                        eprintln!("{}", warning);
+                    }
+                }
+                for error in errors {
+                    println!("Parser error: {}", error);
                 }
             }
             Err(Err::Error(_)) => println!("Lexer error"),
@@ -36,3 +36,31 @@ fn test_reduce() {
     let eval = evaluator.eval_program(program);
     assert_eq!(eval, Object::Null);
 }
+
+#[test]
+fn test_classes() {
+    let code_string = read_file("examples/classes.hs".to_owned()).ok().unwrap();
+    let mut evaluator = Evaluator::new();
+    let (_, (lex_tokens, spans)) = Lexer::lex_tokens_with_spans(&code_string).unwrap();
+    let tokens = Tokens::with_spans(&lex_tokens, &spans);
+    let (_, program) = Parser::parse_tokens(tokens).unwrap();
+    let eval = evaluator.eval_program(program);
+    assert_eq!(
+        eval,
+        Object::String("Rect 3 4, shape of area 10, True".to_owned())
+    );
+}
+
+#[test]
+fn test_modules() {
+    let code_string = read_file("examples/modules/Main.hs".to_owned()).ok().unwrap();
+    let mut evaluator = Evaluator::new();
+    evaluator.set_search_path(vec!["examples/modules".into()]);
+    let (_, (lex_tokens, spans)) = Lexer::lex_tokens_with_spans(&code_string).unwrap();
+    let tokens = Tokens::with_spans(&lex_tokens, &spans);
+    let (_, module) = Parser::parse_module(tokens).unwrap();
+    let eval = evaluator.eval_module(module);
+    let areas = Object::Array(vec![Object::Float(4.0), Object::Float(3.0)]);
+    let found = Object::Data("Just".to_owned(), vec![Object::String("two".to_owned())]);
+    assert_eq!(eval, Object::Data("(,)".to_owned(), vec![areas, found]));
+}